@@ -2,6 +2,7 @@ struct Component {
     model: mat3x3<f32>,
     id: u32,
     ty: u32,
+    selected: u32,
 }
 struct Wire {
     id: u32,
@@ -9,9 +10,13 @@ struct Wire {
     end: vec2<f32>,
     prev_dir: vec2<f32>,
     next_dir: vec2<f32>,
-    // circular_overlay: u32
+    circle_overlay: u32,
+    selected: u32,
 }
 
+// Color of the selected components and wires
+const SELECTED_COLOR: vec4<f32> = vec4<f32>(0.96, 0.63, 0.26, 1.0);
+
 @group($bg) @binding(0)
 var<storage, read> components: array<Component>;
 @group($bg) @binding(1)
@@ -287,6 +287,10 @@ fn vs_main(
     output.clip_pos = camera.view_proj * mat3_to_mat4(component.model) * output.clip_pos;
     output.component_idx = component_idx;
 
+    if (component.selected != 0u) {
+        output.color = SELECTED_COLOR;
+    }
+
 
     return output;
 }
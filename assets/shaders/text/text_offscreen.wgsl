@@ -12,13 +12,20 @@ struct VertexInput {
     @location(1) bary: vec2<f32>,
 };
 
+// Placement of the glyph in a label, the vertices are in font units
+struct InstanceInput {
+    @location(2) position: vec2<f32>,
+    @location(3) scale: f32,
+};
+
 
 @vertex
-fn vs_offscreen(model: VertexInput) -> VertexOutput {
+fn vs_offscreen(model: VertexInput, instance: InstanceInput) -> VertexOutput {
     var out: VertexOutput;
 
+    let position = instance.position + model.position * instance.scale;
 
-    out.clip_pos = camera.view_proj * vec4<f32>(model.position, 0.0, 1.0);
+    out.clip_pos = camera.view_proj * vec4<f32>(position, 0.0, 1.0);
     out.bary = model.bary;
 
     return out;
@@ -7,9 +7,14 @@ struct VertexOutput {
     @location(0) color: vec4<f32>,
     @location(1) world_coord: vec2<f32>,
     @location(2) ss_coords: vec2<f32>,
+    @location(3) @interpolate(flat) instance: u32,
 }
 
 const THICKNESS: f32 = 0.1;
+const DOT_RADIUS: f32 = 0.15;
+
+const CIRCLE_AT_START: u32 = 2u;
+const CIRCLE_AT_END: u32 = 1u;
 
 @vertex
 fn vs_main(@builtin(vertex_index) vertex_idx: u32, @builtin(instance_index) instance_idx: u32) -> VertexOutput {
@@ -62,14 +67,26 @@ fn vs_main(@builtin(vertex_index) vertex_idx: u32, @builtin(instance_index) inst
     let normal_with_dir = (selected_normal * up_bit_f32 - selected_normal * (1.0 - up_bit_f32)) * THICKNESS / 2.0;
 
 
-    let vertex_model = ((1.0 - right_bit_f32) * wire.start
-                     + (right_bit_f32) * wire.end) + normal_with_dir;
+    let cap_point = (1.0 - right_bit_f32) * wire.start + right_bit_f32 * wire.end;
+
+    var vertex_model = cap_point + normal_with_dir;
+
+    // The ends with a junction dot are widened to a square that contains it, the fragment shader cuts the dot out
+    let has_circle = (cap_at_start && (wire.circle_overlay & CIRCLE_AT_START) != 0u)
+                   || (cap_at_end && (wire.circle_overlay & CIRCLE_AT_END) != 0u);
+    if has_circle {
+        vertex_model = cap_point
+                     + dir * DOT_RADIUS * (right_bit_f32 * 2.0 - 1.0)
+                     + normal * DOT_RADIUS * (up_bit_f32 * 2.0 - 1.0);
+    }
 
     // output.clip_pos = vec4<f32>(vertex.x, vertex.y, 0.0, 1.0);
     // output.color = rgb_from_u32(wire.color);
     output.color = vec4<f32>(0.0, 0.0, 0.0, 1.0);
 
     output.clip_pos = camera.view_proj * vec4<f32>(vertex_model, 0.0, 1.0);
+    output.world_coord = vertex_model;
+    output.instance = instance_idx;
     
     return output;
 }
@@ -81,8 +98,33 @@ struct FragmentOutput {
 @fragment
 fn fs_main(input: VertexOutput) -> FragmentOutput {
     var output: FragmentOutput;
-    
+
+    let wire = wires[input.instance];
+
+    if wire.circle_overlay != 0u {
+        let dir = normalize(wire.end - wire.start);
+        let normal = vec2<f32>(-dir.y, dir.x);
+        let relative = input.world_coord - wire.start;
+        let t = dot(relative, dir);
+
+        let circle_at_start = (wire.circle_overlay & CIRCLE_AT_START) != 0u;
+        let circle_at_end = (wire.circle_overlay & CIRCLE_AT_END) != 0u;
+
+        let outside_wire = abs(dot(relative, normal)) > THICKNESS / 2.0 + 1e-4
+                        || (circle_at_start && t < 0.0)
+                        || (circle_at_end && t > length(wire.end - wire.start));
+        let inside_dot = (circle_at_start && distance(input.world_coord, wire.start) <= DOT_RADIUS)
+                      || (circle_at_end && distance(input.world_coord, wire.end) <= DOT_RADIUS);
+
+        if outside_wire && !inside_dot {
+            discard;
+        }
+    }
+
     output.color = vec4(0.0, 0.0, 0.0, 1.0);
+    if wire.selected != 0u {
+        output.color = SELECTED_COLOR;
+    }
 
     return output;
 }
\ No newline at end of file
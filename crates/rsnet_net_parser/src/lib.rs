@@ -1,101 +1,107 @@
-use pyo3::prelude::*;
 use thiserror::Error;
 
+#[cfg(feature = "onnx")]
+pub mod onnx;
+#[cfg(feature = "torch")]
+pub mod torch;
 pub mod types;
 
+#[cfg(feature = "onnx")]
+pub use onnx::{extract_nn_from_onnx, read_onnx};
+#[cfg(feature = "torch")]
+pub use torch::{
+    extract_nn, extract_nn_with_options, extract_tensor, find_nn_class, get_nn_class, parse_nn, ArgValue,
+    ExtractOptions,
+};
+
+#[cfg(any(feature = "torch", feature = "onnx"))]
 use types::*;
 
 #[derive(Error, Debug)]
 pub enum NnParseError {
     #[error("No suitable Nn class found (suitable classes need to inherit from torch.nn.Module)")]
     NoSuitableNnClassFound,
+    #[error("There is no class called {0} in the source")]
+    NnClassNotFound(String),
+    #[error("{0} is not a subclass of torch.nn.Module")]
+    NotAnNnModule(String),
+    #[error("The source defines several torch.nn.Module subclasses ({0:?}), choose one by name")]
+    AmbiguousNnClass(Vec<String>),
+    #[error("Operation {0} called in forward is not supported")]
+    UnsupportedOperation(String),
     #[error("The following python runtime errors were found when parsing the input {0}")]
     PythonError(String),
+    #[error("Tensor {0} was expected to have {1} elements, found {2}")]
+    InvalidTensorShape(String, usize, usize),
+    #[error("Layer {0} is not supported")]
+    UnsupportedLayer(String),
+    #[error("Layer {0} is only partially supported, found unsupported {1}")]
+    UnsupportedLayerConfig(String, String),
+    #[error("Layer {0} has an invalid {1}")]
+    InvalidLayerConfig(String, String),
+    #[error("An input of size {input:?} is smaller than the dilated kernel of size {kernel:?}")]
+    InputTooSmall {
+        input: (usize, usize),
+        kernel: (usize, usize),
+    },
+    #[error("Batch normalization at layer {0} does not follow a Linear or Conv2d layer with matching outputs, it cannot be folded")]
+    UnfoldableBatchNorm(usize),
+    #[error("The ONNX model could not be decoded: {0}")]
+    OnnxDecodeError(String),
+    #[error("Tensor {0} is not a constant of the model, weights need to be initializers")]
+    MissingInitializer(String),
+    #[error("The model file could not be read: {0}")]
+    IoError(std::io::Error),
 }
 
-impl From<PyErr> for NnParseError {
-    fn from(value: PyErr) -> Self {
-        Self::PythonError(value.to_string())
+impl From<std::io::Error> for NnParseError {
+    fn from(value: std::io::Error) -> Self {
+        Self::IoError(value)
     }
 }
 
-pub fn get_nn_class<'py>(py: Python<'py>, src: &str) -> Result<Bound<'py, PyAny>, NnParseError> {
-    let src_module = PyModule::from_code_bound(py, src, "monmod", "monmod")?;
-
-    // module.dir()
-    let torch_module = py.import_bound("torch.nn")?;
-
-    let module: Bound<'py, PyAny> = torch_module.getattr("Module")?;
-
-    let mut nn_class = None;
-
-    src_module.dir().iter().any(|attr_name| {
-        let attr_str = attr_name.to_string();
-        let item = src_module.getattr(attr_str.as_str()).unwrap();
-
-        item.getattr("__bases__").is_ok_and(|attr| {
-            if let Ok(class) = attr.call_method("__getitem__", (0,), None) {
-                let class_str = class.to_string();
-                if class_str == module.to_string() {
-                    nn_class = Some(item);
-                    return true;
-                }
-            }
-            false
-        })
-    });
-
-    if let Some(nn_class) = nn_class {
-        Ok(nn_class)
-    } else {
-        Err(NnParseError::NoSuitableNnClassFound)
-    }
+/// Returns the (scale, shift) per channel that is equivalent to a batch normalization layer in inference mode
+#[cfg(any(feature = "torch", feature = "onnx"))]
+pub(crate) fn batch_norm_affine(
+    gamma: &[f64],
+    beta: &[f64],
+    mean: &[f64],
+    var: &[f64],
+    eps: f64,
+) -> (Vec<f64>, Vec<f64>) {
+    let scale: Vec<f64> = gamma
+        .iter()
+        .zip(var.iter())
+        .map(|(g, v)| g / (v + eps).sqrt())
+        .collect();
+    let shift = beta
+        .iter()
+        .zip(mean.iter())
+        .zip(scale.iter())
+        .map(|((b, m), s)| b - m * s)
+        .collect();
+
+    (scale, shift)
 }
 
-pub fn parse_nn<'py>(nn_instance: Bound<'py, PyAny>) -> Result<Nn, NnParseError> {
-    let mut nn = Nn { layers: vec![] };
-
-    let layers = nn_instance.getattr("layers")?;
-
-    for layer in layers.iter()? {
-        let layer = layer?;
-        let layer_class = layer.getattr("__class__")?;
-        let layer_class_name = layer_class.getattr("__name__")?;
-
-        match layer_class_name.to_string().as_str() {
-            "Linear" => {
-                let input_size = layer.getattr("in_features")?.extract::<usize>()?;
-                let output_size = layer.getattr("out_features")?.extract::<usize>()?;
-                let bias = layer.getattr("bias")?.extract::<Option<Bound<PyAny>>>()?;
-
-                // info!("input_size: {:?}", input_size);
-                // info!("output_size: {:?}", output_size);
-                // info!("bias: {:?}", bias);
-
-                nn.layers.push(Layer::Linear(LinearLayer {
-                    input_size,
-                    output_size,
-                    bias: bias.is_some(),
-                }))
-            }
-            "ReLU" => {
-                nn.layers.push(Layer::Activation(Activation::ReLU));
-            }
-            _ => {}
+/// Folds a batch normalization (given as a per channel affine transform) into the last parsed layer
+#[cfg(any(feature = "torch", feature = "onnx"))]
+pub(crate) fn fold_batch_norm(
+    layers: &mut [Layer],
+    scale: &[f64],
+    shift: &[f64],
+) -> Result<(), NnParseError> {
+    let layer_idx = layers.len();
+
+    match layers.last_mut() {
+        Some(Layer::Linear(linear)) if linear.output_size == scale.len() => {
+            linear.fold_affine(scale, shift)
         }
+        Some(Layer::Conv2d(conv)) if conv.out_channels == scale.len() => {
+            conv.fold_affine(scale, shift)
+        }
+        _ => return Err(NnParseError::UnfoldableBatchNorm(layer_idx)),
     }
 
-    Ok(nn)
-}
-
-pub fn extract_nn(src: &str) -> Result<Nn, NnParseError> {
-    pyo3::prepare_freethreaded_python();
-
-    Python::with_gil(|py| {
-        let nn_class = get_nn_class(py, src)?;
-
-        let nn_instance = nn_class.call0()?;
-
-        parse_nn(nn_instance)
-    })
+    Ok(())
 }
@@ -0,0 +1,880 @@
+pub mod proto;
+
+use crate::{batch_norm_affine, fold_batch_norm, types::*, NnParseError};
+
+use proto::{data_type, AttributeProto, ModelProto, NodeProto, TensorProto};
+
+use prost::Message;
+use std::{collections::HashMap, path::Path};
+use tracing::debug;
+
+/// Constant tensor of the graph (initializers and outputs of `Constant` nodes)
+#[derive(Debug, Clone)]
+struct Tensor {
+    dims: Vec<usize>,
+    data: Vec<f64>,
+}
+
+impl TryFrom<&TensorProto> for Tensor {
+    type Error = NnParseError;
+
+    fn try_from(tensor: &TensorProto) -> Result<Self, Self::Error> {
+        let raw = &tensor.raw_data;
+
+        let data: Vec<f64> = match tensor.data_type {
+            data_type::FLOAT if !raw.is_empty() => raw
+                .chunks_exact(4)
+                .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]) as f64)
+                .collect(),
+            data_type::FLOAT => tensor.float_data.iter().map(|v| *v as f64).collect(),
+            data_type::DOUBLE if !raw.is_empty() => raw
+                .chunks_exact(8)
+                .map(|b| f64::from_le_bytes(b.try_into().unwrap()))
+                .collect(),
+            data_type::DOUBLE => tensor.double_data.clone(),
+            data_type::INT64 if !raw.is_empty() => raw
+                .chunks_exact(8)
+                .map(|b| i64::from_le_bytes(b.try_into().unwrap()) as f64)
+                .collect(),
+            data_type::INT64 => tensor.int64_data.iter().map(|v| *v as f64).collect(),
+            ty => {
+                return Err(NnParseError::UnsupportedLayerConfig(
+                    format!("tensor {}", tensor.name),
+                    format!("data type {}", ty),
+                ))
+            }
+        };
+
+        let dims = tensor
+            .dims
+            .iter()
+            .map(|d| usize::try_from(*d))
+            .collect::<Result<Vec<usize>, _>>()
+            .map_err(|e| NnParseError::OnnxDecodeError(e.to_string()))?;
+
+        let expected: usize = dims.iter().product();
+        if data.len() != expected {
+            return Err(NnParseError::InvalidTensorShape(
+                tensor.name.clone(),
+                expected,
+                data.len(),
+            ));
+        }
+
+        Ok(Tensor { dims, data })
+    }
+}
+
+/// Element of a shape computed by the graph, e.g. the target shape of `Reshape`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ShapeDim {
+    /// The batch size, the first dimension of the data tensors
+    Batch,
+    Known(i64),
+    /// Any other dimension of a data tensor, unknown without running the graph
+    Unknown,
+}
+
+/// Shape tensor computed by the graph from the shape of a data tensor. torch exports `x.view(x.size(0), -1)` as
+/// `Shape -> Gather -> Unsqueeze -> Concat`, which is folded here to `[Batch, -1]`.
+#[derive(Debug, Clone, PartialEq)]
+enum ShapeValue {
+    /// Output of `Shape`, the whole shape of a data tensor whose rank is unknown
+    Of,
+    Dims(Vec<ShapeDim>),
+}
+
+/// Elements of the shape tensor `name`, either computed by the graph or a constant
+fn shape_dims(
+    name: &str,
+    shapes: &HashMap<String, ShapeValue>,
+    constants: &HashMap<String, Tensor>,
+) -> Option<Vec<ShapeDim>> {
+    match (shapes.get(name), constants.get(name)) {
+        (Some(ShapeValue::Dims(dims)), _) => Some(dims.clone()),
+        (None, Some(tensor)) => Some(
+            tensor
+                .data
+                .iter()
+                .map(|v| ShapeDim::Known(*v as i64))
+                .collect(),
+        ),
+        _ => None,
+    }
+}
+
+/// Folds a node of the subgraph that computes a shape, returns `false` if `node` does not operate on shapes
+fn fold_shape_node(
+    node: &NodeProto,
+    shapes: &mut HashMap<String, ShapeValue>,
+    constants: &HashMap<String, Tensor>,
+) -> Result<bool, NnParseError> {
+    let input = |idx: usize| node.input.get(idx).map(String::as_str).unwrap_or_default();
+
+    let value = match node.op_type.as_str() {
+        "Shape" => ShapeValue::Of,
+        "Gather" => {
+            let Some(shape) = shapes.get(input(0)) else {
+                return Ok(false);
+            };
+            let indices = &constant(node, 1, constants)?.data;
+            match shape {
+                ShapeValue::Of => ShapeValue::Dims(
+                    indices
+                        .iter()
+                        .map(|idx| match *idx as i64 {
+                            0 => ShapeDim::Batch,
+                            _ => ShapeDim::Unknown,
+                        })
+                        .collect(),
+                ),
+                ShapeValue::Dims(dims) => ShapeValue::Dims(
+                    indices
+                        .iter()
+                        .map(|idx| {
+                            usize::try_from(*idx as i64)
+                                .ok()
+                                .and_then(|idx| dims.get(idx).copied())
+                                .unwrap_or(ShapeDim::Unknown)
+                        })
+                        .collect(),
+                ),
+            }
+        }
+        // Only the rank changes, the elements stay the same
+        "Unsqueeze" | "Squeeze" | "Cast" => match shapes.get(input(0)) {
+            Some(value) => value.clone(),
+            None => return Ok(false),
+        },
+        "Concat" => {
+            if !node.input.iter().any(|name| shapes.contains_key(name)) {
+                return Ok(false);
+            }
+            let mut dims = Vec::new();
+            for name in node.input.iter() {
+                dims.extend(shape_dims(name, shapes, constants).ok_or_else(|| {
+                    NnParseError::UnsupportedLayerConfig(
+                        "Concat".to_string(),
+                        format!("shape input {}", name),
+                    )
+                })?);
+            }
+            ShapeValue::Dims(dims)
+        }
+        _ => return Ok(false),
+    };
+
+    if let Some(output) = node.output.first() {
+        shapes.insert(output.clone(), value);
+    }
+
+    Ok(true)
+}
+
+/// Checks that the target shape of a `Reshape` node flattens every sample, i.e. is `[batch, n]` where the batch is
+/// kept (`0` copies it, `1` is the batch torch exports with) and `n` is the size of a sample or `-1`
+fn check_flatten(
+    node: &NodeProto,
+    shapes: &HashMap<String, ShapeValue>,
+    constants: &HashMap<String, Tensor>,
+) -> Result<(), NnParseError> {
+    let name = node.input.get(1).map(String::as_str).unwrap_or_default();
+    let dims = shape_dims(name, shapes, constants)
+        .ok_or_else(|| NnParseError::MissingInitializer(name.to_string()))?;
+
+    match dims[..] {
+        [ShapeDim::Batch | ShapeDim::Known(0 | 1), ShapeDim::Known(n)] if n == -1 || n > 0 => {
+            Ok(())
+        }
+        _ => Err(NnParseError::UnsupportedLayerConfig(
+            "Reshape".to_string(),
+            format!("target shape {:?}, only flattening is supported", dims),
+        )),
+    }
+}
+
+/// Reads the layers of the ONNX model stored in `path`
+pub fn read_onnx(path: impl AsRef<Path>) -> Result<Nn, NnParseError> {
+    let bytes = std::fs::read(path)?;
+    extract_nn_from_onnx(&bytes)
+}
+
+/// Reads the layers of a serialized ONNX model. The graph must be a chain of supported operators, which is what torch
+/// exports for models built from `nn.Sequential` like layers.
+pub fn extract_nn_from_onnx(bytes: &[u8]) -> Result<Nn, NnParseError> {
+    let model =
+        ModelProto::decode(bytes).map_err(|e| NnParseError::OnnxDecodeError(e.to_string()))?;
+    let graph = model
+        .graph
+        .ok_or_else(|| NnParseError::OnnxDecodeError("the model has no graph".to_string()))?;
+
+    let mut constants = HashMap::new();
+    for initializer in graph.initializer.iter() {
+        constants.insert(initializer.name.clone(), Tensor::try_from(initializer)?);
+    }
+
+    let mut nn = Nn { layers: vec![] };
+    let mut shapes = HashMap::new();
+
+    for node in graph.node.iter() {
+        if fold_shape_node(node, &mut shapes, &constants)? {
+            continue;
+        }
+
+        match node.op_type.as_str() {
+            "Gemm" => nn.layers.push(Layer::Linear(parse_gemm(node, &constants)?)),
+            "MatMul" => nn
+                .layers
+                .push(Layer::Linear(parse_matmul(node, &constants)?)),
+            "Add" => add_bias(node, &constants, &mut nn.layers)?,
+            "Conv" => nn.layers.push(Layer::Conv2d(parse_conv(node, &constants)?)),
+            "MaxPool" => nn
+                .layers
+                .push(Layer::Pool2d(parse_pool(node, PoolingKind::Max)?)),
+            "AveragePool" => nn
+                .layers
+                .push(Layer::Pool2d(parse_pool(node, PoolingKind::Avg)?)),
+            "BatchNormalization" => {
+                let eps = attribute(node, "epsilon").map_or(1e-5, |a| a.f as f64);
+                let gamma = &constant(node, 1, &constants)?.data;
+                let beta = &constant(node, 2, &constants)?.data;
+                let mean = &constant(node, 3, &constants)?.data;
+                let var = &constant(node, 4, &constants)?.data;
+
+                let (scale, shift) = batch_norm_affine(gamma, beta, mean, var, eps);
+                fold_batch_norm(&mut nn.layers, &scale, &shift)?;
+            }
+            // torch exports `torch.flatten` as `Flatten` and `x.view(n, -1)` as `Reshape`
+            "Flatten" => nn.layers.push(Layer::Flatten),
+            "Reshape" => {
+                check_flatten(node, &shapes, &constants)?;
+                nn.layers.push(Layer::Flatten);
+            }
+            "Relu" => nn.layers.push(Layer::Activation(Activation::ReLU)),
+            "Sigmoid" => nn.layers.push(Layer::Activation(Activation::Sigmoid)),
+            "Tanh" => nn.layers.push(Layer::Activation(Activation::Tanh)),
+            "Softmax" => nn.layers.push(Layer::Activation(Activation::Softmax)),
+            "Constant" => {
+                if let (Some(value), Some(output)) = (
+                    attribute(node, "value").and_then(|a| a.t.as_ref()),
+                    node.output.first(),
+                ) {
+                    constants.insert(output.clone(), Tensor::try_from(value)?);
+                }
+            }
+            "Dropout" | "Identity" => {
+                // No effect at inference time
+                debug!("Skipping {} node {}", node.op_type, node.name);
+            }
+            _ => return Err(NnParseError::UnsupportedLayer(node.op_type.clone())),
+        }
+    }
+
+    Ok(nn)
+}
+
+fn attribute<'a>(node: &'a NodeProto, name: &str) -> Option<&'a AttributeProto> {
+    node.attribute.iter().find(|a| a.name == name)
+}
+
+/// Integer list attribute of a 2D operator, as (height, width)
+fn pair_attribute(
+    node: &NodeProto,
+    name: &str,
+    default: usize,
+) -> Result<(usize, usize), NnParseError> {
+    match attribute(node, name).map(|a| a.ints.as_slice()) {
+        None => Ok((default, default)),
+        Some([h, w]) if *h >= 0 && *w >= 0 => Ok((*h as usize, *w as usize)),
+        Some(ints) => Err(NnParseError::UnsupportedLayerConfig(
+            node.op_type.clone(),
+            format!("{} = {:?}", name, ints),
+        )),
+    }
+}
+
+/// Kernel sizes, strides and dilations of a 2D operator need to be positive
+fn check_positive(
+    node: &NodeProto,
+    name: &str,
+    (h, w): (usize, usize),
+) -> Result<(), NnParseError> {
+    if h == 0 || w == 0 {
+        return Err(NnParseError::InvalidLayerConfig(
+            node.op_type.clone(),
+            format!("{} = ({}, {})", name, h, w),
+        ));
+    }
+    Ok(())
+}
+
+/// Padding of a 2D operator, ONNX stores it as [top, left, bottom, right] and only symmetric padding is supported
+fn padding_attribute(
+    node: &NodeProto,
+    kernel_size: (usize, usize),
+    stride: (usize, usize),
+    dilation: (usize, usize),
+) -> Result<(usize, usize), NnParseError> {
+    let unsupported =
+        |what: String| NnParseError::UnsupportedLayerConfig(node.op_type.clone(), what);
+
+    let auto_pad = attribute(node, "auto_pad")
+        .map(|a| String::from_utf8_lossy(&a.s).to_string())
+        .unwrap_or_else(|| "NOTSET".to_string());
+
+    match auto_pad.as_str() {
+        "NOTSET" => match attribute(node, "pads").map(|a| a.ints.as_slice()) {
+            None => Ok((0, 0)),
+            Some([top, left, bottom, right])
+                if top == bottom && left == right && *top >= 0 && *left >= 0 =>
+            {
+                Ok((*top as usize, *left as usize))
+            }
+            Some(pads) => Err(unsupported(format!("pads = {:?}", pads))),
+        },
+        "VALID" => Ok((0, 0)),
+        "SAME_UPPER" | "SAME_LOWER" => {
+            let receptive = (
+                dilation.0 * (kernel_size.0 - 1),
+                dilation.1 * (kernel_size.1 - 1),
+            );
+            if stride != (1, 1) || !receptive.0.is_multiple_of(2) || !receptive.1.is_multiple_of(2)
+            {
+                return Err(unsupported(format!("asymmetric auto_pad = {}", auto_pad)));
+            }
+            Ok((receptive.0 / 2, receptive.1 / 2))
+        }
+        _ => Err(unsupported(format!("auto_pad = {}", auto_pad))),
+    }
+}
+
+/// The `idx`-th input of `node`, which must be a constant (the weights of the layers)
+fn constant<'a>(
+    node: &NodeProto,
+    idx: usize,
+    constants: &'a HashMap<String, Tensor>,
+) -> Result<&'a Tensor, NnParseError> {
+    let name = node.input.get(idx).ok_or_else(|| {
+        NnParseError::OnnxDecodeError(format!(
+            "{} node {} has no input {}",
+            node.op_type, node.name, idx
+        ))
+    })?;
+
+    constants
+        .get(name)
+        .ok_or_else(|| NnParseError::MissingInitializer(name.clone()))
+}
+
+/// Same as [`constant`] for optional inputs, that can be missing or have an empty name
+fn optional_constant<'a>(
+    node: &NodeProto,
+    idx: usize,
+    constants: &'a HashMap<String, Tensor>,
+) -> Result<Option<&'a Tensor>, NnParseError> {
+    match node.input.get(idx) {
+        Some(name) if !name.is_empty() => constant(node, idx, constants).map(Some),
+        _ => Ok(None),
+    }
+}
+
+/// Row major (`out` x `in`) copy of a (`in` x `out`) matrix
+fn transpose(data: &[f64], rows: usize, cols: usize) -> Vec<f64> {
+    (0..cols)
+        .flat_map(|col| (0..rows).map(move |row| data[row * cols + col]))
+        .collect()
+}
+
+/// Bias of `output_size` elements, broadcasting a single value
+fn broadcast_bias(
+    tensor: &Tensor,
+    output_size: usize,
+    name: &str,
+) -> Result<Vec<f64>, NnParseError> {
+    match tensor.data.len() {
+        len if len == output_size => Ok(tensor.data.clone()),
+        1 => Ok(vec![tensor.data[0]; output_size]),
+        len => Err(NnParseError::InvalidTensorShape(
+            name.to_string(),
+            output_size,
+            len,
+        )),
+    }
+}
+
+fn matrix_dims(node: &NodeProto, tensor: &Tensor) -> Result<(usize, usize), NnParseError> {
+    match tensor.dims.as_slice() {
+        [rows, cols] => Ok((*rows, *cols)),
+        dims => Err(NnParseError::UnsupportedLayerConfig(
+            node.op_type.clone(),
+            format!("weights with shape {:?}", dims),
+        )),
+    }
+}
+
+/// `Y = alpha * A * B' + beta * C`, with `B` and `C` constants
+fn parse_gemm(
+    node: &NodeProto,
+    constants: &HashMap<String, Tensor>,
+) -> Result<LinearLayer, NnParseError> {
+    if attribute(node, "transA").is_some_and(|a| a.i != 0) {
+        return Err(NnParseError::UnsupportedLayerConfig(
+            "Gemm".to_string(),
+            "transA".to_string(),
+        ));
+    }
+    let trans_b = attribute(node, "transB").is_some_and(|a| a.i != 0);
+    let alpha = attribute(node, "alpha").map_or(1.0, |a| a.f as f64);
+    let beta = attribute(node, "beta").map_or(1.0, |a| a.f as f64);
+
+    let b = constant(node, 1, constants)?;
+    let (rows, cols) = matrix_dims(node, b)?;
+
+    // torch exports `Linear` with transB = 1, so B already has the (out x in) layout
+    let (input_size, output_size, weights) = if trans_b {
+        (cols, rows, b.data.clone())
+    } else {
+        (rows, cols, transpose(&b.data, rows, cols))
+    };
+
+    let bias = optional_constant(node, 2, constants)?
+        .map(|c| broadcast_bias(c, output_size, &node.input[2]))
+        .transpose()?
+        .map(|bias| bias.into_iter().map(|b| b * beta).collect());
+
+    Ok(LinearLayer {
+        input_size,
+        output_size,
+        weights: weights.into_iter().map(|w| w * alpha).collect(),
+        bias,
+    })
+}
+
+/// `Y = A * B`, with `B` constant, the bias usually follows as an `Add` node
+fn parse_matmul(
+    node: &NodeProto,
+    constants: &HashMap<String, Tensor>,
+) -> Result<LinearLayer, NnParseError> {
+    let b = constant(node, 1, constants)?;
+    let (rows, cols) = matrix_dims(node, b)?;
+
+    Ok(LinearLayer {
+        input_size: rows,
+        output_size: cols,
+        weights: transpose(&b.data, rows, cols),
+        bias: None,
+    })
+}
+
+/// Adds a constant to the outputs of the last layer (the bias of a `MatMul`)
+fn add_bias(
+    node: &NodeProto,
+    constants: &HashMap<String, Tensor>,
+    layers: &mut [Layer],
+) -> Result<(), NnParseError> {
+    let (idx, tensor) = (0..node.input.len())
+        .find_map(|idx| constant(node, idx, constants).ok().map(|t| (idx, t)))
+        .ok_or_else(|| {
+            NnParseError::UnsupportedLayerConfig(
+                "Add".to_string(),
+                "non constant operands".to_string(),
+            )
+        })?;
+
+    let (output_size, bias) = match layers.last_mut() {
+        Some(Layer::Linear(linear)) => (linear.output_size, &mut linear.bias),
+        Some(Layer::Conv2d(conv)) => (conv.out_channels, &mut conv.bias),
+        _ => {
+            return Err(NnParseError::UnsupportedLayerConfig(
+                "Add".to_string(),
+                "addition that is not the bias of a Linear or Conv2d layer".to_string(),
+            ))
+        }
+    };
+
+    let values = broadcast_bias(tensor, output_size, &node.input[idx])?;
+    let bias = bias.get_or_insert_with(|| vec![0.0; output_size]);
+    bias.iter_mut().zip(values).for_each(|(b, v)| *b += v);
+
+    Ok(())
+}
+
+fn parse_conv(
+    node: &NodeProto,
+    constants: &HashMap<String, Tensor>,
+) -> Result<Conv2dLayer, NnParseError> {
+    let group = attribute(node, "group").map_or(1, |a| a.i);
+    if group != 1 {
+        return Err(NnParseError::UnsupportedLayerConfig(
+            "Conv".to_string(),
+            format!("group = {} (only 1 is supported)", group),
+        ));
+    }
+
+    let w = constant(node, 1, constants)?;
+    let (out_channels, in_channels, kh, kw) = match w.dims.as_slice() {
+        [o, i, kh, kw] => (*o, *i, *kh, *kw),
+        dims => {
+            return Err(NnParseError::UnsupportedLayerConfig(
+                "Conv".to_string(),
+                format!(
+                    "weights with shape {:?} (only 2D convolutions are supported)",
+                    dims
+                ),
+            ))
+        }
+    };
+
+    let kernel_size = (kh, kw);
+    let stride = pair_attribute(node, "strides", 1)?;
+    let dilation = pair_attribute(node, "dilations", 1)?;
+    check_positive(node, "kernel_shape", kernel_size)?;
+    check_positive(node, "strides", stride)?;
+    check_positive(node, "dilations", dilation)?;
+    let padding = padding_attribute(node, kernel_size, stride, dilation)?;
+
+    let bias = optional_constant(node, 2, constants)?
+        .map(|b| broadcast_bias(b, out_channels, &node.input[2]))
+        .transpose()?;
+
+    Ok(Conv2dLayer {
+        in_channels,
+        out_channels,
+        kernel_size,
+        stride,
+        padding,
+        dilation,
+        weights: w.data.clone(),
+        bias,
+    })
+}
+
+fn parse_pool(node: &NodeProto, kind: PoolingKind) -> Result<Pool2dLayer, NnParseError> {
+    let kernel_size = match attribute(node, "kernel_shape") {
+        Some(_) => pair_attribute(node, "kernel_shape", 1)?,
+        None => {
+            return Err(NnParseError::OnnxDecodeError(format!(
+                "{} node {} has no kernel_shape",
+                node.op_type, node.name
+            )))
+        }
+    };
+    let stride = pair_attribute(node, "strides", 1)?;
+    check_positive(node, "kernel_shape", kernel_size)?;
+    check_positive(node, "strides", stride)?;
+    let padding = padding_attribute(node, kernel_size, stride, (1, 1))?;
+
+    let unsupported =
+        |what: String| NnParseError::UnsupportedLayerConfig(node.op_type.clone(), what);
+    if pair_attribute(node, "dilations", 1)? != (1, 1) {
+        return Err(unsupported("dilations".to_string()));
+    }
+    let ceil_mode = attribute(node, "ceil_mode").map_or(0, |a| a.i);
+    if ceil_mode != 0 {
+        return Err(unsupported(format!("ceil_mode = {}", ceil_mode)));
+    }
+    // The padding is counted as in torch, unlike the ONNX default. Without padding both modes are the same.
+    let count_include_pad = attribute(node, "count_include_pad").map_or(0, |a| a.i);
+    if kind == PoolingKind::Avg && padding != (0, 0) && count_include_pad == 0 {
+        return Err(unsupported("count_include_pad = 0".to_string()));
+    }
+
+    Ok(Pool2dLayer {
+        kind,
+        kernel_size,
+        stride,
+        padding,
+    })
+}
+
+#[cfg(test)]
+mod onnx_test {
+    use super::*;
+    use proto::{GraphProto, ModelProto};
+
+    fn tensor(name: &str, dims: &[i64], data: &[f32]) -> TensorProto {
+        TensorProto {
+            name: name.to_string(),
+            dims: dims.to_vec(),
+            data_type: data_type::FLOAT,
+            float_data: data.to_vec(),
+            ..Default::default()
+        }
+    }
+
+    fn node(op_type: &str, input: &[&str], attribute: Vec<AttributeProto>) -> NodeProto {
+        NodeProto {
+            op_type: op_type.to_string(),
+            input: input.iter().map(|i| i.to_string()).collect(),
+            attribute,
+            ..Default::default()
+        }
+    }
+
+    fn ints(name: &str, ints: &[i64]) -> AttributeProto {
+        AttributeProto {
+            name: name.to_string(),
+            ints: ints.to_vec(),
+            ..Default::default()
+        }
+    }
+
+    fn int(name: &str, i: i64) -> AttributeProto {
+        AttributeProto {
+            name: name.to_string(),
+            i,
+            ..Default::default()
+        }
+    }
+
+    fn encode(nodes: Vec<NodeProto>, initializer: Vec<TensorProto>) -> Vec<u8> {
+        ModelProto {
+            ir_version: 8,
+            graph: Some(GraphProto {
+                node: nodes,
+                initializer,
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+        .encode_to_vec()
+    }
+
+    #[test]
+    fn test_cnn() {
+        // Conv(1 -> 2, 3x3, pads 1) -> BatchNorm -> Relu -> MaxPool -> Flatten -> Gemm(8 -> 3) -> MatMul(3 -> 2) + Add
+        let mut raw_conv_weights = Vec::new();
+        for v in 0..18 {
+            raw_conv_weights.extend_from_slice(&(v as f32).to_le_bytes());
+        }
+
+        let initializer = vec![
+            TensorProto {
+                name: "conv.w".to_string(),
+                dims: vec![2, 1, 3, 3],
+                data_type: data_type::FLOAT,
+                raw_data: raw_conv_weights,
+                ..Default::default()
+            },
+            tensor("conv.b", &[2], &[1.0, 2.0]),
+            tensor("bn.gamma", &[2], &[2.0, 1.0]),
+            tensor("bn.beta", &[2], &[0.0, 1.0]),
+            tensor("bn.mean", &[2], &[1.0, 0.0]),
+            tensor("bn.var", &[2], &[1.0, 1.0]),
+            tensor("fc.w", &[3, 8], &[0.5; 24]),
+            tensor("fc.b", &[3], &[0.1, 0.2, 0.3]),
+            tensor("mm.w", &[3, 2], &[1.0, 2.0, 3.0, 4.0, 5.0, 6.0]),
+            tensor("mm.b", &[2], &[-1.0, 1.0]),
+        ];
+
+        let nodes = vec![
+            node(
+                "Conv",
+                &["x", "conv.w", "conv.b"],
+                vec![ints("pads", &[1, 1, 1, 1]), ints("kernel_shape", &[3, 3])],
+            ),
+            node(
+                "BatchNormalization",
+                &["c", "bn.gamma", "bn.beta", "bn.mean", "bn.var"],
+                vec![AttributeProto {
+                    name: "epsilon".to_string(),
+                    f: 0.0,
+                    ..Default::default()
+                }],
+            ),
+            node("Relu", &["b"], vec![]),
+            node(
+                "MaxPool",
+                &["r"],
+                vec![ints("kernel_shape", &[2, 2]), ints("strides", &[2, 2])],
+            ),
+            node("Flatten", &["p"], vec![]),
+            node("Gemm", &["f", "fc.w", "fc.b"], vec![int("transB", 1)]),
+            node("Dropout", &["g"], vec![]),
+            node("MatMul", &["g", "mm.w"], vec![]),
+            node("Add", &["m", "mm.b"], vec![]),
+            node("Softmax", &["a"], vec![]),
+        ];
+
+        let nn = extract_nn_from_onnx(&encode(nodes, initializer)).unwrap();
+        assert_eq!(nn.layers.len(), 7);
+
+        let Layer::Conv2d(conv) = &nn.layers[0] else {
+            panic!("expected a convolution, got {:?}", nn.layers[0]);
+        };
+        assert_eq!((conv.in_channels, conv.out_channels), (1, 2));
+        assert_eq!(conv.padding, (1, 1));
+        // Batch normalization folded: channel 0 is scaled by 2 and shifted by -2, channel 1 is shifted by 1
+        assert_eq!(conv.weights[1], 2.0);
+        assert_eq!(conv.weights[10], 10.0);
+        assert_eq!(conv.bias, Some(vec![0.0, 3.0]));
+
+        assert!(matches!(nn.layers[1], Layer::Activation(Activation::ReLU)));
+        assert!(matches!(
+            nn.layers[2],
+            Layer::Pool2d(Pool2dLayer {
+                kind: PoolingKind::Max,
+                stride: (2, 2),
+                ..
+            })
+        ));
+        assert!(matches!(nn.layers[3], Layer::Flatten));
+
+        let Layer::Linear(fc) = &nn.layers[4] else {
+            panic!("expected a linear layer, got {:?}", nn.layers[4]);
+        };
+        assert_eq!((fc.input_size, fc.output_size), (8, 3));
+        assert_eq!(
+            fc.bias,
+            Some(vec![0.1f32 as f64, 0.2f32 as f64, 0.3f32 as f64])
+        );
+
+        let Layer::Linear(mm) = &nn.layers[5] else {
+            panic!("expected a linear layer, got {:?}", nn.layers[5]);
+        };
+        // MatMul weights are stored (in x out), they are transposed to the torch layout
+        assert_eq!(mm.weights, vec![1.0, 3.0, 5.0, 2.0, 4.0, 6.0]);
+        assert_eq!(mm.bias, Some(vec![-1.0, 1.0]));
+
+        assert!(matches!(
+            nn.layers[6],
+            Layer::Activation(Activation::Softmax)
+        ));
+    }
+
+    fn int64_tensor(name: &str, dims: &[i64], data: &[i64]) -> TensorProto {
+        TensorProto {
+            name: name.to_string(),
+            dims: dims.to_vec(),
+            data_type: data_type::INT64,
+            int64_data: data.to_vec(),
+            ..Default::default()
+        }
+    }
+
+    fn node_with_output(op_type: &str, input: &[&str], output: &str) -> NodeProto {
+        NodeProto {
+            output: vec![output.to_string()],
+            ..node(op_type, input, vec![])
+        }
+    }
+
+    #[test]
+    fn test_view_as_reshape() {
+        // Relu -> x.view(x.size(0), -1) -> Gemm(4 -> 2), as exported by torch
+        let view = |tail: &[i64]| {
+            let nodes = vec![
+                node_with_output("Relu", &["x"], "relu"),
+                node_with_output("Shape", &["relu"], "shape"),
+                node_with_output("Gather", &["shape", "zero"], "batch"),
+                node_with_output("Unsqueeze", &["batch"], "batch_1d"),
+                node_with_output("Concat", &["batch_1d", "tail"], "target"),
+                node_with_output("Reshape", &["relu", "target"], "flat"),
+                node("Gemm", &["flat", "fc.w"], vec![int("transB", 1)]),
+            ];
+            let initializer = vec![
+                int64_tensor("zero", &[], &[0]),
+                int64_tensor("tail", &[tail.len() as i64], tail),
+                tensor("fc.w", &[2, 4], &[0.5; 8]),
+            ];
+            extract_nn_from_onnx(&encode(nodes, initializer))
+        };
+
+        let nn = view(&[-1]).unwrap();
+        assert_eq!(nn.layers.len(), 3);
+        assert!(matches!(nn.layers[0], Layer::Activation(Activation::ReLU)));
+        assert!(matches!(nn.layers[1], Layer::Flatten));
+        assert!(matches!(nn.layers[2], Layer::Linear(_)));
+
+        // x.view(x.size(0), 2, -1) keeps a 3D tensor, it is not a flatten
+        assert!(matches!(
+            view(&[2, -1]),
+            Err(NnParseError::UnsupportedLayerConfig(op, _)) if op == "Reshape"
+        ));
+
+        // A constant target shape is checked too
+        let nodes = vec![
+            node_with_output("Reshape", &["x", "target"], "flat"),
+            node("Gemm", &["flat", "fc.w"], vec![int("transB", 1)]),
+        ];
+        let flatten = |target: &[i64]| {
+            let initializer = vec![
+                int64_tensor("target", &[target.len() as i64], target),
+                tensor("fc.w", &[2, 4], &[0.5; 8]),
+            ];
+            extract_nn_from_onnx(&encode(nodes.clone(), initializer))
+        };
+        assert!(flatten(&[1, 4]).is_ok());
+        assert!(flatten(&[4, 1]).is_err());
+    }
+
+    #[test]
+    fn test_unsupported_and_missing() {
+        let bytes = encode(vec![node("LSTM", &["x"], vec![])], vec![]);
+        assert!(matches!(
+            extract_nn_from_onnx(&bytes),
+            Err(NnParseError::UnsupportedLayer(op)) if op == "LSTM"
+        ));
+
+        let bytes = encode(vec![node("Gemm", &["x", "w"], vec![])], vec![]);
+        assert!(matches!(
+            extract_nn_from_onnx(&bytes),
+            Err(NnParseError::MissingInitializer(name)) if name == "w"
+        ));
+
+        assert!(matches!(
+            extract_nn_from_onnx(&[0xff, 0xff, 0xff]),
+            Err(NnParseError::OnnxDecodeError(_))
+        ));
+    }
+
+    #[test]
+    fn test_pool_options() {
+        let pool = |op_type: &str, mut attribute: Vec<AttributeProto>| {
+            attribute.push(ints("kernel_shape", &[2, 2]));
+            parse_pool(&node(op_type, &["x"], attribute), PoolingKind::Avg)
+        };
+        let pads = || ints("pads", &[1, 1, 1, 1]);
+
+        assert!(pool("AveragePool", vec![]).is_ok());
+        assert!(pool("AveragePool", vec![pads(), int("count_include_pad", 1)]).is_ok());
+        for attribute in [
+            vec![int("ceil_mode", 1)],
+            vec![ints("dilations", &[2, 2])],
+            vec![pads()],
+            vec![pads(), int("count_include_pad", 0)],
+        ] {
+            assert!(matches!(
+                pool("AveragePool", attribute),
+                Err(NnParseError::UnsupportedLayerConfig(op, _)) if op == "AveragePool"
+            ));
+        }
+
+        // A zero kernel or stride is rejected before the automatic padding is computed
+        let same = AttributeProto {
+            name: "auto_pad".to_string(),
+            s: b"SAME_UPPER".to_vec(),
+            ..Default::default()
+        };
+        for attribute in [ints("strides", &[0, 1]), ints("kernel_shape", &[0, 2])] {
+            let node = node(
+                "MaxPool",
+                &["x"],
+                vec![attribute, ints("kernel_shape", &[2, 2]), same.clone()],
+            );
+            assert!(matches!(
+                parse_pool(&node, PoolingKind::Max),
+                Err(NnParseError::InvalidLayerConfig(..))
+            ));
+        }
+
+        let bytes = encode(
+            vec![node("Conv", &["x", "w"], vec![same])],
+            vec![tensor("w", &[1, 1, 0, 3], &[])],
+        );
+        assert!(matches!(
+            extract_nn_from_onnx(&bytes),
+            Err(NnParseError::InvalidLayerConfig(op, _)) if op == "Conv"
+        ));
+    }
+}
@@ -0,0 +1,568 @@
+use crate::{batch_norm_affine, fold_batch_norm, types::*, NnParseError};
+
+use pyo3::prelude::*;
+use std::collections::HashMap;
+use tracing::{debug, warn};
+
+/// Names used in the body of every class defined at the top level of a source, the bases of a class are not part of
+/// its body
+const CLASS_BODY_NAMES_SRC: &str = r#"
+import ast
+
+def class_body_names(src):
+    return {
+        node.name: [
+            name.id for statement in node.body for name in ast.walk(statement) if isinstance(name, ast.Name)
+        ]
+        for node in ast.parse(src).body
+        if isinstance(node, ast.ClassDef)
+    }
+"#;
+
+impl From<PyErr> for NnParseError {
+    fn from(value: PyErr) -> Self {
+        Self::PythonError(value.to_string())
+    }
+}
+
+/// Value of a keyword argument passed to the constructor of the model
+#[derive(Debug, Clone, PartialEq)]
+pub enum ArgValue {
+    Bool(bool),
+    Int(i64),
+    Float(f64),
+    Str(String),
+    IntList(Vec<i64>),
+}
+
+impl ToPyObject for ArgValue {
+    fn to_object(&self, py: Python<'_>) -> PyObject {
+        match self {
+            ArgValue::Bool(v) => v.to_object(py),
+            ArgValue::Int(v) => v.to_object(py),
+            ArgValue::Float(v) => v.to_object(py),
+            ArgValue::Str(v) => v.to_object(py),
+            ArgValue::IntList(v) => v.to_object(py),
+        }
+    }
+}
+
+/// How the model is picked from the python source and instantiated
+#[derive(Debug, Clone, Default)]
+pub struct ExtractOptions {
+    /// Name of the `nn.Module` subclass to use, required if the source defines several that are not used by another
+    pub class_name: Option<String>,
+    /// Keyword arguments passed to the constructor of the class
+    pub kwargs: Vec<(String, ArgValue)>,
+}
+
+/// Returns the only subclass of `torch.nn.Module` defined in `src`
+pub fn get_nn_class<'py>(py: Python<'py>, src: &str) -> Result<Bound<'py, PyAny>, NnParseError> {
+    find_nn_class(py, src, None)
+}
+
+/// Returns the subclass of `torch.nn.Module` called `class_name` defined in `src`. If no name is given, returns the
+/// top-level one: the only class not used in the body of another one, e.g. the network built from the blocks defined
+/// before it. Classes imported by the source (e.g. `from torch.nn import Linear`) are not considered.
+pub fn find_nn_class<'py>(
+    py: Python<'py>,
+    src: &str,
+    class_name: Option<&str>,
+) -> Result<Bound<'py, PyAny>, NnParseError> {
+    // Python executes the code in the module of the same name if there is one, which still holds the classes of the
+    // previous source
+    let modules = py.import_bound("sys")?.getattr("modules")?;
+    if modules.contains("monmod")? {
+        modules.del_item("monmod")?;
+    }
+    let src_module = PyModule::from_code_bound(py, src, "monmod", "monmod")?;
+
+    let module = py.import_bound("torch.nn")?.getattr("Module")?;
+    let is_nn_class = |item: &Bound<'py, PyAny>| -> Result<bool, NnParseError> {
+        let is_class = item.is_instance_of::<pyo3::types::PyType>();
+        Ok(is_class
+            && !item.is(&module)
+            && item
+                .downcast::<pyo3::types::PyType>()
+                .map_err(|e| NnParseError::PythonError(e.to_string()))?
+                .is_subclass(&module)?)
+    };
+
+    if let Some(class_name) = class_name {
+        let item = src_module
+            .getattr(class_name)
+            .map_err(|_| NnParseError::NnClassNotFound(class_name.to_string()))?;
+
+        return if is_nn_class(&item)? {
+            Ok(item)
+        } else {
+            Err(NnParseError::NotAnNnModule(class_name.to_string()))
+        };
+    }
+
+    let mut candidates = Vec::new();
+    for attr_name in src_module.dir().iter() {
+        let attr_name = attr_name.extract::<String>()?;
+        let item = src_module.getattr(attr_name.as_str())?;
+
+        let defined_here = item
+            .getattr("__module__")
+            .is_ok_and(|module_name| module_name.to_string() == "monmod");
+
+        if defined_here && is_nn_class(&item)? {
+            candidates.push((attr_name, item));
+        }
+    }
+
+    if candidates.len() > 1 {
+        let body_names: HashMap<String, Vec<String>> = PyModule::from_code_bound(
+            py,
+            CLASS_BODY_NAMES_SRC,
+            "class_body_names",
+            "class_body_names",
+        )?
+        .getattr("class_body_names")?
+        .call1((src,))?
+        .extract()?;
+
+        let is_used = |name: &str| {
+            candidates.iter().any(|(other, _)| {
+                other != name
+                    && body_names
+                        .get(other)
+                        .is_some_and(|names| names.iter().any(|used| used == name))
+            })
+        };
+        let top_level: Vec<usize> = (0..candidates.len())
+            .filter(|idx| !is_used(&candidates[*idx].0))
+            .collect();
+        if let [idx] = top_level[..] {
+            return Ok(candidates.swap_remove(idx).1);
+        }
+    }
+
+    match candidates.len() {
+        0 => Err(NnParseError::NoSuitableNnClassFound),
+        1 => Ok(candidates.pop().unwrap().1),
+        _ => Err(NnParseError::AmbiguousNnClass(
+            candidates.into_iter().map(|(name, _)| name).collect(),
+        )),
+    }
+}
+
+/// Copies the values of a torch tensor (or parameter) in row major order
+pub fn extract_tensor(tensor: &Bound<'_, PyAny>) -> Result<Vec<f64>, NnParseError> {
+    let values = tensor
+        .call_method0("detach")?
+        .call_method0("cpu")?
+        .call_method0("flatten")?
+        .call_method0("tolist")?;
+
+    Ok(values.extract::<Vec<f64>>()?)
+}
+
+/// Extracts the parameter `name` of `layer`, checking that it has `expected_len` elements
+fn extract_param(
+    layer: &Bound<'_, PyAny>,
+    name: &str,
+    expected_len: usize,
+) -> Result<Vec<f64>, NnParseError> {
+    let values = extract_tensor(&layer.getattr(name)?)?;
+
+    if values.len() != expected_len {
+        return Err(NnParseError::InvalidTensorShape(
+            name.to_string(),
+            expected_len,
+            values.len(),
+        ));
+    }
+
+    Ok(values)
+}
+
+/// Same as [`extract_param`] for parameters that can be `None` (e.g. the bias)
+fn extract_optional_param(
+    layer: &Bound<'_, PyAny>,
+    name: &str,
+    expected_len: usize,
+) -> Result<Option<Vec<f64>>, NnParseError> {
+    if layer.getattr(name)?.is_none() {
+        return Ok(None);
+    }
+
+    extract_param(layer, name, expected_len).map(Some)
+}
+
+/// Extracts a size attribute that torch stores either as an int or as a (height, width) tuple
+fn extract_pair(layer: &Bound<'_, PyAny>, name: &str) -> Result<(usize, usize), NnParseError> {
+    let attr = layer.getattr(name)?;
+
+    if let Ok(value) = attr.extract::<usize>() {
+        return Ok((value, value));
+    }
+
+    Ok(attr.extract::<(usize, usize)>()?)
+}
+
+fn parse_linear(layer: &Bound<'_, PyAny>) -> Result<LinearLayer, NnParseError> {
+    let input_size = layer.getattr("in_features")?.extract::<usize>()?;
+    let output_size = layer.getattr("out_features")?.extract::<usize>()?;
+
+    Ok(LinearLayer {
+        input_size,
+        output_size,
+        weights: extract_param(layer, "weight", input_size * output_size)?,
+        bias: extract_optional_param(layer, "bias", output_size)?,
+    })
+}
+
+fn parse_conv2d(layer: &Bound<'_, PyAny>) -> Result<Conv2dLayer, NnParseError> {
+    let in_channels = layer.getattr("in_channels")?.extract::<usize>()?;
+    let out_channels = layer.getattr("out_channels")?.extract::<usize>()?;
+    let kernel_size = extract_pair(layer, "kernel_size")?;
+    let stride = extract_pair(layer, "stride")?;
+    let dilation = extract_pair(layer, "dilation")?;
+
+    let groups = layer.getattr("groups")?.extract::<usize>()?;
+    if groups != 1 {
+        return Err(NnParseError::UnsupportedLayerConfig(
+            "Conv2d".to_string(),
+            format!("groups = {} (only 1 is supported)", groups),
+        ));
+    }
+
+    let padding_mode = layer.getattr("padding_mode")?.extract::<String>()?;
+    if padding_mode != "zeros" {
+        return Err(NnParseError::UnsupportedLayerConfig(
+            "Conv2d".to_string(),
+            format!("padding_mode = {} (only zeros is supported)", padding_mode),
+        ));
+    }
+
+    // `padding` can also be one of the strings "valid" or "same"
+    let padding = match layer.getattr("padding")?.extract::<String>() {
+        Ok(padding) if padding == "valid" => (0, 0),
+        Ok(padding) if padding == "same" => {
+            // torch only allows "same" with unit strides, odd receptive fields are padded symmetrically
+            let same = |k: usize, d: usize| d * (k - 1) / 2;
+            if (dilation.0 * (kernel_size.0 - 1)) % 2 != 0 || (dilation.1 * (kernel_size.1 - 1)) % 2 != 0 {
+                return Err(NnParseError::UnsupportedLayerConfig(
+                    "Conv2d".to_string(),
+                    "asymmetric \"same\" padding".to_string(),
+                ));
+            }
+            (same(kernel_size.0, dilation.0), same(kernel_size.1, dilation.1))
+        }
+        Ok(padding) => {
+            return Err(NnParseError::UnsupportedLayerConfig(
+                "Conv2d".to_string(),
+                format!("padding = {}", padding),
+            ))
+        }
+        Err(_) => extract_pair(layer, "padding")?,
+    };
+
+    Ok(Conv2dLayer {
+        in_channels,
+        out_channels,
+        kernel_size,
+        stride,
+        padding,
+        dilation,
+        weights: extract_param(
+            layer,
+            "weight",
+            out_channels * in_channels * kernel_size.0 * kernel_size.1,
+        )?,
+        bias: extract_optional_param(layer, "bias", out_channels)?,
+    })
+}
+
+fn parse_pool2d(layer: &Bound<'_, PyAny>, kind: PoolingKind) -> Result<Pool2dLayer, NnParseError> {
+    let name = match kind {
+        PoolingKind::Max => "MaxPool2d",
+        PoolingKind::Avg => "AvgPool2d",
+    };
+    let unsupported =
+        |what: &str| NnParseError::UnsupportedLayerConfig(name.to_string(), what.to_string());
+
+    if kind == PoolingKind::Max && extract_pair(layer, "dilation")? != (1, 1) {
+        return Err(unsupported("dilation"));
+    }
+    if layer.getattr("ceil_mode")?.extract::<bool>()? {
+        return Err(unsupported("ceil_mode = True"));
+    }
+
+    let kernel_size = extract_pair(layer, "kernel_size")?;
+    let padding = extract_pair(layer, "padding")?;
+    if kind == PoolingKind::Avg {
+        if !layer.getattr("divisor_override")?.is_none() {
+            return Err(unsupported("divisor_override"));
+        }
+        // Without padding every window is full and both modes are the same
+        if padding != (0, 0) && !layer.getattr("count_include_pad")?.extract::<bool>()? {
+            return Err(unsupported("count_include_pad = False"));
+        }
+    }
+
+    Ok(Pool2dLayer {
+        kind,
+        kernel_size,
+        // torch replaces a missing stride by the kernel size, but older versions keep it as None
+        stride: if layer.getattr("stride")?.is_none() {
+            kernel_size
+        } else {
+            extract_pair(layer, "stride")?
+        },
+        padding,
+    })
+}
+
+/// Returns the (scale, shift) that is equivalent to a batch normalization layer in inference mode
+fn parse_batch_norm(layer: &Bound<'_, PyAny>) -> Result<(Vec<f64>, Vec<f64>), NnParseError> {
+    let num_features = layer.getattr("num_features")?.extract::<usize>()?;
+    let eps = layer.getattr("eps")?.extract::<f64>()?;
+
+    let mean = extract_optional_param(layer, "running_mean", num_features)?
+        .unwrap_or_else(|| vec![0.0; num_features]);
+    let var = extract_optional_param(layer, "running_var", num_features)?
+        .unwrap_or_else(|| vec![1.0; num_features]);
+    let gamma = extract_optional_param(layer, "weight", num_features)?
+        .unwrap_or_else(|| vec![1.0; num_features]);
+    let beta = extract_optional_param(layer, "bias", num_features)?
+        .unwrap_or_else(|| vec![0.0; num_features]);
+
+    Ok(batch_norm_affine(&gamma, &beta, &mean, &var, eps))
+}
+
+/// Appends the layer equivalent to the leaf module `layer`
+fn parse_module(layer: &Bound<'_, PyAny>, nn: &mut Nn) -> Result<(), NnParseError> {
+    let layer_class = layer.getattr("__class__")?;
+    let layer_class_name = layer_class.getattr("__name__")?.to_string();
+
+    match layer_class_name.as_str() {
+        "Linear" => nn.layers.push(Layer::Linear(parse_linear(layer)?)),
+        "Conv2d" => nn.layers.push(Layer::Conv2d(parse_conv2d(layer)?)),
+        "MaxPool2d" => nn
+            .layers
+            .push(Layer::Pool2d(parse_pool2d(layer, PoolingKind::Max)?)),
+        "AvgPool2d" => nn
+            .layers
+            .push(Layer::Pool2d(parse_pool2d(layer, PoolingKind::Avg)?)),
+        "BatchNorm1d" | "BatchNorm2d" => {
+            let (scale, shift) = parse_batch_norm(layer)?;
+
+            fold_batch_norm(&mut nn.layers, &scale, &shift)?;
+        }
+        "Flatten" => nn.layers.push(Layer::Flatten),
+        "ReLU" => nn.layers.push(Layer::Activation(Activation::ReLU)),
+        "Sigmoid" => nn.layers.push(Layer::Activation(Activation::Sigmoid)),
+        "Tanh" => nn.layers.push(Layer::Activation(Activation::Tanh)),
+        "Softmax" => nn.layers.push(Layer::Activation(Activation::Softmax)),
+        "Dropout" | "Identity" => {
+            // No effect at inference time
+            debug!("Skipping {} layer", layer_class_name);
+        }
+        _ => return Err(NnParseError::UnsupportedLayer(layer_class_name)),
+    }
+
+    Ok(())
+}
+
+/// Appends the layer equivalent to a function (`torch.relu`, `F.softmax`, ...) or tensor method (`x.view`, ...)
+/// called in `forward`
+fn parse_function(name: &str, nn: &mut Nn) -> Result<(), NnParseError> {
+    match name {
+        "relu" => nn.layers.push(Layer::Activation(Activation::ReLU)),
+        "sigmoid" => nn.layers.push(Layer::Activation(Activation::Sigmoid)),
+        "tanh" => nn.layers.push(Layer::Activation(Activation::Tanh)),
+        "softmax" => nn.layers.push(Layer::Activation(Activation::Softmax)),
+        "flatten" | "view" | "reshape" => nn.layers.push(Layer::Flatten),
+        // Shape queries (e.g. `x.view(x.size(0), -1)`) and inference no-ops
+        "size" | "getattr" | "dropout" | "contiguous" => {}
+        _ => return Err(NnParseError::UnsupportedOperation(name.to_string())),
+    }
+
+    Ok(())
+}
+
+/// Recovers the layers in execution order from the `torch.fx` trace of `forward`
+fn parse_traced(
+    nn_instance: &Bound<'_, PyAny>,
+    graph_module: &Bound<'_, PyAny>,
+) -> Result<Nn, NnParseError> {
+    let mut nn = Nn { layers: vec![] };
+
+    for node in graph_module.getattr("graph")?.getattr("nodes")?.iter()? {
+        let node = node?;
+        let op = node.getattr("op")?.extract::<String>()?;
+        let target = node.getattr("target")?;
+
+        match op.as_str() {
+            "call_module" => {
+                let module = nn_instance.call_method1("get_submodule", (target,))?;
+                parse_module(&module, &mut nn)?;
+            }
+            "call_function" => {
+                let name = target
+                    .getattr("__name__")
+                    .map_or_else(|_| target.to_string(), |name| name.to_string());
+                parse_function(&name, &mut nn)?;
+            }
+            "call_method" => parse_function(&target.extract::<String>()?, &mut nn)?,
+            // Inputs, outputs and parameters accessed directly
+            _ => {}
+        }
+    }
+
+    Ok(nn)
+}
+
+/// Walks the leaf modules in registration order, used when `forward` cannot be traced. Functions called in `forward`
+/// are not seen, so e.g. `torch.relu(x)` is missing from the result.
+fn parse_named_modules(nn_instance: &Bound<'_, PyAny>) -> Result<Nn, NnParseError> {
+    let mut nn = Nn { layers: vec![] };
+
+    for item in nn_instance.call_method0("named_modules")?.iter()? {
+        let (_, module) = item?.extract::<(String, Bound<'_, PyAny>)>()?;
+
+        let is_leaf = module.call_method0("children")?.iter()?.next().is_none();
+        if is_leaf && !module.is(nn_instance) {
+            parse_module(&module, &mut nn)?;
+        }
+    }
+
+    Ok(nn)
+}
+
+/// Extracts the layers of an instance of a `torch.nn.Module` subclass, in execution order when `forward` can be
+/// traced with `torch.fx` and in registration order otherwise.
+pub fn parse_nn<'py>(nn_instance: Bound<'py, PyAny>) -> Result<Nn, NnParseError> {
+    nn_instance.call_method0("eval")?;
+
+    let fx = nn_instance.py().import_bound("torch.fx")?;
+
+    match fx.call_method1("symbolic_trace", (&nn_instance,)) {
+        Ok(graph_module) => parse_traced(&nn_instance, &graph_module),
+        Err(e) => {
+            // Data dependent control flow cannot be traced
+            warn!(
+                "forward could not be traced ({}), using the registration order of the modules",
+                e
+            );
+            parse_named_modules(&nn_instance)
+        }
+    }
+}
+
+/// Extracts the layers of the only `torch.nn.Module` subclass defined in `src`, which must have a constructor without
+/// arguments
+pub fn extract_nn(src: &str) -> Result<Nn, NnParseError> {
+    extract_nn_with_options(src, &ExtractOptions::default())
+}
+
+pub fn extract_nn_with_options(src: &str, options: &ExtractOptions) -> Result<Nn, NnParseError> {
+    pyo3::prepare_freethreaded_python();
+
+    Python::with_gil(|py| {
+        let nn_class = find_nn_class(py, src, options.class_name.as_deref())?;
+
+        let kwargs = pyo3::types::PyDict::new_bound(py);
+        for (name, value) in options.kwargs.iter() {
+            kwargs.set_item(name, value)?;
+        }
+
+        let nn_instance = nn_class.call((), Some(&kwargs))?;
+
+        parse_nn(nn_instance)
+    })
+}
+
+#[cfg(test)]
+mod torch_test {
+    use super::*;
+
+    /// Stands in for torch when it is not installed, finding the class only needs `torch.nn.Module`
+    const TORCH_STUB_SRC: &str = r#"
+try:
+    import torch.nn
+except ImportError:
+    import sys, types
+
+    class Module:
+        pass
+
+    torch = types.ModuleType("torch")
+    torch.nn = types.ModuleType("torch.nn")
+    torch.nn.Module = Module
+    sys.modules["torch"] = torch
+    sys.modules["torch.nn"] = torch.nn
+"#;
+
+    const BLOCK_SRC: &str = r#"
+import torch.nn as nn
+
+class Block(nn.Module):
+    def __init__(self):
+        super().__init__()
+"#;
+
+    const NET_SRC: &str = r#"
+class Net(nn.Module):
+    def __init__(self):
+        super().__init__()
+        self.blocks = nn.Sequential(Block(), Block())
+"#;
+
+    const OTHER_SRC: &str = r#"
+class Other(nn.Module):
+    pass
+"#;
+
+    /// Name of the class found in `src`
+    fn found_class(src: &str, class_name: Option<&str>) -> Result<String, NnParseError> {
+        pyo3::prepare_freethreaded_python();
+        Python::with_gil(|py| {
+            py.run_bound(TORCH_STUB_SRC, None, None)?;
+            let class = find_nn_class(py, src, class_name)?;
+            Ok(class.getattr("__name__")?.extract()?)
+        })
+    }
+
+    #[test]
+    fn test_single_class() {
+        assert_eq!(found_class(BLOCK_SRC, None).unwrap(), "Block");
+    }
+
+    #[test]
+    fn test_nested_classes() {
+        let src = format!("{}{}", BLOCK_SRC, NET_SRC);
+        assert_eq!(found_class(&src, None).unwrap(), "Net");
+    }
+
+    #[test]
+    fn test_explicit_class_name() {
+        let src = format!("{}{}", BLOCK_SRC, NET_SRC);
+        assert_eq!(found_class(&src, Some("Block")).unwrap(), "Block");
+        assert!(matches!(
+            found_class(&src, Some("Missing")),
+            Err(NnParseError::NnClassNotFound(_))
+        ));
+        assert!(matches!(
+            found_class(&src, Some("nn")),
+            Err(NnParseError::NotAnNnModule(_))
+        ));
+    }
+
+    #[test]
+    fn test_ambiguous_classes() {
+        let src = format!("{}{}", BLOCK_SRC, OTHER_SRC);
+        match found_class(&src, None) {
+            Err(NnParseError::AmbiguousNnClass(mut names)) => {
+                names.sort();
+                assert_eq!(names, vec!["Block", "Other"]);
+            }
+            result => panic!("unexpected result {:?}", result),
+        }
+    }
+}
@@ -1,23 +1,174 @@
 use std::fmt::Debug;
 
-#[derive(Debug, Clone)]
+use crate::NnParseError;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Activation {
     ReLU,
-    // Sigmoid,
-    // Tanh,
-    // Softmax,
+    Sigmoid,
+    Tanh,
+    Softmax,
 }
 
 #[derive(Debug, Clone)]
 pub struct LinearLayer {
     pub input_size: usize,
     pub output_size: usize,
-    pub bias: bool,
+    /// Weight matrix in row major order, with the same shape as in torch (`output_size` x `input_size`).
+    pub weights: Vec<f64>,
+    /// One value per output, `None` if the layer has no bias.
+    pub bias: Option<Vec<f64>>,
+}
+
+impl LinearLayer {
+    /// Weight that connects the input `input` to the output `output`
+    pub fn weight(&self, output: usize, input: usize) -> f64 {
+        self.weights[output * self.input_size + input]
+    }
+
+    pub fn has_bias(&self) -> bool {
+        self.bias.is_some()
+    }
+
+    /// Applies `y * scale[o] + shift[o]` to every output `o` (e.g. a folded batch normalization)
+    pub fn fold_affine(&mut self, scale: &[f64], shift: &[f64]) {
+        fold_affine(&mut self.weights, &mut self.bias, self.input_size, scale, shift);
+    }
+}
+
+/// 2D convolution, only `groups = 1` is supported.
+#[derive(Debug, Clone)]
+pub struct Conv2dLayer {
+    pub in_channels: usize,
+    pub out_channels: usize,
+    /// (height, width)
+    pub kernel_size: (usize, usize),
+    pub stride: (usize, usize),
+    pub padding: (usize, usize),
+    pub dilation: (usize, usize),
+    /// Kernels in row major order, with the same shape as in torch (`out_channels` x `in_channels` x `kernel_size`).
+    pub weights: Vec<f64>,
+    /// One value per output channel, `None` if the layer has no bias.
+    pub bias: Option<Vec<f64>>,
+}
+
+impl Conv2dLayer {
+    /// Length of an unrolled input patch (the number of crossbar rows used by the kernels)
+    pub fn patch_size(&self) -> usize {
+        self.in_channels * self.kernel_size.0 * self.kernel_size.1
+    }
+
+    /// Spatial size of the output for an input of size (`height`, `width`). Fails if the kernel size, stride or
+    /// dilation is zero, or if the padded input is smaller than the dilated kernel.
+    pub fn output_size(&self, height: usize, width: usize) -> Result<(usize, usize), NnParseError> {
+        for (name, (h, w)) in [
+            ("kernel size", self.kernel_size),
+            ("stride", self.stride),
+            ("dilation", self.dilation),
+        ] {
+            if h == 0 || w == 0 {
+                return Err(NnParseError::InvalidLayerConfig(
+                    "Conv2d".to_string(),
+                    format!("{} of {:?}", name, (h, w)),
+                ));
+            }
+        }
+
+        let kernel = (
+            self.dilation.0 * (self.kernel_size.0 - 1) + 1,
+            self.dilation.1 * (self.kernel_size.1 - 1) + 1,
+        );
+        let padded = (height + 2 * self.padding.0, width + 2 * self.padding.1);
+        if padded.0 < kernel.0 || padded.1 < kernel.1 {
+            return Err(NnParseError::InputTooSmall {
+                input: (height, width),
+                kernel,
+            });
+        }
+
+        Ok((
+            (padded.0 - kernel.0) / self.stride.0 + 1,
+            (padded.1 - kernel.1) / self.stride.1 + 1,
+        ))
+    }
+
+    /// Unrolls the kernels into the matrix of an equivalent linear layer (im2col), every output channel is an output
+    /// and every element of a patch, ordered as (channel, kernel row, kernel column), is an input.
+    pub fn to_linear(&self) -> LinearLayer {
+        // The torch layout of the kernels is already the row major unrolled matrix
+        LinearLayer {
+            input_size: self.patch_size(),
+            output_size: self.out_channels,
+            weights: self.weights.clone(),
+            bias: self.bias.clone(),
+        }
+    }
+
+    /// Unrolls the patches of `input` (`in_channels` x `height` x `width`, row major) seen by the kernels, returns one
+    /// patch (the input of [`Conv2dLayer::to_linear`]) per output position, in row major order.
+    pub fn im2col(&self, input: &[f64], height: usize, width: usize) -> Result<Vec<Vec<f64>>, NnParseError> {
+        let (out_h, out_w) = self.output_size(height, width)?;
+        let (kh, kw) = self.kernel_size;
+
+        let mut patches = Vec::with_capacity(out_h * out_w);
+        for oy in 0..out_h {
+            for ox in 0..out_w {
+                let mut patch = Vec::with_capacity(self.patch_size());
+                for c in 0..self.in_channels {
+                    for ky in 0..kh {
+                        for kx in 0..kw {
+                            let y = (oy * self.stride.0 + ky * self.dilation.0) as isize
+                                - self.padding.0 as isize;
+                            let x = (ox * self.stride.1 + kx * self.dilation.1) as isize
+                                - self.padding.1 as isize;
+
+                            let inside = (0..height as isize).contains(&y)
+                                && (0..width as isize).contains(&x);
+                            patch.push(if inside {
+                                input[(c * height + y as usize) * width + x as usize]
+                            } else {
+                                0.0
+                            });
+                        }
+                    }
+                }
+                patches.push(patch);
+            }
+        }
+
+        Ok(patches)
+    }
+
+    /// Applies `y * scale[c] + shift[c]` to every output channel `c` (e.g. a folded batch normalization)
+    pub fn fold_affine(&mut self, scale: &[f64], shift: &[f64]) {
+        let patch_size = self.patch_size();
+        fold_affine(&mut self.weights, &mut self.bias, patch_size, scale, shift);
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PoolingKind {
+    Max,
+    Avg,
+}
+
+/// 2D pooling with the output size rounded down. Average pooling divides by the kernel size, counting the padding
+/// zeros (the torch default).
+#[derive(Debug, Clone)]
+pub struct Pool2dLayer {
+    pub kind: PoolingKind,
+    /// (height, width)
+    pub kernel_size: (usize, usize),
+    pub stride: (usize, usize),
+    pub padding: (usize, usize),
 }
 
 #[derive(Debug, Clone)]
 pub enum Layer {
     Linear(LinearLayer),
+    Conv2d(Conv2dLayer),
+    Pool2d(Pool2dLayer),
+    Flatten,
     Activation(Activation),
 }
 
@@ -25,3 +176,129 @@ pub enum Layer {
 pub struct Nn {
     pub layers: Vec<Layer>,
 }
+
+/// Scales the rows (one per output) of a row major weight matrix and updates the bias, creating it if needed
+fn fold_affine(
+    weights: &mut [f64],
+    bias: &mut Option<Vec<f64>>,
+    row_size: usize,
+    scale: &[f64],
+    shift: &[f64],
+) {
+    for (row, s) in weights.chunks_mut(row_size).zip(scale) {
+        row.iter_mut().for_each(|w| *w *= s);
+    }
+
+    let bias = bias.get_or_insert_with(|| vec![0.0; scale.len()]);
+    for ((b, s), t) in bias.iter_mut().zip(scale).zip(shift) {
+        *b = *b * s + t;
+    }
+}
+
+#[cfg(test)]
+mod types_test {
+    use super::*;
+
+    fn conv() -> Conv2dLayer {
+        // 2 output channels, 1 input channel, 2x2 kernels
+        Conv2dLayer {
+            in_channels: 1,
+            out_channels: 2,
+            kernel_size: (2, 2),
+            stride: (1, 1),
+            padding: (0, 0),
+            dilation: (1, 1),
+            weights: vec![1.0, 0.0, 0.0, 1.0, 0.0, 1.0, 1.0, 0.0],
+            bias: None,
+        }
+    }
+
+    #[test]
+    fn test_im2col_matches_convolution() {
+        let conv = conv();
+        let input: Vec<f64> = (0..9).map(|v| v as f64).collect();
+
+        assert_eq!(conv.output_size(3, 3).unwrap(), (2, 2));
+
+        let patches = conv.im2col(&input, 3, 3).unwrap();
+        assert_eq!(patches[1], vec![1.0, 2.0, 4.0, 5.0]);
+
+        let linear = conv.to_linear();
+        let outputs: Vec<Vec<f64>> = patches
+            .iter()
+            .map(|patch| {
+                (0..linear.output_size)
+                    .map(|o| (0..linear.input_size).map(|i| linear.weight(o, i) * patch[i]).sum())
+                    .collect()
+            })
+            .collect();
+
+        // Main and anti diagonals of every 2x2 window
+        assert_eq!(outputs, vec![vec![4.0, 4.0], vec![6.0, 6.0], vec![10.0, 10.0], vec![12.0, 12.0]]);
+
+        let padded = Conv2dLayer {
+            padding: (1, 1),
+            stride: (2, 2),
+            ..conv
+        };
+        assert_eq!(padded.output_size(3, 3).unwrap(), (2, 2));
+        assert_eq!(padded.im2col(&input, 3, 3).unwrap()[0], vec![0.0, 0.0, 0.0, 0.0]);
+    }
+
+    #[test]
+    fn test_invalid_output_size() {
+        let dilated = Conv2dLayer {
+            dilation: (2, 2),
+            ..conv()
+        };
+        assert!(matches!(
+            dilated.output_size(2, 2),
+            Err(NnParseError::InputTooSmall {
+                input: (2, 2),
+                kernel: (3, 3)
+            })
+        ));
+        assert_eq!(dilated.output_size(3, 3).unwrap(), (1, 1));
+
+        for invalid in [
+            Conv2dLayer {
+                kernel_size: (0, 2),
+                ..conv()
+            },
+            Conv2dLayer {
+                stride: (1, 0),
+                ..conv()
+            },
+            Conv2dLayer {
+                dilation: (0, 0),
+                ..conv()
+            },
+        ] {
+            assert!(matches!(
+                invalid.output_size(4, 4),
+                Err(NnParseError::InvalidLayerConfig(..))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_fold_affine() {
+        let mut linear = LinearLayer {
+            input_size: 2,
+            output_size: 2,
+            weights: vec![1.0, 2.0, 3.0, 4.0],
+            bias: None,
+        };
+        linear.fold_affine(&[2.0, 0.5], &[1.0, -1.0]);
+
+        assert_eq!(linear.weights, vec![2.0, 4.0, 1.5, 2.0]);
+        assert_eq!(linear.bias, Some(vec![1.0, -1.0]));
+
+        let mut conv = conv();
+        conv.bias = Some(vec![1.0, 1.0]);
+        conv.fold_affine(&[2.0, 3.0], &[0.0, 1.0]);
+
+        assert_eq!(conv.weights, vec![2.0, 0.0, 0.0, 2.0, 0.0, 3.0, 3.0, 0.0]);
+        assert_eq!(conv.bias, Some(vec![2.0, 4.0]));
+    }
+}
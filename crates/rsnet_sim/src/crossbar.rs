@@ -0,0 +1,397 @@
+use crate::{
+    mna::{solve_dc, Circuit, DcOptions, MnaError},
+    types::{ElementId, NodeId},
+};
+
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum CrossbarError {
+    #[error("Expected {expected} {what}, got {got}")]
+    DimensionMismatch {
+        what: &'static str,
+        expected: usize,
+        got: usize,
+    },
+    #[error("A crossbar needs at least one row and one column, got {rows}x{cols}")]
+    EmptyCrossbar { rows: usize, cols: usize },
+    #[error("Parasitic resistance {name} must be non negative and finite, got {value}")]
+    InvalidParasitic { name: &'static str, value: f64 },
+    #[error("There was an error while simulating the crossbar: {0}")]
+    SimulationError(MnaError),
+}
+
+impl From<MnaError> for CrossbarError {
+    fn from(value: MnaError) -> Self {
+        Self::SimulationError(value)
+    }
+}
+
+/// Wire parasitics of a crossbar. A zero resistance is an ideal connection.
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub struct CrossbarParasitics {
+    /// Resistance of the word/bit line segment between two adjacent cells. [Ohm]
+    pub wire_resistance: f64,
+    /// Output resistance of the drivers of the rows. [Ohm]
+    pub driver_resistance: f64,
+    /// Input resistance of the sense amplifiers at the bottom of the columns. [Ohm]
+    pub sense_resistance: f64,
+}
+
+impl CrossbarParasitics {
+    pub fn new(wire_resistance: f64, driver_resistance: f64, sense_resistance: f64) -> Self {
+        Self {
+            wire_resistance,
+            driver_resistance,
+            sense_resistance,
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), CrossbarError> {
+        for (name, value) in [
+            ("wire_resistance", self.wire_resistance),
+            ("driver_resistance", self.driver_resistance),
+            ("sense_resistance", self.sense_resistance),
+        ] {
+            if !(value.is_finite() && value >= 0.0) {
+                return Err(CrossbarError::InvalidParasitic { name, value });
+            }
+        }
+
+        Ok(())
+    }
+
+    pub fn is_ideal(&self) -> bool {
+        self.wire_resistance == 0.0 && self.driver_resistance == 0.0 && self.sense_resistance == 0.0
+    }
+}
+
+/// Output currents of a crossbar with parasitics compared to the ideal matrix-vector product
+#[derive(Debug, Clone, Default)]
+pub struct IrDropReport {
+    /// Ideal column currents, `sum_i(G[i][j] * V[i])`. [A]
+    pub ideal_currents: Vec<f64>,
+    /// Column currents measured at the sense amplifiers. [A]
+    pub currents: Vec<f64>,
+    /// `|I - I_ideal| / |I_ideal|` of every column. Columns whose ideal current is zero are normalized by the largest
+    /// ideal current of the crossbar instead.
+    pub relative_errors: Vec<f64>,
+    pub max_relative_error: f64,
+    pub mean_relative_error: f64,
+}
+
+impl IrDropReport {
+    /// Computes the error statistics of `currents` with respect to `ideal_currents`
+    pub fn new(ideal_currents: Vec<f64>, currents: Vec<f64>) -> Self {
+        let max_ideal = ideal_currents
+            .iter()
+            .fold(0.0f64, |acc, i| acc.max(i.abs()));
+
+        let relative_errors: Vec<f64> = ideal_currents
+            .iter()
+            .zip(currents.iter())
+            .map(|(ideal, actual)| {
+                let reference = if *ideal != 0.0 {
+                    ideal.abs()
+                } else {
+                    max_ideal
+                };
+                if reference == 0.0 {
+                    0.0
+                } else {
+                    (actual - ideal).abs() / reference
+                }
+            })
+            .collect();
+
+        let max_relative_error = relative_errors.iter().fold(0.0f64, |acc, e| acc.max(*e));
+        let mean_relative_error = if relative_errors.is_empty() {
+            0.0
+        } else {
+            relative_errors.iter().sum::<f64>() / relative_errors.len() as f64
+        };
+
+        IrDropReport {
+            ideal_currents,
+            currents,
+            relative_errors,
+            max_relative_error,
+            mean_relative_error,
+        }
+    }
+}
+
+/// Crossbar of `rows` x `cols` memristors, inputs are applied to the rows (word lines) and the outputs are the currents
+/// collected at the bottom of the columns (bit lines), which are held at virtual ground.
+///
+/// Conductances are stored column major (`conductances[row + col * rows]`), the same order used for the component ids
+/// of the crossbar construct in the scene.
+#[derive(Debug, Clone)]
+pub struct CrossbarCircuit {
+    rows: usize,
+    cols: usize,
+    conductances: Vec<f64>,
+    parasitics: CrossbarParasitics,
+}
+
+impl CrossbarCircuit {
+    pub fn new(
+        rows: usize,
+        cols: usize,
+        conductances: Vec<f64>,
+        parasitics: CrossbarParasitics,
+    ) -> Result<Self, CrossbarError> {
+        if rows == 0 || cols == 0 {
+            return Err(CrossbarError::EmptyCrossbar { rows, cols });
+        }
+        if conductances.len() != rows * cols {
+            return Err(CrossbarError::DimensionMismatch {
+                what: "conductances",
+                expected: rows * cols,
+                got: conductances.len(),
+            });
+        }
+        parasitics.validate()?;
+
+        Ok(Self {
+            rows,
+            cols,
+            conductances,
+            parasitics,
+        })
+    }
+
+    pub fn rows(&self) -> usize {
+        self.rows
+    }
+
+    pub fn cols(&self) -> usize {
+        self.cols
+    }
+
+    pub fn conductance(&self, row: usize, col: usize) -> f64 {
+        self.conductances[row + col * self.rows]
+    }
+
+    pub fn parasitics(&self) -> &CrossbarParasitics {
+        &self.parasitics
+    }
+
+    /// Ideal matrix-vector product of the crossbar for the row voltages `inputs`. [A]
+    pub fn ideal_currents(&self, inputs: &[f64]) -> Result<Vec<f64>, CrossbarError> {
+        self.check_inputs(inputs)?;
+
+        Ok((0..self.cols)
+            .map(|col| {
+                inputs
+                    .iter()
+                    .enumerate()
+                    .map(|(row, v)| self.conductance(row, col) * v)
+                    .sum()
+            })
+            .collect())
+    }
+
+    /// Column currents with the wire parasitics, solving the full resistive network. [A]
+    pub fn currents(&self, inputs: &[f64]) -> Result<Vec<f64>, CrossbarError> {
+        self.check_inputs(inputs)?;
+
+        let (circuit, sense_elements) = self.to_circuit(inputs);
+        let op = solve_dc(&circuit, &DcOptions::default())?;
+
+        Ok(sense_elements
+            .into_iter()
+            .map(|id| op.current(id).unwrap_or(0.0))
+            .collect())
+    }
+
+    /// Compares the column currents of the crossbar with parasitics against the ideal ones
+    pub fn ir_drop(&self, inputs: &[f64]) -> Result<IrDropReport, CrossbarError> {
+        let ideal_currents = self.ideal_currents(inputs)?;
+        let currents = self.currents(inputs)?;
+
+        Ok(IrDropReport::new(ideal_currents, currents))
+    }
+
+    fn check_inputs(&self, inputs: &[f64]) -> Result<(), CrossbarError> {
+        if inputs.len() != self.rows {
+            return Err(CrossbarError::DimensionMismatch {
+                what: "inputs",
+                expected: self.rows,
+                got: inputs.len(),
+            });
+        }
+
+        Ok(())
+    }
+
+    /// Builds the resistive network of the crossbar, returns it with the ids of the elements whose current is the
+    /// output of each column.
+    ///
+    /// The cell at (`row`, `col`) connects its own word line node to its own bit line node, adjacent nodes of the same
+    /// line are connected by a wire segment. Drivers are on the left of the word lines and the sense amplifiers at the
+    /// bottom (last row) of the bit lines.
+    fn to_circuit(&self, inputs: &[f64]) -> (Circuit, Vec<ElementId>) {
+        let (rows, cols) = (self.rows, self.cols);
+        let p = &self.parasitics;
+
+        let ground: NodeId = 0;
+        let word_line = |row: usize, col: usize| -> NodeId { 1 + row * cols + col };
+        let bit_line = |row: usize, col: usize| -> NodeId { 1 + rows * cols + row * cols + col };
+        let driver = |row: usize| -> NodeId { 1 + 2 * rows * cols + row };
+
+        let mut circuit = Circuit::new(ground);
+        let mut next_id: ElementId = 0;
+        let mut next_id = move || {
+            let id = next_id;
+            next_id += 1;
+            id
+        };
+
+        for (row, v) in inputs.iter().enumerate() {
+            circuit.add_voltage_source(next_id(), driver(row), ground, *v);
+            add_series(
+                &mut circuit,
+                next_id(),
+                driver(row),
+                word_line(row, 0),
+                p.driver_resistance,
+            );
+
+            for col in 1..cols {
+                add_series(
+                    &mut circuit,
+                    next_id(),
+                    word_line(row, col - 1),
+                    word_line(row, col),
+                    p.wire_resistance,
+                );
+            }
+        }
+
+        let mut sense_elements = Vec::with_capacity(cols);
+        for col in 0..cols {
+            for row in 1..rows {
+                add_series(
+                    &mut circuit,
+                    next_id(),
+                    bit_line(row - 1, col),
+                    bit_line(row, col),
+                    p.wire_resistance,
+                );
+            }
+
+            let sense = next_id();
+            add_series(
+                &mut circuit,
+                sense,
+                bit_line(rows - 1, col),
+                ground,
+                p.sense_resistance,
+            );
+            sense_elements.push(sense);
+
+            for row in 0..rows {
+                circuit.add_memristor(
+                    next_id(),
+                    word_line(row, col),
+                    bit_line(row, col),
+                    self.conductance(row, col),
+                );
+            }
+        }
+
+        (circuit, sense_elements)
+    }
+}
+
+/// Adds a resistor between `pos` and `neg`, a zero volt source if the resistance is zero
+fn add_series(circuit: &mut Circuit, id: ElementId, pos: NodeId, neg: NodeId, resistance: f64) {
+    if resistance > 0.0 {
+        circuit.add_resistor(id, pos, neg, resistance);
+    } else {
+        circuit.add_voltage_source(id, pos, neg, 0.0);
+    }
+}
+
+#[cfg(test)]
+mod crossbar_test {
+    use super::*;
+
+    #[test]
+    fn test_ideal_crossbar_matches_mvm() {
+        let conductances = vec![1e-4, 3e-4, 2e-4, 4e-4];
+        let crossbar =
+            CrossbarCircuit::new(2, 2, conductances, CrossbarParasitics::default()).unwrap();
+
+        let report = crossbar.ir_drop(&[0.1, 0.2]).unwrap();
+
+        assert_eq!(report.ideal_currents.len(), 2);
+        assert!((report.ideal_currents[0] - 7e-5).abs() < 1e-15);
+        assert!((report.ideal_currents[1] - 1e-4).abs() < 1e-15);
+        assert!(report.max_relative_error < 1e-6);
+    }
+
+    #[test]
+    fn test_single_cell_series_resistance() {
+        let parasitics = CrossbarParasitics::new(5.0, 100.0, 50.0);
+        let crossbar = CrossbarCircuit::new(1, 1, vec![1e-3], parasitics).unwrap();
+
+        // A single cell has no wire segments, only the driver and sense resistances are in series
+        let currents = crossbar.currents(&[1.0]).unwrap();
+        let expected = 1.0 / (100.0 + 1e3 + 50.0);
+        assert!((currents[0] - expected).abs() < 1e-6 * expected);
+    }
+
+    #[test]
+    fn test_ir_drop_grows_with_wire_resistance() {
+        let (rows, cols) = (16, 16);
+        let conductances = vec![1e-4; rows * cols];
+        let inputs = vec![0.2; rows];
+
+        let errors: Vec<f64> = [1.0, 5.0]
+            .into_iter()
+            .map(|wire_resistance| {
+                let parasitics = CrossbarParasitics::new(wire_resistance, 0.0, 0.0);
+                CrossbarCircuit::new(rows, cols, conductances.clone(), parasitics)
+                    .unwrap()
+                    .ir_drop(&inputs)
+                    .unwrap()
+            })
+            .map(|report| {
+                // Every output loses current and the farthest column is the worst
+                assert!(report
+                    .currents
+                    .iter()
+                    .zip(report.ideal_currents.iter())
+                    .all(|(actual, ideal)| actual < ideal));
+                assert_eq!(
+                    report.max_relative_error,
+                    *report.relative_errors.last().unwrap()
+                );
+                report.max_relative_error
+            })
+            .collect();
+
+        assert!(errors[0] > 0.0);
+        assert!(errors[1] > errors[0]);
+    }
+
+    #[test]
+    fn test_dimension_mismatch() {
+        assert!(matches!(
+            CrossbarCircuit::new(2, 2, vec![1e-4; 3], CrossbarParasitics::default()),
+            Err(CrossbarError::DimensionMismatch { .. })
+        ));
+    }
+
+    #[test]
+    fn test_empty_crossbar() {
+        for (rows, cols) in [(0, 2), (2, 0), (0, 0)] {
+            assert!(matches!(
+                CrossbarCircuit::new(rows, cols, vec![], CrossbarParasitics::default()),
+                Err(CrossbarError::EmptyCrossbar { .. })
+            ));
+        }
+    }
+}
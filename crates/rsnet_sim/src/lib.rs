@@ -0,0 +1,17 @@
+pub mod crossbar;
+pub mod mapping;
+pub mod mna;
+pub mod range;
+pub mod spice;
+pub mod stanford;
+pub mod tiling;
+pub mod types;
+
+pub use crossbar::{CrossbarCircuit, CrossbarError, CrossbarParasitics, IrDropReport};
+pub use mapping::{ConductanceMapping, MappedLayer, MappingError};
+pub use range::{Range, RangeType};
+pub use spice::{
+    parse_spice, MemristorModel, SpiceAnalysis, SpiceError, SpiceNetlist, SpiceOptions, SpiceWriter,
+};
+pub use stanford::{StanfordModel, StanfordModelError, StanfordModelParams, StanfordStep};
+pub use tiling::{Tile, TilingConfig, TilingError, TilingPlan};
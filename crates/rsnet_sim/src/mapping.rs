@@ -0,0 +1,269 @@
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum MappingError {
+    #[error("Invalid conductance range [{0}, {1}], it must satisfy 0 <= G_min < G_max")]
+    InvalidConductanceRange(f64, f64),
+    #[error("At least 2 conductance levels are needed, got {0}")]
+    InvalidLevels(u32),
+    #[error("Expected {expected} {what}, got {got}")]
+    DimensionMismatch {
+        what: &'static str,
+        expected: usize,
+        got: usize,
+    },
+    #[error("Weight {0} is not finite")]
+    NonFiniteWeight(usize),
+}
+
+/// How the weights of a layer are programmed as memristor conductances.
+///
+/// With differential pairs every weight uses two devices and `w = (G+ - G-) / scale`, one of them is left at `g_min`.
+/// Otherwise every weight uses a single device and the weights are shifted so that the smallest one maps to `g_min`,
+/// `w = (G - g_min) / scale + offset`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ConductanceMapping {
+    /// [S]
+    pub g_min: f64,
+    /// [S]
+    pub g_max: f64,
+    pub differential: bool,
+    /// Number of evenly spaced conductance levels in `[g_min, g_max]` the devices can be programmed to, `None` for
+    /// analog (continuous) programming.
+    pub levels: Option<u32>,
+}
+
+impl Default for ConductanceMapping {
+    fn default() -> Self {
+        Self {
+            g_min: 1e-6,
+            g_max: 1e-4,
+            differential: true,
+            levels: None,
+        }
+    }
+}
+
+/// Conductances of a crossbar that implements a linear layer, see [`ConductanceMapping::map_linear`]
+#[derive(Debug, Clone, Default)]
+pub struct MappedLayer {
+    pub rows: usize,
+    pub cols: usize,
+    /// Column major, `conductances[row + col * rows]`. [S]
+    pub conductances: Vec<f64>,
+    /// Conductance per unit of weight. [S]
+    pub scale: f64,
+    /// Weight represented by `g_min` when not using differential pairs.
+    pub offset: f64,
+}
+
+impl MappedLayer {
+    pub fn conductance(&self, row: usize, col: usize) -> f64 {
+        self.conductances[row + col * self.rows]
+    }
+}
+
+impl ConductanceMapping {
+    pub fn new(g_min: f64, g_max: f64, differential: bool, levels: Option<u32>) -> Self {
+        Self {
+            g_min,
+            g_max,
+            differential,
+            levels,
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), MappingError> {
+        if !(self.g_min.is_finite()
+            && self.g_max.is_finite()
+            && 0.0 <= self.g_min
+            && self.g_min < self.g_max)
+        {
+            return Err(MappingError::InvalidConductanceRange(
+                self.g_min, self.g_max,
+            ));
+        }
+
+        match self.levels {
+            Some(levels) if levels < 2 => Err(MappingError::InvalidLevels(levels)),
+            _ => Ok(()),
+        }
+    }
+
+    /// Devices used by every weight
+    pub fn devices_per_weight(&self) -> usize {
+        if self.differential {
+            2
+        } else {
+            1
+        }
+    }
+
+    /// Clamps `g` to `[g_min, g_max]` and rounds it to the closest programmable level
+    pub fn quantize(&self, g: f64) -> f64 {
+        let g = g.clamp(self.g_min, self.g_max);
+
+        match self.levels {
+            Some(levels) => {
+                let step = (self.g_max - self.g_min) / (levels - 1) as f64;
+                self.g_min + ((g - self.g_min) / step).round() * step
+            }
+            None => g,
+        }
+    }
+
+    /// Maps the weights of a linear layer to the conductances of a crossbar.
+    ///
+    /// `weights` are in row major order with shape `outputs` x `inputs` (as in torch). Every input drives a row and
+    /// the bias, if any, is an extra row at the bottom that must be driven with the same voltage that represents a
+    /// unit input. Every output is a column, or two adjacent columns (G+ then G-) with differential pairs.
+    pub fn map_linear(
+        &self,
+        inputs: usize,
+        outputs: usize,
+        weights: &[f64],
+        bias: Option<&[f64]>,
+    ) -> Result<MappedLayer, MappingError> {
+        self.validate()?;
+
+        if weights.len() != inputs * outputs {
+            return Err(MappingError::DimensionMismatch {
+                what: "weights",
+                expected: inputs * outputs,
+                got: weights.len(),
+            });
+        }
+        if let Some(bias) = bias {
+            if bias.len() != outputs {
+                return Err(MappingError::DimensionMismatch {
+                    what: "bias values",
+                    expected: outputs,
+                    got: bias.len(),
+                });
+            }
+        }
+
+        let all_weights = weights.iter().chain(bias.into_iter().flatten());
+        let (mut w_min, mut w_max) = (f64::INFINITY, f64::NEG_INFINITY);
+        for (i, w) in all_weights.enumerate() {
+            if !w.is_finite() {
+                return Err(MappingError::NonFiniteWeight(i));
+            }
+            w_min = w_min.min(*w);
+            w_max = w_max.max(*w);
+        }
+
+        let g_range = self.g_max - self.g_min;
+        let (scale, offset) = if self.differential {
+            let w_abs = w_min.abs().max(w_max.abs());
+            (
+                if w_abs > 0.0 {
+                    g_range / w_abs
+                } else {
+                    g_range
+                },
+                0.0,
+            )
+        } else if w_max > w_min {
+            (g_range / (w_max - w_min), w_min)
+        } else {
+            (g_range, w_min.min(0.0))
+        };
+
+        let rows = inputs + bias.map_or(0, |_| 1);
+        let cols = outputs * self.devices_per_weight();
+        let mut conductances = vec![self.g_min; rows * cols];
+
+        for output in 0..outputs {
+            for row in 0..rows {
+                let w = match (row < inputs, bias) {
+                    (true, _) => weights[output * inputs + row],
+                    (false, Some(bias)) => bias[output],
+                    (false, None) => unreachable!(),
+                };
+
+                if self.differential {
+                    let g = self.quantize(self.g_min + w.abs() * scale);
+                    let col = 2 * output + if w >= 0.0 { 0 } else { 1 };
+                    conductances[row + col * rows] = g;
+                } else {
+                    conductances[row + output * rows] =
+                        self.quantize(self.g_min + (w - offset) * scale);
+                }
+            }
+        }
+
+        Ok(MappedLayer {
+            rows,
+            cols,
+            conductances,
+            scale,
+            offset,
+        })
+    }
+}
+
+#[cfg(test)]
+mod mapping_test {
+    use super::*;
+
+    #[test]
+    fn test_differential_mapping() {
+        let mapping = ConductanceMapping::new(1e-6, 1e-4, true, None);
+        // 2 outputs x 3 inputs
+        let weights = [0.5, -1.0, 0.0, 0.25, 1.0, -0.5];
+        let bias = [0.1, -0.2];
+
+        let mapped = mapping.map_linear(3, 2, &weights, Some(&bias)).unwrap();
+
+        assert_eq!((mapped.rows, mapped.cols), (4, 4));
+        for output in 0..2 {
+            for row in 0..4 {
+                let w = if row < 3 {
+                    weights[output * 3 + row]
+                } else {
+                    bias[output]
+                };
+                let g_pos = mapped.conductance(row, 2 * output);
+                let g_neg = mapped.conductance(row, 2 * output + 1);
+
+                assert!((g_pos - g_neg - w * mapped.scale).abs() < 1e-15);
+                assert!(g_pos.min(g_neg) == 1e-6);
+            }
+        }
+        // The largest weight uses the whole range
+        assert!((mapped.conductance(1, 1) - 1e-4).abs() < 1e-15);
+    }
+
+    #[test]
+    fn test_single_device_quantized_mapping() {
+        let mapping = ConductanceMapping::new(0.0, 1e-4, false, Some(5));
+        let weights = [-1.0, 0.0, 0.3, 1.0];
+
+        let mapped = mapping.map_linear(4, 1, &weights, None).unwrap();
+
+        assert_eq!((mapped.rows, mapped.cols), (4, 1));
+        assert_eq!(mapped.offset, -1.0);
+        let expected = [0.0, 0.5e-4, 0.75e-4, 1e-4];
+        for (row, g) in expected.iter().enumerate() {
+            assert!((mapped.conductance(row, 0) - g).abs() < 1e-15);
+        }
+    }
+
+    #[test]
+    fn test_invalid_mapping() {
+        let weights = [1.0; 4];
+        assert!(matches!(
+            ConductanceMapping::new(1e-4, 1e-6, true, None).map_linear(2, 2, &weights, None),
+            Err(MappingError::InvalidConductanceRange(..))
+        ));
+        assert!(matches!(
+            ConductanceMapping::new(1e-6, 1e-4, true, Some(1)).map_linear(2, 2, &weights, None),
+            Err(MappingError::InvalidLevels(1))
+        ));
+        assert!(matches!(
+            ConductanceMapping::default().map_linear(2, 3, &weights, None),
+            Err(MappingError::DimensionMismatch { .. })
+        ));
+    }
+}
@@ -0,0 +1,231 @@
+use super::MnaError;
+
+use crate::types::{ElementId, NodeId};
+
+use std::collections::{HashMap, HashSet};
+
+/// Thermal voltage at 300 K. [V]
+pub const THERMAL_VOLTAGE: f64 = 0.025852;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ElementKind {
+    /// Linear resistor. [Ohm]
+    Resistor(f64),
+    /// Memristor frozen at a given conductance. [S]
+    Memristor(f64),
+    /// Ideal voltage source, `v(pos) - v(neg) = voltage`. [V]
+    VoltageSource(f64),
+    /// Ideal current source, the current flows from `pos` to `neg` through the source. [A]
+    CurrentSource(f64),
+    /// Junction diode from `pos` (anode) to `neg` (cathode), `i = saturation_current * (exp(v / (ideality * Vt)) - 1)`
+    Diode {
+        /// [A]
+        saturation_current: f64,
+        ideality: f64,
+    },
+    /// Op-amp with a finite open loop `gain`, the output is `pos` referred to `neg`:
+    /// `v(pos) - v(neg) = gain * (v(non_inverting) - v(inverting))`
+    OpAmp {
+        gain: f64,
+        non_inverting: NodeId,
+        inverting: NodeId,
+    },
+}
+
+impl ElementKind {
+    /// Whether the I-V relation of the element is nonlinear, such elements are solved with Newton iterations
+    pub fn is_nonlinear(&self) -> bool {
+        matches!(self, ElementKind::Diode { .. })
+    }
+
+    /// Whether the element adds its branch current as an unknown of the MNA system
+    pub fn has_branch(&self) -> bool {
+        matches!(self, ElementKind::VoltageSource(_) | ElementKind::OpAmp { .. })
+    }
+}
+
+/// A two terminal circuit element. Branch currents are reported flowing from `pos` to `neg` through the element.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Element {
+    pub id: ElementId,
+    pub kind: ElementKind,
+    pub pos: NodeId,
+    pub neg: NodeId,
+}
+
+impl Element {
+    /// Every node the element depends on, including the control nodes of op-amps
+    pub fn nodes(&self) -> Vec<NodeId> {
+        match self.kind {
+            ElementKind::OpAmp {
+                non_inverting,
+                inverting,
+                ..
+            } => vec![self.pos, self.neg, non_inverting, inverting],
+            _ => vec![self.pos, self.neg],
+        }
+    }
+}
+
+/// Flat description of a circuit for the MNA solvers, nodes are identified by the netlist node ids.
+#[derive(Debug, Clone)]
+pub struct Circuit {
+    ground: NodeId,
+    elements: Vec<Element>,
+}
+
+impl Circuit {
+    pub fn new(ground: NodeId) -> Self {
+        Circuit {
+            ground,
+            elements: Vec::new(),
+        }
+    }
+
+    pub fn ground(&self) -> NodeId {
+        self.ground
+    }
+
+    pub fn elements(&self) -> &Vec<Element> {
+        &self.elements
+    }
+
+    pub fn add_element(&mut self, id: ElementId, kind: ElementKind, pos: NodeId, neg: NodeId) {
+        self.elements.push(Element { id, kind, pos, neg });
+    }
+
+    pub fn add_resistor(&mut self, id: ElementId, pos: NodeId, neg: NodeId, resistance: f64) {
+        self.add_element(id, ElementKind::Resistor(resistance), pos, neg);
+    }
+
+    pub fn add_memristor(&mut self, id: ElementId, pos: NodeId, neg: NodeId, conductance: f64) {
+        self.add_element(id, ElementKind::Memristor(conductance), pos, neg);
+    }
+
+    pub fn add_voltage_source(&mut self, id: ElementId, pos: NodeId, neg: NodeId, voltage: f64) {
+        self.add_element(id, ElementKind::VoltageSource(voltage), pos, neg);
+    }
+
+    pub fn add_current_source(&mut self, id: ElementId, pos: NodeId, neg: NodeId, current: f64) {
+        self.add_element(id, ElementKind::CurrentSource(current), pos, neg);
+    }
+
+    pub fn add_diode(
+        &mut self,
+        id: ElementId,
+        anode: NodeId,
+        cathode: NodeId,
+        saturation_current: f64,
+        ideality: f64,
+    ) {
+        let kind = ElementKind::Diode {
+            saturation_current,
+            ideality,
+        };
+        self.add_element(id, kind, anode, cathode);
+    }
+
+    /// Adds an op-amp whose output is referred to ground
+    pub fn add_op_amp(
+        &mut self,
+        id: ElementId,
+        non_inverting: NodeId,
+        inverting: NodeId,
+        output: NodeId,
+        gain: f64,
+    ) {
+        let kind = ElementKind::OpAmp {
+            gain,
+            non_inverting,
+            inverting,
+        };
+        self.add_element(id, kind, output, self.ground);
+    }
+
+    /// Whether any element needs Newton iterations to be solved
+    pub fn is_nonlinear(&self) -> bool {
+        self.elements.iter().any(|element| element.kind.is_nonlinear())
+    }
+
+    /// Assigns a row of the MNA matrix to every node other than ground and to every voltage source and op-amp.
+    /// Element ids need to be unique, the currents are reported by id.
+    pub fn index(&self) -> Result<CircuitIndex, MnaError> {
+        let mut nodes = HashMap::new();
+        let mut branches = HashMap::new();
+        let mut ids = HashSet::new();
+
+        for element in self.elements.iter() {
+            validate_element(element)?;
+            if !ids.insert(element.id) {
+                return Err(MnaError::DuplicateElement(element.id));
+            }
+
+            for node in element.nodes() {
+                if node != self.ground && !nodes.contains_key(&node) {
+                    nodes.insert(node, nodes.len());
+                }
+            }
+        }
+
+        for element in self.elements.iter() {
+            if element.kind.has_branch() {
+                branches.insert(element.id, nodes.len() + branches.len());
+            }
+        }
+
+        Ok(CircuitIndex { nodes, branches })
+    }
+}
+
+fn validate_element(element: &Element) -> Result<(), MnaError> {
+    let invalid = |reason: &str| Err(MnaError::InvalidElement(element.id, reason.to_string()));
+
+    match element.kind {
+        ElementKind::Resistor(r) if !(r.is_finite() && r > 0.0) => {
+            invalid(&format!("resistance must be positive and finite, got {}", r))
+        }
+        ElementKind::Memristor(g) if !(g.is_finite() && g >= 0.0) => {
+            invalid(&format!("conductance must be non negative and finite, got {}", g))
+        }
+        ElementKind::VoltageSource(v) | ElementKind::CurrentSource(v) if !v.is_finite() => {
+            invalid(&format!("source value must be finite, got {}", v))
+        }
+        ElementKind::Diode {
+            saturation_current,
+            ideality,
+        } if !(saturation_current.is_finite()
+            && saturation_current > 0.0
+            && ideality.is_finite()
+            && ideality > 0.0) =>
+        {
+            invalid(&format!(
+                "saturation current and ideality must be positive and finite, got {} and {}",
+                saturation_current, ideality
+            ))
+        }
+        ElementKind::OpAmp { gain, .. } if !(gain.is_finite() && gain > 0.0) => {
+            invalid(&format!("gain must be positive and finite, got {}", gain))
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Maps the nodes and voltage source branches of a [`Circuit`] to rows of the MNA system.
+#[derive(Debug, Clone, Default)]
+pub struct CircuitIndex {
+    /// Row of each (non ground) node
+    pub nodes: HashMap<NodeId, usize>,
+    /// Row of the branch current of each voltage source and op-amp
+    pub branches: HashMap<ElementId, usize>,
+}
+
+impl CircuitIndex {
+    pub fn size(&self) -> usize {
+        self.nodes.len() + self.branches.len()
+    }
+
+    /// Row of `node`, `None` for ground
+    pub fn node(&self, node: NodeId) -> Option<usize> {
+        self.nodes.get(&node).copied()
+    }
+}
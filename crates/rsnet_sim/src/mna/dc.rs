@@ -0,0 +1,331 @@
+use super::{Circuit, CircuitIndex, Element, ElementKind, MnaError, MnaSystem, THERMAL_VOLTAGE};
+
+use crate::types::{ElementId, NodeId};
+
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, Copy)]
+pub struct DcOptions {
+    /// Conductance added from every node to ground so that floating nodes do not make the system singular. [S]
+    pub gmin: f64,
+    /// Newton iterations allowed for circuits with nonlinear elements
+    pub max_newton_iterations: usize,
+    /// Absolute tolerance of the Newton updates. [V] or [A]
+    pub abstol: f64,
+    /// Relative tolerance of the Newton updates
+    pub reltol: f64,
+}
+
+impl Default for DcOptions {
+    fn default() -> Self {
+        Self {
+            gmin: 1e-12,
+            max_newton_iterations: 200,
+            abstol: 1e-9,
+            reltol: 1e-6,
+        }
+    }
+}
+
+/// Result of a DC analysis
+#[derive(Debug, Clone, Default)]
+pub struct OperatingPoint {
+    /// Voltage of every node with respect to ground (ground included). [V]
+    pub node_voltages: HashMap<NodeId, f64>,
+    /// Current through every element, flowing from its `pos` to its `neg` node. [A]
+    pub branch_currents: HashMap<ElementId, f64>,
+}
+
+impl OperatingPoint {
+    pub fn voltage(&self, node: NodeId) -> Option<f64> {
+        self.node_voltages.get(&node).copied()
+    }
+
+    pub fn current(&self, element: ElementId) -> Option<f64> {
+        self.branch_currents.get(&element).copied()
+    }
+}
+
+/// Stamps every linear element of `circuit` into `system`
+pub fn stamp_linear(circuit: &Circuit, index: &CircuitIndex, system: &mut MnaSystem, gmin: f64) {
+    for row in index.nodes.values() {
+        system.add(*row, *row, gmin);
+    }
+
+    for element in circuit.elements() {
+        stamp_element(element, index, system);
+    }
+}
+
+/// Stamps a single element into `system`, nonlinear elements are linearized at 0 V (see [`stamp_nonlinear`])
+pub fn stamp_element(element: &Element, index: &CircuitIndex, system: &mut MnaSystem) {
+    let (a, b) = (index.node(element.pos), index.node(element.neg));
+
+    match element.kind {
+        ElementKind::Resistor(r) => system.stamp_conductance(a, b, 1.0 / r),
+        ElementKind::Memristor(g) => system.stamp_conductance(a, b, g),
+        ElementKind::CurrentSource(i) => system.stamp_current_source(a, b, i),
+        ElementKind::VoltageSource(v) => {
+            system.stamp_voltage_source(index.branches[&element.id], a, b, v)
+        }
+        ElementKind::Diode {
+            saturation_current,
+            ideality,
+        } => {
+            let (_, g) = diode_current(0.0, saturation_current, ideality);
+            system.stamp_conductance(a, b, g);
+        }
+        ElementKind::OpAmp {
+            gain,
+            non_inverting,
+            inverting,
+        } => {
+            let branch = index.branches[&element.id];
+            system.stamp_voltage_source(branch, a, b, 0.0);
+            if let Some(row) = index.node(non_inverting) {
+                system.add(branch, row, -gain);
+            }
+            if let Some(row) = index.node(inverting) {
+                system.add(branch, row, gain);
+            }
+        }
+    }
+}
+
+/// Stamps the companion model of a nonlinear element linearized at the solution `x`. Returns `false`, stamping
+/// nothing, if the element is linear.
+pub fn stamp_nonlinear(
+    element: &Element,
+    index: &CircuitIndex,
+    system: &mut MnaSystem,
+    x: &[f64],
+) -> bool {
+    let (a, b) = (index.node(element.pos), index.node(element.neg));
+
+    match element.kind {
+        ElementKind::Diode {
+            saturation_current,
+            ideality,
+        } => {
+            // Companion model: i = g_eq * v + i_eq
+            let v = a.map_or(0.0, |row| x[row]) - b.map_or(0.0, |row| x[row]);
+            let (i, g_eq) = diode_current(v, saturation_current, ideality);
+            system.stamp_conductance(a, b, g_eq);
+            system.stamp_current_source(a, b, i - g_eq * v);
+            true
+        }
+        _ => false,
+    }
+}
+
+/// Current and conductance of a diode biased at `v`. Above the critical voltage (where the exponential makes Newton
+/// iterations diverge) the characteristic continues linearly.
+pub fn diode_current(v: f64, saturation_current: f64, ideality: f64) -> (f64, f64) {
+    let vt = ideality * THERMAL_VOLTAGE;
+    let v_crit = vt * (vt / (std::f64::consts::SQRT_2 * saturation_current)).ln();
+
+    let exp = (v.min(v_crit) / vt).exp();
+    let (i, g) = (saturation_current * (exp - 1.0), saturation_current * exp / vt);
+
+    if v > v_crit {
+        (i + g * (v - v_crit), g)
+    } else {
+        (i, g)
+    }
+}
+
+/// Builds the operating point from the solution vector of the MNA system
+pub fn operating_point(circuit: &Circuit, index: &CircuitIndex, x: &[f64]) -> OperatingPoint {
+    let mut node_voltages: HashMap<NodeId, f64> =
+        index.nodes.iter().map(|(node, row)| (*node, x[*row])).collect();
+    node_voltages.insert(circuit.ground(), 0.0);
+
+    let branch_currents = circuit
+        .elements()
+        .iter()
+        .map(|element| {
+            let v = node_voltages[&element.pos] - node_voltages[&element.neg];
+            let i = match element.kind {
+                ElementKind::Resistor(r) => v / r,
+                ElementKind::Memristor(g) => v * g,
+                ElementKind::CurrentSource(i) => i,
+                ElementKind::Diode {
+                    saturation_current,
+                    ideality,
+                } => diode_current(v, saturation_current, ideality).0,
+                // The MNA unknown is the current entering the source at `pos`
+                ElementKind::VoltageSource(_) | ElementKind::OpAmp { .. } => {
+                    x[index.branches[&element.id]]
+                }
+            };
+            (element.id, i)
+        })
+        .collect();
+
+    OperatingPoint {
+        node_voltages,
+        branch_currents,
+    }
+}
+
+/// Computes the DC operating point of a circuit, using Newton iterations if it has nonlinear elements
+pub fn solve_dc(circuit: &Circuit, options: &DcOptions) -> Result<OperatingPoint, MnaError> {
+    let index = circuit.index()?;
+
+    if !circuit.is_nonlinear() {
+        let mut system = MnaSystem::new(index.size());
+        stamp_linear(circuit, &index, &mut system, options.gmin);
+
+        let x = system.solve()?;
+        return Ok(operating_point(circuit, &index, &x));
+    }
+
+    let mut x = vec![0.0; index.size()];
+    for _ in 0..options.max_newton_iterations {
+        let mut system = MnaSystem::new(index.size());
+        for row in index.nodes.values() {
+            system.add(*row, *row, options.gmin);
+        }
+        for element in circuit.elements() {
+            if !stamp_nonlinear(element, &index, &mut system, &x) {
+                stamp_element(element, &index, &mut system);
+            }
+        }
+
+        let x_new = system.solve()?;
+        let converged = x
+            .iter()
+            .zip(x_new.iter())
+            .all(|(x_i, x_new_i)| (x_new_i - x_i).abs() <= options.abstol + options.reltol * x_new_i.abs());
+        x = x_new;
+
+        if converged {
+            return Ok(operating_point(circuit, &index, &x));
+        }
+    }
+
+    Err(MnaError::NoConvergence(0.0))
+}
+
+#[cfg(test)]
+mod dc_test {
+    use super::*;
+
+    /// gmin introduces a small error, so values are compared with a relative tolerance
+    fn assert_close(a: f64, b: f64) {
+        assert!((a - b).abs() <= 1e-6 * b.abs().max(1e-9), "{} != {}", a, b);
+    }
+
+    #[test]
+    fn test_voltage_divider() {
+        let mut circuit = Circuit::new(0);
+        circuit.add_voltage_source(0, 1, 0, 1.0);
+        circuit.add_resistor(1, 1, 2, 1e3);
+        circuit.add_resistor(2, 2, 0, 3e3);
+
+        let op = solve_dc(&circuit, &DcOptions::default()).unwrap();
+
+        assert_close(op.voltage(1).unwrap(), 1.0);
+        assert_close(op.voltage(2).unwrap(), 0.75);
+        assert_close(op.current(1).unwrap(), 0.25e-3);
+        // The source delivers the current, so it flows from `neg` to `pos` through it
+        assert_close(op.current(0).unwrap(), -0.25e-3);
+    }
+
+    #[test]
+    fn test_crossbar_column_currents() {
+        // 2x2 crossbar with grounded columns, each column current is the dot product of inputs and conductances
+        let g = [[1e-4, 2e-4], [3e-4, 4e-4]];
+        let v = [0.1, 0.2];
+
+        let mut circuit = Circuit::new(0);
+        let rows = [1, 2];
+        let cols = [3, 4];
+        for (i, row) in rows.iter().enumerate() {
+            circuit.add_voltage_source(10 + i as u32, *row, 0, v[i]);
+        }
+        for (j, col) in cols.iter().enumerate() {
+            // Zero volt sources act as ammeters that keep the columns at virtual ground
+            circuit.add_voltage_source(20 + j as u32, *col, 0, 0.0);
+        }
+        for i in 0..2 {
+            for j in 0..2 {
+                circuit.add_memristor((i * 2 + j) as u32, rows[i], cols[j], g[i][j]);
+            }
+        }
+
+        let op = solve_dc(&circuit, &DcOptions::default()).unwrap();
+
+        for (j, col_source) in [20, 21].into_iter().enumerate() {
+            let expected = v[0] * g[0][j] + v[1] * g[1][j];
+            assert_close(op.current(col_source).unwrap(), expected);
+        }
+    }
+
+    #[test]
+    fn test_current_source() {
+        let mut circuit = Circuit::new(0);
+        circuit.add_current_source(0, 0, 1, 1e-3);
+        circuit.add_resistor(1, 1, 0, 2e3);
+
+        let op = solve_dc(&circuit, &DcOptions::default()).unwrap();
+
+        assert_close(op.voltage(1).unwrap(), 2.0);
+    }
+
+    #[test]
+    fn test_inverting_amplifier() {
+        let mut circuit = Circuit::new(0);
+        circuit.add_voltage_source(0, 1, 0, 0.1);
+        circuit.add_resistor(1, 1, 2, 1e3);
+        circuit.add_resistor(2, 2, 3, 1e4);
+        circuit.add_op_amp(3, 0, 2, 3, 1e6);
+
+        let op = solve_dc(&circuit, &DcOptions::default()).unwrap();
+
+        // The finite gain leaves a relative error of about (1 + R2 / R1) / gain
+        assert!((op.voltage(3).unwrap() + 1.0).abs() < 1e-4);
+        // Virtual ground
+        assert!(op.voltage(2).unwrap().abs() < 1e-5);
+    }
+
+    #[test]
+    fn test_precision_rectifier() {
+        // The op-amp drives the diode until the output follows the input, negative inputs leave the output at 0 V
+        for (input, expected) in [(0.3, 0.3), (-0.3, 0.0)] {
+            let mut circuit = Circuit::new(0);
+            circuit.add_voltage_source(0, 1, 0, input);
+            circuit.add_op_amp(1, 1, 3, 2, 1e6);
+            circuit.add_diode(2, 2, 3, 1e-14, 1.0);
+            circuit.add_resistor(3, 3, 0, 1e4);
+
+            let op = solve_dc(&circuit, &DcOptions::default()).unwrap();
+
+            assert!((op.voltage(3).unwrap() - expected).abs() < 1e-5);
+        }
+    }
+
+    #[test]
+    fn test_invalid_resistor() {
+        let mut circuit = Circuit::new(0);
+        circuit.add_resistor(7, 1, 0, 0.0);
+
+        assert!(matches!(
+            solve_dc(&circuit, &DcOptions::default()),
+            Err(MnaError::InvalidElement(7, _))
+        ));
+    }
+
+    #[test]
+    fn test_duplicate_element() {
+        let mut circuit = Circuit::new(0);
+        circuit.add_voltage_source(0, 1, 0, 1.0);
+        circuit.add_resistor(1, 1, 2, 1e3);
+        circuit.add_voltage_source(1, 2, 0, 0.5);
+
+        assert!(matches!(
+            solve_dc(&circuit, &DcOptions::default()),
+            Err(MnaError::DuplicateElement(1))
+        ));
+    }
+}
@@ -0,0 +1,35 @@
+pub mod circuit;
+pub mod dc;
+pub mod system;
+pub mod transient;
+pub mod waveform;
+
+pub use circuit::*;
+pub use dc::*;
+pub use system::MnaSystem;
+pub use transient::*;
+pub use waveform::Waveform;
+
+use crate::types::ElementId;
+
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum MnaError {
+    #[error("Element {0} has an invalid value: {1}")]
+    InvalidElement(ElementId, String),
+    #[error("Element {0} is not part of the circuit")]
+    UnknownElement(ElementId),
+    #[error("Element {0} appears more than once in the circuit")]
+    DuplicateElement(ElementId),
+    #[error("Newton iterations did not converge at t = {0} s")]
+    NoConvergence(f64),
+    #[error("The time step fell below the minimum ({1} s) at t = {0} s")]
+    TimeStepTooSmall(f64, f64),
+    #[error("The MNA matrix is singular (floating node or loop of voltage sources?): {0}")]
+    SingularMatrix(String),
+    #[error("Analysis option {name} must be positive and finite, got {value}")]
+    InvalidOption { name: &'static str, value: f64 },
+    #[error("The gap of device {1} changed by {2} m in a step of the minimum size at t = {0} s")]
+    GapChangeTooLarge(f64, ElementId, f64),
+}
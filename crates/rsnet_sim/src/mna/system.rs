@@ -0,0 +1,106 @@
+use super::MnaError;
+
+use faer::{
+    linalg::solvers::Solve,
+    sparse::{SparseColMat, Triplet},
+    Col,
+};
+
+/// Sparse MNA system `A x = b` built by stamping elements. Rows are the ones assigned by a
+/// [`CircuitIndex`](super::CircuitIndex), `None` stands for the ground node.
+#[derive(Debug, Clone)]
+pub struct MnaSystem {
+    size: usize,
+    triplets: Vec<Triplet<usize, usize, f64>>,
+    rhs: Vec<f64>,
+}
+
+impl MnaSystem {
+    pub fn new(size: usize) -> Self {
+        MnaSystem {
+            size,
+            triplets: Vec::new(),
+            rhs: vec![0.0; size],
+        }
+    }
+
+    pub fn size(&self) -> usize {
+        self.size
+    }
+
+    pub fn add(&mut self, row: usize, col: usize, value: f64) {
+        self.triplets.push(Triplet::new(row, col, value));
+    }
+
+    pub fn add_rhs(&mut self, row: usize, value: f64) {
+        self.rhs[row] += value;
+    }
+
+    /// Stamps a conductance `g` between nodes `a` and `b`
+    pub fn stamp_conductance(&mut self, a: Option<usize>, b: Option<usize>, g: f64) {
+        if let Some(a) = a {
+            self.add(a, a, g);
+        }
+        if let Some(b) = b {
+            self.add(b, b, g);
+        }
+        if let (Some(a), Some(b)) = (a, b) {
+            self.add(a, b, -g);
+            self.add(b, a, -g);
+        }
+    }
+
+    /// Stamps a current `i` flowing from `a` to `b` through the source
+    pub fn stamp_current_source(&mut self, a: Option<usize>, b: Option<usize>, i: f64) {
+        if let Some(a) = a {
+            self.add_rhs(a, -i);
+        }
+        if let Some(b) = b {
+            self.add_rhs(b, i);
+        }
+    }
+
+    /// Stamps a voltage source `v(a) - v(b) = v` whose branch current is the unknown at row `branch`
+    pub fn stamp_voltage_source(&mut self, branch: usize, a: Option<usize>, b: Option<usize>, v: f64) {
+        if let Some(a) = a {
+            self.add(a, branch, 1.0);
+            self.add(branch, a, 1.0);
+        }
+        if let Some(b) = b {
+            self.add(b, branch, -1.0);
+            self.add(branch, b, -1.0);
+        }
+        self.add_rhs(branch, v);
+    }
+
+    /// Solves the system with a sparse LU factorization
+    pub fn solve(&self) -> Result<Vec<f64>, MnaError> {
+        if self.size == 0 {
+            return Ok(Vec::new());
+        }
+
+        let matrix = SparseColMat::<usize, f64>::try_new_from_triplets(
+            self.size,
+            self.size,
+            &self.triplets,
+        )
+        .map_err(|e| MnaError::SingularMatrix(format!("{:?}", e)))?;
+
+        let lu = matrix
+            .sp_lu()
+            .map_err(|e| MnaError::SingularMatrix(format!("{:?}", e)))?;
+
+        let rhs = Col::<f64>::from_fn(self.size, |i| self.rhs[i]);
+        let x = lu.solve(&rhs);
+
+        let x = (0..self.size).map(|i| x[i]).collect::<Vec<f64>>();
+
+        if x.iter().any(|v| !v.is_finite()) {
+            return Err(MnaError::SingularMatrix(
+                "the solution contains non finite values".to_string(),
+            ));
+        }
+
+        Ok(x)
+    }
+}
@@ -0,0 +1,543 @@
+use super::{
+    stamp_element, stamp_nonlinear, Circuit, CircuitIndex, Element, ElementKind, MnaError, MnaSystem, Waveform,
+};
+
+use crate::{
+    stanford::StanfordModel,
+    types::{ElementId, NodeId},
+};
+
+use std::collections::HashMap;
+use tracing::debug;
+
+#[derive(Debug, Clone, Copy)]
+pub struct TransientOptions {
+    /// Final simulation time. [s]
+    pub t_stop: f64,
+    /// Largest time step, `None` uses the smallest `time_step` bound of the devices. [s]
+    pub max_step: Option<f64>,
+    /// The analysis fails if the step has to be reduced below this value. [s]
+    pub min_step: f64,
+    /// First time step tried. [s]
+    pub initial_step: f64,
+    /// Largest gap change allowed to any device in a single step, larger changes make the step be rejected. [m]
+    pub max_gap_change: f64,
+    /// Maximum Newton iterations per time step
+    pub max_newton_iterations: usize,
+    /// Absolute tolerance on the node voltages for the Newton iterations. [V]
+    pub abstol: f64,
+    /// Relative tolerance on the node voltages for the Newton iterations
+    pub reltol: f64,
+    /// Largest change of a node voltage in a single Newton iteration, limits the overshoot of the `sinh` I-V. [V]
+    pub max_voltage_update: f64,
+    /// See [`DcOptions::gmin`](super::DcOptions::gmin). [S]
+    pub gmin: f64,
+    /// Whether to store the voltage of every node at every accepted time step
+    pub save_node_voltages: bool,
+}
+
+impl Default for TransientOptions {
+    fn default() -> Self {
+        Self {
+            t_stop: 0.0,
+            max_step: None,
+            min_step: 1e-15,
+            initial_step: 1e-12,
+            max_gap_change: 1e-11,
+            max_newton_iterations: 100,
+            abstol: 1e-6,
+            reltol: 1e-3,
+            max_voltage_update: 0.5,
+            gmin: 1e-12,
+            save_node_voltages: true,
+        }
+    }
+}
+
+impl TransientOptions {
+    /// Checks that the time and step options let the analysis advance. `t_stop` may be zero.
+    pub fn validate(&self) -> Result<(), MnaError> {
+        let steps = [
+            ("max_step", self.max_step.unwrap_or(1.0)),
+            ("min_step", self.min_step),
+            ("initial_step", self.initial_step),
+            ("max_gap_change", self.max_gap_change),
+        ];
+        for (name, value) in steps {
+            if !(value.is_finite() && value > 0.0) {
+                return Err(MnaError::InvalidOption { name, value });
+            }
+        }
+        if !(self.t_stop.is_finite() && self.t_stop >= 0.0) {
+            return Err(MnaError::InvalidOption {
+                name: "t_stop",
+                value: self.t_stop,
+            });
+        }
+
+        Ok(())
+    }
+}
+
+/// State of a memristor over time
+#[derive(Debug, Clone, Default)]
+pub struct DeviceHistory {
+    /// [m]
+    pub gap: Vec<f64>,
+    /// [A]
+    pub current: Vec<f64>,
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct TransientResult {
+    /// Accepted time points. [s]
+    pub times: Vec<f64>,
+    /// Voltage of every node at each time point, empty if `save_node_voltages` is not set. [V]
+    pub node_voltages: HashMap<NodeId, Vec<f64>>,
+    /// Gap and current of every simulated memristor at each time point
+    pub devices: HashMap<ElementId, DeviceHistory>,
+}
+
+/// Transient analysis of a circuit whose memristors evolve according to the Stanford model.
+///
+/// Memristor elements of the circuit with an attached [`StanfordModel`] are solved with their nonlinear I-V, every
+/// other element behaves as in the DC analysis. Sources may be given a [`Waveform`].
+pub struct TransientAnalysis<'a> {
+    circuit: &'a Circuit,
+    index: CircuitIndex,
+    devices: HashMap<ElementId, StanfordModel>,
+    waveforms: HashMap<ElementId, Waveform>,
+    options: TransientOptions,
+}
+
+impl<'a> TransientAnalysis<'a> {
+    pub fn new(circuit: &'a Circuit, options: TransientOptions) -> Result<Self, MnaError> {
+        options.validate()?;
+
+        Ok(Self {
+            index: circuit.index()?,
+            circuit,
+            devices: HashMap::new(),
+            waveforms: HashMap::new(),
+            options,
+        })
+    }
+
+    /// Simulates the memristor element `id` with `model` instead of its fixed conductance
+    pub fn set_device(&mut self, id: ElementId, model: StanfordModel) -> Result<(), MnaError> {
+        match self.find_element(id)?.kind {
+            ElementKind::Memristor(_) => {
+                self.devices.insert(id, model);
+                Ok(())
+            }
+            _ => Err(MnaError::InvalidElement(
+                id,
+                "only memristors can be simulated with the Stanford model".to_string(),
+            )),
+        }
+    }
+
+    /// Drives the source `id` with `waveform` instead of its DC value
+    pub fn set_waveform(&mut self, id: ElementId, waveform: Waveform) -> Result<(), MnaError> {
+        match self.find_element(id)?.kind {
+            ElementKind::VoltageSource(_) | ElementKind::CurrentSource(_) => {
+                self.waveforms.insert(id, waveform);
+                Ok(())
+            }
+            _ => Err(MnaError::InvalidElement(
+                id,
+                "only sources can be driven by a waveform".to_string(),
+            )),
+        }
+    }
+
+    pub fn devices(&self) -> &HashMap<ElementId, StanfordModel> {
+        &self.devices
+    }
+
+    fn find_element(&self, id: ElementId) -> Result<&Element, MnaError> {
+        self.circuit
+            .elements()
+            .iter()
+            .find(|element| element.id == id)
+            .ok_or(MnaError::UnknownElement(id))
+    }
+
+    fn max_step(&self) -> f64 {
+        self.options.max_step.unwrap_or_else(|| {
+            self.devices
+                .values()
+                .map(|model| model.params().time_step)
+                .fold(f64::INFINITY, f64::min)
+                .min(self.options.t_stop)
+        })
+    }
+
+    /// Next corner of a waveform after `t`. Corners closer than `min_step` to `t` were already reached up to the
+    /// rounding of the time, stepping to them again would not advance.
+    fn next_breakpoint(&self, t: f64) -> Option<f64> {
+        self.waveforms
+            .values()
+            .filter_map(|waveform| waveform.next_breakpoint(t + self.options.min_step))
+            .fold(None, |acc: Option<f64>, bp| Some(acc.map_or(bp, |acc| acc.min(bp))))
+    }
+
+    fn voltage(&self, x: &[f64], node: NodeId) -> f64 {
+        self.index.node(node).map_or(0.0, |row| x[row])
+    }
+
+    /// Solves the circuit at time `t` with the device gaps frozen, starting from the guess `x`
+    fn newton(&self, t: f64, mut x: Vec<f64>) -> Result<Vec<f64>, MnaError> {
+        let options = &self.options;
+
+        for _ in 0..options.max_newton_iterations {
+            let mut system = MnaSystem::new(self.index.size());
+            for row in self.index.nodes.values() {
+                system.add(*row, *row, options.gmin);
+            }
+
+            for element in self.circuit.elements() {
+                if let Some(model) = self.devices.get(&element.id) {
+                    // Companion model of the linearized device: i = g_eq * v + i_eq
+                    let v = self.voltage(&x, element.pos) - self.voltage(&x, element.neg);
+                    let g_eq = model.conductance_at(v);
+                    let i_eq = model.current_at(v) - g_eq * v;
+
+                    let (a, b) = (self.index.node(element.pos), self.index.node(element.neg));
+                    system.stamp_conductance(a, b, g_eq);
+                    system.stamp_current_source(a, b, i_eq);
+                    continue;
+                }
+                if stamp_nonlinear(element, &self.index, &mut system, &x) {
+                    continue;
+                }
+
+                match (self.waveforms.get(&element.id), element.kind) {
+                    (Some(waveform), ElementKind::VoltageSource(_)) => stamp_element(
+                        &Element {
+                            kind: ElementKind::VoltageSource(waveform.value(t)),
+                            ..*element
+                        },
+                        &self.index,
+                        &mut system,
+                    ),
+                    (Some(waveform), ElementKind::CurrentSource(_)) => stamp_element(
+                        &Element {
+                            kind: ElementKind::CurrentSource(waveform.value(t)),
+                            ..*element
+                        },
+                        &self.index,
+                        &mut system,
+                    ),
+                    _ => stamp_element(element, &self.index, &mut system),
+                }
+            }
+
+            let x_new = system.solve()?;
+
+            let mut converged = true;
+            for (x_i, x_new_i) in x.iter_mut().zip(x_new) {
+                let delta = x_new_i - *x_i;
+                if delta.abs() > options.abstol + options.reltol * x_new_i.abs() {
+                    converged = false;
+                }
+                *x_i += delta.clamp(-options.max_voltage_update, options.max_voltage_update);
+            }
+
+            if converged {
+                return Ok(x);
+            }
+        }
+
+        Err(MnaError::NoConvergence(t))
+    }
+
+    fn record(&self, result: &mut TransientResult, t: f64, x: &[f64], currents: &[(ElementId, f64)]) {
+        result.times.push(t);
+
+        if self.options.save_node_voltages {
+            result
+                .node_voltages
+                .entry(self.circuit.ground())
+                .or_default()
+                .push(0.0);
+            for (node, row) in self.index.nodes.iter() {
+                result.node_voltages.entry(*node).or_default().push(x[*row]);
+            }
+        }
+
+        for (id, current) in currents {
+            let history = result.devices.entry(*id).or_default();
+            history.gap.push(self.devices[id].effective_gap());
+            history.current.push(*current);
+        }
+    }
+
+    /// Current through every simulated device for the solution `x`
+    fn device_currents(&self, x: &[f64]) -> Vec<(ElementId, f64)> {
+        self.circuit
+            .elements()
+            .iter()
+            .filter_map(|element| {
+                self.devices.get(&element.id).map(|model| {
+                    let v = self.voltage(x, element.pos) - self.voltage(x, element.neg);
+                    (element.id, model.current_at(v))
+                })
+            })
+            .collect()
+    }
+
+    /// Runs the analysis from `t = 0` to `t_stop`, the devices keep their final state afterwards
+    pub fn run(&mut self) -> Result<TransientResult, MnaError> {
+        let options = self.options;
+        let max_step = self.max_step();
+
+        let mut result = TransientResult::default();
+
+        let mut t = 0.0;
+        let mut x = self.newton(t, vec![0.0; self.index.size()])?;
+        let currents = self.device_currents(&x);
+        self.record(&mut result, t, &x, &currents);
+
+        let mut dt = options.initial_step.min(max_step);
+
+        while t < options.t_stop {
+            let mut step = dt.min(options.t_stop - t);
+            if let Some(bp) = self.next_breakpoint(t) {
+                step = step.min(bp - t);
+            }
+            if step <= 0.0 {
+                return Err(MnaError::TimeStepTooSmall(t, options.min_step));
+            }
+            let t_new = t + step;
+
+            let x_new = match self.newton(t_new, x.clone()) {
+                Ok(x_new) => x_new,
+                Err(MnaError::NoConvergence(_)) => {
+                    dt = step / 2.0;
+                    if dt < options.min_step {
+                        return Err(MnaError::TimeStepTooSmall(t, options.min_step));
+                    }
+                    continue;
+                }
+                Err(e) => return Err(e),
+            };
+
+            // Advance every device on a copy so that the step can be rejected
+            let (mut max_change, mut max_change_id): (f64, ElementId) = (0.0, 0);
+            let mut stepped = HashMap::with_capacity(self.devices.len());
+            for element in self.circuit.elements() {
+                if let Some(model) = self.devices.get(&element.id) {
+                    let (te, be) = (self.voltage(&x_new, element.pos), self.voltage(&x_new, element.neg));
+
+                    let mut model = model.clone();
+                    model.set_current(model.current_at(te - be));
+                    model.set_voltages(te, be);
+                    let gap = model.step(t_new).gap;
+
+                    let change = (gap - self.devices[&element.id].effective_gap()).abs();
+                    if change > max_change {
+                        (max_change, max_change_id) = (change, element.id);
+                    }
+                    stepped.insert(element.id, model);
+                }
+            }
+
+            if max_change > options.max_gap_change {
+                if step <= options.min_step {
+                    return Err(MnaError::GapChangeTooLarge(t, max_change_id, max_change));
+                }
+                dt = (step / 2.0).max(options.min_step);
+                continue;
+            }
+
+            let currents = self.device_currents(&x_new);
+            self.devices.extend(stepped);
+            t = t_new;
+            x = x_new;
+            self.record(&mut result, t, &x, &currents);
+
+            if max_change < options.max_gap_change / 2.0 {
+                dt = (step * 2.0).min(max_step);
+            } else {
+                dt = step;
+            }
+        }
+
+        debug!("Transient analysis finished after {} steps", result.times.len());
+
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod transient_test {
+    use super::*;
+    use crate::stanford::StanfordModelParams;
+
+    #[test]
+    fn test_set_pulse_increases_gap() {
+        // Single memristor driven through a small series resistance, so that it stays above the switching threshold
+        let mut circuit = Circuit::new(0);
+        circuit.add_voltage_source(0, 1, 0, 0.0);
+        circuit.add_resistor(1, 1, 2, 1.0);
+        circuit.add_memristor(2, 2, 0, 0.0);
+
+        let options = TransientOptions {
+            t_stop: 20e-9,
+            ..Default::default()
+        };
+
+        let mut analysis = TransientAnalysis::new(&circuit, options).unwrap();
+        analysis
+            .set_device(2, StanfordModel::with_seed(StanfordModelParams::default(), 0).unwrap())
+            .unwrap();
+        analysis
+            .set_waveform(
+                0,
+                Waveform::Pulse {
+                    initial: 0.0,
+                    pulsed: -1.6,
+                    delay: 1e-9,
+                    rise: 1e-10,
+                    fall: 1e-10,
+                    width: 10e-9,
+                    period: 0.0,
+                },
+            )
+            .unwrap();
+
+        let result = analysis.run().unwrap();
+        let history = &result.devices[&2];
+
+        assert_eq!(history.gap.len(), result.times.len());
+        assert_eq!(*result.times.last().unwrap(), 20e-9);
+
+        let first_gap = *history.gap.first().unwrap();
+        let last_gap = *history.gap.last().unwrap();
+        assert!(last_gap > first_gap);
+
+        // No step can jump more than the allowed gap change
+        for w in history.gap.windows(2) {
+            assert!((w[1] - w[0]).abs() <= TransientOptions::default().max_gap_change * 1.0001);
+        }
+
+        // The source is off after the pulse, so the device current should vanish
+        assert!(history.current.last().unwrap().abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_breakpoint_reached_up_to_rounding() {
+        let mut circuit = Circuit::new(0);
+        circuit.add_voltage_source(0, 1, 0, 0.0);
+        circuit.add_resistor(1, 1, 0, 1e3);
+
+        let options = TransientOptions {
+            t_stop: 3e-9,
+            ..Default::default()
+        };
+        let mut analysis = TransientAnalysis::new(&circuit, options).unwrap();
+        analysis
+            .set_waveform(
+                0,
+                Waveform::Pulse {
+                    initial: 0.0,
+                    pulsed: 1.0,
+                    delay: 1e-9,
+                    rise: 0.0,
+                    fall: 0.0,
+                    width: 1e-9,
+                    period: 0.0,
+                },
+            )
+            .unwrap();
+
+        // A time that rounded just below the delay does not step to it again
+        assert_eq!(analysis.next_breakpoint(1e-9 - 1e-24), Some(2e-9));
+        assert_eq!(analysis.next_breakpoint(0.0), Some(1e-9));
+
+        let result = analysis.run().unwrap();
+        assert!(result.times.windows(2).all(|w| w[1] > w[0]));
+        assert_eq!(*result.times.last().unwrap(), 3e-9);
+    }
+
+    #[test]
+    fn test_invalid_options() {
+        let mut circuit = Circuit::new(0);
+        circuit.add_voltage_source(0, 1, 0, 1.0);
+        circuit.add_resistor(1, 1, 0, 1e3);
+
+        let invalid = [
+            TransientOptions {
+                t_stop: 1e-9,
+                max_step: Some(0.0),
+                ..Default::default()
+            },
+            TransientOptions {
+                t_stop: 1e-9,
+                min_step: -1e-15,
+                ..Default::default()
+            },
+            TransientOptions {
+                t_stop: 1e-9,
+                initial_step: 0.0,
+                ..Default::default()
+            },
+            TransientOptions {
+                t_stop: f64::INFINITY,
+                ..Default::default()
+            },
+        ];
+        for options in invalid {
+            assert!(matches!(
+                TransientAnalysis::new(&circuit, options),
+                Err(MnaError::InvalidOption { .. })
+            ));
+        }
+    }
+
+    #[test]
+    fn test_gap_change_at_min_step() {
+        let mut circuit = Circuit::new(0);
+        circuit.add_voltage_source(0, 1, 0, -1.6);
+        circuit.add_resistor(1, 1, 2, 1.0);
+        circuit.add_memristor(2, 2, 0, 0.0);
+
+        // The device switches, so no step down to the minimum keeps the gap change below the limit
+        let options = TransientOptions {
+            t_stop: 1e-9,
+            min_step: 1e-12,
+            max_gap_change: 1e-30,
+            ..Default::default()
+        };
+
+        let mut analysis = TransientAnalysis::new(&circuit, options).unwrap();
+        analysis
+            .set_device(2, StanfordModel::with_seed(StanfordModelParams::default(), 0).unwrap())
+            .unwrap();
+
+        assert!(matches!(analysis.run(), Err(MnaError::GapChangeTooLarge(_, 2, _))));
+    }
+
+    #[test]
+    fn test_waveform_breakpoints() {
+        let pulse = Waveform::Pulse {
+            initial: 0.0,
+            pulsed: 1.0,
+            delay: 1.0,
+            rise: 0.5,
+            fall: 0.5,
+            width: 1.0,
+            period: 4.0,
+        };
+
+        assert_eq!(pulse.value(0.5), 0.0);
+        assert_eq!(pulse.value(1.25), 0.5);
+        assert_eq!(pulse.value(2.0), 1.0);
+        assert_eq!(pulse.value(5.25), 0.5);
+
+        assert_eq!(pulse.next_breakpoint(0.0), Some(1.0));
+        assert_eq!(pulse.next_breakpoint(1.6), Some(2.5));
+        assert_eq!(pulse.next_breakpoint(3.5), Some(5.0));
+    }
+}
@@ -0,0 +1,106 @@
+/// Time dependent value of an independent source
+#[derive(Debug, Clone, PartialEq)]
+pub enum Waveform {
+    Dc(f64),
+    /// Trapezoidal pulse train, equivalent to the SPICE `PULSE` source
+    Pulse {
+        initial: f64,
+        pulsed: f64,
+        delay: f64,
+        rise: f64,
+        fall: f64,
+        width: f64,
+        /// Repetition period, no repetition if it is not positive
+        period: f64,
+    },
+    /// Piecewise linear waveform given by `(time, value)` points sorted by time
+    Pwl(Vec<(f64, f64)>),
+}
+
+impl Waveform {
+    pub fn value(&self, t: f64) -> f64 {
+        match self {
+            Waveform::Dc(v) => *v,
+            Waveform::Pulse {
+                initial,
+                pulsed,
+                delay,
+                rise,
+                fall,
+                width,
+                period,
+            } => {
+                if t < *delay {
+                    return *initial;
+                }
+
+                let mut local_t = t - delay;
+                if *period > 0.0 {
+                    local_t %= period;
+                }
+
+                if local_t < *rise {
+                    initial + (pulsed - initial) * local_t / rise
+                } else if local_t < rise + width {
+                    *pulsed
+                } else if local_t < rise + width + fall {
+                    pulsed + (initial - pulsed) * (local_t - rise - width) / fall
+                } else {
+                    *initial
+                }
+            }
+            Waveform::Pwl(points) => {
+                let (first, last) = match (points.first(), points.last()) {
+                    (Some(first), Some(last)) => (first, last),
+                    _ => return 0.0,
+                };
+
+                if t <= first.0 {
+                    return first.1;
+                }
+                if t >= last.0 {
+                    return last.1;
+                }
+
+                let i = points.partition_point(|(pt, _)| *pt <= t);
+                let ((t0, v0), (t1, v1)) = (points[i - 1], points[i]);
+                v0 + (v1 - v0) * (t - t0) / (t1 - t0)
+            }
+        }
+    }
+
+    /// First time strictly after `t` where the waveform has a corner, time steps should not jump over them
+    pub fn next_breakpoint(&self, t: f64) -> Option<f64> {
+        match self {
+            Waveform::Dc(_) => None,
+            Waveform::Pulse {
+                delay,
+                rise,
+                fall,
+                width,
+                period,
+                ..
+            } => {
+                let corners = [0.0, *rise, rise + width, rise + width + fall];
+
+                if t < *delay {
+                    return Some(*delay);
+                }
+
+                let (start, local_t) = if *period > 0.0 {
+                    let n = ((t - delay) / period).floor();
+                    (delay + n * period, t - delay - n * period)
+                } else {
+                    (*delay, t - delay)
+                };
+
+                corners
+                    .iter()
+                    .find(|corner| **corner > local_t)
+                    .map(|corner| start + corner)
+                    .or_else(|| (*period > 0.0).then(|| start + period))
+            }
+            Waveform::Pwl(points) => points.iter().map(|(pt, _)| *pt).find(|pt| *pt > t),
+        }
+    }
+}
@@ -0,0 +1,51 @@
+use std::fmt::Display;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RangeType {
+    OpenOpen,
+    OpenClosed,
+    ClosedOpen,
+    ClosedClosed,
+}
+
+impl RangeType {
+    /// The opening and closing brackets used to print a range of this type
+    fn brackets(&self) -> (char, char) {
+        match self {
+            RangeType::OpenOpen => ('(', ')'),
+            RangeType::OpenClosed => ('(', ']'),
+            RangeType::ClosedOpen => ('[', ')'),
+            RangeType::ClosedClosed => ('[', ']'),
+        }
+    }
+}
+
+/// A (possibly unbounded) interval of valid values for a model parameter
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Range {
+    pub min: f64,
+    pub max: f64,
+    pub ty: RangeType,
+}
+
+impl Range {
+    pub const fn new(min: f64, max: f64, ty: RangeType) -> Self {
+        Range { min, max, ty }
+    }
+
+    pub fn validate(&self, value: f64) -> bool {
+        match self.ty {
+            RangeType::OpenOpen => self.min < value && value < self.max,
+            RangeType::OpenClosed => self.min < value && value <= self.max,
+            RangeType::ClosedOpen => self.min <= value && value < self.max,
+            RangeType::ClosedClosed => self.min <= value && value <= self.max,
+        }
+    }
+}
+
+impl Display for Range {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (open, close) = self.ty.brackets();
+        write!(f, "{}{}, {}{}", open, self.min, self.max, close)
+    }
+}
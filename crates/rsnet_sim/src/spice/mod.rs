@@ -0,0 +1,32 @@
+pub mod parser;
+pub mod writer;
+
+pub use parser::*;
+pub use writer::*;
+
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum SpiceError {
+    #[error("Could not write the netlist: {0}")]
+    Io(std::io::Error),
+    #[error("Line {line}: {message}")]
+    Parse { line: usize, message: String },
+    #[error("Line {line}: subcircuit {name} is not defined")]
+    UnknownSubckt { line: usize, name: String },
+    #[error("Line {line}: subcircuit {name} has {expected} ports, the instance connects {got}")]
+    PortMismatch {
+        line: usize,
+        name: String,
+        expected: usize,
+        got: usize,
+    },
+    #[error("Line {line}: subcircuit {name} instantiates itself")]
+    RecursiveSubckt { line: usize, name: String },
+}
+
+impl From<std::io::Error> for SpiceError {
+    fn from(value: std::io::Error) -> Self {
+        Self::Io(value)
+    }
+}
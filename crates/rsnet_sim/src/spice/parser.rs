@@ -0,0 +1,643 @@
+use super::SpiceError;
+
+use crate::stanford::{device_conductance, StanfordModelParams};
+
+use std::collections::HashMap;
+
+use tracing::warn;
+
+/// Element of a netlist, identified by the first letter of its name
+#[derive(Debug, Clone, PartialEq)]
+pub enum SpiceElementKind {
+    /// ```R<name> n+ n- value```. [Ohm]
+    Resistor(f64),
+    /// ```D<name> anode cathode model```
+    Diode { model: String },
+    /// ```M<name> drain gate source bulk model```
+    Mosfet { model: String },
+    /// ```N<name> te be model [param=value ...]``` (a Verilog-A device) or ```YMEMRISTOR <name> te be model```
+    Memristor {
+        model: String,
+        params: HashMap<String, f64>,
+    },
+    /// ```V<name> n+ n- [DC] value```, only the DC value is kept. [V]
+    VoltageSource(f64),
+    /// ```I<name> n+ n- [DC] value```, only the DC value is kept. [A]
+    CurrentSource(f64),
+    /// ```E<name> n+ n- nc+ nc- gain```
+    Vcvs(f64),
+    /// ```X<name> nodes... subckt```
+    Instance { subckt: String },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpiceElement {
+    pub name: String,
+    pub kind: SpiceElementKind,
+    /// Nodes in the order of the element line
+    pub nodes: Vec<String>,
+    /// Line of the netlist where the element starts
+    pub line: usize,
+}
+
+/// ```.model name type (param=value ...)```, names and parameters are lowercase
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpiceModel {
+    pub name: String,
+    pub ty: String,
+    pub params: HashMap<String, f64>,
+    pub line: usize,
+}
+
+impl SpiceModel {
+    /// Stanford model parameters set by the model, the others keep their default value
+    pub fn stanford_params(&self) -> StanfordModelParams {
+        let mut params = StanfordModelParams::default();
+        let fields: [(&str, &mut f64); 20] = [
+            ("g0", &mut params.g0),
+            ("v0", &mut params.v0),
+            ("vel0", &mut params.vel0),
+            ("i0", &mut params.i0),
+            ("beta", &mut params.beta),
+            ("alpha", &mut params.alpha),
+            ("gamma0", &mut params.gamma0),
+            ("t_crit", &mut params.t_crit),
+            ("deltagap0", &mut params.delta_gap0),
+            ("t_smth", &mut params.t_smth),
+            ("ea", &mut params.ea),
+            ("a0", &mut params.a0),
+            ("t_ini", &mut params.t_ini),
+            ("f_min", &mut params.f_min),
+            ("gap_ini", &mut params.gap_ini),
+            ("gap_min", &mut params.gap_min),
+            ("gap_max", &mut params.gap_max),
+            ("rth", &mut params.rth),
+            ("tox", &mut params.tox),
+            ("time_step", &mut params.time_step),
+        ];
+        for (name, field) in fields {
+            if let Some(value) = self.params.get(name) {
+                *field = *value;
+            }
+        }
+
+        params
+    }
+}
+
+/// ```.subckt name ports...``` ... ```.ends```
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpiceSubckt {
+    pub name: String,
+    pub ports: Vec<String>,
+    pub elements: Vec<SpiceElement>,
+    pub line: usize,
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SpiceNetlist {
+    pub title: String,
+    /// Elements of the top level
+    pub elements: Vec<SpiceElement>,
+    /// Keyed by the lowercase name
+    pub subckts: HashMap<String, SpiceSubckt>,
+    /// Keyed by the lowercase name
+    pub models: HashMap<String, SpiceModel>,
+}
+
+/// Whether ```node``` is the global ground of SPICE
+pub fn is_ground(node: &str) -> bool {
+    node == "0" || node.eq_ignore_ascii_case("gnd")
+}
+
+impl SpiceNetlist {
+    /// Top level elements with every subcircuit instance replaced by the elements of the subcircuit. Elements and
+    /// internal nodes of an instance are prefixed with the name of the instance, e.g. ```x1.r2```.
+    pub fn flatten(&self) -> Result<Vec<SpiceElement>, SpiceError> {
+        let mut flat = Vec::new();
+        self.expand(
+            &self.elements,
+            "",
+            &HashMap::new(),
+            &mut Vec::new(),
+            &mut flat,
+        )?;
+
+        Ok(flat)
+    }
+
+    fn expand(
+        &self,
+        elements: &[SpiceElement],
+        prefix: &str,
+        ports: &HashMap<String, String>,
+        stack: &mut Vec<String>,
+        flat: &mut Vec<SpiceElement>,
+    ) -> Result<(), SpiceError> {
+        let scoped = |name: &str| {
+            if prefix.is_empty() {
+                name.to_string()
+            } else {
+                format!("{}.{}", prefix, name)
+            }
+        };
+        let node = |node: &String| {
+            if is_ground(node) {
+                "0".to_string()
+            } else {
+                ports.get(node).cloned().unwrap_or_else(|| scoped(node))
+            }
+        };
+
+        for element in elements {
+            let nodes: Vec<String> = element.nodes.iter().map(node).collect();
+
+            let SpiceElementKind::Instance { subckt } = &element.kind else {
+                flat.push(SpiceElement {
+                    name: scoped(&element.name),
+                    nodes,
+                    ..element.clone()
+                });
+                continue;
+            };
+
+            let definition = self
+                .subckts
+                .get(subckt)
+                .ok_or_else(|| SpiceError::UnknownSubckt {
+                    line: element.line,
+                    name: subckt.clone(),
+                })?;
+            if stack.contains(subckt) {
+                return Err(SpiceError::RecursiveSubckt {
+                    line: element.line,
+                    name: subckt.clone(),
+                });
+            }
+            if definition.ports.len() != nodes.len() {
+                return Err(SpiceError::PortMismatch {
+                    line: element.line,
+                    name: subckt.clone(),
+                    expected: definition.ports.len(),
+                    got: nodes.len(),
+                });
+            }
+
+            let instance_ports = definition.ports.iter().cloned().zip(nodes).collect();
+            stack.push(subckt.clone());
+            self.expand(
+                &definition.elements,
+                &scoped(&element.name),
+                &instance_ports,
+                stack,
+                flat,
+            )?;
+            stack.pop();
+        }
+
+        Ok(())
+    }
+
+    /// Small signal conductance of a memristor instance, from the ```gap_ini``` of the instance or of its model. [S]
+    pub fn memristor_conductance(&self, model: &str, params: &HashMap<String, f64>) -> f64 {
+        let mut stanford = self
+            .models
+            .get(model)
+            .map(|model| model.stanford_params())
+            .unwrap_or_default();
+        if let Some(gap) = params.get("gap_ini") {
+            stanford.gap_ini = *gap;
+        }
+
+        device_conductance(&stanford, stanford.gap_ini, 0.0)
+    }
+}
+
+/// Parses a SPICE netlist. The first line is the title, lines starting with ```+``` continue the previous one and
+/// comments start with ```*``` or ```;```. Elements that are not a [`SpiceElementKind`] and file inclusions are
+/// skipped with a warning, other control cards are ignored.
+pub fn parse_spice(source: &str) -> Result<SpiceNetlist, SpiceError> {
+    let mut netlist = SpiceNetlist::default();
+    let mut lines = logical_lines(source).into_iter();
+
+    if let Some((_, title)) = lines.next() {
+        netlist.title = title;
+    }
+
+    // Subcircuits being defined, they can be nested
+    let mut open: Vec<SpiceSubckt> = Vec::new();
+    let mut in_control = false;
+
+    for (line, text) in lines {
+        let tokens = tokenize(&text);
+        let Some(first) = tokens.first() else {
+            continue;
+        };
+        let keyword = first.to_lowercase();
+
+        if in_control {
+            in_control = keyword != ".endc";
+            continue;
+        }
+
+        match keyword.as_str() {
+            ".end" => break,
+            ".control" => in_control = true,
+            ".subckt" => {
+                let name = tokens
+                    .get(1)
+                    .ok_or_else(|| parse_error(line, "the subcircuit has no name"))?;
+                let ports = tokens[2..]
+                    .iter()
+                    .take_while(|token| {
+                        !token.contains('=') && !token.eq_ignore_ascii_case("params:")
+                    })
+                    .cloned()
+                    .collect();
+                open.push(SpiceSubckt {
+                    name: name.to_lowercase(),
+                    ports,
+                    elements: Vec::new(),
+                    line,
+                });
+            }
+            ".ends" => {
+                let subckt = open
+                    .pop()
+                    .ok_or_else(|| parse_error(line, ".ends without .subckt"))?;
+                netlist.subckts.insert(subckt.name.clone(), subckt);
+            }
+            ".model" => {
+                let model = parse_model(&tokens, line)?;
+                netlist.models.insert(model.name.clone(), model);
+            }
+            ".include" | ".inc" | ".lib" => {
+                warn!(
+                    "Line {}: {} is not supported, the file is not read",
+                    line, first
+                );
+            }
+            _ if keyword.starts_with('.') => {}
+            _ => {
+                if let Some(element) = parse_element(&tokens, line)? {
+                    match open.last_mut() {
+                        Some(subckt) => subckt.elements.push(element),
+                        None => netlist.elements.push(element),
+                    }
+                }
+            }
+        }
+    }
+
+    if let Some(subckt) = open.last() {
+        return Err(parse_error(
+            subckt.line,
+            &format!("subcircuit {} is not closed with .ends", subckt.name),
+        ));
+    }
+
+    Ok(netlist)
+}
+
+fn parse_error(line: usize, message: &str) -> SpiceError {
+    SpiceError::Parse {
+        line,
+        message: message.to_string(),
+    }
+}
+
+/// Joins continuation lines and removes comments, returns every non empty line with the number of its first line
+fn logical_lines(source: &str) -> Vec<(usize, String)> {
+    let mut lines: Vec<(usize, String)> = Vec::new();
+
+    for (idx, text) in source.lines().enumerate() {
+        let text = text.split(';').next().unwrap_or_default();
+        let text = text.split(" $ ").next().unwrap_or_default().trim();
+
+        // The title is kept even if it looks like a comment
+        if lines.is_empty() && idx == 0 {
+            lines.push((1, text.to_string()));
+            continue;
+        }
+        if text.is_empty() || text.starts_with('*') {
+            continue;
+        }
+
+        match (text.strip_prefix('+'), lines.last_mut()) {
+            (Some(continuation), Some((_, previous))) => {
+                previous.push(' ');
+                previous.push_str(continuation);
+            }
+            _ => lines.push((idx + 1, text.to_string())),
+        }
+    }
+
+    lines
+}
+
+/// Splits a line in tokens, parentheses and commas separate tokens and ```name = value``` is a single token
+fn tokenize(text: &str) -> Vec<String> {
+    let spaced: String = text
+        .chars()
+        .map(|c| if matches!(c, '(' | ')' | ',') { ' ' } else { c })
+        .collect();
+
+    let mut tokens: Vec<String> = Vec::new();
+    for token in spaced.split_whitespace() {
+        match tokens.last_mut() {
+            Some(last) if last.ends_with('=') || token.starts_with('=') => last.push_str(token),
+            _ => tokens.push(token.to_string()),
+        }
+    }
+
+    tokens
+}
+
+/// Parses a number with an optional SPICE scale factor (```f p n u m k meg g t mil```), trailing units are ignored
+pub fn parse_value(token: &str) -> Option<f64> {
+    let token = token.to_lowercase();
+    let bytes = token.as_bytes();
+
+    let mut end = 0;
+    if end < bytes.len() && matches!(bytes[end], b'+' | b'-') {
+        end += 1;
+    }
+    while end < bytes.len() && (bytes[end].is_ascii_digit() || bytes[end] == b'.') {
+        end += 1;
+    }
+    // The exponent needs digits, "1meg" has none
+    if end < bytes.len() && bytes[end] == b'e' {
+        let mut exponent_end = end + 1;
+        if exponent_end < bytes.len() && matches!(bytes[exponent_end], b'+' | b'-') {
+            exponent_end += 1;
+        }
+        if exponent_end < bytes.len() && bytes[exponent_end].is_ascii_digit() {
+            end = exponent_end;
+            while end < bytes.len() && bytes[end].is_ascii_digit() {
+                end += 1;
+            }
+        }
+    }
+
+    let value: f64 = token[..end].parse().ok()?;
+    let suffix = &token[end..];
+    let scale = if suffix.starts_with("meg") {
+        1e6
+    } else if suffix.starts_with("mil") {
+        25.4e-6
+    } else {
+        match suffix.chars().next() {
+            Some('t') => 1e12,
+            Some('g') => 1e9,
+            Some('k') => 1e3,
+            Some('m') => 1e-3,
+            Some('u') => 1e-6,
+            Some('n') => 1e-9,
+            Some('p') => 1e-12,
+            Some('f') => 1e-15,
+            _ => 1.0,
+        }
+    };
+
+    Some(value * scale)
+}
+
+/// ```name=value``` tokens as lowercase parameters
+fn parse_params(tokens: &[String], line: usize) -> Result<HashMap<String, f64>, SpiceError> {
+    tokens
+        .iter()
+        .filter_map(|token| token.split_once('='))
+        .map(|(name, value)| {
+            let parsed = parse_value(value).ok_or_else(|| {
+                parse_error(line, &format!("invalid value {} for {}", value, name))
+            })?;
+            Ok((name.to_lowercase(), parsed))
+        })
+        .collect()
+}
+
+fn parse_model(tokens: &[String], line: usize) -> Result<SpiceModel, SpiceError> {
+    let (Some(name), Some(ty)) = (tokens.get(1), tokens.get(2)) else {
+        return Err(parse_error(line, ".model needs a name and a type"));
+    };
+
+    Ok(SpiceModel {
+        name: name.to_lowercase(),
+        ty: ty.to_lowercase(),
+        params: parse_params(&tokens[3..], line)?,
+        line,
+    })
+}
+
+fn parse_element(tokens: &[String], line: usize) -> Result<Option<SpiceElement>, SpiceError> {
+    let name = tokens[0].clone();
+    let letter = name.chars().next().unwrap_or_default().to_ascii_lowercase();
+
+    // Positional tokens, the instance parameters are the ones with '='
+    let positional: Vec<&String> = tokens.iter().filter(|token| !token.contains('=')).collect();
+    let expect = |count: usize| -> Result<(), SpiceError> {
+        if positional.len() < count {
+            return Err(parse_error(
+                line,
+                &format!("{} needs {} fields, got {}", name, count, positional.len()),
+            ));
+        }
+        Ok(())
+    };
+    let value = |token: &String| {
+        parse_value(token)
+            .ok_or_else(|| parse_error(line, &format!("invalid value {} for {}", token, name)))
+    };
+    let nodes = |range: std::ops::Range<usize>| -> Vec<String> {
+        positional[range]
+            .iter()
+            .map(|node| node.to_string())
+            .collect()
+    };
+
+    let (kind, nodes) = match letter {
+        'r' => {
+            expect(4)?;
+            (
+                SpiceElementKind::Resistor(value(positional[3])?),
+                nodes(1..3),
+            )
+        }
+        'd' => {
+            expect(4)?;
+            let model = positional[3].to_lowercase();
+            (SpiceElementKind::Diode { model }, nodes(1..3))
+        }
+        'm' => {
+            expect(6)?;
+            let model = positional[5].to_lowercase();
+            (SpiceElementKind::Mosfet { model }, nodes(1..5))
+        }
+        'n' => {
+            expect(4)?;
+            let model = positional[positional.len() - 1].to_lowercase();
+            let kind = SpiceElementKind::Memristor {
+                model,
+                params: parse_params(tokens, line)?,
+            };
+            (kind, nodes(1..positional.len() - 1))
+        }
+        'y' if name.eq_ignore_ascii_case("ymemristor") => {
+            expect(5)?;
+            let kind = SpiceElementKind::Memristor {
+                model: positional[4].to_lowercase(),
+                params: parse_params(tokens, line)?,
+            };
+            let element = SpiceElement {
+                name: positional[1].clone(),
+                kind,
+                nodes: nodes(2..4),
+                line,
+            };
+            return Ok(Some(element));
+        }
+        'v' | 'i' => {
+            expect(3)?;
+            let dc = match positional.get(3) {
+                Some(token) if token.eq_ignore_ascii_case("dc") => match positional.get(4) {
+                    Some(token) => value(token)?,
+                    None => return Err(parse_error(line, &format!("{} has no DC value", name))),
+                },
+                Some(token) => parse_value(token).unwrap_or_else(|| {
+                    warn!(
+                        "Line {}: only the DC value of {} is read, it is 0",
+                        line, name
+                    );
+                    0.0
+                }),
+                None => 0.0,
+            };
+            let kind = if letter == 'v' {
+                SpiceElementKind::VoltageSource(dc)
+            } else {
+                SpiceElementKind::CurrentSource(dc)
+            };
+            (kind, nodes(1..3))
+        }
+        'e' => {
+            expect(6)?;
+            (SpiceElementKind::Vcvs(value(positional[5])?), nodes(1..5))
+        }
+        'x' => {
+            expect(2)?;
+            let subckt = positional[positional.len() - 1].to_lowercase();
+            (
+                SpiceElementKind::Instance { subckt },
+                nodes(1..positional.len() - 1),
+            )
+        }
+        _ => {
+            warn!("Line {}: {} is not supported, it is skipped", line, name);
+            return Ok(None);
+        }
+    };
+
+    Ok(Some(SpiceElement {
+        name,
+        kind,
+        nodes,
+        line,
+    }))
+}
+
+#[cfg(test)]
+mod parser_test {
+    use super::*;
+
+    #[test]
+    fn test_parse_value() {
+        assert_eq!(parse_value("10k"), Some(10e3));
+        assert_eq!(parse_value("2.5MEG"), Some(2.5e6));
+        assert_eq!(parse_value("1e-3"), Some(1e-3));
+        assert_eq!(parse_value("3mOhm"), Some(3e-3));
+        assert!((parse_value("-4.7uF").unwrap() + 4.7e-6).abs() < 1e-18);
+        assert_eq!(parse_value("1e"), Some(1.0));
+        assert_eq!(parse_value("abc"), None);
+    }
+
+    #[test]
+    fn test_parse_and_flatten() {
+        let source = "\
+* divider with a subcircuit
+.model dmod D(IS=1e-12, N = 1.5)
+.subckt half in out
+R1 in mid 1k
+Xinner mid out leaf
+.ends
+.subckt leaf a b
+D1 a b dmod
+.ends
+V1 top 0 DC 1
+X1 top n1 half
+X2 n1 gnd half
++ ; continued
+M1 d g 0 0 nch
+.op
+.end
+";
+        let netlist = parse_spice(source).unwrap();
+        assert_eq!(netlist.title, "* divider with a subcircuit");
+        assert_eq!(netlist.models["dmod"].params["n"], 1.5);
+        assert_eq!(netlist.subckts["half"].ports, vec!["in", "out"]);
+
+        let flat = netlist.flatten().unwrap();
+        let names: Vec<&str> = flat.iter().map(|element| element.name.as_str()).collect();
+        assert_eq!(
+            names,
+            vec!["V1", "X1.R1", "X1.Xinner.D1", "X2.R1", "X2.Xinner.D1", "M1"]
+        );
+
+        assert_eq!(flat[1].nodes, vec!["top", "X1.mid"]);
+        assert_eq!(flat[2].nodes, vec!["X1.mid", "n1"]);
+        assert_eq!(flat[4].nodes, vec!["X2.mid", "0"]);
+        assert_eq!(flat[1].line, 4);
+    }
+
+    #[test]
+    fn test_errors() {
+        let error = parse_spice("title\nR1 a b\n").unwrap_err();
+        assert!(matches!(error, SpiceError::Parse { line: 2, .. }));
+
+        let error = parse_spice("title\n\nR1 a b abc\n").unwrap_err();
+        assert!(matches!(error, SpiceError::Parse { line: 3, .. }));
+
+        let error = parse_spice("title\n.subckt s a b\nR1 a b 1\n").unwrap_err();
+        assert!(matches!(error, SpiceError::Parse { line: 2, .. }));
+
+        let netlist = parse_spice("title\n.subckt s a b\nX1 a b s\n.ends\nX1 1 2 s\n").unwrap();
+        assert!(matches!(
+            netlist.flatten(),
+            Err(SpiceError::RecursiveSubckt { line: 3, .. })
+        ));
+
+        let netlist = parse_spice("title\nX1 1 2 3 missing\n").unwrap();
+        assert!(matches!(
+            netlist.flatten(),
+            Err(SpiceError::UnknownSubckt { line: 2, .. })
+        ));
+    }
+
+    #[test]
+    fn test_memristor_conductance() {
+        let source = "\
+title
+.model rram RRAM_v0 model_switch=0 g0=2.5e-10 V0=2.5e-1 I0=1e-3
+N1 a 0 rram gap_ini=1e-9
+YMEMRISTOR m2 a b rram
+";
+        let netlist = parse_spice(source).unwrap();
+        let SpiceElementKind::Memristor { model, params } = &netlist.elements[0].kind else {
+            panic!("N1 is not a memristor");
+        };
+        let expected = 1e-3 * (-1e-9f64 / 2.5e-10).exp() / 0.25;
+        assert!((netlist.memristor_conductance(model, params) - expected).abs() < 1e-15);
+
+        assert_eq!(netlist.elements[1].name, "m2");
+        assert_eq!(netlist.elements[1].nodes, vec!["a", "b"]);
+    }
+}
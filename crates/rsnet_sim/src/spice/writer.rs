@@ -0,0 +1,358 @@
+use super::SpiceError;
+
+use crate::mna::{Element, ElementKind, Waveform};
+use crate::stanford::{gap_for_conductance, StanfordModelParams};
+use crate::types::NodeId;
+
+use std::collections::HashMap;
+use std::io::Write;
+
+/// How memristors are written to the netlist
+#[derive(Debug, Clone, PartialEq)]
+pub enum MemristorModel {
+    /// A resistor with the programmed conductance of the device, enough for DC analyses at read voltages
+    Resistor,
+    /// An instance of the Verilog-A Stanford model (e.g. compiled to OSDI for ngspice) called ```module```, the gap of
+    /// every instance starts at the value that gives its programmed conductance
+    Stanford {
+        module: String,
+        params: StanfordModelParams,
+    },
+}
+
+/// Analysis card added at the end of the netlist
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SpiceAnalysis {
+    /// ```.op```
+    Op,
+    /// ```.tran step stop```. [s]
+    Tran { step: f64, stop: f64 },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpiceOptions {
+    /// First line of the netlist
+    pub title: String,
+    pub memristor_model: MemristorModel,
+    pub analyses: Vec<SpiceAnalysis>,
+}
+
+impl Default for SpiceOptions {
+    fn default() -> Self {
+        Self {
+            title: "rsnet circuit".to_string(),
+            memristor_model: MemristorModel::Resistor,
+            analyses: vec![SpiceAnalysis::Op],
+        }
+    }
+}
+
+/// Name of the model card shared by every memristor instance
+const MEMRISTOR_MODEL_NAME: &str = "rram";
+
+/// Writes a SPICE netlist one card at a time, so circuits of any size can be written without holding the netlist in
+/// memory. Cards are written with [`SpiceWriter::element`] after [`SpiceWriter::begin`], and the netlist is closed
+/// with [`SpiceWriter::end`].
+pub struct SpiceWriter<W: Write> {
+    writer: W,
+    options: SpiceOptions,
+
+    ground: NodeId,
+    node_names: HashMap<NodeId, String>,
+
+    /// Names of the diode models already written, keyed by the bits of their saturation current and ideality
+    diode_models: HashMap<(u64, u64), String>,
+}
+
+impl<W: Write> SpiceWriter<W> {
+    pub fn new(writer: W, ground: NodeId, options: SpiceOptions) -> Self {
+        Self {
+            writer,
+            options,
+            ground,
+            node_names: HashMap::new(),
+            diode_models: HashMap::new(),
+        }
+    }
+
+    /// Names ```node``` in the netlist, characters SPICE does not accept are replaced. Unnamed nodes are written as
+    /// ```n<id>``` and ground is always ```0```.
+    pub fn set_node_name(&mut self, node: NodeId, name: &str) {
+        let name: String = name
+            .chars()
+            .map(|c| {
+                if c.is_ascii_alphanumeric() || c == '_' || c == '.' {
+                    c
+                } else {
+                    '_'
+                }
+            })
+            .collect();
+        self.node_names.insert(node, name);
+    }
+
+    pub fn node_name(&self, node: NodeId) -> String {
+        if node == self.ground {
+            return "0".to_string();
+        }
+
+        self.node_names
+            .get(&node)
+            .cloned()
+            .unwrap_or_else(|| format!("n{}", node))
+    }
+
+    /// Writes the title and the model of the memristors
+    pub fn begin(&mut self) -> Result<(), SpiceError> {
+        writeln!(self.writer, "{}", self.options.title)?;
+
+        if let MemristorModel::Stanford { module, params } = &self.options.memristor_model {
+            writeln!(
+                self.writer,
+                "* Memristors use the Verilog-A Stanford RRAM model, load it before running"
+            )?;
+            writeln!(
+                self.writer,
+                ".model {} {} model_switch={} g0={:e} V0={:e} Vel0={:e} I0={:e} Beta={:e} alpha={:e} gamma0={:e}",
+                MEMRISTOR_MODEL_NAME,
+                module,
+                f64::from(params.model_switch),
+                params.g0,
+                params.v0,
+                params.vel0,
+                params.i0,
+                params.beta,
+                params.alpha,
+                params.gamma0,
+            )?;
+            writeln!(
+                self.writer,
+                "+ T_crit={:e} deltaGap0={:e} T_smth={:e} Ea={:e} a0={:e} T_ini={:e} F_min={:e}",
+                params.t_crit,
+                params.delta_gap0,
+                params.t_smth,
+                params.ea,
+                params.a0,
+                params.t_ini,
+                params.f_min,
+            )?;
+            writeln!(
+                self.writer,
+                "+ gap_min={:e} gap_max={:e} Rth={:e} tox={:e} time_step={:e}",
+                params.gap_min, params.gap_max, params.rth, params.tox, params.time_step,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    pub fn comment(&mut self, text: &str) -> Result<(), SpiceError> {
+        for line in text.lines() {
+            writeln!(self.writer, "* {}", line)?;
+        }
+        Ok(())
+    }
+
+    /// Writes the card of ```element```, independent sources follow ```waveform``` when it is given
+    pub fn element(
+        &mut self,
+        element: &Element,
+        waveform: Option<&Waveform>,
+    ) -> Result<(), SpiceError> {
+        let id = element.id;
+        let (pos, neg) = (self.node_name(element.pos), self.node_name(element.neg));
+
+        match element.kind {
+            ElementKind::Resistor(r) => writeln!(self.writer, "R{} {} {} {:e}", id, pos, neg, r)?,
+            ElementKind::Memristor(g) => match &self.options.memristor_model {
+                MemristorModel::Resistor => {
+                    writeln!(self.writer, "R{} {} {} {:e}", id, pos, neg, 1.0 / g)?
+                }
+                MemristorModel::Stanford { params, .. } => {
+                    let gap = gap_for_conductance(params, g);
+                    writeln!(
+                        self.writer,
+                        "N{} {} {} {} gap_ini={:e}",
+                        id, pos, neg, MEMRISTOR_MODEL_NAME, gap
+                    )?
+                }
+            },
+            ElementKind::VoltageSource(v) => {
+                let value = source_value(v, waveform);
+                writeln!(self.writer, "V{} {} {} {}", id, pos, neg, value)?
+            }
+            ElementKind::CurrentSource(i) => {
+                let value = source_value(i, waveform);
+                writeln!(self.writer, "I{} {} {} {}", id, pos, neg, value)?
+            }
+            ElementKind::Diode {
+                saturation_current,
+                ideality,
+            } => {
+                let key = (saturation_current.to_bits(), ideality.to_bits());
+                let model = match self.diode_models.get(&key) {
+                    Some(model) => model.clone(),
+                    None => {
+                        let model = format!("diode{}", self.diode_models.len());
+                        writeln!(
+                            self.writer,
+                            ".model {} D(IS={:e} N={:e})",
+                            model, saturation_current, ideality
+                        )?;
+                        self.diode_models.insert(key, model.clone());
+                        model
+                    }
+                };
+                writeln!(self.writer, "D{} {} {} {}", id, pos, neg, model)?
+            }
+            ElementKind::OpAmp {
+                gain,
+                non_inverting,
+                inverting,
+            } => {
+                let (non_inverting, inverting) =
+                    (self.node_name(non_inverting), self.node_name(inverting));
+                writeln!(
+                    self.writer,
+                    "E{} {} {} {} {} {:e}",
+                    id, pos, neg, non_inverting, inverting, gain
+                )?
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Writes the analysis cards and ```.end```, and returns the inner writer
+    pub fn end(mut self) -> Result<W, SpiceError> {
+        for analysis in self.options.analyses.iter() {
+            match analysis {
+                SpiceAnalysis::Op => writeln!(self.writer, ".op")?,
+                SpiceAnalysis::Tran { step, stop } => {
+                    writeln!(self.writer, ".tran {:e} {:e}", step, stop)?
+                }
+            }
+        }
+        writeln!(self.writer, ".end")?;
+        self.writer.flush()?;
+
+        Ok(self.writer)
+    }
+}
+
+fn source_value(dc: f64, waveform: Option<&Waveform>) -> String {
+    match waveform {
+        None => format!("DC {:e}", dc),
+        Some(Waveform::Dc(v)) => format!("DC {:e}", v),
+        Some(Waveform::Pulse {
+            initial,
+            pulsed,
+            delay,
+            rise,
+            fall,
+            width,
+            period,
+        }) => format!(
+            "PULSE({:e} {:e} {:e} {:e} {:e} {:e} {:e})",
+            initial, pulsed, delay, rise, fall, width, period
+        ),
+        Some(Waveform::Pwl(points)) => {
+            let points: Vec<String> = points
+                .iter()
+                .map(|(t, v)| format!("{:e} {:e}", t, v))
+                .collect();
+            format!("PWL({})", points.join(" "))
+        }
+    }
+}
+
+#[cfg(test)]
+mod writer_test {
+    use super::*;
+    use crate::mna::Circuit;
+    use crate::stanford::device_conductance;
+
+    fn write(
+        circuit: &Circuit,
+        options: SpiceOptions,
+        waveforms: &HashMap<u32, Waveform>,
+    ) -> String {
+        let mut writer = SpiceWriter::new(Vec::new(), circuit.ground(), options);
+        writer.set_node_name(1, "in put");
+        writer.begin().unwrap();
+        for element in circuit.elements() {
+            writer.element(element, waveforms.get(&element.id)).unwrap();
+        }
+        String::from_utf8(writer.end().unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_write_cards() {
+        let mut circuit = Circuit::new(0);
+        circuit.add_voltage_source(0, 1, 0, 0.2);
+        circuit.add_memristor(1, 1, 2, 1e-4);
+        circuit.add_resistor(2, 2, 0, 1e3);
+        circuit.add_op_amp(3, 0, 2, 3, 1e6);
+        circuit.add_diode(4, 3, 4, 1e-14, 1.0);
+        circuit.add_diode(5, 4, 0, 1e-14, 1.0);
+
+        let options = SpiceOptions {
+            analyses: vec![
+                SpiceAnalysis::Op,
+                SpiceAnalysis::Tran {
+                    step: 1e-9,
+                    stop: 1e-6,
+                },
+            ],
+            ..SpiceOptions::default()
+        };
+        let netlist = write(&circuit, options, &HashMap::new());
+        let lines: Vec<&str> = netlist.lines().collect();
+
+        assert_eq!(lines[0], "rsnet circuit");
+        assert!(lines.contains(&"V0 in_put 0 DC 2e-1"));
+        assert!(lines.contains(&"R1 in_put n2 1e4"));
+        assert!(lines.contains(&"R2 n2 0 1e3"));
+        assert!(lines.contains(&"E3 n3 0 0 n2 1e6"));
+        // Both diodes share their model
+        assert_eq!(
+            lines
+                .iter()
+                .filter(|line| line.starts_with(".model"))
+                .count(),
+            1
+        );
+        assert_eq!(
+            &lines[lines.len() - 3..],
+            &[".op", ".tran 1e-9 1e-6", ".end"]
+        );
+    }
+
+    #[test]
+    fn test_stanford_instances() {
+        let mut circuit = Circuit::new(0);
+        circuit.add_voltage_source(0, 1, 0, 0.2);
+        circuit.add_memristor(1, 1, 0, 5e-5);
+
+        let params = StanfordModelParams::default();
+        let options = SpiceOptions {
+            memristor_model: MemristorModel::Stanford {
+                module: "RRAM_v0".to_string(),
+                params: params.clone(),
+            },
+            ..SpiceOptions::default()
+        };
+        let waveforms = HashMap::from([(0, Waveform::Pwl(vec![(0.0, 0.0), (1e-6, 1.0)]))]);
+        let netlist = write(&circuit, options, &waveforms);
+
+        assert!(netlist.contains(".model rram RRAM_v0 model_switch=0 g0=2.5e-10"));
+        assert!(netlist.contains("V0 in_put 0 PWL(0e0 0e0 1e-6 1e0)"));
+
+        let instance = netlist
+            .lines()
+            .find(|line| line.starts_with("N1 "))
+            .unwrap();
+        let gap: f64 = instance.split("gap_ini=").nth(1).unwrap().parse().unwrap();
+        assert!((device_conductance(&params, gap, 0.0) - 5e-5).abs() < 1e-12);
+    }
+}
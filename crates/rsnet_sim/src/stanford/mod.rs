@@ -0,0 +1,24 @@
+pub mod model;
+pub mod params;
+
+pub use model::*;
+pub use params::*;
+
+use thiserror::Error;
+
+use crate::range::Range;
+
+/// Boltzmann constant. [J/K]
+pub const KB: f64 = 1.380649e-23;
+/// Elementary charge. [C]
+pub const Q: f64 = 1.602176634e-19;
+
+#[derive(Error, Debug)]
+pub enum StanfordModelError {
+    #[error("Invalid value ({name} = {value}) for parameter `{name}`, range is {range}")]
+    InvalidParameter {
+        name: &'static str,
+        value: f64,
+        range: Range,
+    },
+}
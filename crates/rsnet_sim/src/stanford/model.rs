@@ -0,0 +1,302 @@
+use super::{StanfordModelError, StanfordModelParams, KB, Q};
+
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use rand_distr::StandardNormal;
+
+/// Result of advancing the model by one time step
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct StanfordStep {
+    /// Current across the device (top to bottom electrode). [A]
+    pub current: f64,
+    /// Gap distance after the step, clamped to `[gap_min, gap_max]`. [m]
+    pub gap: f64,
+}
+
+/// Native port of `StanfordModel` in `sim/python_stanford_model/lib.py`.
+///
+/// The integration scheme (trapezoidal rule between consecutive calls to [`StanfordModel::step`]) and its quirks are
+/// kept as in the python implementation, so both produce the same results for the standard model.
+#[derive(Debug, Clone)]
+pub struct StanfordModel {
+    params: StanfordModelParams,
+    rng: StdRng,
+
+    /// Voltage at top electrode. [V]
+    te: f64,
+    /// Voltage at bottom electrode. [V]
+    be: f64,
+    /// Current across the device, used to compute the self heating of the device. [A]
+    itb: f64,
+
+    gap: f64,
+    gap_ddt: f64,
+    gap_random_ddt: f64,
+    temperature: f64,
+
+    prev_time: f64,
+    prev_gap: f64,
+    prev_gap_ddt: Option<f64>,
+    prev_gap_random_ddt: Option<f64>,
+}
+
+impl StanfordModel {
+    /// Creates a model whose variability (only used by [`ModelSwitch::Dynamic`]) is seeded from entropy
+    pub fn new(params: StanfordModelParams) -> Result<Self, StanfordModelError> {
+        Self::with_rng(params, StdRng::from_entropy())
+    }
+
+    /// Creates a model with a reproducible variability sequence
+    pub fn with_seed(params: StanfordModelParams, seed: u64) -> Result<Self, StanfordModelError> {
+        Self::with_rng(params, StdRng::seed_from_u64(seed))
+    }
+
+    fn with_rng(params: StanfordModelParams, rng: StdRng) -> Result<Self, StanfordModelError> {
+        params.validate()?;
+
+        Ok(Self {
+            rng,
+            te: 0.0,
+            be: 0.0,
+            itb: 0.0,
+            // The python model starts with a zero gap until the first integration step is taken
+            gap: 0.0,
+            gap_ddt: 0.0,
+            gap_random_ddt: 0.0,
+            temperature: params.t_ini,
+            prev_time: 0.0,
+            prev_gap: params.gap_ini,
+            prev_gap_ddt: None,
+            prev_gap_random_ddt: None,
+            params,
+        })
+    }
+
+    pub fn params(&self) -> &StanfordModelParams {
+        &self.params
+    }
+
+    pub fn set_voltages(&mut self, te: f64, be: f64) {
+        self.te = te;
+        self.be = be;
+    }
+
+    pub fn voltages(&self) -> (f64, f64) {
+        (self.te, self.be)
+    }
+
+    /// Sets the current used to compute the device temperature on the next step
+    pub fn set_current(&mut self, itb: f64) {
+        self.itb = itb;
+    }
+
+    pub fn current(&self) -> f64 {
+        self.itb
+    }
+
+    /// Gap computed on the last step. As in the python model this is zero before the first step, see
+    /// [`StanfordModel::effective_gap`]
+    pub fn gap(&self) -> f64 {
+        self.gap
+    }
+
+    /// Gap that determines the conductance of the device, `gap_ini` until the first step is taken. [m]
+    pub fn effective_gap(&self) -> f64 {
+        if self.prev_gap_ddt.is_none() {
+            self.params.gap_ini
+        } else {
+            self.gap
+        }
+    }
+
+    /// Time derivative of the gap computed on the last step (without the random component). [m/s]
+    pub fn gap_ddt(&self) -> f64 {
+        self.gap_ddt
+    }
+
+    /// Device temperature used on the last step. [K]
+    pub fn temperature(&self) -> f64 {
+        self.temperature
+    }
+
+    pub fn prev_time(&self) -> f64 {
+        self.prev_time
+    }
+
+    /// Current for a given voltage at the present gap, it does not modify the state of the model. [A]
+    pub fn current_at(&self, vtb: f64) -> f64 {
+        device_current(&self.params, self.effective_gap(), vtb)
+    }
+
+    /// Small signal conductance for a given voltage at the present gap. [S]
+    pub fn conductance_at(&self, vtb: f64) -> f64 {
+        device_conductance(&self.params, self.effective_gap(), vtb)
+    }
+
+    /// Advances the model to `current_time` using the voltages set by [`StanfordModel::set_voltages`]
+    pub fn step(&mut self, current_time: f64) -> StanfordStep {
+        let p = &self.params;
+        let vtb = self.te - self.be;
+
+        self.temperature = p.t_ini + (vtb * self.itb * p.rth).abs();
+
+        self.gap_ddt = gap_ddt(p, self.gap, vtb, self.temperature);
+
+        let delta_gap = p.delta_gap0 * f64::from(p.model_switch);
+        let randn: f64 = self.rng.sample(StandardNormal);
+        self.gap_random_ddt =
+            randn * delta_gap / (1.0 + ((p.t_crit - self.temperature) / p.t_smth).exp());
+
+        self.gap = self.compute_gap_int(current_time);
+
+        self.prev_time = current_time;
+        self.prev_gap_random_ddt = Some(self.gap_random_ddt);
+        self.prev_gap_ddt = Some(self.gap_ddt);
+        // As in the python model, the integral keeps the unclamped value
+        self.prev_gap = self.gap;
+
+        let p = &self.params;
+        self.gap = self.gap.clamp(p.gap_min, p.gap_max);
+
+        StanfordStep {
+            current: device_current(p, self.gap, vtb),
+            gap: self.gap,
+        }
+    }
+
+    fn compute_gap_int(&mut self, current_time: f64) -> f64 {
+        let prev_gap_ddt = *self.prev_gap_ddt.get_or_insert(self.gap_ddt);
+
+        let prev_gap_random_ddt = match self.prev_gap_random_ddt {
+            Some(prev) => prev,
+            None => {
+                self.prev_gap_random_ddt = Some(self.gap_random_ddt);
+                return self.prev_gap;
+            }
+        };
+
+        self.prev_gap
+            + (current_time - self.prev_time)
+                * ((prev_gap_ddt + prev_gap_random_ddt) + (self.gap_ddt + self.gap_random_ddt))
+                / 2.0
+    }
+}
+
+/// Current across a device with gap `gap` when `vtb` is applied between its electrodes. [A]
+pub fn device_current(params: &StanfordModelParams, gap: f64, vtb: f64) -> f64 {
+    params.i0 * (-gap / params.g0).exp() * (vtb / params.v0).sinh()
+}
+
+/// Derivative of [`device_current`] with respect to `vtb`. [S]
+pub fn device_conductance(params: &StanfordModelParams, gap: f64, vtb: f64) -> f64 {
+    params.i0 * (-gap / params.g0).exp() * (vtb / params.v0).cosh() / params.v0
+}
+
+/// Gap for which the small signal conductance (```vtb``` = 0) of the device is ```conductance```, the inverse of
+/// [`device_conductance`]. It is clamped to ```[gap_min, gap_max]```. [m]
+pub fn gap_for_conductance(params: &StanfordModelParams, conductance: f64) -> f64 {
+    let gap = params.g0 * (params.i0 / (params.v0 * conductance)).ln();
+    gap.clamp(params.gap_min, params.gap_max)
+}
+
+/// Deterministic gap growth rate for a device at `temperature`. [m/s]
+pub fn gap_ddt(params: &StanfordModelParams, gap: f64, vtb: f64, temperature: f64) -> f64 {
+    let gamma_ini = if vtb < 0.0 { 16.0 } else { params.gamma0 };
+
+    let mut gamma = gamma_ini - params.beta * (gap / 1e-9).powf(params.alpha);
+
+    if gamma * vtb.abs() / params.tox < params.f_min {
+        gamma = 0.0;
+    }
+
+    -params.vel0
+        * (-Q * params.ea / KB / temperature).exp()
+        * (gamma * params.a0 / params.tox * Q * vtb / KB / temperature).sinh()
+}
+
+#[cfg(test)]
+mod stanford_model_test {
+    use super::*;
+    use crate::stanford::ModelSwitch;
+
+    /// Pulse applied in the golden tests: SET, read and RESET with 1 ns steps
+    fn pulse(i: u32) -> f64 {
+        match i {
+            1..=15 => -1.6,
+            16..=20 => 0.2,
+            _ => 1.6,
+        }
+    }
+
+    fn assert_rel_eq(a: f64, b: f64) {
+        assert!(
+            ((a - b) / b).abs() < 1e-9,
+            "{} and {} are not equal (relative tolerance 1e-9)",
+            a,
+            b
+        );
+    }
+
+    #[test]
+    fn test_golden_standard_model() {
+        // (step, current, gap) generated with `sim/python_stanford_model/lib.py` and the default parameters
+        let golden = [
+            (1, -0.13521283042017218, 2e-10),
+            (2, -0.03062223327710289, 5.712809339630033e-10),
+            (3, -0.007907819759245222, 9.097494864549374e-10),
+            (10, -0.0009349969244020536, 1.4435155244430252e-09),
+            (15, -0.0007038679332421495, 1.5145036484780923e-09),
+            (16, 2.032238844534715e-06, 1.5199882551926256e-09),
+            (20, 2.032238844534715e-06, 1.5199882551926256e-09),
+            (21, 0.0007021145210622101, 1.5151272028772198e-09),
+            (30, 0.0011477845455190956, 1.3922541141248235e-09),
+            (40, 0.009099503271889495, 8.746575580343399e-10),
+        ];
+
+        let mut model = StanfordModel::with_seed(StanfordModelParams::default(), 0).unwrap();
+        let mut steps = Vec::new();
+        for i in 1..=40 {
+            model.set_voltages(pulse(i), 0.0);
+            steps.push(model.step(i as f64 * 1e-9));
+        }
+
+        for (i, current, gap) in golden {
+            let step = steps[i - 1];
+            assert_rel_eq(step.current, current);
+            assert_rel_eq(step.gap, gap);
+        }
+    }
+
+    #[test]
+    fn test_dynamic_model_is_seedable() {
+        let params = StanfordModelParams {
+            model_switch: ModelSwitch::Dynamic,
+            ..Default::default()
+        };
+
+        let run = |seed| {
+            let mut model = StanfordModel::with_seed(params.clone(), seed).unwrap();
+            (1..=40)
+                .map(|i| {
+                    model.set_voltages(pulse(i), 0.0);
+                    model.step(i as f64 * 1e-9).gap
+                })
+                .collect::<Vec<f64>>()
+        };
+
+        assert_eq!(run(7), run(7));
+        assert_ne!(run(7), run(8));
+    }
+
+    #[test]
+    fn test_invalid_params() {
+        let params = StanfordModelParams {
+            t_crit: 300.0,
+            ..Default::default()
+        };
+
+        match StanfordModel::new(params) {
+            Err(StanfordModelError::InvalidParameter { name, .. }) => assert_eq!(name, "t_crit"),
+            _ => panic!("t_crit out of range should not be accepted"),
+        }
+    }
+}
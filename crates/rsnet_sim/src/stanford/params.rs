@@ -0,0 +1,170 @@
+use super::StanfordModelError;
+
+use crate::range::{Range, RangeType};
+
+/// Selects between the standard (deterministic) and the dynamic (with gap variability) Stanford model.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+#[repr(u8)]
+pub enum ModelSwitch {
+    #[default]
+    Standard = 0,
+    Dynamic = 1,
+}
+
+impl From<ModelSwitch> for f64 {
+    fn from(value: ModelSwitch) -> Self {
+        value as u8 as f64
+    }
+}
+
+/// Parameters for the RRAM Stanford model
+#[derive(Debug, Clone, PartialEq)]
+pub struct StanfordModelParams {
+    /// Switch between the standard model (0) or the dynamic model (1). [N/A]
+    pub model_switch: ModelSwitch,
+
+    /// Average switching parameter. Represents the resistance window. [m]
+    pub g0: f64,
+    /// Average switching parameter. Depicts the nonlinarity of the resistance curve. [V]
+    pub v0: f64,
+    /// Average switching parameter. Determines the voltage level at wich the gap starts to grow. It mainly changes the
+    /// RESET knee-point voltage. [nm/ns]
+    pub vel0: f64,
+    /// Average switching parameter. Shifts the curve to different current levels. [A]
+    pub i0: f64,
+    /// Average switching parameter. Captures the RESET slope (slope of the RESET slope as per the paper). [N/A]
+    pub beta: f64,
+    /// Average switching parameter. Changes the curvature of the Reset slope. [N/A]
+    pub alpha: f64,
+    /// Average switching parameter. Determines the voltage level at wich the gap starts to grow. It mainly changes the
+    /// SET voltage. [N/A]
+    pub gamma0: f64,
+
+    /// Threshold temperature for significant random variations in the dynamic model. [K]
+    pub t_crit: f64,
+    /// Variations fitting parameters. [m]
+    pub delta_gap0: f64,
+    /// Variations smoothing parameter. [K]
+    pub t_smth: f64,
+    /// Activation energy for vacancy generation. [eV]
+    pub ea: f64,
+    /// Lattice parameter. Atom spacing. [m]
+    pub a0: f64,
+    /// Initial room temperature in devices. [K]
+    pub t_ini: f64,
+    /// Minimum field requierement to enhance gap formation. [V/m]
+    pub f_min: f64,
+
+    /// Initial gap distance. [m]
+    pub gap_ini: f64,
+    /// Minimum gap distance. [m]
+    pub gap_min: f64,
+    /// Maximum gap distance. [m]
+    pub gap_max: f64,
+
+    /// Thermal resistance. [K/W]
+    pub rth: f64,
+    /// Oxide thickness. [m]
+    pub tox: f64,
+    /// Time step bound for the simulation. [s]
+    pub time_step: f64,
+}
+
+impl Default for StanfordModelParams {
+    fn default() -> Self {
+        Self {
+            model_switch: ModelSwitch::Standard,
+            g0: 0.25e-9,
+            v0: 0.25,
+            vel0: 10.0,
+            i0: 1000e-6,
+            beta: 0.8,
+            alpha: 3.0,
+            gamma0: 16.0,
+            t_crit: 450.0,
+            delta_gap0: 0.02,
+            t_smth: 500.0,
+            ea: 0.6,
+            a0: 0.25e-9,
+            t_ini: 273.0 + 25.0,
+            f_min: 1.4e9,
+            gap_ini: 2e-10,
+            gap_min: 2e-10,
+            gap_max: 17e-10,
+            rth: 2.1e3,
+            tox: 12e-9,
+            time_step: 100e-6,
+        }
+    }
+}
+
+const INF: f64 = f64::INFINITY;
+
+impl StanfordModelParams {
+    /// Valid range of every parameter, in declaration order
+    pub const RANGES: [(&'static str, Range); 21] = [
+        ("model_switch", Range::new(0.0, 1.0, RangeType::ClosedClosed)),
+        ("g0", Range::new(0.0, 2e-9, RangeType::OpenOpen)),
+        ("v0", Range::new(0.0, 10.0, RangeType::OpenOpen)),
+        ("vel0", Range::new(0.0, 20.0, RangeType::OpenOpen)),
+        ("i0", Range::new(0.0, 1e-2, RangeType::OpenOpen)),
+        ("beta", Range::new(0.0, INF, RangeType::OpenOpen)),
+        ("alpha", Range::new(0.0, INF, RangeType::OpenOpen)),
+        ("gamma0", Range::new(0.0, INF, RangeType::OpenOpen)),
+        ("t_crit", Range::new(390.0, 460.0, RangeType::OpenOpen)),
+        ("delta_gap0", Range::new(0.0, 0.1, RangeType::ClosedOpen)),
+        ("t_smth", Range::new(400.0, 600.0, RangeType::OpenOpen)),
+        ("ea", Range::new(0.0, 1.0, RangeType::OpenOpen)),
+        ("a0", Range::new(0.0, INF, RangeType::OpenOpen)),
+        ("t_ini", Range::new(0.0, INF, RangeType::OpenOpen)),
+        ("f_min", Range::new(0.0, 3e9, RangeType::OpenOpen)),
+        ("gap_ini", Range::new(0.0, 100e-10, RangeType::OpenOpen)),
+        ("gap_min", Range::new(0.0, 100e-10, RangeType::OpenOpen)),
+        ("gap_max", Range::new(0.0, 100e-10, RangeType::OpenOpen)),
+        ("rth", Range::new(0.0, INF, RangeType::OpenOpen)),
+        ("tox", Range::new(0.0, 100e-9, RangeType::OpenOpen)),
+        ("time_step", Range::new(1e-15, 1.0, RangeType::OpenOpen)),
+    ];
+
+    /// Parameter values in the same order as [`StanfordModelParams::RANGES`]
+    fn values(&self) -> [f64; 21] {
+        [
+            self.model_switch.into(),
+            self.g0,
+            self.v0,
+            self.vel0,
+            self.i0,
+            self.beta,
+            self.alpha,
+            self.gamma0,
+            self.t_crit,
+            self.delta_gap0,
+            self.t_smth,
+            self.ea,
+            self.a0,
+            self.t_ini,
+            self.f_min,
+            self.gap_ini,
+            self.gap_min,
+            self.gap_max,
+            self.rth,
+            self.tox,
+            self.time_step,
+        ]
+    }
+
+    /// Checks every parameter against its valid range, returns the first invalid one
+    pub fn validate(&self) -> Result<(), StanfordModelError> {
+        for ((name, range), value) in Self::RANGES.iter().zip(self.values()) {
+            if !range.validate(value) {
+                return Err(StanfordModelError::InvalidParameter {
+                    name,
+                    value,
+                    range: *range,
+                });
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,213 @@
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum TilingError {
+    #[error("Invalid tile size {0}x{1}, both dimensions must be positive")]
+    InvalidTileSize(usize, usize),
+    #[error("Tiles with {max_cols} columns cannot hold whole groups of {group} columns")]
+    ColumnGroupTooLarge { max_cols: usize, group: usize },
+    #[error("Expected {expected} conductances, got {got}")]
+    DimensionMismatch { expected: usize, got: usize },
+}
+
+/// Size of the physical crossbar arrays
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TilingConfig {
+    pub max_rows: usize,
+    pub max_cols: usize,
+}
+
+impl Default for TilingConfig {
+    fn default() -> Self {
+        Self {
+            max_rows: 128,
+            max_cols: 128,
+        }
+    }
+}
+
+impl TilingConfig {
+    pub fn new(max_rows: usize, max_cols: usize) -> Self {
+        Self { max_rows, max_cols }
+    }
+}
+
+/// Part of a layer matrix assigned to one physical array
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Tile {
+    /// Position of the tile in the grid of tiles of the layer, (row, column)
+    pub grid_pos: (usize, usize),
+    /// First row of the layer matrix in the tile
+    pub row_start: usize,
+    pub rows: usize,
+    /// First column of the layer matrix in the tile
+    pub col_start: usize,
+    pub cols: usize,
+}
+
+/// Split of a `rows` x `cols` layer matrix into tiles of at most `config` size.
+///
+/// Tiles on the same grid row share inputs, tiles on the same grid column produce partial sums of the same outputs that
+/// need to be accumulated.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TilingPlan {
+    pub rows: usize,
+    pub cols: usize,
+    pub config: TilingConfig,
+    /// Number of tiles along the rows and the columns
+    pub grid: (usize, usize),
+    /// Row major order over the grid
+    pub tiles: Vec<Tile>,
+}
+
+impl TilingPlan {
+    /// Plans the tiles of a `rows` x `cols` matrix. Columns are split in multiples of `col_group` so that devices
+    /// that implement the same weight (e.g. differential pairs) stay in the same array.
+    pub fn new(
+        rows: usize,
+        cols: usize,
+        config: TilingConfig,
+        col_group: usize,
+    ) -> Result<Self, TilingError> {
+        if config.max_rows == 0 || config.max_cols == 0 {
+            return Err(TilingError::InvalidTileSize(
+                config.max_rows,
+                config.max_cols,
+            ));
+        }
+
+        let col_group = col_group.max(1);
+        let tile_cols = config.max_cols - config.max_cols % col_group;
+        if tile_cols == 0 {
+            return Err(TilingError::ColumnGroupTooLarge {
+                max_cols: config.max_cols,
+                group: col_group,
+            });
+        }
+        let tile_rows = config.max_rows;
+
+        let grid = (rows.div_ceil(tile_rows), cols.div_ceil(tile_cols));
+
+        let mut tiles = Vec::with_capacity(grid.0 * grid.1);
+        for grid_row in 0..grid.0 {
+            for grid_col in 0..grid.1 {
+                let (row_start, col_start) = (grid_row * tile_rows, grid_col * tile_cols);
+                tiles.push(Tile {
+                    grid_pos: (grid_row, grid_col),
+                    row_start,
+                    rows: tile_rows.min(rows - row_start),
+                    col_start,
+                    cols: tile_cols.min(cols - col_start),
+                });
+            }
+        }
+
+        Ok(Self {
+            rows,
+            cols,
+            config,
+            grid,
+            tiles,
+        })
+    }
+
+    pub fn array_count(&self) -> usize {
+        self.tiles.len()
+    }
+
+    /// Fraction of the devices of the arrays that are used by the layer
+    pub fn utilization(&self) -> f64 {
+        let available = self.array_count() * self.config.max_rows * self.config.max_cols;
+        if available == 0 {
+            0.0
+        } else {
+            (self.rows * self.cols) as f64 / available as f64
+        }
+    }
+
+    /// Whether the outputs are split in partial sums that need to be accumulated
+    pub fn needs_accumulation(&self) -> bool {
+        self.grid.0 > 1
+    }
+
+    /// Tiles whose partial sums are added to produce the outputs of the grid column `grid_col`
+    pub fn column_tiles(&self, grid_col: usize) -> impl Iterator<Item = &Tile> {
+        self.tiles
+            .iter()
+            .filter(move |tile| tile.grid_pos.1 == grid_col)
+    }
+
+    /// Extracts the conductances of `tile` from the column major `conductances` of the whole layer, the result is
+    /// column major too.
+    pub fn tile_conductances(
+        &self,
+        tile: &Tile,
+        conductances: &[f64],
+    ) -> Result<Vec<f64>, TilingError> {
+        if conductances.len() != self.rows * self.cols {
+            return Err(TilingError::DimensionMismatch {
+                expected: self.rows * self.cols,
+                got: conductances.len(),
+            });
+        }
+
+        Ok((tile.col_start..tile.col_start + tile.cols)
+            .flat_map(|col| {
+                let start = col * self.rows + tile.row_start;
+                conductances[start..start + tile.rows].iter().copied()
+            })
+            .collect())
+    }
+}
+
+#[cfg(test)]
+mod tiling_test {
+    use super::*;
+
+    #[test]
+    fn test_tiling_plan() {
+        // 30 inputs x 1000 outputs with differential pairs
+        let plan = TilingPlan::new(30, 2000, TilingConfig::new(16, 127), 2).unwrap();
+
+        assert_eq!(plan.grid, (2, 16));
+        assert_eq!(plan.array_count(), 32);
+        assert!(plan.needs_accumulation());
+
+        // Odd tile widths are reduced so that pairs are not split
+        assert!(plan
+            .tiles
+            .iter()
+            .all(|tile| tile.cols % 2 == 0 && tile.cols <= 126));
+        let last = plan.tiles.last().unwrap();
+        assert_eq!((last.row_start, last.rows), (16, 14));
+        assert_eq!((last.col_start, last.cols), (1890, 110));
+
+        let used: usize = plan.tiles.iter().map(|tile| tile.rows * tile.cols).sum();
+        assert_eq!(used, 30 * 2000);
+        assert!((plan.utilization() - 60000.0 / (32.0 * 16.0 * 127.0)).abs() < 1e-12);
+
+        assert_eq!(plan.column_tiles(3).count(), 2);
+    }
+
+    #[test]
+    fn test_tile_conductances() {
+        // 3x3 matrix, column major
+        let conductances: Vec<f64> = (0..9).map(|v| v as f64).collect();
+        let plan = TilingPlan::new(3, 3, TilingConfig::new(2, 2), 1).unwrap();
+
+        assert_eq!(plan.grid, (2, 2));
+        assert_eq!(
+            plan.tile_conductances(&plan.tiles[0], &conductances)
+                .unwrap(),
+            vec![0.0, 1.0, 3.0, 4.0]
+        );
+        assert_eq!(
+            plan.tile_conductances(&plan.tiles[3], &conductances)
+                .unwrap(),
+            vec![8.0]
+        );
+        assert!(!TilingPlan::new(3, 3, TilingConfig::new(4, 4), 1)
+            .unwrap()
+            .needs_accumulation());
+    }
+}
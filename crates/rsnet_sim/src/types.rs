@@ -0,0 +1,4 @@
+/// Identifier of a circuit node, matches the `NodeId` used by the scene netlist.
+pub type NodeId = usize;
+/// Identifier of a circuit element, matches the component `Id` used by the scene.
+pub type ElementId = u32;
@@ -225,6 +225,20 @@ impl CameraController {
         &self.camera
     }
 
+    /// Position of the mouse in the window [px]
+    pub fn mouse_position(&self) -> PhysicalPosition<f64> {
+        self.current_mouse.position
+    }
+
+    pub fn modifiers(&self) -> ModifiersState {
+        self.modifiers
+    }
+
+    /// Point of the scene under a position of the window
+    pub fn screen_to_world(&self, position: &PhysicalPosition<f64>) -> Option<Vector2<f32>> {
+        screen_to_world(&self.camera.build_view_proj(), &self.window_size, position)
+    }
+
     fn check_upside_down(&mut self) {
         self.upside_down = self.vert_angle.abs() > FRAC_PI_2;
     }
@@ -546,6 +560,32 @@ fn mouse_diff_from_radius(
     Vector2::new(diff.x, diff.y)
 }
 
+/// Point of the scene plane (z = 0) under a position of the window, found by unprojecting the ray through it. ```None```
+/// if the view is parallel to the plane.
+pub fn screen_to_world(
+    view_proj: &Matrix4<f32>,
+    window_size: &PhysicalSize<u32>,
+    position: &PhysicalPosition<f64>,
+) -> Option<Vector2<f32>> {
+    // The far plane is so far that it is at infinity in f32, so the ray goes through the near plane and the middle
+    // of the depth range, unprojected in f64 as they are very close to each other
+    let inverse = view_proj.cast::<f64>().try_inverse()?;
+
+    // The y axis of the window points down, the one of the NDC points up
+    let ndc_x = 2.0 * position.x / window_size.width as f64 - 1.0;
+    let ndc_y = 1.0 - 2.0 * position.y / window_size.height as f64;
+
+    let near = inverse.transform_point(&Point3::new(ndc_x, ndc_y, -1.0));
+    let mid = inverse.transform_point(&Point3::new(ndc_x, ndc_y, 0.0));
+    let dir = mid - near;
+    if dir.z.abs() < f64::EPSILON {
+        return None;
+    }
+
+    let t = -near.z / dir.z;
+    Some(Vector2::new((near.x + dir.x * t) as f32, (near.y + dir.y * t) as f32))
+}
+
 /// Get screen space axis aligned bounding box
 #[inline]
 pub fn get_ss_aabb(perspective: &Perspective3<f32>, radius: f32, center: &Vector3<f32>) -> AaBb {
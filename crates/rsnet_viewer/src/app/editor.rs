@@ -0,0 +1,364 @@
+use super::{selection::Selection, utils::chunk_size_from_step_idx};
+use crate::{
+    scene::{
+        component::{Component, DefaultComponentTypes},
+        history::{id_ranges, Command, History},
+        scene_manager::{SceneManager, SceneManagerError},
+        wire::Wire,
+    },
+    types::Id,
+};
+
+use nalgebra::Vector2;
+
+/// What a left click does in edit mode
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Tool {
+    /// Selects, dragging a selected component moves the selection
+    #[default]
+    Select,
+    /// Places a component of the type
+    Place(DefaultComponentTypes),
+    /// Draws a wire from where the button is pressed to where it is released
+    Wire,
+}
+
+/// Components moved by a drag. They are moved while dragging and the move is recorded when the drag ends.
+#[derive(Debug)]
+pub struct MoveDrag {
+    /// Point of the scene where the drag started
+    start: Vector2<f32>,
+    /// Position of the component the drag started on, it is the one snapped to the grid
+    anchor: Vector2<f32>,
+    /// Ids of the moved components, as ```(first id, last id)``` ranges
+    ranges: Vec<(Id, Id)>,
+    /// Translation of the components since the drag started
+    offset: Vector2<f32>,
+}
+
+/// Wire drawn by a drag, it is added to the scene once it is longer than zero
+#[derive(Debug)]
+pub struct WireDrag {
+    id: Id,
+    start: Vector2<f32>,
+    is_added: bool,
+}
+
+/// Edit mode of the viewer: the tool in use and the history of the edits
+#[derive(Debug, Default)]
+pub struct Editor {
+    is_enabled: bool,
+    tool: Tool,
+    history: History,
+}
+
+impl Editor {
+    pub fn is_enabled(&self) -> bool {
+        self.is_enabled
+    }
+
+    /// Enables or disables the edit mode, the tool goes back to ```Tool::Select``` when it is disabled
+    pub fn set_enabled(&mut self, is_enabled: bool) {
+        self.is_enabled = is_enabled;
+        if !is_enabled {
+            self.tool = Tool::Select;
+        }
+    }
+
+    pub fn tool(&self) -> Tool {
+        self.tool
+    }
+
+    pub fn set_tool(&mut self, tool: Tool) {
+        self.tool = tool;
+    }
+
+    pub fn history(&self) -> &History {
+        &self.history
+    }
+
+    /// Used to record edits made outside of the edit mode, e.g. in the inspector
+    pub fn history_mut(&mut self) -> &mut History {
+        &mut self.history
+    }
+
+    /// Places a component of type ```ty``` at ```position``` and selects it
+    pub fn place(
+        &mut self,
+        scene_manager: &mut SceneManager,
+        selection: &mut Selection,
+        ty: DefaultComponentTypes,
+        position: Vector2<f32>,
+    ) -> Result<Id, SceneManagerError> {
+        let id = scene_manager.next_free_id();
+        let mut component = Component::new(id, 0, position, 0.0, ty.into());
+        component.set_params(ty.default_params());
+
+        self.history.execute(
+            scene_manager,
+            Command::AddComponent {
+                chunk_step_idx: 0,
+                component,
+                bindings: vec![],
+            },
+        )?;
+
+        selection.clear();
+        selection.select_component(id);
+        Ok(id)
+    }
+
+    /// Starts moving the selected components from ```point```, the drag started on the component ```anchor_id```
+    pub fn begin_move(
+        &self,
+        scene_manager: &SceneManager,
+        selection: &Selection,
+        anchor_id: Id,
+        point: Vector2<f32>,
+    ) -> Option<MoveDrag> {
+        let anchor = *scene_manager.scene().get_component(anchor_id)?.position();
+
+        Some(MoveDrag {
+            start: point,
+            anchor,
+            ranges: id_ranges(selection.components().iter().copied()),
+            offset: Vector2::zeros(),
+        })
+    }
+
+    /// Moves the components of ```drag``` with the mouse at ```point```, so that the anchor is on the grid
+    pub fn update_move(
+        &self,
+        scene_manager: &mut SceneManager,
+        drag: &mut MoveDrag,
+        point: Vector2<f32>,
+        grid_step: f32,
+    ) -> Result<(), SceneManagerError> {
+        let offset = snap(&(drag.anchor + point - drag.start), grid_step) - drag.anchor;
+        if offset == drag.offset {
+            return Ok(());
+        }
+
+        // Previewed without recording it, ```end_move``` records the whole move
+        Command::TranslateComponents {
+            ranges: drag.ranges.clone(),
+            offset: offset - drag.offset,
+        }
+        .apply(scene_manager)?;
+        drag.offset = offset;
+
+        Ok(())
+    }
+
+    /// Records the move of ```drag```, returns whether the components were moved
+    pub fn end_move(&mut self, drag: MoveDrag) -> bool {
+        if drag.offset == Vector2::zeros() {
+            return false;
+        }
+
+        self.history.record(Command::TranslateComponents {
+            ranges: drag.ranges,
+            offset: -drag.offset,
+        });
+
+        true
+    }
+
+    /// Rotates every selected component by ```angle``` around its position
+    pub fn rotate_selection(
+        &mut self,
+        scene_manager: &mut SceneManager,
+        selection: &Selection,
+        angle: f32,
+    ) -> Result<(), SceneManagerError> {
+        let scene = scene_manager.scene();
+        let commands = selection
+            .components()
+            .iter()
+            .filter_map(|id| scene.get_component(*id))
+            .map(|component| Command::TransformComponent {
+                id: component.id(),
+                position: *component.position(),
+                rotation: (component.rotation() + angle).rem_euclid(std::f32::consts::TAU),
+                scale: component.scale(),
+            })
+            .collect();
+
+        self.history.execute(scene_manager, Command::Batch(commands))
+    }
+
+    /// Removes the selected components and wires
+    pub fn delete_selection(
+        &mut self,
+        scene_manager: &mut SceneManager,
+        selection: &mut Selection,
+    ) -> Result<(), SceneManagerError> {
+        let components = selection.components().iter().map(|id| Command::RemoveComponent(*id));
+        let wires = selection.wires().iter().map(|id| Command::RemoveWire(*id));
+
+        self.history
+            .execute(scene_manager, Command::Batch(components.chain(wires).collect()))?;
+        selection.clear();
+
+        Ok(())
+    }
+
+    /// Starts drawing a wire from ```point```, snapped to the grid
+    pub fn begin_wire(&self, scene_manager: &SceneManager, point: Vector2<f32>, grid_step: f32) -> WireDrag {
+        WireDrag {
+            id: scene_manager.scene().next_wire_id(),
+            start: snap(&point, grid_step),
+            is_added: false,
+        }
+    }
+
+    /// Moves the end of the wire of ```drag``` to ```point```, snapped to the grid
+    pub fn update_wire(
+        &self,
+        scene_manager: &mut SceneManager,
+        drag: &mut WireDrag,
+        point: Vector2<f32>,
+        grid_step: f32,
+    ) {
+        let end = snap(&point, grid_step);
+        let scene = scene_manager.scene_mut();
+
+        if end == drag.start {
+            if drag.is_added {
+                scene.remove_wire(drag.id);
+                drag.is_added = false;
+            }
+            return;
+        }
+
+        let wire = Wire::new(
+            drag.id,
+            drag.start,
+            end,
+            Vector2::zeros(),
+            Vector2::zeros(),
+            chunk_size_from_step_idx(0),
+        );
+        scene.add_wire(0, wire);
+        drag.is_added = true;
+    }
+
+    /// Records the wire of ```drag```, returns whether one was drawn
+    pub fn end_wire(&mut self, drag: WireDrag) -> bool {
+        if drag.is_added {
+            self.history.record(Command::RemoveWire(drag.id));
+        }
+        drag.is_added
+    }
+
+    /// Reverts the last edit, the selection keeps only what is still in the scene
+    pub fn undo(
+        &mut self,
+        scene_manager: &mut SceneManager,
+        selection: &mut Selection,
+    ) -> Result<bool, SceneManagerError> {
+        let is_undone = self.history.undo(scene_manager)?;
+        selection.retain_existing(scene_manager.scene());
+        Ok(is_undone)
+    }
+
+    /// Applies again the last undone edit, the selection keeps only what is still in the scene
+    pub fn redo(
+        &mut self,
+        scene_manager: &mut SceneManager,
+        selection: &mut Selection,
+    ) -> Result<bool, SceneManagerError> {
+        let is_redone = self.history.redo(scene_manager)?;
+        selection.retain_existing(scene_manager.scene());
+        Ok(is_redone)
+    }
+}
+
+/// Closest point to ```point``` on a grid with ```grid_step``` spacing
+pub fn snap(point: &Vector2<f32>, grid_step: f32) -> Vector2<f32> {
+    point.map(|coord| (coord / grid_step).round() * grid_step)
+}
+
+/// Spacing of the points of the grid drawn for a chunk size, the grid effect draws a dot every quarter of a chunk
+pub fn grid_step(chunk_size: f32) -> f32 {
+    chunk_size / 4.0
+}
+
+#[cfg(test)]
+mod editor_test {
+    use super::*;
+
+    #[test]
+    fn test_edit() {
+        let mut scene_manager = SceneManager::new();
+        let mut selection = Selection::default();
+        let mut editor = Editor::default();
+
+        let first = editor
+            .place(
+                &mut scene_manager,
+                &mut selection,
+                DefaultComponentTypes::Resistor,
+                Vector2::zeros(),
+            )
+            .unwrap();
+        let second = editor
+            .place(
+                &mut scene_manager,
+                &mut selection,
+                DefaultComponentTypes::Memristor,
+                Vector2::new(5.0, 0.0),
+            )
+            .unwrap();
+        assert_eq!((first, second), (0, 1));
+        assert_eq!(selection.components(), &[second].into());
+
+        // The anchor snaps to the grid and the rest of the selection follows it
+        selection.select_component(first);
+        let mut drag = editor
+            .begin_move(&scene_manager, &selection, second, Vector2::new(5.0, 0.0))
+            .unwrap();
+        editor
+            .update_move(&mut scene_manager, &mut drag, Vector2::new(6.4, 1.9), 2.5)
+            .unwrap();
+        assert!(editor.end_move(drag));
+        let scene = scene_manager.scene();
+        assert_eq!(scene.get_component(second).unwrap().position(), &Vector2::new(7.5, 2.5));
+        assert_eq!(scene.get_component(first).unwrap().position(), &Vector2::new(2.5, 2.5));
+
+        editor
+            .rotate_selection(&mut scene_manager, &selection, std::f32::consts::FRAC_PI_2)
+            .unwrap();
+        editor.delete_selection(&mut scene_manager, &mut selection).unwrap();
+        assert_eq!(scene_manager.scene().component_count(), 0);
+        assert!(selection.is_empty());
+
+        let mut drag = editor.begin_wire(&scene_manager, Vector2::new(0.1, 0.1), 2.5);
+        editor.update_wire(&mut scene_manager, &mut drag, Vector2::new(0.2, 0.0), 2.5);
+        editor.update_wire(&mut scene_manager, &mut drag, Vector2::new(4.0, 0.0), 2.5);
+        assert!(editor.end_wire(drag));
+        assert_eq!(scene_manager.scene().wires()[&0].end(), &Vector2::new(5.0, 0.0));
+
+        // Undo the wire, the deletion, the rotation and the move
+        for _ in 0..4 {
+            assert!(editor.undo(&mut scene_manager, &mut selection).unwrap());
+        }
+        let scene = scene_manager.scene();
+        assert!(scene.wires().is_empty());
+        assert_eq!(scene.get_component(second).unwrap().position(), &Vector2::new(5.0, 0.0));
+        assert_eq!(scene.get_component(second).unwrap().rotation(), 0.0);
+
+        assert!(editor.redo(&mut scene_manager, &mut selection).unwrap());
+        assert_eq!(
+            scene_manager.scene().get_component(first).unwrap().position(),
+            &Vector2::new(2.5, 2.5)
+        );
+    }
+
+    #[test]
+    fn test_snap() {
+        assert_eq!(snap(&Vector2::new(1.3, -1.3), 2.5), Vector2::new(2.5, -2.5));
+        assert_eq!(snap(&Vector2::new(1.2, -1.2), 2.5), Vector2::new(0.0, 0.0));
+        assert_eq!(grid_step(10.0), 2.5);
+    }
+}
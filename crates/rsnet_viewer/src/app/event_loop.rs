@@ -2,11 +2,12 @@ use super::App;
 
 use crate::gui::renderer::GuiRenderer;
 use crate::renderer::Renderer;
+use crate::scene::scene_manager::SceneManager;
+use crate::scene::Scene;
 use crate::utils::frame_counter::FrameCounter;
 use crate::utils::wgpu::{Context, SurfaceWrapper};
 
 use std::sync::Arc;
-use tracing_subscriber::{filter::EnvFilter, fmt, prelude::*};
 use winit::dpi::LogicalSize;
 use winit::event::{Event, KeyEvent, WindowEvent};
 use winit::event_loop::{ControlFlow, EventLoopWindowTarget};
@@ -47,17 +48,12 @@ impl EventLoopWrapper {
     }
 }
 
-pub async fn run() {
-    cfg_if::cfg_if! {
-        if #[cfg(target_arch = "wasm32")] {
-            std::panic::set_hook(Box::new(console_error_panic_hook::hook));
-            console_log::init_with_level(log::Level::Warn).expect("Couldn't initialize logger");
-        } else {
-            tracing_subscriber::registry()
-                .with(fmt::layer())
-                .with(EnvFilter::from_default_env())
-                .init();
-        }
+/// Opens the viewer, showing the scene of ```scene_manager``` if it is given and the default network otherwise
+pub async fn run(scene_manager: Option<SceneManager>) {
+    #[cfg(target_arch = "wasm32")]
+    {
+        std::panic::set_hook(Box::new(console_error_panic_hook::hook));
+        console_log::init_with_level(log::Level::Warn).expect("Couldn't initialize logger");
     }
 
     let window_loop = EventLoopWrapper::new("rsnet");
@@ -76,7 +72,7 @@ pub async fn run() {
 
     let _ = (event_loop_function)(
         window_loop.event_loop,
-        event_handler(window_loop.window, surface, context),
+        event_handler(window_loop.window, surface, context, scene_manager),
     );
 }
 
@@ -84,10 +80,13 @@ fn event_handler<'a>(
     window: Arc<Window>,
     surface: SurfaceWrapper<'a>,
     context: Context,
+    scene_manager: Option<SceneManager>,
 ) -> impl FnMut(Event<()>, &EventLoopWindowTarget<()>) -> () + 'a {
     // let mut frame_counter = FrameCounter::new();
 
-    let mut app = App::new(None, None, surface, context, window.clone());
+    // The default network is only built when no input is given
+    let scene_manager = scene_manager.unwrap_or_else(|| SceneManager::from_scene(Scene::new()));
+    let mut app = App::new(None, None, surface, context, window.clone(), scene_manager);
 
     move |event: Event<()>, target: &EventLoopWindowTarget<()>| {
         target.set_control_flow(ControlFlow::Poll);
@@ -140,7 +139,7 @@ fn event_handler<'a>(
                             ..
                         },
                     ..
-                } if s == "r" => {
+                } if s == "r" && !app.state.editor.is_enabled() => {
                     println!("{:#?}", app.context.instance.generate_report());
                 }
                 WindowEvent::RedrawRequested => {
@@ -1,9 +1,12 @@
 pub mod camera;
+pub mod editor;
 pub mod event_loop;
+pub mod selection;
 pub mod state;
 pub mod utils;
 
 use camera::CameraController;
+use editor::{MoveDrag, Tool, WireDrag};
 use smaa::SmaaTarget;
 
 pub use self::state::State;
@@ -15,14 +18,27 @@ use crate::{
     utils::{
         frame_counter,
         wgpu::{context::Context, surface::SurfaceWrapper},
-        FrameCounter,
+        AaBb, FrameCounter,
     },
 };
 
 use egui_wgpu::ScreenDescriptor;
-use std::{iter, sync::Arc};
+use nalgebra::Vector2;
+use std::{f32::consts::FRAC_PI_2, iter, sync::Arc};
+use tracing::warn;
 use wgpu::{CommandEncoderDescriptor, TextureViewDescriptor};
-use winit::window::Window;
+use winit::{
+    dpi::PhysicalPosition,
+    event::{ElementState, KeyEvent, MouseButton, WindowEvent},
+    keyboard::{Key, NamedKey},
+    window::Window,
+};
+
+/// Distance the mouse has to move while pressed for a click to become a box selection [px]
+const DRAG_THRESHOLD: f64 = 4.0;
+
+/// Distance from a wire at which a click still picks it [px]
+const PICK_TOLERANCE: f64 = 4.0;
 
 pub struct App<'a> {
     pub gui_renderer: Option<GuiRenderer>,
@@ -41,6 +57,19 @@ pub struct App<'a> {
 
     frame_counter: FrameCounter,
     smaa_target: Option<SmaaTarget>,
+
+    /// What the left button is doing, while it is held
+    pointer_drag: Option<PointerDrag>,
+}
+
+/// What a drag with the left button does
+enum PointerDrag {
+    /// Selects what is inside the dragged box, or what is under the mouse if it did not move. Starts at the position.
+    Select(PhysicalPosition<f64>),
+    /// Moves the selection. Starts at the position, the flag tells whether the press selected the dragged component.
+    Move(PhysicalPosition<f64>, MoveDrag, bool),
+    /// Draws a wire
+    Wire(WireDrag),
 }
 
 impl<'a> App<'a> {
@@ -50,6 +79,7 @@ impl<'a> App<'a> {
         surface: SurfaceWrapper<'a>,
         context: Context,
         window: Arc<Window>,
+        scene_manager: scene::scene_manager::SceneManager,
     ) -> Self {
         let camera_controller = CameraController::new(window.inner_size());
         let frame_counter = FrameCounter::new();
@@ -61,27 +91,245 @@ impl<'a> App<'a> {
             surface,
             context,
             window,
-            state: State::default(),
+            state: State::new(scene_manager),
             ui_state: gui::state::State::default(),
             camera_controller,
             frame_counter,
             smaa_target: None,
+            pointer_drag: None,
         }
     }
 
     pub fn window_event_handler(&mut self, event: winit::event::WindowEvent) {
-        if self.gui_renderer.is_some()
-            && self
-                .gui_renderer
-                .as_mut()
-                .unwrap()
-                .handle_input(&self.window, &event)
-                .consumed
-        {}
+        let consumed = self
+            .gui_renderer
+            .as_mut()
+            .is_some_and(|gui_renderer| gui_renderer.handle_input(&self.window, &event).consumed);
+        if !consumed {
+            self.pointer_event_handler(&event);
+        }
 
         self.camera_controller.event_handler(event);
     }
 
+    /// A left click selects what is under the mouse and a left drag what is inside the dragged box. Holding shift
+    /// extends the selection instead of replacing it. In edit mode the left button uses the tool of the editor.
+    fn pointer_event_handler(&mut self, event: &WindowEvent) {
+        match event {
+            WindowEvent::CursorMoved { position, .. } => {
+                if let Some(scene_renderer) = &mut self.scene_renderer {
+                    let size = self.window.inner_size();
+                    let ndc = (
+                        2.0 * position.x as f32 / size.width as f32 - 1.0,
+                        1.0 - 2.0 * position.y as f32 / size.height as f32,
+                    );
+                    scene_renderer.update_mouse(ndc, &self.context.queue);
+                }
+
+                self.update_drag(position);
+            }
+            WindowEvent::MouseInput {
+                state: ElementState::Pressed,
+                button: MouseButton::Left,
+                ..
+            } => {
+                self.begin_drag();
+            }
+            WindowEvent::MouseInput {
+                state: ElementState::Released,
+                button: MouseButton::Left,
+                ..
+            } => {
+                self.end_drag();
+            }
+            WindowEvent::KeyboardInput {
+                event:
+                    KeyEvent {
+                        state: ElementState::Pressed,
+                        logical_key,
+                        ..
+                    },
+                ..
+            } if self.state.editor.is_enabled() => {
+                self.edit_key_handler(logical_key);
+            }
+            _ => {}
+        }
+    }
+
+    fn begin_drag(&mut self) {
+        let position = self.camera_controller.mouse_position();
+        let Some(point) = self.camera_controller.screen_to_world(&position) else {
+            return;
+        };
+        let extend = self.camera_controller.modifiers().shift_key();
+        let grid_step = editor::grid_step(self.camera_controller.chunk_size);
+
+        let State {
+            scene_manager,
+            selection,
+            editor,
+            ..
+        } = &mut self.state;
+        if !editor.is_enabled() {
+            self.pointer_drag = Some(PointerDrag::Select(position));
+            return;
+        }
+
+        self.pointer_drag = match editor.tool() {
+            Tool::Select => {
+                let component = scene_manager.scene().components_at(0, &point).first().map(|component| component.id());
+                match component {
+                    Some(id) => {
+                        // Dragging a component that is not selected selects it first
+                        let is_selected_on_press = !selection.contains_component(id);
+                        if is_selected_on_press {
+                            selection.select_at(scene_manager.scene(), &point, 0.0, extend);
+                        }
+                        editor
+                            .begin_move(scene_manager, selection, id, point)
+                            .map(|drag| PointerDrag::Move(position, drag, is_selected_on_press))
+                    }
+                    None => Some(PointerDrag::Select(position)),
+                }
+            }
+            Tool::Place(ty) => {
+                if let Err(e) = editor.place(scene_manager, selection, ty, editor::snap(&point, grid_step)) {
+                    warn!("Could not place the component: {e}");
+                }
+                None
+            }
+            Tool::Wire => Some(PointerDrag::Wire(editor.begin_wire(scene_manager, point, grid_step))),
+        };
+    }
+
+    fn update_drag(&mut self, position: &PhysicalPosition<f64>) {
+        let Some(drag) = &mut self.pointer_drag else {
+            return;
+        };
+        let grid_step = editor::grid_step(self.camera_controller.chunk_size);
+
+        match drag {
+            PointerDrag::Select(start) => {
+                let is_drag = distance(start, position) > DRAG_THRESHOLD;
+                self.state.set_selection_box(is_drag.then(|| window_box(start, position)));
+            }
+            PointerDrag::Move(_, drag, _) => {
+                let Some(point) = self.camera_controller.screen_to_world(position) else {
+                    return;
+                };
+                let State {
+                    scene_manager, editor, ..
+                } = &mut self.state;
+                if let Err(e) = editor.update_move(scene_manager, drag, point, grid_step) {
+                    warn!("Could not move the selection: {e}");
+                }
+            }
+            PointerDrag::Wire(drag) => {
+                let Some(point) = self.camera_controller.screen_to_world(position) else {
+                    return;
+                };
+                let State {
+                    scene_manager, editor, ..
+                } = &mut self.state;
+                editor.update_wire(scene_manager, drag, point, grid_step);
+            }
+        }
+    }
+
+    fn end_drag(&mut self) {
+        let Some(drag) = self.pointer_drag.take() else {
+            return;
+        };
+        let end = self.camera_controller.mouse_position();
+        let extend = self.camera_controller.modifiers().shift_key();
+
+        match drag {
+            PointerDrag::Select(start) => {
+                self.state.set_selection_box(None);
+                if distance(&start, &end) > DRAG_THRESHOLD {
+                    self.box_select(&start, &end, extend);
+                } else {
+                    self.click_select(&end, extend);
+                }
+            }
+            PointerDrag::Move(start, drag, is_selected_on_press) => {
+                // A click on a selected component selects it alone, or deselects it with shift
+                let is_moved = self.state.editor.end_move(drag);
+                if !is_moved && !is_selected_on_press && distance(&start, &end) <= DRAG_THRESHOLD {
+                    self.click_select(&end, extend);
+                }
+            }
+            PointerDrag::Wire(drag) => {
+                self.state.editor.end_wire(drag);
+            }
+        }
+    }
+
+    fn click_select(&mut self, position: &PhysicalPosition<f64>, extend: bool) {
+        let Some(point) = self.camera_controller.screen_to_world(position) else {
+            return;
+        };
+        let tolerance = self
+            .camera_controller
+            .screen_to_world(&PhysicalPosition::new(position.x + PICK_TOLERANCE, position.y))
+            .map_or(0.0, |tolerance_point| (tolerance_point - point).norm());
+
+        let State {
+            scene_manager,
+            selection,
+            ..
+        } = &mut self.state;
+        selection.select_at(scene_manager.scene(), &point, tolerance, extend);
+    }
+
+    fn box_select(&mut self, start: &PhysicalPosition<f64>, end: &PhysicalPosition<f64>, extend: bool) {
+        let (Some(world_start), Some(world_end)) = (
+            self.camera_controller.screen_to_world(start),
+            self.camera_controller.screen_to_world(end),
+        ) else {
+            return;
+        };
+
+        let State {
+            scene_manager,
+            selection,
+            ..
+        } = &mut self.state;
+        let rect = AaBb::from_points([world_start, world_end]).unwrap();
+        selection.select_in_rect(scene_manager.scene(), &rect, extend);
+    }
+
+    /// Shortcuts of the edit mode: R rotates the selection by 90° (clockwise with shift), Delete removes it, Ctrl+Z
+    /// undoes and Ctrl+Y or Ctrl+Shift+Z redoes
+    fn edit_key_handler(&mut self, key: &Key) {
+        let modifiers = self.camera_controller.modifiers();
+        let State {
+            scene_manager,
+            selection,
+            editor,
+            ..
+        } = &mut self.state;
+
+        let result = match key {
+            Key::Named(NamedKey::Delete | NamedKey::Backspace) => editor.delete_selection(scene_manager, selection),
+            Key::Character(c) => match (c.to_lowercase().as_str(), modifiers.control_key(), modifiers.shift_key()) {
+                ("r", false, is_clockwise) => {
+                    let angle = if is_clockwise { -FRAC_PI_2 } else { FRAC_PI_2 };
+                    editor.rotate_selection(scene_manager, selection, angle)
+                }
+                ("z", true, false) => editor.undo(scene_manager, selection).map(|_| ()),
+                ("z", true, true) | ("y", true, _) => editor.redo(scene_manager, selection).map(|_| ()),
+                _ => Ok(()),
+            },
+            _ => Ok(()),
+        };
+
+        if let Err(e) = result {
+            warn!("Could not edit the scene: {e}");
+        }
+    }
+
     pub fn resize(&mut self, size: winit::dpi::PhysicalSize<u32>) {
         if self.msaa_view.is_some() {
             self.create_msaa_view();
@@ -138,6 +386,8 @@ impl<'a> App<'a> {
 
         let window_size = self.window.inner_size();
 
+        let dirty_chunks = self.state.scene_manager.scene_mut().take_dirty_chunks();
+
         if let Some(scene_renderer) = &mut self.scene_renderer {
             scene_renderer.render(
                 self.msaa_view.as_ref(),
@@ -146,7 +396,8 @@ impl<'a> App<'a> {
                 &mut encoder,
                 &self.state,
                 &mut self.camera_controller,
-                &self.state.scene,
+                self.state.scene_manager.scene(),
+                &dirty_chunks,
             );
         }
 
@@ -249,3 +500,11 @@ impl<'a> App<'a> {
         self.smaa_target = Some(smaa_target);
     }
 }
+
+fn distance(a: &PhysicalPosition<f64>, b: &PhysicalPosition<f64>) -> f64 {
+    ((a.x - b.x).powi(2) + (a.y - b.y).powi(2)).sqrt()
+}
+
+fn window_box(a: &PhysicalPosition<f64>, b: &PhysicalPosition<f64>) -> AaBb {
+    AaBb::from_points([a, b].map(|p| Vector2::new(p.x as f32, p.y as f32))).unwrap()
+}
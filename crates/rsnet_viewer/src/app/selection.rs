@@ -0,0 +1,217 @@
+use crate::{scene::Scene, types::Id, utils::AaBb};
+
+use nalgebra::Vector2;
+use std::collections::HashSet;
+
+/// Components and wires selected by the user. Every change increases ```version```, so the renderer only updates the
+/// highlighted instances when it changed.
+#[derive(Debug, Default)]
+pub struct Selection {
+    components: HashSet<Id>,
+    wires: HashSet<Id>,
+    version: u64,
+}
+
+impl Selection {
+    pub fn components(&self) -> &HashSet<Id> {
+        &self.components
+    }
+
+    pub fn wires(&self) -> &HashSet<Id> {
+        &self.wires
+    }
+
+    pub fn version(&self) -> u64 {
+        self.version
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.components.is_empty() && self.wires.is_empty()
+    }
+
+    pub fn contains_component(&self, id: Id) -> bool {
+        self.components.contains(&id)
+    }
+
+    pub fn contains_wire(&self, id: Id) -> bool {
+        self.wires.contains(&id)
+    }
+
+    pub fn clear(&mut self) {
+        if !self.is_empty() {
+            self.components.clear();
+            self.wires.clear();
+            self.version += 1;
+        }
+    }
+
+    /// Deselects the components and wires that are not in ```scene``` anymore
+    pub fn retain_existing(&mut self, scene: &Scene) {
+        let n_selected = self.components.len() + self.wires.len();
+        self.components.retain(|id| scene.get_component(*id).is_some());
+        self.wires.retain(|id| scene.wires().contains_key(id));
+
+        if self.components.len() + self.wires.len() != n_selected {
+            self.version += 1;
+        }
+    }
+
+    pub fn select_component(&mut self, id: Id) {
+        if self.components.insert(id) {
+            self.version += 1;
+        }
+    }
+
+    pub fn select_wire(&mut self, id: Id) {
+        if self.wires.insert(id) {
+            self.version += 1;
+        }
+    }
+
+    /// Selects the component, or deselects it if it was selected
+    pub fn toggle_component(&mut self, id: Id) {
+        if !self.components.remove(&id) {
+            self.components.insert(id);
+        }
+        self.version += 1;
+    }
+
+    /// Selects the wire, or deselects it if it was selected
+    pub fn toggle_wire(&mut self, id: Id) {
+        if !self.wires.remove(&id) {
+            self.wires.insert(id);
+        }
+        self.version += 1;
+    }
+
+    /// Selects what is under ```point```: the smallest component containing it, otherwise the closest wire within
+    /// ```tolerance```. With ```extend``` the picked item is toggled and the rest of the selection kept, without it
+    /// the selection is replaced (and cleared when nothing is picked).
+    ///
+    /// The components of step 0 are picked at any zoom, not the aggregates of the coarser levels.
+    pub fn select_at(&mut self, scene: &Scene, point: &Vector2<f32>, tolerance: f32, extend: bool) {
+        let component = scene.components_at(0, point).first().map(|component| component.id());
+        let wire = component
+            .is_none()
+            .then(|| scene.wires_at(0, point, tolerance).first().map(|wire| wire.id()))
+            .flatten();
+
+        if !extend {
+            self.clear();
+        }
+
+        match (component, wire) {
+            (Some(id), _) if extend => self.toggle_component(id),
+            (Some(id), _) => self.select_component(id),
+            (None, Some(id)) if extend => self.toggle_wire(id),
+            (None, Some(id)) => self.select_wire(id),
+            (None, None) => (),
+        }
+    }
+
+    /// Selects every component and wire of step 0 overlapping ```rect```, added to the selection with ```extend```
+    pub fn select_in_rect(&mut self, scene: &Scene, rect: &AaBb, extend: bool) {
+        if !extend {
+            self.clear();
+        }
+
+        for component in scene.components_in_rect(0, rect) {
+            self.select_component(component.id());
+        }
+        for wire in scene.wires_in_rect(0, rect) {
+            self.select_wire(wire.id());
+        }
+    }
+}
+
+#[cfg(test)]
+mod selection_test {
+    use super::*;
+    use crate::{
+        app::camera::{screen_to_world, CameraController},
+        scene::{
+            component::{Component, DefaultComponentTypes},
+            wire::Wire,
+        },
+    };
+
+    use nalgebra::{Point3, Vector2};
+    use winit::dpi::{PhysicalPosition, PhysicalSize};
+
+    #[test]
+    fn test_select() {
+        let mut scene = Scene::new_empty();
+        for (id, x) in [(0, 0.0), (1, 1.0), (2, 2.0)] {
+            scene
+                .add_component(
+                    0,
+                    Component::new(id, 0, Vector2::new(x, 0.0), 0.0, DefaultComponentTypes::Resistor as u32),
+                )
+                .unwrap();
+        }
+        scene.add_wire(
+            0,
+            Wire::new(
+                5,
+                Vector2::new(0.0, -3.0),
+                Vector2::new(2.0, -3.0),
+                Vector2::zeros(),
+                Vector2::zeros(),
+                10.0,
+            ),
+        );
+
+        let mut selection = Selection::default();
+        selection.select_at(&scene, &Vector2::new(1.0, 0.0), 0.1, false);
+        assert_eq!(selection.components(), &HashSet::from([1]));
+
+        // Shift-clicks toggle
+        selection.select_at(&scene, &Vector2::new(2.0, 0.0), 0.1, true);
+        selection.select_at(&scene, &Vector2::new(1.0, -3.05), 0.1, true);
+        assert_eq!(selection.components(), &HashSet::from([1, 2]));
+        assert_eq!(selection.wires(), &HashSet::from([5]));
+        selection.select_at(&scene, &Vector2::new(1.0, 0.0), 0.1, true);
+        assert_eq!(selection.components(), &HashSet::from([2]));
+
+        // A click on nothing clears it
+        let version = selection.version();
+        selection.select_at(&scene, &Vector2::new(1.0, 5.0), 0.1, false);
+        assert!(selection.is_empty());
+        assert!(selection.version() > version);
+
+        let rect = AaBb::new(Vector2::new(0.5, -3.5), Vector2::new(3.0, 0.5));
+        selection.select_in_rect(&scene, &rect, false);
+        assert_eq!(selection.components(), &HashSet::from([1, 2]));
+        assert_eq!(selection.wires(), &HashSet::from([5]));
+        selection.select_in_rect(
+            &scene,
+            &AaBb::new(Vector2::new(-0.1, -0.1), Vector2::new(0.1, 0.1)),
+            true,
+        );
+        assert_eq!(selection.components(), &HashSet::from([0, 1, 2]));
+
+        // Selecting again what is selected does not change it
+        let version = selection.version();
+        selection.select_component(0);
+        assert_eq!(selection.version(), version);
+    }
+
+    #[test]
+    fn test_screen_to_world() {
+        let window_size = PhysicalSize::new(800, 600);
+        let camera_controller = CameraController::new(window_size);
+        let view_proj = camera_controller.get_camera().build_view_proj();
+
+        // Project a point of the scene to the window and back
+        let point = Vector2::new(1.5, -0.75);
+        let ndc = view_proj.transform_point(&Point3::new(point.x, point.y, 0.0));
+        let position = PhysicalPosition::new(
+            ((ndc.x + 1.0) / 2.0 * 800.0) as f64,
+            ((1.0 - ndc.y) / 2.0 * 600.0) as f64,
+        );
+
+        let unprojected = screen_to_world(&view_proj, &window_size, &position).unwrap();
+        assert!((unprojected - point).norm() < 1e-3);
+        assert_eq!(camera_controller.screen_to_world(&position), Some(unprojected));
+    }
+}
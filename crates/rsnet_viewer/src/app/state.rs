@@ -1,15 +1,19 @@
+use super::{editor::Editor, selection::Selection};
 use crate::{
     gui,
-    scene::{self, utils::ChunkRange},
-    utils::FrameCounter,
+    scene::{scene_manager::SceneManager, utils::ChunkRange, Scene},
+    utils::{AaBb, FrameCounter},
 };
 
 use smaa::SmaaMode;
 
 pub struct State {
-    pub scene: scene::Scene,
+    pub scene_manager: SceneManager,
+    pub selection: Selection,
+    pub editor: Editor,
 
-    grid: bool, // If the grid is visible
+    grid: bool,   // If the grid is visible
+    labels: bool, // If the labels of the components, nets and layers are visible
     current_frame_time: f32,
     msaa_count: u32,
     rebuild_bundles: bool, // Controls whether to rebuild the render pipelines and texture views
@@ -21,13 +25,24 @@ pub struct State {
     chunk_step_idx: usize,
     chunk_size: f32,
     screen_chunk_range: ChunkRange,
+    /// Box being dragged to select, in window coordinates [px]
+    selection_box: Option<AaBb>,
 }
 
 impl Default for State {
     fn default() -> Self {
+        Self::new(SceneManager::from_scene(Scene::new_empty()))
+    }
+}
+
+impl State {
+    pub fn new(scene_manager: SceneManager) -> Self {
         Self {
-            scene: scene::Scene::default(),
+            scene_manager,
+            selection: Selection::default(),
+            editor: Editor::default(),
             grid: false,
+            labels: true,
             current_frame_time: f32::MAX,
             msaa_count: 8,
             rebuild_bundles: true,
@@ -39,11 +54,10 @@ impl Default for State {
             chunk_step_idx: 0,
             chunk_size: 0.0,
             screen_chunk_range: ChunkRange::default(),
+            selection_box: None,
         }
     }
-}
 
-impl State {
     pub fn grid(&self) -> bool {
         self.grid
     }
@@ -52,6 +66,14 @@ impl State {
         self.grid = grid;
     }
 
+    pub fn labels(&self) -> bool {
+        self.labels
+    }
+
+    pub fn set_labels(&mut self, labels: bool) {
+        self.labels = labels;
+    }
+
     pub fn current_frame_time(&self) -> f32 {
         self.current_frame_time
     }
@@ -143,4 +165,12 @@ impl State {
     pub fn set_screen_chunk_range(&mut self, range: ChunkRange) {
         self.screen_chunk_range = range;
     }
+
+    pub fn selection_box(&self) -> Option<&AaBb> {
+        self.selection_box.as_ref()
+    }
+
+    pub fn set_selection_box(&mut self, selection_box: Option<AaBb>) {
+        self.selection_box = selection_box;
+    }
 }
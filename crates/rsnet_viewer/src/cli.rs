@@ -0,0 +1,595 @@
+//! Command line interface of the ```rsnet``` binary.
+//!
+//! Only ```view``` opens a window, every other subcommand is headless: it never creates a window nor a wgpu device,
+//! so it can run on machines without a display (e.g. batch jobs). Failures exit with ```EXIT_FAILURE``` after
+//! logging the error, invalid arguments exit with ```EXIT_USAGE```.
+
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+
+use crate::app;
+use crate::scene::format::SceneFormat;
+use crate::scene::peripherals::PeripheralConfig;
+use crate::scene::scene_manager::{SceneManager, SceneManagerError, GROUND_NODE_NAME};
+use crate::types::NodeId;
+
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use rsnet_net_parser::types::Nn;
+use rsnet_net_parser::NnParseError;
+use rsnet_sim::mapping::ConductanceMapping;
+use rsnet_sim::mna::TransientOptions;
+use rsnet_sim::spice::{parse_spice, MemristorModel, SpiceAnalysis, SpiceOptions};
+use rsnet_sim::stanford::StanfordModelParams;
+use rsnet_sim::tiling::TilingConfig;
+use thiserror::Error;
+use tracing::error;
+
+/// Exit code of a subcommand that failed
+pub const EXIT_FAILURE: u8 = 1;
+/// Exit code for invalid arguments, the one used by clap
+pub const EXIT_USAGE: u8 = 2;
+
+#[derive(Error, Debug)]
+pub enum CliError {
+    #[error("Could not access {path}: {source}")]
+    Io { path: PathBuf, source: io::Error },
+    #[error("Could not read the model {path}: {source}")]
+    Model { path: PathBuf, source: NnParseError },
+    #[error("{0}")]
+    Scene(SceneManagerError),
+    #[error("Node {0} does not exist")]
+    UnknownNode(String),
+    #[error("Unknown kind of input {0}, expected .py, .onnx, .rsn, .json, .sp, .cir, .net or .spice")]
+    UnknownInputKind(PathBuf),
+    #[error("Reading {path} needs rsnet to be built with the {feature} feature")]
+    MissingFeature { path: PathBuf, feature: &'static str },
+    #[error("Could not open the viewer: {0}")]
+    Viewer(io::Error),
+}
+
+impl From<SceneManagerError> for CliError {
+    fn from(value: SceneManagerError) -> Self {
+        Self::Scene(value)
+    }
+}
+
+/// Views, builds, exports and simulates memristive neural network circuits
+#[derive(Parser, Debug)]
+#[command(name = "rsnet", version)]
+pub struct Cli {
+    /// Opens the viewer with the default network when no subcommand is given
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Opens a model, scene or SPICE netlist in the viewer
+    View {
+        /// Model (.py, .onnx), scene (.rsn, .json) or SPICE netlist, the default network if omitted
+        input: Option<PathBuf>,
+        #[command(flatten)]
+        model: ModelArgs,
+    },
+    /// Builds the circuit of a model and saves it as a scene file
+    Build {
+        /// Model (.py, .onnx) or SPICE netlist
+        input: PathBuf,
+        /// Scene file to write
+        #[arg(short, long)]
+        output: PathBuf,
+        /// Format of the scene file, taken from the extension of the output by default (binary unless .json)
+        #[arg(long, value_enum)]
+        format: Option<FormatArg>,
+        #[command(flatten)]
+        model: ModelArgs,
+    },
+    /// Exports a circuit to another tool
+    Export {
+        /// Model, scene or SPICE netlist
+        input: PathBuf,
+        /// Writes a SPICE netlist
+        #[arg(long, required = true)]
+        spice: bool,
+        /// File to write, the standard output if omitted
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        /// Voltage of the sources added to the crossbar inputs that are not driven [V]
+        #[arg(long, default_value_t = 0.0)]
+        input_voltage: f64,
+        /// Instantiates memristors as the Verilog-A Stanford model MODULE instead of resistors
+        #[arg(long, value_name = "MODULE")]
+        stanford: Option<String>,
+        /// Adds a transient analysis card with the given time step and stop time [s]
+        #[arg(long, num_args = 2, value_names = ["STEP", "STOP"])]
+        tran: Option<Vec<f64>>,
+        #[command(flatten)]
+        model: ModelArgs,
+    },
+    /// Simulates a circuit, the DC operating point unless an analysis is chosen
+    Sim {
+        /// Model, scene or SPICE netlist
+        input: PathBuf,
+        /// Runs an inference of the network with these comma separated inputs and prints its outputs
+        #[arg(
+            long,
+            value_delimiter = ',',
+            allow_negative_numbers = true,
+            conflicts_with = "t_stop"
+        )]
+        inputs: Option<Vec<f64>>,
+        #[command(flatten)]
+        tran: TranArgs,
+        /// Comma separated nodes to print, every named node but ground by default
+        #[arg(long, value_delimiter = ',')]
+        nodes: Option<Vec<String>>,
+        /// CSV file to write, the standard output if omitted
+        #[arg(short, long)]
+        output: Option<PathBuf>,
+        #[command(flatten)]
+        model: ModelArgs,
+    },
+}
+
+/// Transient analysis of ```sim```
+#[derive(Args, Debug, Clone)]
+pub struct TranArgs {
+    /// Runs a transient analysis until STOP, memristors follow the Stanford model [s]
+    #[arg(long = "tran", value_name = "STOP", value_parser = parse_positive_time)]
+    pub t_stop: Option<f64>,
+    /// Largest time step of the transient analysis [s]
+    #[arg(long, requires = "t_stop", value_parser = parse_positive_time)]
+    pub max_step: Option<f64>,
+    /// Seed of the variability of the memristors in the transient analysis, runs with the same seed are identical
+    #[arg(long, default_value_t = 0, requires = "t_stop")]
+    pub seed: u64,
+}
+
+/// Parses a time of the transient analysis, which has to be positive and finite
+fn parse_positive_time(value: &str) -> Result<f64, String> {
+    let time: f64 = value.parse().map_err(|e| format!("{}", e))?;
+    if !(time.is_finite() && time > 0.0) {
+        return Err(format!("{} is not a positive and finite time", value));
+    }
+    Ok(time)
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum FormatArg {
+    Binary,
+    Json,
+}
+
+/// How a model is turned into a circuit, ignored for scenes and SPICE netlists
+#[derive(Args, Debug, Clone)]
+pub struct ModelArgs {
+    /// Name of the torch.nn.Module subclass of a python model, the one not used by the others by default
+    #[arg(long, value_name = "NAME")]
+    pub class: Option<String>,
+    /// Rows of the largest crossbar array, larger layers are split into tiles
+    #[arg(long)]
+    pub max_rows: Option<usize>,
+    /// Columns of the largest crossbar array
+    #[arg(long)]
+    pub max_cols: Option<usize>,
+    /// Lowest conductance of the memristors [S]
+    #[arg(long)]
+    pub g_min: Option<f64>,
+    /// Highest conductance of the memristors [S]
+    #[arg(long)]
+    pub g_max: Option<f64>,
+    /// Number of conductance levels the memristors can be programmed to, analog if omitted
+    #[arg(long)]
+    pub levels: Option<u32>,
+    /// Voltage that represents a unit input [V]
+    #[arg(long)]
+    pub read_voltage: Option<f64>,
+    /// Resolution of the input DACs, ideal if omitted
+    #[arg(long)]
+    pub dac_bits: Option<u32>,
+    /// Resolution of the output ADCs, ideal if omitted
+    #[arg(long)]
+    pub adc_bits: Option<u32>,
+}
+
+impl ModelArgs {
+    /// Applies the options to ```scene_manager```, before a model is loaded
+    fn configure(&self, scene_manager: &mut SceneManager) {
+        let tiling = *scene_manager.tiling();
+        scene_manager.set_tiling(TilingConfig {
+            max_rows: self.max_rows.unwrap_or(tiling.max_rows),
+            max_cols: self.max_cols.unwrap_or(tiling.max_cols),
+        });
+
+        let mapping = *scene_manager.conductance_mapping();
+        scene_manager.set_conductance_mapping(ConductanceMapping {
+            g_min: self.g_min.unwrap_or(mapping.g_min),
+            g_max: self.g_max.unwrap_or(mapping.g_max),
+            levels: self.levels.or(mapping.levels),
+            ..mapping
+        });
+
+        let peripherals = *scene_manager.peripherals();
+        scene_manager.set_peripherals(PeripheralConfig {
+            read_voltage: self.read_voltage.unwrap_or(peripherals.read_voltage),
+            dac_bits: self.dac_bits.or(peripherals.dac_bits),
+            adc_bits: self.adc_bits.or(peripherals.adc_bits),
+            ..peripherals
+        });
+    }
+}
+
+/// Kind of file, from its extension
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum InputKind {
+    Python,
+    Onnx,
+    Scene,
+    Spice,
+}
+
+impl InputKind {
+    fn from_path(path: &Path) -> Result<InputKind, CliError> {
+        match path.extension().and_then(|extension| extension.to_str()) {
+            Some("py") => Ok(InputKind::Python),
+            Some("onnx") => Ok(InputKind::Onnx),
+            Some("sp" | "cir" | "net" | "spice") => Ok(InputKind::Spice),
+            _ if SceneFormat::from_path(path).is_some() => Ok(InputKind::Scene),
+            _ => Err(CliError::UnknownInputKind(path.to_path_buf())),
+        }
+    }
+}
+
+/// Loads a model, a scene file or a SPICE netlist
+pub fn load(path: &Path, model: &ModelArgs) -> Result<SceneManager, CliError> {
+    let io_error = |source| CliError::Io {
+        path: path.to_path_buf(),
+        source,
+    };
+    let model_error = |source| CliError::Model {
+        path: path.to_path_buf(),
+        source,
+    };
+
+    let kind = InputKind::from_path(path)?;
+    let nn: Nn = match kind {
+        InputKind::Scene => return Ok(SceneManager::open(path)?),
+        InputKind::Spice => {
+            let source = std::fs::read_to_string(path).map_err(io_error)?;
+            let mut scene_manager = SceneManager::new();
+            scene_manager.load_spice(&parse_spice(&source).map_err(SceneManagerError::SpiceImportError)?)?;
+            return Ok(scene_manager);
+        }
+        #[cfg(feature = "torch")]
+        InputKind::Python => {
+            let source = std::fs::read_to_string(path).map_err(io_error)?;
+            let options = rsnet_net_parser::ExtractOptions {
+                class_name: model.class.clone(),
+                ..Default::default()
+            };
+            rsnet_net_parser::extract_nn_with_options(&source, &options).map_err(model_error)?
+        }
+        #[cfg(feature = "onnx")]
+        InputKind::Onnx => rsnet_net_parser::read_onnx(path).map_err(model_error)?,
+        #[cfg(not(feature = "torch"))]
+        InputKind::Python => {
+            return Err(CliError::MissingFeature {
+                path: path.to_path_buf(),
+                feature: "torch",
+            })
+        }
+        #[cfg(not(feature = "onnx"))]
+        InputKind::Onnx => {
+            return Err(CliError::MissingFeature {
+                path: path.to_path_buf(),
+                feature: "onnx",
+            })
+        }
+    };
+
+    let mut scene_manager = SceneManager::new();
+    model.configure(&mut scene_manager);
+    scene_manager.load_nn(nn)?;
+
+    Ok(scene_manager)
+}
+
+/// Runs the subcommand and returns the exit code of the process
+pub fn run(cli: Cli) -> ExitCode {
+    let result = match cli.command {
+        None => view(None, None),
+        Some(Command::View { input, model }) => view(input.as_deref(), Some(&model)),
+        Some(Command::Build {
+            input,
+            output,
+            format,
+            model,
+        }) => build(&input, &output, format, &model),
+        Some(Command::Export {
+            input,
+            spice: _,
+            output,
+            input_voltage,
+            stanford,
+            tran,
+            model,
+        }) => export_spice(&input, output.as_deref(), input_voltage, stanford, tran, &model),
+        Some(Command::Sim {
+            input,
+            inputs,
+            tran,
+            nodes,
+            output,
+            model,
+        }) => simulate(&input, inputs, &tran, nodes, output.as_deref(), &model),
+    };
+
+    match result {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => {
+            error!("{}", e);
+            match e {
+                CliError::UnknownInputKind(_) => ExitCode::from(EXIT_USAGE),
+                _ => ExitCode::from(EXIT_FAILURE),
+            }
+        }
+    }
+}
+
+fn view(input: Option<&Path>, model: Option<&ModelArgs>) -> Result<(), CliError> {
+    // Loaded before the window is created, so errors are reported without flashing a window
+    let scene_manager = match (input, model) {
+        (Some(input), Some(model)) => Some(load(input, model)?),
+        _ => None,
+    };
+
+    let runtime = tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .map_err(CliError::Viewer)?;
+    runtime.block_on(app::event_loop::run(scene_manager));
+
+    Ok(())
+}
+
+fn build(input: &Path, output: &Path, format: Option<FormatArg>, model: &ModelArgs) -> Result<(), CliError> {
+    let scene_manager = load(input, model)?;
+
+    let format = match format {
+        Some(FormatArg::Binary) => SceneFormat::Binary,
+        Some(FormatArg::Json) => SceneFormat::Json,
+        None => SceneFormat::from_path(output).unwrap_or(SceneFormat::Binary),
+    };
+    scene_manager.save(output, format)?;
+
+    Ok(())
+}
+
+fn export_spice(
+    input: &Path,
+    output: Option<&Path>,
+    input_voltage: f64,
+    stanford: Option<String>,
+    tran: Option<Vec<f64>>,
+    model: &ModelArgs,
+) -> Result<(), CliError> {
+    let scene_manager = load(input, model)?;
+
+    let mut options = SpiceOptions {
+        title: format!("rsnet {}", input.display()),
+        ..SpiceOptions::default()
+    };
+    if let Some(module) = stanford {
+        options.memristor_model = MemristorModel::Stanford {
+            module,
+            params: StanfordModelParams::default(),
+        };
+    }
+    if let Some([step, stop]) = tran.as_deref() {
+        options.analyses.push(SpiceAnalysis::Tran {
+            step: *step,
+            stop: *stop,
+        });
+    }
+
+    let writer = open_output(output)?;
+    let mut writer = scene_manager.write_spice(writer, options, input_voltage, &HashMap::new())?;
+    writer.flush().map_err(|source| output_error(output, source))?;
+
+    Ok(())
+}
+
+fn simulate(
+    input: &Path,
+    inputs: Option<Vec<f64>>,
+    tran: &TranArgs,
+    nodes: Option<Vec<String>>,
+    output: Option<&Path>,
+    model: &ModelArgs,
+) -> Result<(), CliError> {
+    let mut scene_manager = load(input, model)?;
+    let mut writer = open_output(output)?;
+    let write_error = |source| output_error(output, source);
+
+    if let Some(inputs) = inputs {
+        let outputs = scene_manager.run_inference(&inputs)?;
+        writeln!(writer, "output,value").map_err(write_error)?;
+        for (idx, value) in outputs.iter().enumerate() {
+            writeln!(writer, "{},{}", idx, value).map_err(write_error)?;
+        }
+        return writer.flush().map_err(write_error);
+    }
+
+    let nodes = selected_nodes(&scene_manager, nodes)?;
+
+    match tran.t_stop {
+        Some(t_stop) => {
+            let options = TransientOptions {
+                t_stop,
+                max_step: tran.max_step,
+                ..TransientOptions::default()
+            };
+            let result =
+                scene_manager.transient(options, &StanfordModelParams::default(), tran.seed, HashMap::new())?;
+
+            let header: Vec<&str> = nodes.iter().map(|(name, _)| name.as_str()).collect();
+            writeln!(writer, "time,{}", header.join(",")).map_err(write_error)?;
+            for (step, time) in result.times.iter().enumerate() {
+                let voltages: Vec<String> = nodes
+                    .iter()
+                    .map(|(_, node)| {
+                        let voltage = result.node_voltages.get(node).and_then(|voltages| voltages.get(step));
+                        voltage.copied().unwrap_or(0.0).to_string()
+                    })
+                    .collect();
+                writeln!(writer, "{},{}", time, voltages.join(",")).map_err(write_error)?;
+            }
+        }
+        None => {
+            let op = scene_manager.dc_operating_point()?;
+
+            writeln!(writer, "node,voltage").map_err(write_error)?;
+            for (name, node) in &nodes {
+                writeln!(writer, "{},{}", name, op.voltage(*node).unwrap_or(0.0)).map_err(write_error)?;
+            }
+        }
+    }
+
+    writer.flush().map_err(write_error)
+}
+
+/// Names and ids of the ```nodes``` to print, every named node but ground sorted by name if none are given
+fn selected_nodes(scene_manager: &SceneManager, nodes: Option<Vec<String>>) -> Result<Vec<(String, NodeId)>, CliError> {
+    match nodes {
+        Some(names) => names
+            .into_iter()
+            .map(|name| match scene_manager.node_id(&name) {
+                Some(node) => Ok((name, node)),
+                None => Err(CliError::UnknownNode(name)),
+            })
+            .collect(),
+        None => {
+            let mut nodes: Vec<(String, NodeId)> = scene_manager
+                .netlist()
+                .net_names()
+                .filter(|(name, _)| *name != GROUND_NODE_NAME)
+                .map(|(name, node)| (name.to_string(), node))
+                .collect();
+            nodes.sort();
+            Ok(nodes)
+        }
+    }
+}
+
+fn open_output(output: Option<&Path>) -> Result<Box<dyn Write>, CliError> {
+    Ok(match output {
+        Some(path) => Box::new(BufWriter::new(
+            File::create(path).map_err(|source| output_error(Some(path), source))?,
+        )),
+        None => Box::new(BufWriter::new(io::stdout().lock())),
+    })
+}
+
+fn output_error(output: Option<&Path>, source: io::Error) -> CliError {
+    CliError::Io {
+        path: output.map_or_else(|| PathBuf::from("<stdout>"), Path::to_path_buf),
+        source,
+    }
+}
+
+#[cfg(test)]
+mod cli_test {
+    use super::*;
+
+    const DIVIDER: &str = "divider\nV1 in 0 1\nR1 in out 1k\nR2 out 0 3k\n.end\n";
+
+    fn temp_path(name: &str) -> PathBuf {
+        std::env::temp_dir().join(format!("rsnet_cli_test_{}_{}", std::process::id(), name))
+    }
+
+    #[test]
+    fn test_parse() {
+        let cli = Cli::try_parse_from(["rsnet", "sim", "net.cir", "--inputs", "0.5,-1", "--max-rows", "64"]).unwrap();
+        match cli.command {
+            Some(Command::Sim { inputs, model, .. }) => {
+                assert_eq!(inputs, Some(vec![0.5, -1.0]));
+                assert_eq!(model.max_rows, Some(64));
+            }
+            command => panic!("unexpected command {:?}", command),
+        }
+
+        assert!(Cli::try_parse_from(["rsnet"]).unwrap().command.is_none());
+        // The format of the export is required, and inferences are DC only
+        assert!(Cli::try_parse_from(["rsnet", "export", "net.cir"]).is_err());
+        assert!(Cli::try_parse_from(["rsnet", "sim", "net.cir", "--inputs", "1", "--tran", "1e-6"]).is_err());
+        assert!(Cli::try_parse_from(["rsnet", "sim", "net.cir", "--seed", "3"]).is_err());
+        match Cli::try_parse_from(["rsnet", "sim", "net.cir", "--tran", "1e-6", "--seed", "3"])
+            .unwrap()
+            .command
+        {
+            Some(Command::Sim { tran, .. }) => assert_eq!((tran.t_stop, tran.seed), (Some(1e-6), 3)),
+            command => panic!("unexpected command {:?}", command),
+        }
+        // Times that would stall the transient analysis are usage errors
+        for times in [["0", "1e-12"], ["inf", "1e-12"], ["1e-9", "0"], ["1e-9", "-1e-12"], ["1e-9", "NaN"]] {
+            let (tran, max_step) = (format!("--tran={}", times[0]), format!("--max-step={}", times[1]));
+            let error = Cli::try_parse_from(["rsnet", "sim", "net.cir", &tran, &max_step]).unwrap_err();
+            assert_eq!(error.kind(), clap::error::ErrorKind::ValueValidation);
+            assert_eq!(error.exit_code(), EXIT_USAGE as i32);
+        }
+        assert_eq!(
+            Cli::try_parse_from(["rsnet", "build", "model.onnx"])
+                .unwrap_err()
+                .exit_code(),
+            EXIT_USAGE as i32
+        );
+    }
+
+    #[test]
+    fn test_headless_commands() {
+        let netlist = temp_path("divider.cir");
+        let scene = temp_path("divider.rsn");
+        let spice = temp_path("divider_export.cir");
+        let csv = temp_path("divider.csv");
+        std::fs::write(&netlist, DIVIDER).unwrap();
+
+        let run_args = |args: &[&str]| {
+            let args = ["rsnet"].iter().chain(args).copied();
+            run(Cli::try_parse_from(args).unwrap())
+        };
+
+        let netlist_arg = netlist.to_str().unwrap();
+        let scene_arg = scene.to_str().unwrap();
+        let spice_arg = spice.to_str().unwrap();
+        let csv_arg = csv.to_str().unwrap();
+
+        assert_eq!(run_args(&["build", netlist_arg, "-o", scene_arg]), ExitCode::SUCCESS);
+        assert_eq!(
+            run_args(&["export", scene_arg, "--spice", "-o", spice_arg]),
+            ExitCode::SUCCESS
+        );
+        assert_eq!(
+            run_args(&["sim", spice_arg, "--nodes", "out", "-o", csv_arg]),
+            ExitCode::SUCCESS
+        );
+        let result = std::fs::read_to_string(&csv);
+        assert_eq!(
+            run_args(&["sim", scene_arg, "--nodes", "missing", "-o", csv_arg]),
+            ExitCode::from(EXIT_FAILURE)
+        );
+        assert_eq!(run_args(&["sim", "divider.txt"]), ExitCode::from(EXIT_USAGE));
+
+        for path in [&netlist, &scene, &spice, &csv] {
+            std::fs::remove_file(path).unwrap();
+        }
+
+        let result = result.unwrap();
+        let mut lines = result.lines();
+        assert_eq!(lines.next(), Some("node,voltage"));
+        let voltage: f64 = lines.next().unwrap().strip_prefix("out,").unwrap().parse().unwrap();
+        assert!((voltage - 0.75).abs() < 1e-9);
+    }
+}
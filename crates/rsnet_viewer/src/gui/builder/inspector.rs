@@ -0,0 +1,339 @@
+use crate::{
+    app,
+    gui::state::WidgetSystem,
+    scene::{
+        component::{ComponentParams, DefaultComponentTypes},
+        hierarchy::ConstructNodeKind,
+        history::Command,
+        scene_manager::SceneManager,
+        types::ComponentType,
+    },
+    types::Id,
+};
+
+use rsnet_derive::Widget;
+use rsnet_sim::stanford::{
+    model::{device_conductance, gap_for_conductance},
+    StanfordModelParams,
+};
+use tracing::warn;
+
+/// Shows the selected component and lets the user edit its placement and parameters. The edits are recorded in the
+/// history of the editor, dragging a value is a single edit.
+#[derive(Default, Widget)]
+pub struct Inspector {}
+
+impl WidgetSystem for Inspector {
+    fn system(
+        app_state: &mut app::State,
+        ui_state: &mut crate::gui::State,
+        ui: Option<&mut egui::Ui>,
+        context: Option<&egui::Context>,
+        id: crate::gui::state::WidgetId,
+    ) {
+        let Some(context) = context else {
+            return;
+        };
+
+        let selection = &app_state.selection;
+        if selection.is_empty() {
+            return;
+        }
+
+        // Only a single component is inspected, otherwise the size of the selection is shown
+        let component_id = match (selection.components().len(), selection.wires().len()) {
+            (1, 0) => selection.components().iter().next().copied(),
+            _ => None,
+        };
+        let Some(component_id) = component_id else {
+            let summary = format!(
+                "{} components and {} wires selected",
+                selection.components().len(),
+                selection.wires().len()
+            );
+            egui::Window::new("Inspector")
+                .collapsible(true)
+                .resizable(false)
+                .show(context, |ui| ui.label(summary));
+            return;
+        };
+
+        let scene_manager = &app_state.scene_manager;
+        let Some(component) = scene_manager.scene().get_component(component_id) else {
+            return;
+        };
+
+        let ty = component.ty();
+        let mut position = *component.position();
+        let mut rotation = component.rotation().to_degrees();
+        let mut scale = component.scale();
+        let mut params = *component.params();
+        let ports = ports(scene_manager, component_id);
+        let owner = owner(scene_manager, component_id);
+
+        let mut transform_edit = Edit::default();
+        let mut params_edit = Edit::default();
+
+        egui::Window::new("Inspector")
+            .collapsible(true)
+            .resizable(false)
+            .show(context, |ui| {
+                egui::Grid::new("Inspector Grid")
+                    .num_columns(2)
+                    .spacing([40.0, 4.0])
+                    .striped(true)
+                    .show(ui, |ui| {
+                        ui.label("Id");
+                        ui.label(format!("{component_id}"));
+                        ui.end_row();
+
+                        ui.label("Type");
+                        ui.label(DefaultComponentTypes::name(ty));
+                        ui.end_row();
+
+                        ui.label("Position");
+                        ui.horizontal(|ui| {
+                            transform_edit.track(
+                                ui.add(
+                                    egui::DragValue::new(&mut position.x)
+                                        .speed(0.1)
+                                        .prefix("x: "),
+                                ),
+                            );
+                            transform_edit.track(
+                                ui.add(
+                                    egui::DragValue::new(&mut position.y)
+                                        .speed(0.1)
+                                        .prefix("y: "),
+                                ),
+                            );
+                        });
+                        ui.end_row();
+
+                        ui.label("Rotation");
+                        transform_edit.track(
+                            ui.add(egui::DragValue::new(&mut rotation).speed(1.0).suffix("°")),
+                        );
+                        ui.end_row();
+
+                        ui.label("Scale");
+                        transform_edit.track(
+                            ui.add(
+                                egui::DragValue::new(&mut scale)
+                                    .speed(0.01)
+                                    .clamp_range(0.01..=f32::MAX),
+                            ),
+                        );
+                        ui.end_row();
+
+                        ui.label("Construct");
+                        ui.label(owner.as_deref().unwrap_or("None"));
+                        ui.end_row();
+
+                        for (port, net) in ports.iter() {
+                            ui.label(format!("Port {port}"));
+                            ui.label(net.as_deref().unwrap_or("Unconnected"));
+                            ui.end_row();
+                        }
+
+                        params_ui(ui, ty, &mut params, &mut params_edit);
+                    });
+            });
+
+        let app::State {
+            scene_manager,
+            editor,
+            ..
+        } = app_state;
+        let history = editor.history_mut();
+
+        if transform_edit.is_drag_started || params_edit.is_drag_started {
+            history.begin_group();
+        }
+
+        let mut commands = Vec::new();
+        if transform_edit.is_changed {
+            commands.push(Command::TransformComponent {
+                id: component_id,
+                position,
+                rotation: rotation.to_radians(),
+                scale,
+            });
+        }
+        if params_edit.is_changed {
+            commands.push(Command::SetComponentParams {
+                id: component_id,
+                params,
+            });
+        }
+        if let Err(e) = history.execute(scene_manager, Command::Batch(commands)) {
+            warn!("Could not edit component {component_id}: {e}");
+        }
+
+        if transform_edit.is_drag_released || params_edit.is_drag_released {
+            history.end_group();
+        }
+    }
+
+    fn init(&mut self, app_state: &mut app::State) {}
+}
+
+/// Edits made with the widgets of the inspector
+#[derive(Default)]
+struct Edit {
+    is_changed: bool,
+    is_drag_started: bool,
+    is_drag_released: bool,
+}
+
+impl Edit {
+    /// Adds the edit of a widget, returns whether its value changed
+    fn track(&mut self, response: egui::Response) -> bool {
+        let is_changed = response.changed();
+        self.is_changed |= is_changed;
+        self.is_drag_started |= response.drag_started();
+        self.is_drag_released |= response.drag_released();
+        is_changed
+    }
+}
+
+/// Rows of the parameters of a component
+fn params_ui(ui: &mut egui::Ui, ty: ComponentType, params: &mut ComponentParams, edit: &mut Edit) {
+    match params {
+        ComponentParams::None => {
+            ui.label("Parameters");
+            ui.label("None");
+            ui.end_row();
+        }
+        ComponentParams::Resistance(r) => {
+            let speed = relative_speed(*r);
+            ui.label("Resistance");
+            edit.track(
+                ui.add(
+                    egui::DragValue::new(r)
+                        .speed(speed)
+                        .clamp_range(f64::MIN_POSITIVE..=f64::MAX)
+                        .suffix(" Ω"),
+                ),
+            );
+            ui.end_row();
+        }
+        ComponentParams::Conductance(g) => {
+            // Shown in µS, the programmed conductances of the crossbars are in that range
+            let mut g_micro = *g * 1e6;
+            let speed = relative_speed(g_micro);
+            ui.label("Conductance");
+            if edit.track(
+                ui.add(
+                    egui::DragValue::new(&mut g_micro)
+                        .speed(speed)
+                        .clamp_range(f64::MIN_POSITIVE..=f64::MAX)
+                        .suffix(" µS"),
+                ),
+            ) {
+                *g = g_micro * 1e-6;
+            }
+            ui.end_row();
+
+            if ty == DefaultComponentTypes::Memristor as u32 {
+                stanford_gap_ui(ui, g, edit);
+            }
+        }
+        ComponentParams::Gain(gain) => {
+            let speed = relative_speed(*gain);
+            ui.label("Gain");
+            edit.track(ui.add(egui::DragValue::new(gain).speed(speed)));
+            ui.end_row();
+        }
+        ComponentParams::Diode {
+            saturation_current,
+            ideality,
+        } => {
+            let speed = relative_speed(*saturation_current);
+            ui.label("Saturation current");
+            edit.track(
+                ui.add(
+                    egui::DragValue::new(saturation_current)
+                        .speed(speed)
+                        .clamp_range(f64::MIN_POSITIVE..=f64::MAX)
+                        .suffix(" A"),
+                ),
+            );
+            ui.end_row();
+
+            ui.label("Ideality");
+            edit.track(
+                ui.add(
+                    egui::DragValue::new(ideality)
+                        .speed(0.01)
+                        .clamp_range(0.1..=10.0),
+                ),
+            );
+            ui.end_row();
+        }
+    }
+}
+
+/// Dragging changes the values by 1% of ```value``` per point, they span orders of magnitude
+fn relative_speed(value: f64) -> f64 {
+    value.abs() * 0.01
+}
+
+/// Gap of the Stanford model (with the default parameters) for which the memristor has the conductance ```g```. Editing
+/// it sets the conductance of that gap.
+fn stanford_gap_ui(ui: &mut egui::Ui, g: &mut f64, edit: &mut Edit) {
+    let params = StanfordModelParams::default();
+    let mut gap_nano = gap_for_conductance(&params, *g) * 1e9;
+
+    ui.label("Stanford gap");
+    let is_changed = edit.track(
+        ui.add(
+            egui::DragValue::new(&mut gap_nano)
+                .speed(0.01)
+                .clamp_range(params.gap_min * 1e9..=params.gap_max * 1e9)
+                .suffix(" nm"),
+        ),
+    );
+    ui.end_row();
+
+    if is_changed {
+        *g = device_conductance(&params, gap_nano * 1e-9, 0.0);
+    }
+}
+
+/// Name of every port of a component with the name of the net it is bound to
+fn ports(scene_manager: &SceneManager, component_id: Id) -> Vec<(String, Option<String>)> {
+    scene_manager
+        .component_ports(component_id)
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(port, node_id)| {
+            let net = node_id.map(|node_id| {
+                scene_manager
+                    .node_name(node_id)
+                    .map_or_else(|| format!("{node_id}"), |name| name.to_string())
+            });
+            (port.name, net)
+        })
+        .collect()
+}
+
+/// Path in the construct tree of the construct that owns a component, e.g. ```Network / Layer 0 / Crossbar```
+fn owner(scene_manager: &SceneManager, component_id: Id) -> Option<String> {
+    let hierarchy = scene_manager.hierarchy();
+    let leaf = hierarchy.construct_of(component_id)?;
+
+    let mut names: Vec<&str> = hierarchy
+        .ancestors(leaf)
+        .filter_map(|node_id| hierarchy.node(node_id))
+        .filter(|node| node.kind() != ConstructNodeKind::Root)
+        .map(|node| node.name())
+        .collect();
+    names.reverse();
+
+    let path = names.join(" / ");
+    match scene_manager.construct(leaf) {
+        Some(construct) => Some(format!("{path} ({})", construct.type_name())),
+        None => Some(path),
+    }
+}
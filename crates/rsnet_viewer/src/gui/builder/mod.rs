@@ -1,4 +1,6 @@
 mod debug_gui;
+mod inspector;
+mod palette;
 mod settings;
 mod top_panel;
 
@@ -7,6 +9,7 @@ use crate::{app, gui};
 use egui::RichText;
 use egui::{Color32, Context};
 
+use self::inspector::Inspector;
 use self::settings::Settings;
 use self::top_panel::TopPanel;
 
@@ -31,6 +34,41 @@ pub fn build(context: &Context, app_state: &mut app::State, ui_state: &mut gui::
         Some(context),
         WidgetId::new("Top Panel"),
     );
+
+    widget::<Inspector>(
+        app_state,
+        ui_state,
+        None,
+        Some(context),
+        WidgetId::new("Inspector"),
+    );
+
+    selection_box(context, app_state);
+}
+
+/// Draws the box being dragged to select what is inside it
+fn selection_box(context: &Context, app_state: &app::State) {
+    let Some(selection_box) = app_state.selection_box() else {
+        return;
+    };
+
+    // The box is in physical pixels of the window, egui works in points
+    let pixels_per_point = context.pixels_per_point();
+    let rect = egui::Rect::from_min_max(
+        egui::pos2(selection_box.min.x, selection_box.min.y) / pixels_per_point,
+        egui::pos2(selection_box.max.x, selection_box.max.y) / pixels_per_point,
+    );
+
+    let painter = context.layer_painter(egui::LayerId::new(
+        egui::Order::Foreground,
+        egui::Id::new("Selection Box"),
+    ));
+    painter.rect(
+        rect,
+        0.0,
+        Color32::from_rgba_unmultiplied(245, 160, 66, 24),
+        egui::Stroke::new(1.0, Color32::from_rgb(245, 160, 66)),
+    );
 }
 
 pub fn style() -> egui::Style {
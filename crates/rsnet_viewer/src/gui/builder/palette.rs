@@ -0,0 +1,97 @@
+use crate::{
+    app::{self, editor::Tool},
+    gui::state::WidgetSystem,
+    scene::component::DefaultComponentTypes,
+};
+
+use rsnet_derive::Widget;
+use std::f32::consts::FRAC_PI_2;
+use strum::IntoEnumIterator;
+use tracing::warn;
+
+/// Tools of the edit mode, the components that can be placed and the edits of the selection
+#[derive(Default, Widget)]
+pub struct Palette {}
+
+impl WidgetSystem for Palette {
+    fn system(
+        app_state: &mut app::State,
+        ui_state: &mut crate::gui::State,
+        ui: Option<&mut egui::Ui>,
+        context: Option<&egui::Context>,
+        id: crate::gui::state::WidgetId,
+    ) {
+        if ui.is_none() {
+            return;
+        }
+
+        let ui = ui.unwrap();
+
+        let app::State {
+            scene_manager,
+            selection,
+            editor,
+            ..
+        } = app_state;
+
+        let mut tool = editor.tool();
+        ui.selectable_value(&mut tool, Tool::Select, "Select")
+            .on_hover_text("Select, drag a selected component to move the selection.");
+        ui.selectable_value(&mut tool, Tool::Wire, "Wire")
+            .on_hover_text("Drag to draw a wire.");
+
+        ui.separator();
+        for ty in DefaultComponentTypes::iter() {
+            let name: &'static str = ty.into();
+            ui.selectable_value(&mut tool, Tool::Place(ty), name)
+                .on_hover_text("Click to place the component.");
+        }
+        if tool != editor.tool() {
+            editor.set_tool(tool);
+        }
+
+        ui.separator();
+        let result = ui
+            .horizontal(|ui| {
+                let has_selection = !selection.is_empty();
+
+                if ui
+                    .add_enabled(has_selection, egui::Button::new("⟲"))
+                    .on_hover_text("Rotate the selection by 90° (R).")
+                    .clicked()
+                {
+                    return editor.rotate_selection(scene_manager, selection, FRAC_PI_2);
+                }
+                if ui
+                    .add_enabled(has_selection, egui::Button::new("Delete"))
+                    .on_hover_text("Delete the selection (Del).")
+                    .clicked()
+                {
+                    return editor.delete_selection(scene_manager, selection);
+                }
+                if ui
+                    .add_enabled(editor.history().can_undo(), egui::Button::new("Undo"))
+                    .on_hover_text("Ctrl+Z")
+                    .clicked()
+                {
+                    return editor.undo(scene_manager, selection).map(|_| ());
+                }
+                if ui
+                    .add_enabled(editor.history().can_redo(), egui::Button::new("Redo"))
+                    .on_hover_text("Ctrl+Y")
+                    .clicked()
+                {
+                    return editor.redo(scene_manager, selection).map(|_| ());
+                }
+
+                Ok(())
+            })
+            .inner;
+
+        if let Err(e) = result {
+            warn!("Could not edit the scene: {e}");
+        }
+    }
+
+    fn init(&mut self, app_state: &mut app::State) {}
+}
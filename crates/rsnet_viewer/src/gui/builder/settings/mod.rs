@@ -49,6 +49,18 @@ impl WidgetSystem for Settings {
                 if grid != app_state.grid() {
                     app_state.set_grid(grid);
                 }
+                ui.end_row();
+
+                let mut labels = app_state.labels();
+
+                ui.add(egui::Label::new("Labels"));
+
+                ui.add(toggle_switch::toggle(&mut labels))
+                    .on_hover_text("Toggle the labels of the components, nets and layers.");
+
+                if labels != app_state.labels() {
+                    app_state.set_labels(labels);
+                }
             });
     }
 
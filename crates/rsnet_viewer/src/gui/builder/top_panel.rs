@@ -5,6 +5,7 @@ use tracing::info;
 
 use crate::gui::state::{widget, WidgetId, WidgetSystem};
 
+use super::palette::Palette;
 use super::settings::Settings;
 
 #[derive(Default, Widget)]
@@ -40,6 +41,11 @@ impl WidgetSystem for TopPanel {
                 if ui.selectable_label(state.debug_open, "Debug").clicked() {
                     state.debug_open = !state.debug_open;
                 }
+
+                let is_editing = app_state.editor.is_enabled();
+                if ui.selectable_label(is_editing, "Edit").clicked() {
+                    app_state.editor.set_enabled(!is_editing);
+                }
             });
         });
 
@@ -87,6 +93,26 @@ impl WidgetSystem for TopPanel {
             }
         }
 
+        if app_state.editor.is_enabled() {
+            let mut open = true;
+            egui::Window::new("Palette")
+                .collapsible(true)
+                .resizable(false)
+                .open(&mut open)
+                .show(context, |ui| {
+                    widget::<Palette>(
+                        app_state,
+                        ui_state,
+                        Some(ui),
+                        Some(context),
+                        WidgetId::new("Palette"),
+                    );
+                });
+            if !open {
+                app_state.editor.set_enabled(false);
+            }
+        }
+
         // egui::Window::new("Settings")
         //     .collapsible(false)
         //     .frame(frame)
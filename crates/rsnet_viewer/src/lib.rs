@@ -1,4 +1,5 @@
 pub mod app;
+pub mod cli;
 pub mod gui;
 pub mod renderer;
 pub mod scene;
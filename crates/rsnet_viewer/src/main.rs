@@ -1,9 +1,15 @@
-use rsnet_viewer::app;
+use std::process::ExitCode;
 
-fn main() {
-    tokio::runtime::Builder::new_multi_thread()
-        .enable_all()
-        .build()
-        .unwrap()
-        .block_on(async { app::event_loop::run().await })
+use clap::Parser;
+use rsnet_viewer::cli::{self, Cli};
+use tracing_subscriber::{filter::EnvFilter, fmt, prelude::*};
+
+fn main() -> ExitCode {
+    // Logs go to stderr, so the output of the headless commands can be piped
+    tracing_subscriber::registry()
+        .with(fmt::layer().with_writer(std::io::stderr))
+        .with(EnvFilter::from_default_env())
+        .init();
+
+    cli::run(Cli::parse())
 }
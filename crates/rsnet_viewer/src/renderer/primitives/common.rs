@@ -16,6 +16,31 @@ const MEMRISTOR_TERMINAL_LEN: f32 = 0.2;
 
 const LINE_THICKNESS: f32 = 0.02;
 
+/// Colors of the conductance heatmap of the zoomed out levels, from the lowest to the highest conductance
+pub const HEATMAP_COLORS: [u32; 8] = [
+    0x440154, 0x46327E, 0x365C8D, 0x277F8E, 0x1FA187, 0x4AC16D, 0xA0DA39, 0xFDE725,
+];
+
+/// Colors of the density tiles of the zoomed out levels, from the sparsest to the densest
+pub const DENSITY_COLORS: [u32; 8] = [
+    0xE0E0E0, 0xC8C8C8, 0xB0B0B0, 0x989898, 0x808080, 0x686868, 0x505050, 0x383838,
+];
+
+/// A square of side ```size``` centered in the origin, scaled to the cell it aggregates
+fn square_primitives(size: f32, color: u32) -> ComponentTyPrimitives {
+    ComponentTyPrimitives {
+        circles: vec![],
+        lines: vec![],
+        rectangles: vec![RectanglePrimitive {
+            position: Vector2::new(0.0, 0.0),
+            size: Vector2::new(size, size),
+            color,
+        }],
+        triangles: vec![],
+        ports: vec![],
+    }
+}
+
 lazy_static!(
     pub static ref UNKNOWN_PRIMITIVE: ComponentTyPrimitives = ComponentTyPrimitives {
         circles: vec![],
@@ -489,7 +514,25 @@ lazy_static!(
             ],
             rectangles: vec![],
             triangles: vec![],
-            ports: vec![]
+            ports: vec![
+                Port {
+                    name: "anode".to_string(),
+                    relative_position: anode_terminal[1],
+                    ty: PortType::InOut
+                },
+                Port {
+                    name: "cathode".to_string(),
+                    relative_position: cathode_terminal[1],
+                    ty: PortType::InOut
+                }
+            ]
         }
     };
+
+    pub static ref HEATMAP_PRIMITIVES: Vec<ComponentTyPrimitives> =
+        HEATMAP_COLORS.iter().map(|color| square_primitives(1.0, *color)).collect();
+
+    /// Slightly smaller than the cell so the tiles stay apart
+    pub static ref DENSITY_PRIMITIVES: Vec<ComponentTyPrimitives> =
+        DENSITY_COLORS.iter().map(|color| square_primitives(0.9, *color)).collect();
 );
@@ -2,6 +2,8 @@ use super::shared;
 
 use shared::*;
 
+use crate::utils::AaBb;
+
 use nalgebra::Vector2;
 use tracing::info;
 
@@ -30,6 +32,35 @@ pub struct ComponentTyPrimitives {
 }
 
 impl ComponentTyPrimitives {
+    /// Box holding every primitive, in the coordinates of the component. ```None``` if there are none.
+    pub fn aabb(&self) -> Option<AaBb> {
+        let around = |center: Vector2<f32>, half_size: Vector2<f32>| {
+            [center - half_size, center + half_size]
+        };
+
+        let circles = self
+            .circles
+            .iter()
+            .flat_map(|circle| around(circle.position, Vector2::new(circle.radius, circle.radius)));
+        let lines = self.lines.iter().flat_map(|line| {
+            let half_thickness = Vector2::new(line.thickness, line.thickness) / 2.0;
+            line.positions
+                .iter()
+                .flat_map(move |position| around(*position, half_thickness))
+        });
+        let rectangles = self
+            .rectangles
+            .iter()
+            .flat_map(|rectangle| around(rectangle.position, rectangle.size / 2.0));
+        // Any direction fits in the circle around the triangle
+        let triangles = self.triangles.iter().flat_map(|triangle| {
+            let radius = triangle.size.max() / 2.0;
+            around(triangle.position, Vector2::new(radius, radius))
+        });
+
+        AaBb::from_points(circles.chain(lines).chain(rectangles).chain(triangles))
+    }
+
     pub fn to_fragments(
         &self,
     ) -> (
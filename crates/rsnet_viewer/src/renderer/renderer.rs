@@ -18,9 +18,11 @@ use crate::{
     app::{
         self,
         camera::{Camera, CameraController},
+        selection::Selection,
+        utils::chunk_size_from_step_idx,
     },
     scene::{
-        self,
+        self, labels, lod,
         shared::{
             create_scene_storage_bind_group, ComponentBufferEntry, SceneStorage, WireBufferEntry,
         },
@@ -33,6 +35,7 @@ use crate::{
     utils::{insert_ordered_at, wgpu::context::Context},
 };
 
+use nalgebra::Vector2;
 use rayon::prelude::*;
 use std::{
     collections::{HashMap, HashSet},
@@ -74,6 +77,8 @@ pub struct Cache {
     pub compty_fragments_index_map:
         HashMap<u32, Vec<(u32, f32 /*This is the maximum camera distance*/)>>,
     pub scene_chunk_step_idx: u32,
+    /// Version of the selection highlighted in the scene storage
+    pub selection_version: u64,
 }
 
 pub struct Renderer<'a> {
@@ -287,6 +292,7 @@ impl<'a> Renderer<'a> {
         state: &app::State,
         camera_controller: &mut CameraController,
         scene: &Scene,
+        dirty_chunks: &DirtyChunks,
     ) {
         let t = std::time::Instant::now();
         let elapsed = t.duration_since(self.last_rendered);
@@ -304,8 +310,19 @@ impl<'a> Renderer<'a> {
             &context.queue,
             camera_controller,
             scene,
+            &state.selection,
+        );
+
+        self.update_dirty_scene_storage(
+            &context.device,
+            &context.queue,
+            scene,
+            dirty_chunks,
+            &state.selection,
         );
 
+        self.check_and_update_selection(&context.device, &context.queue, &state.selection);
+
         self.check_and_update_fragments_storage(&context.device, &context.queue, scene);
 
         let mut render_pass = encoder.begin_render_pass(&RenderPassDescriptor {
@@ -364,13 +381,21 @@ impl<'a> Renderer<'a> {
             &self.shared.scene_storage.bind_group,
         );
 
-        // self.text_renderer.render(
-        //     &context.device,
-        //     &context.queue,
-        //     &mut render_pass,
-        //     &self.shared.common_uniforms.bind_group,
-        //     camera_controller,
-        // );
+        if state.labels() {
+            // The labels are only generated again when the scene changes
+            if self.text_renderer.labels().is_none() || !dirty_chunks.is_empty() {
+                self.text_renderer
+                    .set_labels(labels::scene_labels(&state.scene_manager));
+            }
+
+            self.text_renderer.render(
+                &context.device,
+                &context.queue,
+                &mut render_pass,
+                &self.shared.common_uniforms.bind_group,
+                camera_controller,
+            );
+        }
 
         self.last_rendered = t;
     }
@@ -538,6 +563,7 @@ impl<'a> Renderer<'a> {
         queue: &Queue,
         camera_controller: &CameraController,
         scene: &Scene,
+        selection: &Selection,
     ) {
         let chunk_size = camera_controller.chunk_size;
         // let half_chunk_size = scene.chunk_size() / 2.0;
@@ -565,7 +591,7 @@ impl<'a> Renderer<'a> {
             let chunk_step_idx = self.cache.scene_chunk_step_idx;
 
             // debug!("Visible chunks changed, updating components, ({}, {}), ({}, {})", min_chunk.0, min_chunk.1, max_chunk.0, max_chunk.1);
-            let scene_components = scene.components().get(&(chunk_step_idx as u32));
+            let scene_components = scene.level(chunk_step_idx);
             let scene_wires = scene.wire_segments().get(&(chunk_step_idx as u32));
 
             let mut components = self.shared.scene_storage.components.get_mut();
@@ -777,9 +803,132 @@ impl<'a> Renderer<'a> {
             self.cache.n_components_by_type.retain(|_, n| *n > 0);
 
             if n_aditions + n_deletions > 0 {
-                self.shared.scene_storage.write(device, queue);
+                self.write_scene_storage(device, queue, selection);
+            }
+        }
+    }
+
+    /// Uploads again the visible chunks that changed in the scene since the last frame, so edits show up
+    /// without clearing the whole storage.
+    fn update_dirty_scene_storage(
+        &mut self,
+        device: &Device,
+        queue: &Queue,
+        scene: &Scene,
+        dirty_chunks: &DirtyChunks,
+        selection: &Selection,
+    ) {
+        let chunk_step_idx = self.cache.scene_chunk_step_idx;
+        let Some(chunk_range) = self.cache.chunk_range.as_ref() else {
+            return;
+        };
+        let dirty: HashSet<ChunkId> = dirty_chunks
+            .get(&chunk_step_idx)
+            .map(|chunks| {
+                chunks
+                    .iter()
+                    .filter(|chunk_id| chunk_range.contains(chunk_id))
+                    .copied()
+                    .collect()
+            })
+            .unwrap_or_default();
+
+        if dirty.is_empty() {
+            return;
+        }
+
+        // Components: replace the ones of the dirty chunks, found by their translation
+        let chunk_size = chunk_size_from_step_idx(chunk_step_idx + 1);
+        let components = self.shared.scene_storage.components.get_mut();
+        components.retain(|entry| {
+            let translation = Vector2::new(entry.model()[(0, 2)], entry.model()[(1, 2)]);
+            !dirty.contains(&chunk_id_from_position(&translation, chunk_size))
+        });
+
+        if let Some(scene_components) = scene.level(chunk_step_idx) {
+            dirty
+                .iter()
+                .filter_map(|chunk_id| scene_components.get(chunk_id))
+                .flatten()
+                .for_each(|component| {
+                    components.push(ComponentBufferEntry::from_component(component))
+                });
+        }
+        components.sort_unstable_by(|a, b| a.ty().cmp(&b.ty()).then(a.id().cmp(&b.id())));
+
+        let n_components_by_type = &mut self.cache.n_components_by_type;
+        n_components_by_type.clear();
+        components
+            .iter()
+            .for_each(|c| *n_components_by_type.entry(c.ty()).or_insert(0) += 1);
+
+        // Wires: drop the removed ones, refresh the rest and add the ones cached in the dirty chunks
+        let wire_buffer = self.shared.scene_storage.wires.get_mut();
+        wire_buffer.retain_mut(|entry| match scene.wires().get(&entry.id()) {
+            Some(wire) => {
+                *entry = WireBufferEntry::from_wire(wire);
+                true
             }
+            None => false,
+        });
+
+        if let Some(scene_wires) = scene.wire_segments().get(&chunk_step_idx) {
+            let mut wire_ids: Vec<Id> = dirty
+                .iter()
+                .filter_map(|chunk_id| scene_wires.get(chunk_id))
+                .flatten()
+                .copied()
+                .collect();
+            wire_ids.sort_unstable();
+            wire_ids.dedup();
+
+            wire_ids.iter().for_each(|wire_id| {
+                if let Err(i) = wire_buffer.binary_search_by(|w| w.id().cmp(wire_id)) {
+                    wire_buffer.insert(
+                        i,
+                        WireBufferEntry::from_wire(scene.wires().get(wire_id).unwrap()),
+                    );
+                }
+            });
         }
+
+        self.write_scene_storage(device, queue, selection);
+    }
+
+    /// Highlights the selected instances again when the selection changed
+    fn check_and_update_selection(
+        &mut self,
+        device: &Device,
+        queue: &Queue,
+        selection: &Selection,
+    ) {
+        if self.cache.selection_version != selection.version() {
+            self.write_scene_storage(device, queue, selection);
+        }
+    }
+
+    /// Writes the scene storage, with the selected components and wires flagged. The aggregates of the zoomed out
+    /// levels share ids with the components, so they are never flagged.
+    fn write_scene_storage(&mut self, device: &Device, queue: &Queue, selection: &Selection) {
+        self.shared
+            .scene_storage
+            .components
+            .get_mut()
+            .iter_mut()
+            .for_each(|entry| {
+                entry.selected = (!lod::is_aggregate_type(entry.ty())
+                    && selection.contains_component(entry.id()))
+                    as u32;
+            });
+        self.shared
+            .scene_storage
+            .wires
+            .get_mut()
+            .iter_mut()
+            .for_each(|entry| entry.selected = selection.contains_wire(entry.id()) as u32);
+        self.cache.selection_version = selection.version();
+
+        self.shared.scene_storage.write(device, queue);
     }
 
     fn clear_scene_storage(&mut self, device: &Device, queue: &Queue) {
@@ -4,7 +4,7 @@ use wgpu::{util::DeviceExt, BlendComponent, Device, SurfaceConfiguration};
 
 use crate::renderer::shader;
 
-use super::shared::Vertex;
+use super::shared::{GlyphInstance, Vertex};
 
 pub enum TextPipelineType {
     Offscreen,
@@ -74,7 +74,13 @@ pub fn create_pipeline(
         push_constant_ranges: &[],
     });
 
-    let vertex_layouts = &[Vertex::desc()];
+    // Offscreen, every glyph is drawn once per label that contains it
+    let offscreen_layouts = [Vertex::desc(), GlyphInstance::desc()];
+    let onscreen_layouts = [Vertex::desc()];
+    let vertex_layouts: &[wgpu::VertexBufferLayout] = match pipeline_ty {
+        TextPipelineType::Offscreen => &offscreen_layouts,
+        TextPipelineType::Onscreen => &onscreen_layouts,
+    };
     let color_format = config.format;
     let depth_format = None;
 
@@ -48,20 +48,51 @@ impl Vertex {
     }
 }
 
-pub struct VertexBuffer<'a> {
-    pub value: Vec<Vertex>,
+/// Placement of a glyph of a label, the vertices of the glyph are scaled by ```scale``` and moved to ```position```
+#[repr(C)]
+#[derive(Copy, Clone, Debug, PartialEq, bytemuck::Pod, bytemuck::Zeroable)]
+pub struct GlyphInstance {
+    pub position: [f32; 2],
+    pub scale: f32,
+}
+
+impl GlyphInstance {
+    pub fn desc() -> wgpu::VertexBufferLayout<'static> {
+        wgpu::VertexBufferLayout {
+            array_stride: std::mem::size_of::<GlyphInstance>() as wgpu::BufferAddress,
+            step_mode: wgpu::VertexStepMode::Instance,
+            attributes: &[
+                wgpu::VertexAttribute {
+                    // Position
+                    offset: 0,
+                    shader_location: 2,
+                    format: wgpu::VertexFormat::Float32x2,
+                },
+                wgpu::VertexAttribute {
+                    // Scale
+                    offset: std::mem::size_of::<[f32; 2]>() as wgpu::BufferAddress,
+                    shader_location: 3,
+                    format: wgpu::VertexFormat::Float32,
+                },
+            ],
+        }
+    }
+}
+
+pub struct VertexBuffer<'a, T = Vertex> {
+    pub value: Vec<T>,
     pub label: Option<String>,
     // pub scratch: Option<Vec<Vertex>>,
     pub buffer: Option<wgpu::Buffer>,
     pub buffer_layout: wgpu::VertexBufferLayout<'a>,
 }
 
-impl VertexBuffer<'_> {
-    pub fn get(&self) -> &Vec<Vertex> {
+impl<T: bytemuck::Pod> VertexBuffer<'_, T> {
+    pub fn get(&self) -> &Vec<T> {
         &self.value
     }
 
-    pub fn set(&mut self, value: Vec<Vertex>) {
+    pub fn set(&mut self, value: Vec<T>) {
         self.value = value;
     }
 
@@ -80,6 +111,9 @@ impl VertexBuffer<'_> {
     pub fn write(&mut self, device: &wgpu::Device, queue: &wgpu::Queue) {
         let capacity: u64 = self.buffer.as_ref().map(wgpu::Buffer::size).unwrap_or(0);
         let byte_data = bytemuck::cast_slice(self.value.as_slice());
+        if byte_data.is_empty() {
+            return;
+        }
 
         if capacity < byte_data.len() as u64 {
             self.buffer = Some(
@@ -4,13 +4,18 @@ use super::texture;
 
 use shared::Vertex;
 
-use crate::{app::camera::camera_controller, gui::renderer, renderer::shared::CommonUniforms};
+use crate::{
+    app::camera::camera_controller, gui::renderer, renderer::shared::CommonUniforms,
+    scene::labels::Label, utils::AaBb,
+};
 
 use nalgebra::{Vector2, Vector4};
 use rsnet_derive::include_asset_bytes;
+use std::{collections::HashMap, ops::Range};
 use tracing::{error, info};
 use ttf_parser::{self, GlyphId};
 use wgpu::{core::device, Device, MultisampleState, Queue, RenderPass, SurfaceConfiguration};
+use winit::dpi::PhysicalPosition;
 
 #[derive(Debug, Clone, Copy)]
 pub enum TriangleKind {
@@ -19,14 +24,18 @@ pub enum TriangleKind {
 }
 
 pub struct Glyph {
-    vertices: Vec<Vertex>,
+    /// Vertices of the glyph in the vertex buffer of all the glyphs, in font units
+    vertex_range: Range<u32>,
+    /// Horizontal advance in font units
+    advance: f32,
     name: String,
 }
 
 impl Glyph {
-    pub fn from_builder(builder: GlyphBuilder, name: String) -> Self {
+    pub fn new(vertex_range: Range<u32>, advance: f32, name: String) -> Self {
         Self {
-            vertices: builder.vertices,
+            vertex_range,
+            advance,
             name,
         }
     }
@@ -139,11 +148,23 @@ impl ttf_parser::OutlineBuilder for GlyphBuilder {
     }
 }
 
+/// Glyph instances of the visible labels, grouped by glyph so that every glyph is a single instanced draw
+#[derive(Debug, Default, PartialEq)]
+pub struct LabelsLayout {
+    instances: Vec<shared::GlyphInstance>,
+    /// ```(vertices of the glyph, its instances)``` of every draw
+    draws: Vec<(Range<u32>, Range<u32>)>,
+}
+
+/// Draws the labels of the scene. The glyphs of the font are triangulated once and stored in a single vertex buffer,
+/// every frame the glyphs of the visible labels are instanced from it.
 pub struct TextRenderer<'a> {
     offscreen_pipeline: wgpu::RenderPipeline,
     onscreen_pipeline: wgpu::RenderPipeline,
 
+    /// Vertices of every glyph of the font
     offscreen_vertex_buffer: shared::VertexBuffer<'a>,
+    instance_buffer: shared::VertexBuffer<'a, shared::GlyphInstance>,
     onscreen_vertex_buffer: shared::VertexBuffer<'a>,
 
     msaa_count: u32,
@@ -152,7 +173,12 @@ pub struct TextRenderer<'a> {
 
     face: ttf_parser::Face<'a>,
     font_data: &'a [u8],
-    glyph_map: std::collections::HashMap<GlyphId, Glyph>,
+    glyph_map: HashMap<GlyphId, Glyph>,
+
+    labels: Option<Vec<Label>>,
+    layout: LabelsLayout,
+    /// View and pixels per scene unit the layout was made for, the labels are laid out again when they change
+    layout_view: Option<(AaBb, f32)>,
 }
 
 impl<'a> TextRenderer<'a> {
@@ -172,46 +198,8 @@ impl<'a> TextRenderer<'a> {
             }
         };
 
-        face.number_of_glyphs();
-        let a_gid = face.glyph_index('5').unwrap();
-        let u32_num = u32::from('Ç');
-        let name = face.glyph_name(a_gid).unwrap();
-        info!(
-            "\nGlyph index for 'a': {:?}\n\tname: {}\n\t u32_num: {}",
-            a_gid, name, u32_num
-        );
-
-        let mut triangle_count: usize = 0;
-
-        let mut glyph_map = std::collections::HashMap::new();
-
-        for id in 0..face.number_of_glyphs() {
-            let gid = ttf_parser::GlyphId(id);
-            let name = face.glyph_name(gid).unwrap();
-
-            match face.glyph_svg_image(gid) {
-                Some(_svg) => {
-                    error!(
-                        "Glyph {} has SVG image, we can currently only render outlines",
-                        name
-                    );
-                }
-                None => {
-                    let mut builder = GlyphBuilder::new();
-                    face.outline_glyph(gid, &mut builder);
-                    triangle_count += builder.triangle_count();
-
-                    if gid == a_gid {
-                        info!("\'a\' triangle_count: {}", builder.triangle_count());
-                    }
-
-                    let glyph = Glyph::from_builder(builder, name.to_string());
-                    glyph_map.insert(gid, glyph);
-                }
-            }
-        }
-
-        info!("Total triangle count: {}", triangle_count);
+        let (glyph_map, vertices) = triangulate_glyphs(&face);
+        info!("Total triangle count: {}", vertices.len() / 3);
 
         let msaa_count = 1;
 
@@ -228,9 +216,16 @@ impl<'a> TextRenderer<'a> {
             buffer: None,
             buffer_layout: Vertex::desc(),
         };
-        offscreen_vertex_buffer.set(glyph_map.get(&a_gid).unwrap().vertices.clone());
+        offscreen_vertex_buffer.set(vertices);
         offscreen_vertex_buffer.write(device, queue);
 
+        let instance_buffer = shared::VertexBuffer {
+            value: Vec::new(),
+            label: Some("Text instance buffer".to_string()),
+            buffer: None,
+            buffer_layout: shared::GlyphInstance::desc(),
+        };
+
         let offscreen_texture = texture::Texture::new(device, 1, config.width, config.height);
         let onscreen_texture =
             texture::Texture::new(device, msaa_count, config.width, config.height);
@@ -285,9 +280,13 @@ impl<'a> TextRenderer<'a> {
             onscreen_pipeline,
             msaa_count,
             offscreen_vertex_buffer,
+            instance_buffer,
             onscreen_vertex_buffer,
             offscreen_texture,
             onscreen_texture,
+            labels: None,
+            layout: LabelsLayout::default(),
+            layout_view: None,
         }
     }
 
@@ -295,6 +294,16 @@ impl<'a> TextRenderer<'a> {
         self.msaa_count = count;
     }
 
+    /// Labels drawn, ```None``` until they are set
+    pub fn labels(&self) -> Option<&Vec<Label>> {
+        self.labels.as_ref()
+    }
+
+    pub fn set_labels(&mut self, labels: Vec<Label>) {
+        self.labels = Some(labels);
+        self.layout_view = None;
+    }
+
     pub fn rebuild_pipeline(
         &mut self,
         config: &SurfaceConfiguration,
@@ -324,6 +333,31 @@ impl<'a> TextRenderer<'a> {
         self.onscreen_texture.resize(device, width, height);
     }
 
+    /// Lays out the labels again if the view changed, and uploads the glyph instances
+    fn update_layout(
+        &mut self,
+        device: &Device,
+        queue: &Queue,
+        camera_controller: &camera_controller::CameraController,
+    ) {
+        let Some((view, pixels_per_unit)) = visible_view(camera_controller) else {
+            self.layout = LabelsLayout::default();
+            return;
+        };
+
+        let layout_view = Some((view.clone(), pixels_per_unit));
+        if self.layout_view == layout_view {
+            return;
+        }
+
+        let labels = self.labels.as_deref().unwrap_or_default();
+        self.layout = layout_labels(&self.face, &self.glyph_map, labels, &view, pixels_per_unit);
+        self.layout_view = layout_view;
+
+        self.instance_buffer.set(self.layout.instances.clone());
+        self.instance_buffer.write(device, queue);
+    }
+
     pub fn render<'b, 'c>(
         &'b mut self,
         device: &'b Device,
@@ -334,6 +368,11 @@ impl<'a> TextRenderer<'a> {
     ) where
         'b: 'c,
     {
+        self.update_layout(device, queue, camera_controller);
+        if self.layout.draws.is_empty() {
+            return;
+        }
+
         let mut encoder =
             device.create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
         {
@@ -369,8 +408,11 @@ impl<'a> TextRenderer<'a> {
             render_pass.set_bind_group(0, &common_uniforms_bind_group, &[]);
             render_pass
                 .set_vertex_buffer(0, self.offscreen_vertex_buffer.buffer().unwrap().slice(..));
+            render_pass.set_vertex_buffer(1, self.instance_buffer.buffer().unwrap().slice(..));
 
-            render_pass.draw(0..self.offscreen_vertex_buffer.get().len() as u32, 0..1);
+            for (vertices, instances) in self.layout.draws.iter() {
+                render_pass.draw(vertices.clone(), instances.clone());
+            }
         }
 
         queue.submit(Some(encoder.finish()));
@@ -383,3 +425,157 @@ impl<'a> TextRenderer<'a> {
         render_pass.draw(0..6, 0..1);
     }
 }
+
+/// Triangulates the outline of every glyph of ```face```. Returns the glyphs, indexing the returned vertices.
+fn triangulate_glyphs(face: &ttf_parser::Face) -> (HashMap<GlyphId, Glyph>, Vec<Vertex>) {
+    let mut glyph_map = HashMap::new();
+    let mut vertices = Vec::new();
+
+    for id in 0..face.number_of_glyphs() {
+        let gid = ttf_parser::GlyphId(id);
+        let name = face.glyph_name(gid).unwrap_or_default();
+
+        if face.glyph_svg_image(gid).is_some() {
+            error!(
+                "Glyph {} has SVG image, we can currently only render outlines",
+                name
+            );
+            continue;
+        }
+
+        let mut builder = GlyphBuilder::new();
+        face.outline_glyph(gid, &mut builder);
+
+        let start = vertices.len() as u32;
+        vertices.extend(builder.vertices);
+        let advance = face.glyph_hor_advance(gid).unwrap_or(0) as f32;
+
+        glyph_map.insert(
+            gid,
+            Glyph::new(start..vertices.len() as u32, advance, name.to_string()),
+        );
+    }
+
+    (glyph_map, vertices)
+}
+
+/// Part of the scene on the screen and the number of pixels per scene unit
+fn visible_view(camera_controller: &camera_controller::CameraController) -> Option<(AaBb, f32)> {
+    let size = camera_controller.window_size;
+    let corners = [
+        PhysicalPosition::new(0.0, 0.0),
+        PhysicalPosition::new(size.width as f64, size.height as f64),
+    ];
+    let view = AaBb::from_points(
+        corners
+            .iter()
+            .map(|corner| camera_controller.screen_to_world(corner))
+            .collect::<Option<Vec<_>>>()?,
+    )?;
+
+    let width = view.max.x - view.min.x;
+    if width <= 0.0 {
+        return None;
+    }
+    Some((view, size.width as f32 / width))
+}
+
+/// Glyph instances of the labels that are readable and overlap ```view```
+pub fn layout_labels(
+    face: &ttf_parser::Face,
+    glyph_map: &HashMap<GlyphId, Glyph>,
+    labels: &[Label],
+    view: &AaBb,
+    pixels_per_unit: f32,
+) -> LabelsLayout {
+    // The size of a label is the height of a line, from the descender to the ascender
+    let ascender = face.ascender() as f32;
+    let descender = face.descender() as f32;
+    let line_height = ascender - descender;
+
+    let mut instances_by_glyph: HashMap<GlyphId, Vec<shared::GlyphInstance>> = HashMap::new();
+    for label in labels
+        .iter()
+        .filter(|label| label.is_readable(pixels_per_unit))
+    {
+        let scale = label.size() / line_height;
+        let glyphs: Vec<(GlyphId, &Glyph)> = label
+            .text()
+            .chars()
+            .filter_map(|c| face.glyph_index(c))
+            .filter_map(|gid| glyph_map.get(&gid).map(|glyph| (gid, glyph)))
+            .collect();
+
+        let width = glyphs.iter().map(|(_, glyph)| glyph.advance).sum::<f32>() * scale;
+        let size = Vector2::new(width, label.size());
+        let origin = label.position() + label.anchor().offset(&size);
+        if !AaBb::new(origin, origin + size).overlaps(view) {
+            continue;
+        }
+
+        let mut pen = Vector2::new(origin.x, origin.y - descender * scale);
+        for (gid, glyph) in glyphs {
+            if !glyph.vertex_range.is_empty() {
+                instances_by_glyph
+                    .entry(gid)
+                    .or_default()
+                    .push(shared::GlyphInstance {
+                        position: [pen.x, pen.y],
+                        scale,
+                    });
+            }
+            pen.x += glyph.advance * scale;
+        }
+    }
+
+    let mut layout = LabelsLayout::default();
+    for (gid, instances) in instances_by_glyph {
+        let start = layout.instances.len() as u32;
+        layout.instances.extend(instances);
+        layout.draws.push((
+            glyph_map[&gid].vertex_range.clone(),
+            start..layout.instances.len() as u32,
+        ));
+    }
+    layout
+}
+
+#[cfg(test)]
+mod text_renderer_test {
+    use super::*;
+    use crate::scene::labels::{Anchor, LabelKind};
+
+    #[test]
+    fn test_layout_labels() {
+        let face = ttf_parser::Face::parse(include_asset_bytes!("fonts/cmunrm.ttf"), 0).unwrap();
+        let (glyph_map, _) = triangulate_glyphs(&face);
+        let label = |text: &str, x: f32| {
+            Label::new(
+                text.to_string(),
+                Vector2::new(x, 0.0),
+                1.0,
+                Anchor::Center,
+                LabelKind::Designator,
+            )
+        };
+        let labels = [label("R11", 0.0), label("M1", 2.0), label("R2", 100.0)];
+        let view = AaBb::new(Vector2::new(-5.0, -5.0), Vector2::new(5.0, 5.0));
+
+        // The label out of the view is culled and every glyph is a single draw
+        let layout = layout_labels(&face, &glyph_map, &labels, &view, 10.0);
+        assert_eq!(layout.instances.len(), 5);
+        assert_eq!(layout.draws.len(), 3);
+        let ones = layout
+            .draws
+            .iter()
+            .find(|(vertices, _)| {
+                *vertices == glyph_map[&face.glyph_index('1').unwrap()].vertex_range
+            })
+            .unwrap();
+        assert_eq!(ones.1.len(), 3);
+
+        // Too small to be read
+        let layout = layout_labels(&face, &glyph_map, &labels, &view, 1.0);
+        assert!(layout.instances.is_empty());
+    }
+}
@@ -8,12 +8,13 @@ use crate::{
     types::Id,
 };
 
+use super::lod;
 use super::types::{ComponentType, Primitives};
 
 use egui::epaint::Primitive;
 use nalgebra::{Matrix3, Vector2};
 use strum::IntoEnumIterator;
-use strum_macros::EnumIter;
+use strum_macros::{EnumIter, IntoStaticStr};
 
 /// Specifies the distance at which the component should be visible
 #[derive(Debug, Copy, Clone)]
@@ -23,8 +24,23 @@ pub enum Range {
     Far,
 }
 
+/// Electrical parameters of a component, used when the scene is simulated
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub enum ComponentParams {
+    #[default]
+    None,
+    /// [Ohm]
+    Resistance(f64),
+    /// [S]
+    Conductance(f64),
+    /// Open loop gain of an op-amp
+    Gain(f64),
+    /// Junction diode, see [`rsnet_sim::mna::ElementKind::Diode`]
+    Diode { saturation_current: f64, ideality: f64 },
+}
+
 // A component is a renderable thing. It might be a single memristor or a full crossbar.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct Component {
     range: Range,
     ty: ComponentType,
@@ -39,6 +55,8 @@ pub struct Component {
 
     transform: Matrix3<f32>,
     ports: Vec<Port>,
+
+    params: ComponentParams,
 }
 
 impl Component {
@@ -58,10 +76,23 @@ impl Component {
             rotation: rotation,
             scale: 1.0,
             transform: Component::compute_transform(&position, 1.0, rotation),
-            ports: vec![]
+            ports: vec![],
+            params: ComponentParams::None,
         }
     }
 
+    pub fn range(&self) -> Range {
+        self.range
+    }
+
+    pub fn set_range(&mut self, range: Range) {
+        self.range = range;
+    }
+
+    pub fn attachment(&self) -> u32 {
+        self.attachment
+    }
+
     pub fn position(&self) -> &Vector2<f32> {
         &self.position
     }
@@ -89,6 +120,14 @@ impl Component {
         self.update_transform();
     }
 
+    pub fn params(&self) -> &ComponentParams {
+        &self.params
+    }
+
+    pub fn set_params(&mut self, params: ComponentParams) {
+        self.params = params;
+    }
+
     pub fn transform(&self) -> &Matrix3<f32> {
         &self.transform
     }
@@ -111,19 +150,29 @@ impl Component {
             * Matrix3::new_rotation(angle)
     }
 
+    /// Ports of the component type, taken from its most detailed primitives. Empty for unknown types.
     pub fn ports(&self, primitives: &Primitives) -> Vec<Port> {
-        vec![]
-        // primitives
-        //     .0
-        //     .get(&self.ty.into())
-        //     .unwrap_or_else(||vec![])
-        //     .first()
-        //     .unwrap_or_else(|| vec![])
+        primitives
+            .0
+            .get(&self.ty)
+            .and_then(|levels| levels.first())
+            .map(|(primitives, _)| primitives.ports.clone())
+            .unwrap_or_default()
+    }
+
+    /// Index of the port called ```name```
+    pub fn port_index(&self, primitives: &Primitives, name: &str) -> Option<usize> {
+        self.ports(primitives).iter().position(|port| port.name == name)
+    }
+
+    /// Position of ```port``` in scene coordinates
+    pub fn port_position(&self, port: &Port) -> Vector2<f32> {
+        self.transform.transform_point(&port.relative_position.into()).coords
     }
 
 }
 
-#[derive(Debug, EnumIter)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, EnumIter, IntoStaticStr)]
 #[repr(u32)]
 pub enum DefaultComponentTypes {
     Memristor = 0,
@@ -140,6 +189,45 @@ impl Into<u32> for DefaultComponentTypes {
 }
 
 impl DefaultComponentTypes {
+    /// The default type with id ```ty```, ```None``` for custom and aggregate types
+    pub fn from_ty(ty: ComponentType) -> Option<DefaultComponentTypes> {
+        DefaultComponentTypes::iter().find(|default_ty| *default_ty as u32 == ty)
+    }
+
+    /// Name shown to the user for the type ```ty```
+    pub fn name(ty: ComponentType) -> String {
+        match DefaultComponentTypes::from_ty(ty) {
+            Some(default_ty) => <&'static str>::from(default_ty).to_string(),
+            None => format!("Custom ({ty})"),
+        }
+    }
+
+    /// Prefix of the designators of the components of type ```ty```, e.g. ```R``` for ```R12```
+    pub fn designator_prefix(ty: ComponentType) -> &'static str {
+        match DefaultComponentTypes::from_ty(ty) {
+            Some(DefaultComponentTypes::Memristor) => "M",
+            Some(DefaultComponentTypes::Resistor) => "R",
+            Some(DefaultComponentTypes::Nmos) => "Q",
+            Some(DefaultComponentTypes::OpAmp) => "U",
+            Some(DefaultComponentTypes::Diode) => "D",
+            None => "X",
+        }
+    }
+
+    /// Parameters of a component of this type placed by the user
+    pub fn default_params(self) -> ComponentParams {
+        match self {
+            DefaultComponentTypes::Memristor => ComponentParams::Conductance(1e-5),
+            DefaultComponentTypes::Resistor => ComponentParams::Resistance(1e3),
+            DefaultComponentTypes::Nmos => ComponentParams::None,
+            DefaultComponentTypes::OpAmp => ComponentParams::Gain(1e5),
+            DefaultComponentTypes::Diode => ComponentParams::Diode {
+                saturation_current: 1e-14,
+                ideality: 1.0,
+            },
+        }
+    }
+
     pub fn primitives() -> Primitives {
         let mut primitives = Primitives(HashMap::new());
 
@@ -174,6 +262,7 @@ impl DefaultComponentTypes {
                     .insert(ty as u32, vec![(&DIODE_PRIMITIVES_L0, 400.0)]);
             }
         });
+        primitives.0.extend(lod::primitives());
 
         primitives
     }
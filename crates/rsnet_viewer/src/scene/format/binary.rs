@@ -0,0 +1,251 @@
+//! Fixed size records of the binary variant, every number is little endian.
+
+use std::io::{self, Write};
+
+use super::FormatError;
+
+use crate::scene::component::{Component, ComponentParams, Range};
+use crate::scene::types::ChunkId;
+use crate::scene::wire::Wire;
+use crate::types::{Id, NodeId};
+
+use nalgebra::Vector2;
+
+/// ```step: u32, chunk: (i32, i32), count: u64```
+pub const CHUNK_ENTRY_SIZE: usize = 20;
+/// ```id: u32, ty: u32, attachment: u32, range: u8, params tag: u8, padding: [u8; 2], position: [f32; 2],
+/// rotation: f32, scale: f32, params: [f64; 2]```
+pub const COMPONENT_RECORD_SIZE: usize = 48;
+/// ```id: u32, start: [f32; 2], end: [f32; 2], next_direction: [f32; 2], prev_direction: [f32; 2],
+/// circle_overlay: u32```
+pub const WIRE_RECORD_SIZE: usize = 40;
+/// ```component: u32, port: u32, net: u64```
+pub const BINDING_RECORD_SIZE: usize = 16;
+
+/// Entry of the table of chunks, the records of the components of the chunk follow the table in the same order
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ChunkEntry {
+    pub chunk_step_idx: u32,
+    pub chunk_id: ChunkId,
+    pub count: u64,
+}
+
+pub fn write_chunk_entry<W: Write>(writer: &mut W, entry: &ChunkEntry) -> io::Result<()> {
+    let mut record = [0u8; CHUNK_ENTRY_SIZE];
+    record[0..4].copy_from_slice(&entry.chunk_step_idx.to_le_bytes());
+    record[4..8].copy_from_slice(&entry.chunk_id.0.to_le_bytes());
+    record[8..12].copy_from_slice(&entry.chunk_id.1.to_le_bytes());
+    record[12..20].copy_from_slice(&entry.count.to_le_bytes());
+    writer.write_all(&record)
+}
+
+pub fn read_chunk_entry(reader: &mut Reader) -> Result<ChunkEntry, FormatError> {
+    Ok(ChunkEntry {
+        chunk_step_idx: reader.u32()?,
+        chunk_id: (reader.i32()?, reader.i32()?),
+        count: reader.u64()?,
+    })
+}
+
+pub fn write_component<W: Write>(writer: &mut W, component: &Component) -> io::Result<()> {
+    let range: u8 = match component.range() {
+        Range::Near => 0,
+        Range::Mid => 1,
+        Range::Far => 2,
+    };
+    let (tag, params): (u8, [f64; 2]) = match *component.params() {
+        ComponentParams::None => (0, [0.0; 2]),
+        ComponentParams::Resistance(resistance) => (1, [resistance, 0.0]),
+        ComponentParams::Conductance(conductance) => (2, [conductance, 0.0]),
+        ComponentParams::Gain(gain) => (3, [gain, 0.0]),
+        ComponentParams::Diode {
+            saturation_current,
+            ideality,
+        } => (4, [saturation_current, ideality]),
+    };
+
+    let mut record = [0u8; COMPONENT_RECORD_SIZE];
+    record[0..4].copy_from_slice(&component.id().to_le_bytes());
+    record[4..8].copy_from_slice(&component.ty().to_le_bytes());
+    record[8..12].copy_from_slice(&component.attachment().to_le_bytes());
+    record[12] = range;
+    record[13] = tag;
+    record[16..20].copy_from_slice(&component.position().x.to_le_bytes());
+    record[20..24].copy_from_slice(&component.position().y.to_le_bytes());
+    record[24..28].copy_from_slice(&component.rotation().to_le_bytes());
+    record[28..32].copy_from_slice(&component.scale().to_le_bytes());
+    record[32..40].copy_from_slice(&params[0].to_le_bytes());
+    record[40..48].copy_from_slice(&params[1].to_le_bytes());
+    writer.write_all(&record)
+}
+
+/// Decodes a record written by ```write_component```, ```record``` must be ```COMPONENT_RECORD_SIZE``` long
+pub fn read_component(record: &[u8]) -> Result<Component, FormatError> {
+    let mut reader = Reader::new(record);
+
+    let id = reader.u32()?;
+    let ty = reader.u32()?;
+    let attachment = reader.u32()?;
+    let range = match reader.u8()? {
+        0 => Range::Near,
+        1 => Range::Mid,
+        2 => Range::Far,
+        range => {
+            return Err(FormatError::InvalidValue {
+                expected: "a range (0, 1 or 2)",
+                found: format!("{} (component {})", range, id),
+            })
+        }
+    };
+    let tag = reader.u8()?;
+    reader.take(2)?;
+    let position = Vector2::new(reader.f32()?, reader.f32()?);
+    let rotation = reader.f32()?;
+    let scale = reader.f32()?;
+    let params = [reader.f64()?, reader.f64()?];
+
+    let params = match tag {
+        0 => ComponentParams::None,
+        1 => ComponentParams::Resistance(params[0]),
+        2 => ComponentParams::Conductance(params[0]),
+        3 => ComponentParams::Gain(params[0]),
+        4 => ComponentParams::Diode {
+            saturation_current: params[0],
+            ideality: params[1],
+        },
+        tag => {
+            return Err(FormatError::InvalidValue {
+                expected: "a component params tag (0 to 4)",
+                found: format!("{} (component {})", tag, id),
+            })
+        }
+    };
+
+    let mut component = Component::new(id, attachment, position, rotation, ty);
+    component.set_range(range);
+    component.set_scale(scale);
+    component.set_params(params);
+
+    Ok(component)
+}
+
+pub fn write_wire<W: Write>(writer: &mut W, wire: &Wire) -> io::Result<()> {
+    let mut record = [0u8; WIRE_RECORD_SIZE];
+    record[0..4].copy_from_slice(&wire.id().to_le_bytes());
+    let vectors = [wire.start(), wire.end(), wire.next_direction(), wire.prev_direction()];
+    for (idx, vector) in vectors.into_iter().enumerate() {
+        let offset = 4 + idx * 8;
+        record[offset..offset + 4].copy_from_slice(&vector.x.to_le_bytes());
+        record[offset + 4..offset + 8].copy_from_slice(&vector.y.to_le_bytes());
+    }
+    record[36..40].copy_from_slice(&wire.circle_overlay().to_le_bytes());
+    writer.write_all(&record)
+}
+
+/// Decodes a record written by ```write_wire```, ```chunk_size``` is the size of the chunks of the step of the wire
+pub fn read_wire(reader: &mut Reader, chunk_size: f32) -> Result<Wire, FormatError> {
+    let id = reader.u32()?;
+    let mut vectors = [Vector2::zeros(); 4];
+    for vector in &mut vectors {
+        *vector = Vector2::new(reader.f32()?, reader.f32()?);
+    }
+    let [start, end, next_direction, prev_direction] = vectors;
+
+    let mut wire = Wire::new(id, start, end, next_direction, prev_direction, chunk_size);
+    wire.set_circle_overlay(reader.u32()?);
+
+    Ok(wire)
+}
+
+pub fn write_binding<W: Write>(writer: &mut W, (component, port, net): (Id, usize, NodeId)) -> io::Result<()> {
+    let mut record = [0u8; BINDING_RECORD_SIZE];
+    record[0..4].copy_from_slice(&component.to_le_bytes());
+    record[4..8].copy_from_slice(&(port as u32).to_le_bytes());
+    record[8..16].copy_from_slice(&(net as u64).to_le_bytes());
+    writer.write_all(&record)
+}
+
+pub fn read_binding(reader: &mut Reader) -> Result<(Id, usize, NodeId), FormatError> {
+    let component = reader.u32()?;
+    let port = reader.u32()? as usize;
+    let net = reader.u64()?;
+
+    Ok((component, port, reader.index(net)?))
+}
+
+/// Cursor over the bytes of a file
+pub struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes, pos: 0 }
+    }
+
+    pub fn position(&self) -> usize {
+        self.pos
+    }
+
+    /// The next ```len``` bytes
+    pub fn take(&mut self, len: usize) -> Result<&'a [u8], FormatError> {
+        let bytes = self
+            .pos
+            .checked_add(len)
+            .and_then(|end| self.bytes.get(self.pos..end))
+            .ok_or(FormatError::Truncated {
+                offset: self.pos,
+                expected: len,
+            })?;
+        self.pos += len;
+
+        Ok(bytes)
+    }
+
+    /// The next ```count``` records of ```size``` bytes
+    pub fn take_records(&mut self, count: u64, size: usize) -> Result<&'a [u8], FormatError> {
+        let len = self.index(count)?.checked_mul(size).ok_or(FormatError::Truncated {
+            offset: self.pos,
+            expected: usize::MAX,
+        })?;
+
+        self.take(len)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N], FormatError> {
+        Ok(self.take(N)?.try_into().expect("take returns N bytes"))
+    }
+
+    pub fn u8(&mut self) -> Result<u8, FormatError> {
+        Ok(self.array::<1>()?[0])
+    }
+
+    pub fn u32(&mut self) -> Result<u32, FormatError> {
+        self.array().map(u32::from_le_bytes)
+    }
+
+    pub fn i32(&mut self) -> Result<i32, FormatError> {
+        self.array().map(i32::from_le_bytes)
+    }
+
+    pub fn u64(&mut self) -> Result<u64, FormatError> {
+        self.array().map(u64::from_le_bytes)
+    }
+
+    pub fn f32(&mut self) -> Result<f32, FormatError> {
+        self.array().map(f32::from_le_bytes)
+    }
+
+    pub fn f64(&mut self) -> Result<f64, FormatError> {
+        self.array().map(f64::from_le_bytes)
+    }
+
+    /// Converts a count or an index read from the file
+    pub fn index(&self, value: u64) -> Result<usize, FormatError> {
+        value.try_into().map_err(|_| FormatError::InvalidValue {
+            expected: "an index",
+            found: value.to_string(),
+        })
+    }
+}
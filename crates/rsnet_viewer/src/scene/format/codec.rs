@@ -0,0 +1,451 @@
+//! Conversions between the configuration of a scene and ```Value```, shared by the binary (metadata) and the JSON
+//! variants of the format.
+
+use super::{FormatError, Value};
+
+use crate::scene::component::{Component, ComponentParams, Range};
+use crate::scene::hierarchy::{ConstructNodeKind, Transform};
+use crate::scene::peripherals::PeripheralConfig;
+use crate::scene::wire::Wire;
+
+use rsnet_sim::crossbar::CrossbarParasitics;
+use rsnet_sim::mapping::ConductanceMapping;
+use rsnet_sim::mna::{Element, ElementKind};
+use rsnet_sim::tiling::{Tile, TilingConfig, TilingPlan};
+
+impl From<&ConductanceMapping> for Value {
+    fn from(mapping: &ConductanceMapping) -> Self {
+        Value::object([
+            ("g_min", mapping.g_min.into()),
+            ("g_max", mapping.g_max.into()),
+            ("differential", mapping.differential.into()),
+            ("levels", mapping.levels.into()),
+        ])
+    }
+}
+
+impl TryFrom<&Value> for ConductanceMapping {
+    type Error = FormatError;
+
+    fn try_from(value: &Value) -> Result<Self, Self::Error> {
+        Ok(ConductanceMapping {
+            g_min: value.field("g_min")?.as_f64()?,
+            g_max: value.field("g_max")?.as_f64()?,
+            differential: value.field("differential")?.as_bool()?,
+            levels: value.field("levels")?.as_option(Value::as_u32)?,
+        })
+    }
+}
+
+impl From<&TilingConfig> for Value {
+    fn from(config: &TilingConfig) -> Self {
+        Value::object([
+            ("max_rows", config.max_rows.into()),
+            ("max_cols", config.max_cols.into()),
+        ])
+    }
+}
+
+impl TryFrom<&Value> for TilingConfig {
+    type Error = FormatError;
+
+    fn try_from(value: &Value) -> Result<Self, Self::Error> {
+        Ok(TilingConfig::new(
+            value.field("max_rows")?.as_usize()?,
+            value.field("max_cols")?.as_usize()?,
+        ))
+    }
+}
+
+impl From<&Tile> for Value {
+    fn from(tile: &Tile) -> Self {
+        Value::object([
+            ("grid_pos", vec![tile.grid_pos.0, tile.grid_pos.1].into()),
+            ("row_start", tile.row_start.into()),
+            ("rows", tile.rows.into()),
+            ("col_start", tile.col_start.into()),
+            ("cols", tile.cols.into()),
+        ])
+    }
+}
+
+impl TryFrom<&Value> for Tile {
+    type Error = FormatError;
+
+    fn try_from(value: &Value) -> Result<Self, Self::Error> {
+        Ok(Tile {
+            grid_pos: usize_pair(value.field("grid_pos")?)?,
+            row_start: value.field("row_start")?.as_usize()?,
+            rows: value.field("rows")?.as_usize()?,
+            col_start: value.field("col_start")?.as_usize()?,
+            cols: value.field("cols")?.as_usize()?,
+        })
+    }
+}
+
+impl From<&TilingPlan> for Value {
+    fn from(plan: &TilingPlan) -> Self {
+        Value::object([
+            ("rows", plan.rows.into()),
+            ("cols", plan.cols.into()),
+            ("config", (&plan.config).into()),
+            ("grid", vec![plan.grid.0, plan.grid.1].into()),
+            ("tiles", Value::Array(plan.tiles.iter().map(Into::into).collect())),
+        ])
+    }
+}
+
+impl TryFrom<&Value> for TilingPlan {
+    type Error = FormatError;
+
+    fn try_from(value: &Value) -> Result<Self, Self::Error> {
+        Ok(TilingPlan {
+            rows: value.field("rows")?.as_usize()?,
+            cols: value.field("cols")?.as_usize()?,
+            config: value.field("config")?.try_into()?,
+            grid: usize_pair(value.field("grid")?)?,
+            tiles: value
+                .field("tiles")?
+                .as_array()?
+                .iter()
+                .map(Tile::try_from)
+                .collect::<Result<Vec<Tile>, FormatError>>()?,
+        })
+    }
+}
+
+impl From<&CrossbarParasitics> for Value {
+    fn from(parasitics: &CrossbarParasitics) -> Self {
+        Value::object([
+            ("wire_resistance", parasitics.wire_resistance.into()),
+            ("driver_resistance", parasitics.driver_resistance.into()),
+            ("sense_resistance", parasitics.sense_resistance.into()),
+        ])
+    }
+}
+
+impl TryFrom<&Value> for CrossbarParasitics {
+    type Error = FormatError;
+
+    fn try_from(value: &Value) -> Result<Self, Self::Error> {
+        Ok(CrossbarParasitics::new(
+            value.field("wire_resistance")?.as_f64()?,
+            value.field("driver_resistance")?.as_f64()?,
+            value.field("sense_resistance")?.as_f64()?,
+        ))
+    }
+}
+
+impl From<&PeripheralConfig> for Value {
+    fn from(config: &PeripheralConfig) -> Self {
+        Value::object([
+            ("read_voltage", config.read_voltage.into()),
+            ("dac_bits", config.dac_bits.into()),
+            ("adc_bits", config.adc_bits.into()),
+            ("full_scale", config.full_scale.into()),
+            ("driver_resistance", config.driver_resistance.into()),
+            ("adc_input_resistance", config.adc_input_resistance.into()),
+            ("relu_load_resistance", config.relu_load_resistance.into()),
+            ("op_amp_gain", config.op_amp_gain.into()),
+        ])
+    }
+}
+
+impl TryFrom<&Value> for PeripheralConfig {
+    type Error = FormatError;
+
+    fn try_from(value: &Value) -> Result<Self, Self::Error> {
+        Ok(PeripheralConfig {
+            read_voltage: value.field("read_voltage")?.as_f64()?,
+            dac_bits: value.field("dac_bits")?.as_option(Value::as_u32)?,
+            adc_bits: value.field("adc_bits")?.as_option(Value::as_u32)?,
+            full_scale: value.field("full_scale")?.as_f64()?,
+            driver_resistance: value.field("driver_resistance")?.as_f64()?,
+            adc_input_resistance: value.field("adc_input_resistance")?.as_f64()?,
+            relu_load_resistance: value.field("relu_load_resistance")?.as_f64()?,
+            op_amp_gain: value.field("op_amp_gain")?.as_f64()?,
+        })
+    }
+}
+
+impl From<&Element> for Value {
+    fn from(element: &Element) -> Self {
+        let mut fields = vec![("id", element.id.into())];
+        match element.kind {
+            ElementKind::Resistor(resistance) => {
+                fields.extend([("kind", "resistor".into()), ("value", resistance.into())])
+            }
+            ElementKind::Memristor(conductance) => {
+                fields.extend([("kind", "memristor".into()), ("value", conductance.into())])
+            }
+            ElementKind::VoltageSource(voltage) => {
+                fields.extend([("kind", "voltage_source".into()), ("value", voltage.into())])
+            }
+            ElementKind::CurrentSource(current) => {
+                fields.extend([("kind", "current_source".into()), ("value", current.into())])
+            }
+            ElementKind::Diode {
+                saturation_current,
+                ideality,
+            } => fields.extend([
+                ("kind", "diode".into()),
+                ("saturation_current", saturation_current.into()),
+                ("ideality", ideality.into()),
+            ]),
+            ElementKind::OpAmp {
+                gain,
+                non_inverting,
+                inverting,
+            } => fields.extend([
+                ("kind", "op_amp".into()),
+                ("gain", gain.into()),
+                ("non_inverting", non_inverting.into()),
+                ("inverting", inverting.into()),
+            ]),
+        }
+        fields.extend([("pos", element.pos.into()), ("neg", element.neg.into())]);
+
+        Value::object(fields)
+    }
+}
+
+impl TryFrom<&Value> for Element {
+    type Error = FormatError;
+
+    fn try_from(value: &Value) -> Result<Self, Self::Error> {
+        let number = |key: &str| value.field(key)?.as_f64();
+
+        let kind_value = value.field("kind")?;
+        let kind = match kind_value.as_str()? {
+            "resistor" => ElementKind::Resistor(number("value")?),
+            "memristor" => ElementKind::Memristor(number("value")?),
+            "voltage_source" => ElementKind::VoltageSource(number("value")?),
+            "current_source" => ElementKind::CurrentSource(number("value")?),
+            "diode" => ElementKind::Diode {
+                saturation_current: number("saturation_current")?,
+                ideality: number("ideality")?,
+            },
+            "op_amp" => ElementKind::OpAmp {
+                gain: number("gain")?,
+                non_inverting: value.field("non_inverting")?.as_usize()?,
+                inverting: value.field("inverting")?.as_usize()?,
+            },
+            kind => {
+                return Err(FormatError::InvalidValue {
+                    expected: "an element kind",
+                    found: kind.to_string(),
+                })
+            }
+        };
+
+        Ok(Element {
+            id: value.field("id")?.as_u32()?,
+            kind,
+            pos: value.field("pos")?.as_usize()?,
+            neg: value.field("neg")?.as_usize()?,
+        })
+    }
+}
+
+impl From<&ComponentParams> for Value {
+    fn from(params: &ComponentParams) -> Self {
+        match *params {
+            ComponentParams::None => Value::Null,
+            ComponentParams::Resistance(resistance) => Value::object([("resistance", resistance.into())]),
+            ComponentParams::Conductance(conductance) => Value::object([("conductance", conductance.into())]),
+            ComponentParams::Gain(gain) => Value::object([("gain", gain.into())]),
+            ComponentParams::Diode {
+                saturation_current,
+                ideality,
+            } => Value::object([
+                ("saturation_current", saturation_current.into()),
+                ("ideality", ideality.into()),
+            ]),
+        }
+    }
+}
+
+impl TryFrom<&Value> for ComponentParams {
+    type Error = FormatError;
+
+    fn try_from(value: &Value) -> Result<Self, Self::Error> {
+        if *value == Value::Null {
+            return Ok(ComponentParams::None);
+        }
+
+        if let Some(resistance) = value.get("resistance") {
+            Ok(ComponentParams::Resistance(resistance.as_f64()?))
+        } else if let Some(conductance) = value.get("conductance") {
+            Ok(ComponentParams::Conductance(conductance.as_f64()?))
+        } else if let Some(gain) = value.get("gain") {
+            Ok(ComponentParams::Gain(gain.as_f64()?))
+        } else {
+            Ok(ComponentParams::Diode {
+                saturation_current: value.field("saturation_current")?.as_f64()?,
+                ideality: value.field("ideality")?.as_f64()?,
+            })
+        }
+    }
+}
+
+impl From<Range> for Value {
+    fn from(range: Range) -> Self {
+        match range {
+            Range::Near => "near".into(),
+            Range::Mid => "mid".into(),
+            Range::Far => "far".into(),
+        }
+    }
+}
+
+impl TryFrom<&Value> for Range {
+    type Error = FormatError;
+
+    fn try_from(value: &Value) -> Result<Self, Self::Error> {
+        match value.as_str()? {
+            "near" => Ok(Range::Near),
+            "mid" => Ok(Range::Mid),
+            "far" => Ok(Range::Far),
+            range => Err(FormatError::InvalidValue {
+                expected: "a range (near, mid or far)",
+                found: range.to_string(),
+            }),
+        }
+    }
+}
+
+impl From<&Component> for Value {
+    fn from(component: &Component) -> Self {
+        Value::object([
+            ("id", component.id().into()),
+            ("ty", component.ty().into()),
+            ("attachment", component.attachment().into()),
+            ("range", component.range().into()),
+            ("position", component.position().into()),
+            ("rotation", component.rotation().into()),
+            ("scale", component.scale().into()),
+            ("params", component.params().into()),
+        ])
+    }
+}
+
+impl TryFrom<&Value> for Component {
+    type Error = FormatError;
+
+    fn try_from(value: &Value) -> Result<Self, Self::Error> {
+        let mut component = Component::new(
+            value.field("id")?.as_u32()?,
+            value.field("attachment")?.as_u32()?,
+            value.field("position")?.as_vector2()?,
+            value.field("rotation")?.as_f32()?,
+            value.field("ty")?.as_u32()?,
+        );
+        component.set_range(value.field("range")?.try_into()?);
+        component.set_scale(value.field("scale")?.as_f32()?);
+        component.set_params(value.field("params")?.try_into()?);
+
+        Ok(component)
+    }
+}
+
+impl From<&Wire> for Value {
+    fn from(wire: &Wire) -> Self {
+        Value::object([
+            ("id", wire.id().into()),
+            ("start", wire.start().into()),
+            ("end", wire.end().into()),
+            ("next_direction", wire.next_direction().into()),
+            ("prev_direction", wire.prev_direction().into()),
+            ("circle_overlay", wire.circle_overlay().into()),
+        ])
+    }
+}
+
+/// Decodes a wire saved with ```Value::from(&Wire)```, ```chunk_size``` is the size of the chunks of the step it
+/// belongs to
+pub fn wire_from_value(value: &Value, chunk_size: f32) -> Result<Wire, FormatError> {
+    let mut wire = Wire::new(
+        value.field("id")?.as_u32()?,
+        value.field("start")?.as_vector2()?,
+        value.field("end")?.as_vector2()?,
+        value.field("next_direction")?.as_vector2()?,
+        value.field("prev_direction")?.as_vector2()?,
+        chunk_size,
+    );
+    wire.set_circle_overlay(value.field("circle_overlay")?.as_u32()?);
+
+    Ok(wire)
+}
+
+impl From<&Transform> for Value {
+    fn from(transform: &Transform) -> Self {
+        Value::object([
+            ("translation", (&transform.translation).into()),
+            ("rotation", transform.rotation.into()),
+        ])
+    }
+}
+
+impl TryFrom<&Value> for Transform {
+    type Error = FormatError;
+
+    fn try_from(value: &Value) -> Result<Self, Self::Error> {
+        Ok(Transform::new(
+            value.field("translation")?.as_vector2()?,
+            value.field("rotation")?.as_f32()?,
+        ))
+    }
+}
+
+impl From<ConstructNodeKind> for Value {
+    fn from(kind: ConstructNodeKind) -> Self {
+        match kind {
+            ConstructNodeKind::Root => Value::object([("type", "root".into())]),
+            ConstructNodeKind::Network => Value::object([("type", "network".into())]),
+            ConstructNodeKind::Layer(layer_idx) => {
+                Value::object([("type", "layer".into()), ("layer_idx", layer_idx.into())])
+            }
+            ConstructNodeKind::Tile(row, col) => {
+                Value::object([("type", "tile".into()), ("grid_pos", vec![row, col].into())])
+            }
+            ConstructNodeKind::Group => Value::object([("type", "group".into())]),
+            ConstructNodeKind::Construct(construct_idx) => {
+                Value::object([("type", "construct".into()), ("construct", construct_idx.into())])
+            }
+        }
+    }
+}
+
+impl TryFrom<&Value> for ConstructNodeKind {
+    type Error = FormatError;
+
+    fn try_from(value: &Value) -> Result<Self, Self::Error> {
+        Ok(match value.field("type")?.as_str()? {
+            "root" => ConstructNodeKind::Root,
+            "network" => ConstructNodeKind::Network,
+            "layer" => ConstructNodeKind::Layer(value.field("layer_idx")?.as_usize()?),
+            "tile" => {
+                let (row, col) = usize_pair(value.field("grid_pos")?)?;
+                ConstructNodeKind::Tile(row, col)
+            }
+            "group" => ConstructNodeKind::Group,
+            "construct" => ConstructNodeKind::Construct(value.field("construct")?.as_usize()?),
+            ty => {
+                return Err(FormatError::InvalidValue {
+                    expected: "a construct node kind",
+                    found: ty.to_string(),
+                })
+            }
+        })
+    }
+}
+
+fn usize_pair(value: &Value) -> Result<(usize, usize), FormatError> {
+    match value.as_array()?.as_slice() {
+        [first, second] => Ok((first.as_usize()?, second.as_usize()?)),
+        _ => Err(FormatError::InvalidValue {
+            expected: "a pair of indices",
+            found: format!("{} values", value.as_array()?.len()),
+        }),
+    }
+}
@@ -0,0 +1,704 @@
+//! Native scene files, they store the scene together with the netlist, the sources and the constructs of a
+//! ```SceneManager```.
+//!
+//! The binary variant is meant for large scenes. It starts with ```MAGIC``` and the version (u32), followed by the
+//! length (u64) and the JSON text of the metadata: configuration, sources, nets and constructs. The components come
+//! next, grouped by chunk: the number of chunks (u64), a table with the step, id and number of components of every
+//! chunk, and the fixed size records of the components in the order of the table. Chunks are decoded in parallel
+//! straight from the memory mapped file. The wires (grouped by step) and the bindings of the netlist close the file.
+//!
+//! The JSON variant holds the same information in a single human readable document, for small circuits.
+
+pub mod binary;
+pub mod codec;
+pub mod value;
+
+pub use value::Value;
+
+use std::collections::BTreeSet;
+use std::io::{self, Write};
+use std::path::Path;
+
+use super::hierarchy::{ConstructNodeKind, ConstructTree, Transform};
+use super::netlist::Netlist;
+use super::peripherals::{Adc, InputDriver, PeripheralConfig, ReluBlock, Tia};
+use super::scene_manager::{Construct, Crossbar, LayerTiling, PartialSumAdder, SceneManager, SceneManagerError};
+use super::types::ChunkStepIdx;
+use super::wire::Wire;
+use super::Scene;
+
+use crate::app::utils::chunk_size_from_step_idx;
+use crate::types::{Id, NodeId};
+
+use binary::{ChunkEntry, Reader, BINDING_RECORD_SIZE, COMPONENT_RECORD_SIZE, WIRE_RECORD_SIZE};
+use rsnet_sim::mapping::ConductanceMapping;
+use rsnet_sim::mna::Element;
+use rsnet_sim::tiling::TilingConfig;
+
+use rayon::prelude::*;
+use thiserror::Error;
+
+/// First bytes of a binary scene file
+pub const MAGIC: [u8; 8] = *b"RSNETSCN";
+/// Version written by this build, files of older versions can be read
+pub const VERSION: u32 = 1;
+
+/// Value of the ```format``` field of the JSON variant
+const JSON_FORMAT_NAME: &str = "rsnet-scene";
+
+#[derive(Error, Debug)]
+pub enum FormatError {
+    #[error("Could not read or write the scene file: {0}")]
+    Io(io::Error),
+    #[error("Not a scene file, it does not start with the expected magic number")]
+    InvalidMagic,
+    #[error("Scene files of version {0} are not supported, the latest supported version is {VERSION}")]
+    UnsupportedVersion(u32),
+    #[error("The file is truncated, expected {expected} more bytes at offset {offset}")]
+    Truncated { offset: usize, expected: usize },
+    #[error("Invalid JSON at line {line}, column {column}: {message}")]
+    Json {
+        line: usize,
+        column: usize,
+        message: String,
+    },
+    #[error("Missing field {0}")]
+    MissingField(String),
+    #[error("Expected {expected}, found {found}")]
+    InvalidValue { expected: &'static str, found: String },
+    #[error("Unknown construct type {0}")]
+    UnknownConstruct(String),
+}
+
+impl From<io::Error> for FormatError {
+    fn from(value: io::Error) -> Self {
+        Self::Io(value)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SceneFormat {
+    Binary,
+    Json,
+}
+
+impl SceneFormat {
+    /// Format of the files with the extension of ```path```: ```.rsn``` for binary and ```.json``` for JSON files
+    pub fn from_path(path: &Path) -> Option<SceneFormat> {
+        match path.extension()?.to_str()? {
+            "rsn" => Some(SceneFormat::Binary),
+            "json" => Some(SceneFormat::Json),
+            _ => None,
+        }
+    }
+}
+
+/// Writes the binary variant of ```scene_manager``` to ```writer```. Writes are small, ```writer``` should be buffered.
+pub fn write_binary<W: Write>(scene_manager: &SceneManager, mut writer: W) -> Result<W, FormatError> {
+    writer.write_all(&MAGIC)?;
+    writer.write_all(&VERSION.to_le_bytes())?;
+
+    let metadata = Value::object(metadata(scene_manager)).to_json();
+    writer.write_all(&(metadata.len() as u64).to_le_bytes())?;
+    writer.write_all(metadata.as_bytes())?;
+
+    let mut chunks: Vec<_> = scene_manager
+        .scene()
+        .components()
+        .iter()
+        .flat_map(|(step, chunks)| {
+            chunks
+                .iter()
+                .map(move |(chunk_id, components)| (*step, *chunk_id, components))
+        })
+        .collect();
+    chunks.sort_unstable_by_key(|(step, chunk_id, _)| (*step, *chunk_id));
+
+    writer.write_all(&(chunks.len() as u64).to_le_bytes())?;
+    for (chunk_step_idx, chunk_id, components) in &chunks {
+        let entry = ChunkEntry {
+            chunk_step_idx: *chunk_step_idx,
+            chunk_id: *chunk_id,
+            count: components.len() as u64,
+        };
+        binary::write_chunk_entry(&mut writer, &entry)?;
+    }
+    for component in chunks.iter().flat_map(|(_, _, components)| components.iter()) {
+        binary::write_component(&mut writer, component)?;
+    }
+
+    let wire_steps = wire_steps(scene_manager.scene());
+    writer.write_all(&(wire_steps.len() as u64).to_le_bytes())?;
+    for (chunk_step_idx, wires) in wire_steps {
+        writer.write_all(&chunk_step_idx.to_le_bytes())?;
+        writer.write_all(&(wires.len() as u64).to_le_bytes())?;
+        for wire in wires {
+            binary::write_wire(&mut writer, wire)?;
+        }
+    }
+
+    let netlist = scene_manager.netlist();
+    writer.write_all(&(netlist.binding_count() as u64).to_le_bytes())?;
+    for binding in netlist.bindings() {
+        binary::write_binding(&mut writer, binding)?;
+    }
+
+    writer.flush()?;
+    Ok(writer)
+}
+
+/// Reads a scene written by ```write_binary```
+pub fn read_binary(bytes: &[u8]) -> Result<SceneManager, SceneManagerError> {
+    let mut reader = Reader::new(bytes);
+
+    if bytes.len() < MAGIC.len() || reader.take(MAGIC.len())? != MAGIC {
+        return Err(FormatError::InvalidMagic.into());
+    }
+    let version = reader.u32()?;
+    if version > VERSION {
+        return Err(FormatError::UnsupportedVersion(version).into());
+    }
+
+    let metadata_len = reader.u64()?;
+    let metadata = reader.take_records(metadata_len, 1)?;
+    let metadata = std::str::from_utf8(metadata).map_err(|e| FormatError::InvalidValue {
+        expected: "UTF-8 metadata",
+        found: e.to_string(),
+    })?;
+    let metadata = Value::from_json(metadata)?;
+
+    let chunk_count = reader.u64()?;
+    let entries = (0..chunk_count)
+        .map(|_| binary::read_chunk_entry(&mut reader))
+        .collect::<Result<Vec<ChunkEntry>, FormatError>>()?;
+    let records = entries
+        .into_iter()
+        .map(|entry| Ok((entry, reader.take_records(entry.count, COMPONENT_RECORD_SIZE)?)))
+        .collect::<Result<Vec<_>, FormatError>>()?;
+
+    let chunks = records
+        .into_par_iter()
+        .map(|(entry, records)| {
+            let components = records
+                .chunks_exact(COMPONENT_RECORD_SIZE)
+                .map(binary::read_component)
+                .collect::<Result<Vec<_>, FormatError>>()?;
+            Ok((entry, components))
+        })
+        .collect::<Result<Vec<_>, FormatError>>()?;
+
+    let mut scene = Scene::new_empty();
+    for (entry, components) in chunks {
+        scene.insert_chunk(entry.chunk_step_idx, entry.chunk_id, components)?;
+    }
+
+    let wire_step_count = reader.u64()?;
+    for _ in 0..wire_step_count {
+        let chunk_step_idx = reader.u32()?;
+        let count = reader.u64()?;
+        let mut records = Reader::new(reader.take_records(count, WIRE_RECORD_SIZE)?);
+        for _ in 0..count {
+            let wire = binary::read_wire(&mut records, chunk_size_from_step_idx(chunk_step_idx))?;
+            scene.add_wire(chunk_step_idx, wire);
+        }
+    }
+
+    let mut netlist = netlist_from_value(metadata.field("netlist")?)?;
+    let binding_count = reader.u64()?;
+    let mut records = Reader::new(reader.take_records(binding_count, BINDING_RECORD_SIZE)?);
+    for _ in 0..binding_count {
+        let (component, port, net) = binary::read_binding(&mut records)?;
+        netlist.bind(component, port, net)?;
+    }
+
+    restore(scene, netlist, &metadata)
+}
+
+/// Writes the JSON variant of ```scene_manager``` to ```writer```
+pub fn write_json<W: Write>(scene_manager: &SceneManager, mut writer: W) -> Result<W, FormatError> {
+    let scene = scene_manager.scene();
+
+    let mut steps: Vec<ChunkStepIdx> = scene.components().keys().copied().collect();
+    steps.sort_unstable();
+    let components = steps
+        .into_iter()
+        .map(|chunk_step_idx| {
+            let mut components: Vec<_> = scene.components()[&chunk_step_idx].values().flatten().collect();
+            components.sort_unstable_by_key(|component| component.id());
+
+            Value::object([
+                ("step", chunk_step_idx.into()),
+                (
+                    "components",
+                    Value::Array(components.into_iter().map(Into::into).collect()),
+                ),
+            ])
+        })
+        .collect();
+
+    let wires = wire_steps(scene)
+        .into_iter()
+        .map(|(chunk_step_idx, wires)| {
+            Value::object([
+                ("step", chunk_step_idx.into()),
+                ("wires", Value::Array(wires.into_iter().map(Into::into).collect())),
+            ])
+        })
+        .collect();
+
+    let bindings = scene_manager
+        .netlist()
+        .bindings()
+        .map(|(component, port, net)| vec![component as usize, port, net].into())
+        .collect();
+
+    let mut fields = vec![("format", JSON_FORMAT_NAME.into()), ("version", VERSION.into())];
+    fields.extend(metadata(scene_manager));
+    fields.extend([
+        ("components", Value::Array(components)),
+        ("wires", Value::Array(wires)),
+        ("bindings", Value::Array(bindings)),
+    ]);
+
+    writer.write_all(Value::object(fields).to_json().as_bytes())?;
+    writer.flush()?;
+    Ok(writer)
+}
+
+/// Reads a scene written by ```write_json```
+pub fn read_json(source: &str) -> Result<SceneManager, SceneManagerError> {
+    let document = Value::from_json(source)?;
+
+    let format = document.field("format")?;
+    if format.as_str()? != JSON_FORMAT_NAME {
+        return Err(FormatError::InvalidValue {
+            expected: "a scene document",
+            found: format.as_str()?.to_string(),
+        }
+        .into());
+    }
+    let version = document.field("version")?.as_u32()?;
+    if version > VERSION {
+        return Err(FormatError::UnsupportedVersion(version).into());
+    }
+
+    let mut scene = Scene::new_empty();
+    for step in document.field("components")?.as_array()? {
+        let chunk_step_idx = step.field("step")?.as_u32()?;
+        for component in step.field("components")?.as_array()? {
+            scene.add_component(chunk_step_idx, component.try_into()?)?;
+        }
+    }
+    for step in document.field("wires")?.as_array()? {
+        let chunk_step_idx = step.field("step")?.as_u32()?;
+        for wire in step.field("wires")?.as_array()? {
+            scene.add_wire(
+                chunk_step_idx,
+                codec::wire_from_value(wire, chunk_size_from_step_idx(chunk_step_idx))?,
+            );
+        }
+    }
+
+    let mut netlist = netlist_from_value(document.field("netlist")?)?;
+    for binding in document.field("bindings")?.as_array()? {
+        match binding.as_array()?.as_slice() {
+            [component, port, net] => netlist.bind(component.as_u32()?, port.as_usize()?, net.as_usize()?)?,
+            _ => {
+                return Err(FormatError::InvalidValue {
+                    expected: "a binding [component, port, net]",
+                    found: format!("{} values", binding.as_array()?.len()),
+                }
+                .into())
+            }
+        }
+    }
+
+    restore(scene, netlist, &document)
+}
+
+/// Everything but the components, the wires and the bindings
+fn metadata(scene_manager: &SceneManager) -> Vec<(&'static str, Value)> {
+    let layer_tilings = scene_manager
+        .layer_tilings()
+        .iter()
+        .map(|tiling| Value::object([("layer_idx", tiling.layer_idx.into()), ("plan", (&tiling.plan).into())]))
+        .collect();
+
+    vec![
+        ("conductance_mapping", scene_manager.conductance_mapping().into()),
+        ("tiling", scene_manager.tiling().into()),
+        ("peripherals", scene_manager.peripherals().into()),
+        ("layer_tilings", Value::Array(layer_tilings)),
+        ("last_component_id", scene_manager.last_component_id().into()),
+        (
+            "sources",
+            Value::Array(scene_manager.sources().iter().map(Into::into).collect()),
+        ),
+        ("netlist", netlist_to_value(scene_manager.netlist())),
+        (
+            "constructs",
+            Value::Array(
+                scene_manager
+                    .constructs()
+                    .iter()
+                    .map(|construct| construct.to_value())
+                    .collect(),
+            ),
+        ),
+        ("hierarchy", hierarchy_to_value(scene_manager.hierarchy())),
+    ]
+}
+
+/// Nodes of the tree in order, a parent always comes before its children
+fn hierarchy_to_value(hierarchy: &ConstructTree) -> Value {
+    Value::Array(
+        hierarchy
+            .nodes()
+            .map(|(_, node)| {
+                Value::object([
+                    ("name", node.name().into()),
+                    ("kind", node.kind().into()),
+                    ("parent", node.parent().into()),
+                    ("transform", node.transform().into()),
+                ])
+            })
+            .collect(),
+    )
+}
+
+/// Rebuilds the tree saved by ```hierarchy_to_value```. Without one, the constructs are placed at the root.
+fn hierarchy_from_value(
+    value: Option<&Value>,
+    constructs: &[Box<dyn Construct>],
+) -> Result<ConstructTree, SceneManagerError> {
+    let mut hierarchy = ConstructTree::new();
+    let construct = |construct_idx: usize| {
+        constructs.get(construct_idx).ok_or_else(|| FormatError::InvalidValue {
+            expected: "the index of a construct",
+            found: construct_idx.to_string(),
+        })
+    };
+
+    let Some(value) = value else {
+        for (construct_idx, construct) in constructs.iter().enumerate() {
+            let name = construct.type_name().to_string();
+            hierarchy.add_construct(hierarchy.root(), name, construct_idx, construct.components_id_range())?;
+        }
+        return Ok(hierarchy);
+    };
+
+    for (node_id, node) in value.as_array()?.iter().enumerate() {
+        let kind = ConstructNodeKind::try_from(node.field("kind")?)?;
+        let transform = Transform::try_from(node.field("transform")?)?;
+        if node_id == hierarchy.root() {
+            hierarchy.set_transform(node_id, transform)?;
+            continue;
+        }
+
+        let name = node.field("name")?.as_str()?.to_string();
+        let parent = node.field("parent")?.as_usize()?;
+        let added = match kind {
+            ConstructNodeKind::Construct(construct_idx) => {
+                let components_id_range = construct(construct_idx)?.components_id_range();
+                hierarchy.add_construct(parent, name, construct_idx, components_id_range)?
+            }
+            ConstructNodeKind::Root => return Err(FormatError::InvalidValue {
+                expected: "a single root",
+                found: format!("node {}", node_id),
+            }
+            .into()),
+            kind => hierarchy.add_group(parent, name, kind, transform)?,
+        };
+        hierarchy.set_transform(added, transform)?;
+    }
+
+    Ok(hierarchy)
+}
+
+/// Nets (id and name), the names of merged nets and the last id given to a net, the bindings are stored apart
+fn netlist_to_value(netlist: &Netlist) -> Value {
+    let mut nets: Vec<NodeId> = netlist.nets().collect();
+    nets.sort_unstable();
+    let nets = nets
+        .into_iter()
+        .map(|node_id| {
+            Value::Array(vec![
+                node_id.into(),
+                netlist.net_name(node_id).unwrap_or_default().into(),
+            ])
+        })
+        .collect();
+
+    let mut aliases: Vec<(&str, NodeId)> = netlist
+        .net_names()
+        .filter(|(name, node_id)| netlist.net_name(*node_id) != Some(*name))
+        .collect();
+    aliases.sort_unstable();
+    let aliases = aliases
+        .into_iter()
+        .map(|(name, node_id)| Value::Array(vec![name.into(), node_id.into()]))
+        .collect();
+
+    Value::object([
+        ("nets", Value::Array(nets)),
+        ("aliases", Value::Array(aliases)),
+        ("last_net_id", netlist.last_net_id().into()),
+    ])
+}
+
+fn netlist_from_value(value: &Value) -> Result<Netlist, SceneManagerError> {
+    let pair = |value: &Value| -> Result<(Value, Value), FormatError> {
+        match value.as_array()?.as_slice() {
+            [first, second] => Ok((first.clone(), second.clone())),
+            _ => Err(FormatError::InvalidValue {
+                expected: "a pair",
+                found: format!("{} values", value.as_array()?.len()),
+            }),
+        }
+    };
+
+    let mut netlist = Netlist::new();
+    for net in value.field("nets")?.as_array()? {
+        let (node_id, name) = pair(net)?;
+        netlist.insert_net(node_id.as_usize()?, name.as_str()?.to_string())?;
+    }
+    for alias in value.field("aliases")?.as_array()? {
+        let (name, node_id) = pair(alias)?;
+        netlist.add_alias(name.as_str()?.to_string(), node_id.as_usize()?)?;
+    }
+    if let Some(last_net_id) = value.field("last_net_id")?.as_option(Value::as_usize)? {
+        netlist.reserve_net_ids(last_net_id);
+    }
+
+    Ok(netlist)
+}
+
+fn restore(scene: Scene, netlist: Netlist, metadata: &Value) -> Result<SceneManager, SceneManagerError> {
+    let sources = metadata
+        .field("sources")?
+        .as_array()?
+        .iter()
+        .map(Element::try_from)
+        .collect::<Result<Vec<Element>, FormatError>>()?;
+
+    let layer_tilings = metadata
+        .field("layer_tilings")?
+        .as_array()?
+        .iter()
+        .map(|tiling| {
+            Ok(LayerTiling {
+                layer_idx: tiling.field("layer_idx")?.as_usize()?,
+                plan: tiling.field("plan")?.try_into()?,
+            })
+        })
+        .collect::<Result<Vec<LayerTiling>, FormatError>>()?;
+
+    let constructs = metadata
+        .field("constructs")?
+        .as_array()?
+        .iter()
+        .map(|construct| construct_from_value(construct, &scene))
+        .collect::<Result<Vec<Box<dyn Construct>>, SceneManagerError>>()?;
+    let hierarchy = hierarchy_from_value(metadata.get("hierarchy"), &constructs)?;
+
+    let mut scene_manager = SceneManager::from_parts(
+        scene,
+        netlist,
+        sources,
+        metadata.field("last_component_id")?.as_option(Value::as_u32)?,
+        layer_tilings,
+        constructs,
+        hierarchy,
+    );
+    scene_manager.set_conductance_mapping(ConductanceMapping::try_from(metadata.field("conductance_mapping")?)?);
+    scene_manager.set_tiling(TilingConfig::try_from(metadata.field("tiling")?)?);
+    scene_manager.set_peripherals(PeripheralConfig::try_from(metadata.field("peripherals")?)?);
+
+    Ok(scene_manager)
+}
+
+/// Decodes a construct saved with ```Construct::to_value```, dispatching on its ```type``` field
+fn construct_from_value(value: &Value, scene: &Scene) -> Result<Box<dyn Construct>, SceneManagerError> {
+    Ok(match value.field("type")?.as_str()? {
+        "crossbar" => Box::new(Crossbar::from_value(value, scene)?),
+        "partial_sum_adder" => Box::new(PartialSumAdder::from_value(value)?),
+        "input_driver" => Box::new(InputDriver::from_value(value)?),
+        "tia" => Box::new(Tia::from_value(value)?),
+        "relu" => Box::new(ReluBlock::from_value(value)?),
+        "adc" => Box::new(Adc::from_value(value)?),
+        ty => return Err(FormatError::UnknownConstruct(ty.to_string()).into()),
+    })
+}
+
+/// Wires of every step, sorted by step and id
+fn wire_steps(scene: &Scene) -> Vec<(ChunkStepIdx, Vec<&Wire>)> {
+    let mut steps: Vec<(ChunkStepIdx, Vec<&Wire>)> = scene
+        .wire_segments()
+        .iter()
+        .map(|(chunk_step_idx, chunks)| {
+            let ids: BTreeSet<Id> = chunks.values().flatten().copied().collect();
+            (
+                *chunk_step_idx,
+                ids.iter().filter_map(|id| scene.wires().get(id)).collect(),
+            )
+        })
+        .filter(|(_, wires): &(ChunkStepIdx, Vec<&Wire>)| !wires.is_empty())
+        .collect();
+    steps.sort_unstable_by_key(|(chunk_step_idx, _)| *chunk_step_idx);
+
+    steps
+}
+
+#[cfg(test)]
+mod format_test {
+    use super::*;
+
+    use crate::scene::component::ComponentParams;
+
+    use nalgebra::Vector2;
+    use rsnet_net_parser::types::{Activation, Layer, LinearLayer, Nn};
+    use rsnet_sim::crossbar::CrossbarError;
+
+    fn network() -> SceneManager {
+        let mut scene_manager = SceneManager::new();
+        scene_manager.set_tiling(TilingConfig::new(4, 4));
+
+        let nn = Nn {
+            layers: vec![
+                Layer::Linear(LinearLayer {
+                    input_size: 3,
+                    output_size: 3,
+                    weights: vec![1.0, -0.5, 0.25, -1.0, 0.25, 0.5, 0.5, 0.5, -0.75],
+                    bias: Some(vec![0.1, 0.0, -0.2]),
+                }),
+                Layer::Activation(Activation::ReLU),
+                Layer::Linear(LinearLayer {
+                    input_size: 3,
+                    output_size: 1,
+                    weights: vec![0.5, -1.0, 1.0],
+                    bias: None,
+                }),
+            ],
+        };
+        scene_manager.load_nn(nn).unwrap();
+        let layer = scene_manager.hierarchy().node(1).unwrap().children()[0];
+        scene_manager
+            .set_construct_transform(layer, Transform::new(Vector2::new(2.0, -4.0), 0.5))
+            .unwrap();
+        // A merged net leaves an alias and an unused id behind
+        let alias = scene_manager.add_node("alias".to_string());
+        scene_manager
+            .merge_nodes(scene_manager.node_id("l0.x0").unwrap(), alias)
+            .unwrap();
+
+        scene_manager
+    }
+
+    fn assert_same(original: &mut SceneManager, loaded: &mut SceneManager) {
+        assert_eq!(loaded.scene().component_count(), original.scene().component_count());
+        assert_eq!(loaded.scene().wires().len(), original.scene().wires().len());
+        assert_eq!(loaded.constructs().len(), original.constructs().len());
+        assert_eq!(loaded.layer_tilings()[0].plan, original.layer_tilings()[0].plan);
+        assert_eq!(loaded.last_component_id(), original.last_component_id());
+
+        let mut bindings: Vec<_> = loaded.netlist().bindings().collect();
+        let mut expected: Vec<_> = original.netlist().bindings().collect();
+        bindings.sort_unstable();
+        expected.sort_unstable();
+        assert_eq!(bindings, expected);
+        assert_eq!(loaded.node_id("alias"), original.node_id("l0.x0"));
+        assert_eq!(loaded.netlist().last_net_id(), original.netlist().last_net_id());
+
+        let crossbar = loaded.crossbars().next().unwrap();
+        let first = original.crossbars().next().unwrap();
+        assert_eq!(crossbar.conductances(), first.conductances());
+        let id = crossbar.components_id_range().0;
+        let component = loaded.scene().get_component(id).unwrap();
+        assert_eq!(
+            component.position(),
+            original.scene().get_component(id).unwrap().position()
+        );
+        assert!(matches!(component.params(), ComponentParams::Conductance(_)));
+
+        let hierarchy = loaded.hierarchy();
+        assert_eq!(hierarchy.len(), original.hierarchy().len());
+        let leaf = hierarchy.construct_of(id).unwrap();
+        assert_eq!(Some(leaf), original.hierarchy().construct_of(id));
+        assert_eq!(hierarchy.world_transform(leaf).unwrap(), original.hierarchy().world_transform(leaf).unwrap());
+        assert_eq!(hierarchy.node(leaf).unwrap().name(), "crossbar");
+
+        let inputs = [0.5, -0.25, 1.0];
+        // The order of the unknowns of the solver may change, so the last bits of the solution may differ
+        let outputs = loaded.run_inference(&inputs).unwrap();
+        assert!((outputs[0] - original.run_inference(&inputs).unwrap()[0]).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut original = network();
+
+        let bytes = write_binary(&original, Vec::new()).unwrap();
+        assert_eq!(&bytes[..MAGIC.len()], &MAGIC);
+        assert_same(&mut original, &mut read_binary(&bytes).unwrap());
+
+        let json = String::from_utf8(write_json(&original, Vec::new()).unwrap()).unwrap();
+        assert_same(&mut original, &mut read_json(&json).unwrap());
+
+        let path = std::env::temp_dir().join(format!("rsnet_format_test_{}.rsn", std::process::id()));
+        original.save(&path, SceneFormat::Binary).unwrap();
+        let opened = SceneManager::open(&path);
+        std::fs::remove_file(&path).unwrap();
+        assert_same(&mut original, &mut opened.unwrap());
+    }
+
+    #[test]
+    fn test_invalid_files() {
+        let bytes = write_binary(&network(), Vec::new()).unwrap();
+
+        assert!(matches!(
+            read_binary(&bytes[..bytes.len() - 1]),
+            Err(SceneManagerError::FormatError(FormatError::Truncated { .. }))
+        ));
+        assert!(matches!(
+            read_binary(b"RSNET"),
+            Err(SceneManagerError::FormatError(FormatError::InvalidMagic))
+        ));
+
+        let mut newer = bytes.clone();
+        newer[MAGIC.len()..MAGIC.len() + 4].copy_from_slice(&(VERSION + 1).to_le_bytes());
+        assert!(matches!(
+            read_binary(&newer),
+            Err(SceneManagerError::FormatError(FormatError::UnsupportedVersion(_)))
+        ));
+
+        assert!(matches!(
+            read_json(r#"{"format": "rsnet-scene", "version": 1}"#),
+            Err(SceneManagerError::FormatError(FormatError::MissingField(_)))
+        ));
+    }
+
+    #[test]
+    fn test_corrupt_crossbar() {
+        let json = String::from_utf8(write_json(&network(), Vec::new()).unwrap()).unwrap();
+        // Replaces the value of a field of the first crossbar
+        let corrupt = |field: &str, value: &str| {
+            let start = json.find(r#""type": "crossbar""#).unwrap();
+            let start = start + json[start..].find(&format!(r#""{}": "#, field)).unwrap() + field.len() + 4;
+            let end = start + json[start..].find(',').unwrap();
+            format!("{}{}{}", &json[..start], value, &json[end..])
+        };
+
+        assert!(matches!(
+            read_json(&corrupt("rows", "0")),
+            Err(SceneManagerError::CrossbarError(CrossbarError::EmptyCrossbar { .. }))
+        ));
+        assert!(matches!(
+            read_json(&corrupt("cols", "4294967295")),
+            Err(SceneManagerError::CrossbarTooLarge { .. })
+        ));
+        assert!(matches!(
+            read_json(&corrupt("start_component_id", "4294967295")),
+            Err(SceneManagerError::CrossbarTooLarge { .. })
+        ));
+    }
+}
@@ -0,0 +1,546 @@
+use std::fmt::Write;
+
+use super::FormatError;
+
+use nalgebra::Vector2;
+
+/// Tree of a JSON document. Objects keep the order of their fields, so a saved scene reads in the same order every
+/// time.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Null,
+    Bool(bool),
+    /// Non finite numbers are written as the strings ```"inf"```, ```"-inf"``` and ```"nan"```
+    Number(f64),
+    String(String),
+    Array(Vec<Value>),
+    Object(Vec<(String, Value)>),
+}
+
+impl Value {
+    pub fn object(fields: impl IntoIterator<Item = (&'static str, Value)>) -> Value {
+        Value::Object(
+            fields
+                .into_iter()
+                .map(|(key, value)| (key.to_string(), value))
+                .collect(),
+        )
+    }
+
+    pub fn get(&self, key: &str) -> Option<&Value> {
+        match self {
+            Value::Object(fields) => fields.iter().find(|(name, _)| name == key).map(|(_, value)| value),
+            _ => None,
+        }
+    }
+
+    /// Field ```key``` of an object, missing fields are an error
+    pub fn field(&self, key: &str) -> Result<&Value, FormatError> {
+        self.get(key).ok_or_else(|| FormatError::MissingField(key.to_string()))
+    }
+
+    pub fn as_bool(&self) -> Result<bool, FormatError> {
+        match self {
+            Value::Bool(value) => Ok(*value),
+            _ => Err(self.invalid("a boolean")),
+        }
+    }
+
+    pub fn as_f64(&self) -> Result<f64, FormatError> {
+        match self {
+            Value::Number(value) => Ok(*value),
+            Value::String(value) => match value.as_str() {
+                "inf" => Ok(f64::INFINITY),
+                "-inf" => Ok(f64::NEG_INFINITY),
+                "nan" => Ok(f64::NAN),
+                _ => Err(self.invalid("a number")),
+            },
+            _ => Err(self.invalid("a number")),
+        }
+    }
+
+    pub fn as_f32(&self) -> Result<f32, FormatError> {
+        self.as_f64().map(|value| value as f32)
+    }
+
+    pub fn as_u64(&self) -> Result<u64, FormatError> {
+        match self {
+            Value::Number(value) if value.fract() == 0.0 && *value >= 0.0 && *value <= u64::MAX as f64 => {
+                Ok(*value as u64)
+            }
+            _ => Err(self.invalid("an unsigned integer")),
+        }
+    }
+
+    pub fn as_u32(&self) -> Result<u32, FormatError> {
+        self.as_u64()?
+            .try_into()
+            .map_err(|_| self.invalid("a 32 bit unsigned integer"))
+    }
+
+    pub fn as_usize(&self) -> Result<usize, FormatError> {
+        self.as_u64()?.try_into().map_err(|_| self.invalid("an index"))
+    }
+
+    pub fn as_str(&self) -> Result<&str, FormatError> {
+        match self {
+            Value::String(value) => Ok(value),
+            _ => Err(self.invalid("a string")),
+        }
+    }
+
+    pub fn as_array(&self) -> Result<&Vec<Value>, FormatError> {
+        match self {
+            Value::Array(values) => Ok(values),
+            _ => Err(self.invalid("an array")),
+        }
+    }
+
+    /// ```None``` for ```null```, otherwise ```decode(self)```
+    pub fn as_option<T>(
+        &self,
+        decode: impl FnOnce(&Value) -> Result<T, FormatError>,
+    ) -> Result<Option<T>, FormatError> {
+        match self {
+            Value::Null => Ok(None),
+            value => decode(value).map(Some),
+        }
+    }
+
+    pub fn as_strings(&self) -> Result<Vec<String>, FormatError> {
+        self.as_array()?
+            .iter()
+            .map(|value| value.as_str().map(str::to_string))
+            .collect()
+    }
+
+    pub fn as_f64s(&self) -> Result<Vec<f64>, FormatError> {
+        self.as_array()?.iter().map(Value::as_f64).collect()
+    }
+
+    pub fn as_vector2(&self) -> Result<Vector2<f32>, FormatError> {
+        match self.as_array()?.as_slice() {
+            [x, y] => Ok(Vector2::new(x.as_f32()?, y.as_f32()?)),
+            _ => Err(self.invalid("a 2D vector")),
+        }
+    }
+
+    fn invalid(&self, expected: &'static str) -> FormatError {
+        let mut found = String::new();
+        self.write_compact(&mut found);
+        if found.len() > 32 {
+            found.truncate(32);
+            found.push_str("...");
+        }
+
+        FormatError::InvalidValue { expected, found }
+    }
+
+    /// JSON text of the value, indented by two spaces. Arrays of scalars are kept on a single line.
+    pub fn to_json(&self) -> String {
+        let mut out = String::new();
+        self.write_pretty(&mut out, 0);
+        out.push('\n');
+        out
+    }
+
+    fn is_scalar(&self) -> bool {
+        !matches!(self, Value::Array(_) | Value::Object(_))
+    }
+
+    fn write_pretty(&self, out: &mut String, indent: usize) {
+        match self {
+            Value::Array(values) if !values.is_empty() && !values.iter().all(Value::is_scalar) => {
+                out.push_str("[\n");
+                for (idx, value) in values.iter().enumerate() {
+                    push_indent(out, indent + 1);
+                    value.write_pretty(out, indent + 1);
+                    out.push_str(if idx + 1 < values.len() { ",\n" } else { "\n" });
+                }
+                push_indent(out, indent);
+                out.push(']');
+            }
+            Value::Object(fields) if !fields.is_empty() => {
+                out.push_str("{\n");
+                for (idx, (key, value)) in fields.iter().enumerate() {
+                    push_indent(out, indent + 1);
+                    write_string(out, key);
+                    out.push_str(": ");
+                    value.write_pretty(out, indent + 1);
+                    out.push_str(if idx + 1 < fields.len() { ",\n" } else { "\n" });
+                }
+                push_indent(out, indent);
+                out.push('}');
+            }
+            value => value.write_compact(out),
+        }
+    }
+
+    fn write_compact(&self, out: &mut String) {
+        match self {
+            Value::Null => out.push_str("null"),
+            Value::Bool(value) => out.push_str(if *value { "true" } else { "false" }),
+            Value::Number(value) if value.is_nan() => out.push_str("\"nan\""),
+            Value::Number(value) if value.is_infinite() => {
+                out.push_str(if *value > 0.0 { "\"inf\"" } else { "\"-inf\"" })
+            }
+            // Ids and counts are written without a fractional part
+            Value::Number(value) if value.fract() == 0.0 && value.abs() < 1e15 => {
+                write!(out, "{}", value).expect("writing to a string cannot fail")
+            }
+            // The shortest representation that reads back to the same number
+            Value::Number(value) => write!(out, "{:?}", value).expect("writing to a string cannot fail"),
+            Value::String(value) => write_string(out, value),
+            Value::Array(values) => {
+                out.push('[');
+                for (idx, value) in values.iter().enumerate() {
+                    if idx > 0 {
+                        out.push_str(", ");
+                    }
+                    value.write_compact(out);
+                }
+                out.push(']');
+            }
+            Value::Object(fields) => {
+                out.push('{');
+                for (idx, (key, value)) in fields.iter().enumerate() {
+                    if idx > 0 {
+                        out.push_str(", ");
+                    }
+                    write_string(out, key);
+                    out.push_str(": ");
+                    value.write_compact(out);
+                }
+                out.push('}');
+            }
+        }
+    }
+
+    /// Parses a JSON document, errors report the line and column (starting at 1) where parsing stopped
+    pub fn from_json(source: &str) -> Result<Value, FormatError> {
+        let mut parser = Parser { source, pos: 0 };
+
+        let value = parser.value(0)?;
+        parser.skip_whitespace();
+        if parser.pos < source.len() {
+            return Err(parser.error("unexpected characters after the document"));
+        }
+
+        Ok(value)
+    }
+}
+
+fn push_indent(out: &mut String, indent: usize) {
+    for _ in 0..indent {
+        out.push_str("  ");
+    }
+}
+
+fn write_string(out: &mut String, value: &str) {
+    out.push('"');
+    for c in value.chars() {
+        match c {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            c if (c as u32) < 0x20 => write!(out, "\\u{:04x}", c as u32).expect("writing to a string cannot fail"),
+            c => out.push(c),
+        }
+    }
+    out.push('"');
+}
+
+impl From<bool> for Value {
+    fn from(value: bool) -> Self {
+        Value::Bool(value)
+    }
+}
+
+impl From<f64> for Value {
+    fn from(value: f64) -> Self {
+        Value::Number(value)
+    }
+}
+
+impl From<f32> for Value {
+    fn from(value: f32) -> Self {
+        Value::Number(value as f64)
+    }
+}
+
+impl From<u32> for Value {
+    fn from(value: u32) -> Self {
+        Value::Number(value as f64)
+    }
+}
+
+impl From<usize> for Value {
+    fn from(value: usize) -> Self {
+        Value::Number(value as f64)
+    }
+}
+
+impl From<&str> for Value {
+    fn from(value: &str) -> Self {
+        Value::String(value.to_string())
+    }
+}
+
+impl From<String> for Value {
+    fn from(value: String) -> Self {
+        Value::String(value)
+    }
+}
+
+impl From<&Vector2<f32>> for Value {
+    fn from(value: &Vector2<f32>) -> Self {
+        Value::Array(vec![value.x.into(), value.y.into()])
+    }
+}
+
+impl<T: Into<Value>> From<Option<T>> for Value {
+    fn from(value: Option<T>) -> Self {
+        value.map_or(Value::Null, Into::into)
+    }
+}
+
+impl<T: Into<Value>> From<Vec<T>> for Value {
+    fn from(values: Vec<T>) -> Self {
+        Value::Array(values.into_iter().map(Into::into).collect())
+    }
+}
+
+/// Documents nested deeper than this are rejected instead of overflowing the stack
+const MAX_DEPTH: usize = 128;
+
+struct Parser<'a> {
+    source: &'a str,
+    /// Byte offset of the next character
+    pos: usize,
+}
+
+impl Parser<'_> {
+    fn error(&self, message: &str) -> FormatError {
+        let consumed = &self.source[..self.pos];
+        let line = consumed.matches('\n').count() + 1;
+        let column = consumed.rsplit('\n').next().map_or(0, |line| line.chars().count()) + 1;
+
+        FormatError::Json {
+            line,
+            column,
+            message: message.to_string(),
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.source.as_bytes().get(self.pos).copied()
+    }
+
+    fn skip_whitespace(&mut self) {
+        while matches!(self.peek(), Some(b' ' | b'\t' | b'\n' | b'\r')) {
+            self.pos += 1;
+        }
+    }
+
+    fn expect(&mut self, expected: u8) -> Result<(), FormatError> {
+        self.skip_whitespace();
+        if self.peek() != Some(expected) {
+            return Err(self.error(&format!("expected '{}'", expected as char)));
+        }
+        self.pos += 1;
+        Ok(())
+    }
+
+    fn keyword(&mut self, keyword: &str, value: Value) -> Result<Value, FormatError> {
+        if !self.source[self.pos..].starts_with(keyword) {
+            return Err(self.error("unexpected character"));
+        }
+        self.pos += keyword.len();
+        Ok(value)
+    }
+
+    fn value(&mut self, depth: usize) -> Result<Value, FormatError> {
+        if depth > MAX_DEPTH {
+            return Err(self.error("the document is nested too deeply"));
+        }
+
+        self.skip_whitespace();
+        match self.peek() {
+            None => Err(self.error("unexpected end of the document")),
+            Some(b'n') => self.keyword("null", Value::Null),
+            Some(b't') => self.keyword("true", Value::Bool(true)),
+            Some(b'f') => self.keyword("false", Value::Bool(false)),
+            Some(b'"') => self.string().map(Value::String),
+            Some(b'[') => {
+                self.pos += 1;
+                let mut values = Vec::new();
+                self.skip_whitespace();
+                if self.peek() == Some(b']') {
+                    self.pos += 1;
+                    return Ok(Value::Array(values));
+                }
+                loop {
+                    values.push(self.value(depth + 1)?);
+                    self.skip_whitespace();
+                    match self.peek() {
+                        Some(b',') => self.pos += 1,
+                        Some(b']') => {
+                            self.pos += 1;
+                            return Ok(Value::Array(values));
+                        }
+                        _ => return Err(self.error("expected ',' or ']'")),
+                    }
+                }
+            }
+            Some(b'{') => {
+                self.pos += 1;
+                let mut fields = Vec::new();
+                self.skip_whitespace();
+                if self.peek() == Some(b'}') {
+                    self.pos += 1;
+                    return Ok(Value::Object(fields));
+                }
+                loop {
+                    self.skip_whitespace();
+                    if self.peek() != Some(b'"') {
+                        return Err(self.error("expected the name of a field"));
+                    }
+                    let key = self.string()?;
+                    self.expect(b':')?;
+                    fields.push((key, self.value(depth + 1)?));
+                    self.skip_whitespace();
+                    match self.peek() {
+                        Some(b',') => self.pos += 1,
+                        Some(b'}') => {
+                            self.pos += 1;
+                            return Ok(Value::Object(fields));
+                        }
+                        _ => return Err(self.error("expected ',' or '}'")),
+                    }
+                }
+            }
+            Some(b'-' | b'0'..=b'9') => self.number(),
+            Some(_) => Err(self.error("unexpected character")),
+        }
+    }
+
+    fn number(&mut self) -> Result<Value, FormatError> {
+        let start = self.pos;
+        while matches!(self.peek(), Some(b'-' | b'+' | b'.' | b'e' | b'E' | b'0'..=b'9')) {
+            self.pos += 1;
+        }
+
+        self.source[start..self.pos]
+            .parse::<f64>()
+            .map(Value::Number)
+            .map_err(|_| {
+                self.pos = start;
+                self.error("invalid number")
+            })
+    }
+
+    /// Parses a string starting at the opening quote
+    fn string(&mut self) -> Result<String, FormatError> {
+        self.pos += 1;
+        let mut value = String::new();
+
+        loop {
+            let rest = &self.source[self.pos..];
+            let Some(c) = rest.chars().next() else {
+                return Err(self.error("unterminated string"));
+            };
+            self.pos += c.len_utf8();
+
+            match c {
+                '"' => return Ok(value),
+                '\\' => {
+                    let escaped = self.peek().ok_or_else(|| self.error("unterminated string"))?;
+                    self.pos += 1;
+                    match escaped {
+                        b'"' => value.push('"'),
+                        b'\\' => value.push('\\'),
+                        b'/' => value.push('/'),
+                        b'b' => value.push('\u{8}'),
+                        b'f' => value.push('\u{c}'),
+                        b'n' => value.push('\n'),
+                        b'r' => value.push('\r'),
+                        b't' => value.push('\t'),
+                        b'u' => {
+                            let code = self.unicode_escape()?;
+                            // Characters outside of the basic plane are written as a surrogate pair
+                            let code = if (0xd800..0xdc00).contains(&code) && self.source[self.pos..].starts_with("\\u")
+                            {
+                                self.pos += 2;
+                                let low = self.unicode_escape()?;
+                                0x10000 + ((code - 0xd800) << 10) + (low.wrapping_sub(0xdc00) & 0x3ff)
+                            } else {
+                                code
+                            };
+                            value.push(char::from_u32(code).ok_or_else(|| self.error("invalid unicode escape"))?);
+                        }
+                        _ => {
+                            self.pos -= 1;
+                            return Err(self.error("invalid escape sequence"));
+                        }
+                    }
+                }
+                c => value.push(c),
+            }
+        }
+    }
+
+    fn unicode_escape(&mut self) -> Result<u32, FormatError> {
+        let digits = self
+            .source
+            .get(self.pos..self.pos + 4)
+            .ok_or_else(|| self.error("invalid unicode escape"))?;
+        let code = u32::from_str_radix(digits, 16).map_err(|_| self.error("invalid unicode escape"))?;
+        self.pos += 4;
+
+        Ok(code)
+    }
+}
+
+#[cfg(test)]
+mod value_test {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let value = Value::object([
+            ("name", "l0.x1 \"in\"\n".into()),
+            ("values", vec![1.5, -2e-9, 1e300].into()),
+            ("empty", Value::Array(Vec::new())),
+            ("nested", Value::object([("flag", true.into()), ("none", Value::Null)])),
+            ("unicode", "Ω µ".into()),
+        ]);
+        assert_eq!(Value::from_json(&value.to_json()).unwrap(), value);
+
+        // Non finite numbers are not valid JSON, they are written as strings
+        let infinite = Value::from_json(&Value::from(f64::NEG_INFINITY).to_json()).unwrap();
+        assert_eq!(infinite.as_f64().unwrap(), f64::NEG_INFINITY);
+
+        assert_eq!(
+            Value::from_json(r#""\u00b5\ud83d\ude00""#).unwrap(),
+            Value::String("µ😀".to_string())
+        );
+    }
+
+    #[test]
+    fn test_errors() {
+        match Value::from_json("{\n  \"a\": [1, 2,,]\n}") {
+            Err(FormatError::Json { line, column, .. }) => assert_eq!((line, column), (2, 14)),
+            other => panic!("unexpected result {:?}", other),
+        }
+        assert!(matches!(
+            Value::from_json("{}").unwrap().field("a"),
+            Err(FormatError::MissingField(_))
+        ));
+        assert!(matches!(
+            Value::from_json("-1").unwrap().as_u32(),
+            Err(FormatError::InvalidValue { .. })
+        ));
+    }
+}
@@ -0,0 +1,398 @@
+//! Tree of the constructs of a scene: a network contains layers, a layer contains tiles, a tile contains a crossbar
+//! and its periphery. Leaves are the constructs of ```SceneManager```, inner nodes group them.
+//!
+//! Every node has a transform relative to its parent. ```Construct::add_to_scene``` lays the components of a construct
+//! out in the frame of its node, so the world position of a component is the world transform of its node applied to
+//! the position it was laid out at. Moving a node moves every component below it.
+
+use std::collections::HashSet;
+
+use crate::types::Id;
+
+use nalgebra::{Rotation2, Vector2};
+use thiserror::Error;
+
+/// Index of a node in a ```ConstructTree```
+pub type ConstructNodeId = usize;
+
+#[derive(Error, Debug)]
+pub enum ConstructTreeError {
+    #[error("Construct node {0} does not exist")]
+    NodeNotFound(ConstructNodeId),
+    #[error("Construct node {0} is a construct, only groups can have children")]
+    NotAGroup(ConstructNodeId),
+    #[error("Construct node {0} cannot be copied inside itself")]
+    InstanceInsideItself(ConstructNodeId),
+    #[error("Component {0} already belongs to another construct")]
+    ComponentAlreadyOwned(Id),
+    #[error("Construct node {0} is not the last node added, it cannot be removed")]
+    NotLastNode(ConstructNodeId),
+}
+
+/// Rotation (counterclockwise, in radians) followed by a translation
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Transform {
+    pub translation: Vector2<f32>,
+    pub rotation: f32,
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Self::IDENTITY
+    }
+}
+
+impl Transform {
+    pub const IDENTITY: Transform = Transform {
+        translation: Vector2::new(0.0, 0.0),
+        rotation: 0.0,
+    };
+
+    pub fn new(translation: Vector2<f32>, rotation: f32) -> Transform {
+        Transform { translation, rotation }
+    }
+
+    pub fn from_translation(translation: Vector2<f32>) -> Transform {
+        Transform::new(translation, 0.0)
+    }
+
+    pub fn is_identity(&self) -> bool {
+        *self == Self::IDENTITY
+    }
+
+    pub fn apply(&self, point: &Vector2<f32>) -> Vector2<f32> {
+        Rotation2::new(self.rotation) * point + self.translation
+    }
+
+    /// The transform that applies ```child``` and then ```self```, e.g. the world transform of a child node
+    pub fn compose(&self, child: &Transform) -> Transform {
+        Transform::new(self.apply(&child.translation), self.rotation + child.rotation)
+    }
+
+    pub fn inverse(&self) -> Transform {
+        Transform::new(-(Rotation2::new(-self.rotation) * self.translation), -self.rotation)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConstructNodeKind {
+    /// The root of the tree, every scene has one
+    Root,
+    Network,
+    Layer(usize),
+    /// A crossbar of a layer split into several arrays and its periphery, at ```(row, col)``` of the tile grid
+    Tile(usize, usize),
+    /// Any other group of constructs
+    Group,
+    /// A construct of the scene manager, its index in ```SceneManager::constructs```
+    Construct(usize),
+}
+
+#[derive(Debug, Clone)]
+pub struct ConstructNode {
+    name: String,
+    kind: ConstructNodeKind,
+    parent: Option<ConstructNodeId>,
+    children: Vec<ConstructNodeId>,
+    transform: Transform,
+}
+
+impl ConstructNode {
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn kind(&self) -> ConstructNodeKind {
+        self.kind
+    }
+
+    pub fn parent(&self) -> Option<ConstructNodeId> {
+        self.parent
+    }
+
+    pub fn children(&self) -> &Vec<ConstructNodeId> {
+        &self.children
+    }
+
+    /// Transform relative to the parent
+    pub fn transform(&self) -> &Transform {
+        &self.transform
+    }
+
+    /// Index of the construct in ```SceneManager::constructs``` if the node is a leaf
+    pub fn construct(&self) -> Option<usize> {
+        match self.kind {
+            ConstructNodeKind::Construct(idx) => Some(idx),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ConstructTree {
+    nodes: Vec<ConstructNode>,
+    /// ```(first id, last id, leaf)``` of the components of every construct, sorted by first id
+    components: Vec<(Id, Id, ConstructNodeId)>,
+}
+
+impl Default for ConstructTree {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ConstructTree {
+    pub fn new() -> ConstructTree {
+        ConstructTree {
+            nodes: vec![ConstructNode {
+                name: String::from("scene"),
+                kind: ConstructNodeKind::Root,
+                parent: None,
+                children: Vec::new(),
+                transform: Transform::IDENTITY,
+            }],
+            components: Vec::new(),
+        }
+    }
+
+    pub fn root(&self) -> ConstructNodeId {
+        0
+    }
+
+    /// Number of nodes, the root included
+    pub fn len(&self) -> usize {
+        self.nodes.len()
+    }
+
+    /// Whether the tree has no node but the root
+    pub fn is_empty(&self) -> bool {
+        self.nodes.len() == 1
+    }
+
+    pub fn node(&self, node_id: ConstructNodeId) -> Option<&ConstructNode> {
+        self.nodes.get(node_id)
+    }
+
+    /// Nodes in the order they were added, a parent always comes before its children
+    pub fn nodes(&self) -> impl Iterator<Item = (ConstructNodeId, &ConstructNode)> + '_ {
+        self.nodes.iter().enumerate()
+    }
+
+    fn get(&self, node_id: ConstructNodeId) -> Result<&ConstructNode, ConstructTreeError> {
+        self.nodes.get(node_id).ok_or(ConstructTreeError::NodeNotFound(node_id))
+    }
+
+    /// Adds a group below ```parent```, ```kind``` must not be ```Construct``` (see ```add_construct```)
+    pub fn add_group(
+        &mut self,
+        parent: ConstructNodeId,
+        name: String,
+        kind: ConstructNodeKind,
+        transform: Transform,
+    ) -> Result<ConstructNodeId, ConstructTreeError> {
+        debug_assert!(!matches!(
+            kind,
+            ConstructNodeKind::Construct(_) | ConstructNodeKind::Root
+        ));
+        self.add_node(parent, name, kind, transform)
+    }
+
+    /// Adds the leaf of construct ```construct_idx```, made of the components ```components_id_range```
+    pub fn add_construct(
+        &mut self,
+        parent: ConstructNodeId,
+        name: String,
+        construct_idx: usize,
+        components_id_range: (Id, Id),
+    ) -> Result<ConstructNodeId, ConstructTreeError> {
+        let (first_id, last_id) = components_id_range;
+        let pos = self.components.partition_point(|(first, _, _)| *first < first_id);
+        let overlaps_prev = pos > 0 && self.components[pos - 1].1 >= first_id;
+        let overlaps_next = self.components.get(pos).is_some_and(|(first, _, _)| *first <= last_id);
+        if overlaps_prev || overlaps_next {
+            return Err(ConstructTreeError::ComponentAlreadyOwned(first_id));
+        }
+
+        let node_id = self.add_node(
+            parent,
+            name,
+            ConstructNodeKind::Construct(construct_idx),
+            Transform::IDENTITY,
+        )?;
+        self.components.insert(pos, (first_id, last_id, node_id));
+
+        Ok(node_id)
+    }
+
+    fn add_node(
+        &mut self,
+        parent: ConstructNodeId,
+        name: String,
+        kind: ConstructNodeKind,
+        transform: Transform,
+    ) -> Result<ConstructNodeId, ConstructTreeError> {
+        if self.get(parent)?.construct().is_some() {
+            return Err(ConstructTreeError::NotAGroup(parent));
+        }
+
+        let node_id = self.nodes.len();
+        self.nodes.push(ConstructNode {
+            name,
+            kind,
+            parent: Some(parent),
+            children: Vec::new(),
+            transform,
+        });
+        self.nodes[parent].children.push(node_id);
+
+        Ok(node_id)
+    }
+
+    /// Removes ```node_id```, which must be the node added last so that the ids of the others do not change. It has
+    /// no children, the ones of a node are always added after it.
+    pub fn remove_last(&mut self, node_id: ConstructNodeId) -> Result<ConstructNode, ConstructTreeError> {
+        if node_id == self.root() || node_id + 1 != self.nodes.len() {
+            return Err(ConstructTreeError::NotLastNode(node_id));
+        }
+
+        let node = self.nodes.pop().ok_or(ConstructTreeError::NodeNotFound(node_id))?;
+        if let Some(parent) = node.parent {
+            self.nodes[parent].children.retain(|child| *child != node_id);
+        }
+        self.components.retain(|(_, _, leaf)| *leaf != node_id);
+
+        Ok(node)
+    }
+
+    /// Sets the transform of a node relative to its parent, the components are not moved (see
+    /// ```SceneManager::set_construct_transform```)
+    pub fn set_transform(&mut self, node_id: ConstructNodeId, transform: Transform) -> Result<(), ConstructTreeError> {
+        self.nodes
+            .get_mut(node_id)
+            .ok_or(ConstructTreeError::NodeNotFound(node_id))?
+            .transform = transform;
+        Ok(())
+    }
+
+    pub fn set_name(&mut self, node_id: ConstructNodeId, name: String) -> Result<(), ConstructTreeError> {
+        self.nodes
+            .get_mut(node_id)
+            .ok_or(ConstructTreeError::NodeNotFound(node_id))?
+            .name = name;
+        Ok(())
+    }
+
+    /// Transform from the frame of the node to the world
+    pub fn world_transform(&self, node_id: ConstructNodeId) -> Result<Transform, ConstructTreeError> {
+        let mut transform = self.get(node_id)?.transform;
+        for ancestor in self.ancestors(node_id).skip(1) {
+            transform = self.nodes[ancestor].transform.compose(&transform);
+        }
+        Ok(transform)
+    }
+
+    /// The node followed by its parent, the parent of its parent, ... up to the root
+    pub fn ancestors(&self, node_id: ConstructNodeId) -> impl Iterator<Item = ConstructNodeId> + '_ {
+        std::iter::successors(self.nodes.get(node_id).map(|_| node_id), |node_id| {
+            self.nodes[*node_id].parent
+        })
+    }
+
+    /// The node and every node below it, parents before children
+    pub fn descendants(&self, node_id: ConstructNodeId) -> Vec<ConstructNodeId> {
+        let mut descendants = Vec::new();
+        let mut stack = vec![node_id];
+        while let Some(node_id) = stack.pop() {
+            let Some(node) = self.nodes.get(node_id) else { continue };
+            descendants.push(node_id);
+            stack.extend(node.children.iter().rev());
+        }
+        descendants
+    }
+
+    /// Leaf of the construct that ```component_id``` is part of, ```None``` for components that do not belong to any
+    /// construct (e.g. the ones loaded from a SPICE netlist)
+    pub fn construct_of(&self, component_id: Id) -> Option<ConstructNodeId> {
+        let pos = self.components.partition_point(|(first, _, _)| *first <= component_id);
+        let (_, last_id, node_id) = self.components.get(pos.checked_sub(1)?)?;
+        (component_id <= *last_id).then_some(*node_id)
+    }
+
+    /// Ranges of the ids of the components below a node
+    pub fn component_ranges(&self, node_id: ConstructNodeId) -> Vec<(Id, Id)> {
+        let leaves: HashSet<ConstructNodeId> = self.descendants(node_id).into_iter().collect();
+        self.components
+            .iter()
+            .filter(|(_, _, leaf)| leaves.contains(leaf))
+            .map(|(first, last, _)| (*first, *last))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod hierarchy_test {
+    use super::*;
+
+    #[test]
+    fn test_tree() {
+        let mut tree = ConstructTree::new();
+        let network = tree
+            .add_group(
+                tree.root(),
+                "net".into(),
+                ConstructNodeKind::Network,
+                Transform::IDENTITY,
+            )
+            .unwrap();
+        let layer = tree
+            .add_group(
+                network,
+                "l0".into(),
+                ConstructNodeKind::Layer(0),
+                Transform::from_translation(Vector2::new(10.0, 0.0)),
+            )
+            .unwrap();
+        let crossbar = tree.add_construct(layer, "crossbar".into(), 0, (0, 15)).unwrap();
+        let tia = tree.add_construct(layer, "tia".into(), 1, (16, 19)).unwrap();
+
+        assert_eq!(tree.construct_of(0), Some(crossbar));
+        assert_eq!(tree.construct_of(15), Some(crossbar));
+        assert_eq!(tree.construct_of(17), Some(tia));
+        assert_eq!(tree.construct_of(20), None);
+        assert_eq!(
+            tree.ancestors(tia).collect::<Vec<_>>(),
+            vec![tia, layer, network, tree.root()]
+        );
+        assert_eq!(tree.descendants(network), vec![network, layer, crossbar, tia]);
+        assert_eq!(tree.component_ranges(layer), vec![(0, 15), (16, 19)]);
+
+        assert!(matches!(
+            tree.add_construct(layer, "overlap".into(), 2, (19, 25)),
+            Err(ConstructTreeError::ComponentAlreadyOwned(19))
+        ));
+        assert!(matches!(
+            tree.add_group(tia, "child".into(), ConstructNodeKind::Group, Transform::IDENTITY),
+            Err(ConstructTreeError::NotAGroup(_))
+        ));
+
+        // A quarter turn of the network around the origin, then the translation of the layer
+        tree.set_transform(
+            network,
+            Transform::new(Vector2::new(0.0, 1.0), std::f32::consts::FRAC_PI_2),
+        )
+        .unwrap();
+        let world = tree.world_transform(crossbar).unwrap();
+        let point = world.apply(&Vector2::new(1.0, 0.0));
+        assert!((point - Vector2::new(0.0, 12.0)).norm() < 1e-5);
+        assert!((world.inverse().apply(&point) - Vector2::new(1.0, 0.0)).norm() < 1e-5);
+
+        // Only the last node can be removed, the ids of the others stay the same
+        assert!(matches!(
+            tree.remove_last(crossbar),
+            Err(ConstructTreeError::NotLastNode(_))
+        ));
+        assert_eq!(tree.remove_last(tia).unwrap().name(), "tia");
+        assert_eq!(tree.construct_of(17), None);
+        assert_eq!(tree.node(layer).unwrap().children(), &vec![crossbar]);
+    }
+}
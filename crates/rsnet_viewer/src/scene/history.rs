@@ -0,0 +1,682 @@
+//! Reversible edits of a ```SceneManager```. Applying a command returns the command that reverts it, so the history
+//! only keeps what changed and never a copy of the scene.
+
+use super::component::{Component, ComponentParams};
+use super::hierarchy::{ConstructNodeId, ConstructNodeKind, ConstructTreeError, Transform};
+use super::scene_manager::{Construct, SceneManager, SceneManagerError};
+use super::types::ChunkStepIdx;
+use super::wire::Wire;
+use crate::types::{Id, NodeId};
+
+use nalgebra::Vector2;
+
+#[derive(Debug)]
+pub enum Command {
+    /// Adds a component and binds its ports, ```bindings``` pairs the index of a port with its node
+    AddComponent {
+        chunk_step_idx: ChunkStepIdx,
+        component: Component,
+        bindings: Vec<(usize, NodeId)>,
+    },
+    /// Removes a component from the scene and the netlist
+    RemoveComponent(Id),
+    /// Sets the placement of a component
+    TransformComponent {
+        id: Id,
+        position: Vector2<f32>,
+        rotation: f32,
+        scale: f32,
+    },
+    SetComponentParams {
+        id: Id,
+        params: ComponentParams,
+    },
+    AddWire {
+        chunk_step_idx: ChunkStepIdx,
+        wire: Wire,
+    },
+    RemoveWire(Id),
+    /// Moves the components with an id in one of the ```(first id, last id)``` ranges by ```offset```, the ids that
+    /// are not components are skipped. A whole crossbar is a single range, so moving it keeps a single command.
+    TranslateComponents {
+        ranges: Vec<(Id, Id)>,
+        offset: Vector2<f32>,
+    },
+    /// Adds a construct below the group ```parent```
+    AddConstruct {
+        parent: ConstructNodeId,
+        construct: Box<dyn Construct>,
+    },
+    AddConstructGroup {
+        parent: ConstructNodeId,
+        name: String,
+        kind: ConstructNodeKind,
+        transform: Transform,
+    },
+    /// Removes the last node of the construct tree and the nodes (nets) from ```first_node``` on, see
+    /// ```SceneManager::remove_last_construct_node```
+    RemoveConstructNode {
+        node_id: ConstructNodeId,
+        first_node: NodeId,
+    },
+    /// Sets the transform of a node of the construct tree relative to its parent, moving the components below it
+    SetConstructTransform {
+        node_id: ConstructNodeId,
+        transform: Transform,
+    },
+    /// Commands applied in order, and reverted in the opposite order, as a single edit
+    Batch(Vec<Command>),
+}
+
+impl Command {
+    /// Applies the command to ```scene_manager``` and returns the command that reverts it. A batch or a translation
+    /// that fails is reverted, so the scene is left as it was.
+    pub fn apply(self, scene_manager: &mut SceneManager) -> Result<Command, SceneManagerError> {
+        match self {
+            Command::AddComponent {
+                chunk_step_idx,
+                component,
+                bindings,
+            } => {
+                let id = component.id();
+                scene_manager.insert_component(chunk_step_idx, component, &bindings)?;
+                Ok(Command::RemoveComponent(id))
+            }
+            Command::RemoveComponent(id) => {
+                let (chunk_step_idx, component, bindings) = scene_manager.remove_component(id)?;
+                Ok(Command::AddComponent {
+                    chunk_step_idx,
+                    component,
+                    bindings,
+                })
+            }
+            Command::TransformComponent {
+                id,
+                position,
+                rotation,
+                scale,
+            } => {
+                let scene = scene_manager.scene_mut();
+                let component = scene
+                    .get_component(id)
+                    .ok_or(SceneManagerError::ComponentNotFound(id))?;
+                let inverse = Command::TransformComponent {
+                    id,
+                    position: *component.position(),
+                    rotation: component.rotation(),
+                    scale: component.scale(),
+                };
+
+                scene.transform_component(id, position, rotation, scale)?;
+                Ok(inverse)
+            }
+            Command::SetComponentParams { id, params } => {
+                let scene = scene_manager.scene_mut();
+                let previous = *scene
+                    .get_component(id)
+                    .ok_or(SceneManagerError::ComponentNotFound(id))?
+                    .params();
+
+                scene.set_component_params(id, params)?;
+                Ok(Command::SetComponentParams { id, params: previous })
+            }
+            Command::AddWire { chunk_step_idx, wire } => {
+                let id = wire.id();
+                let scene = scene_manager.scene_mut();
+
+                // Adding a wire replaces the one with the same id, which has to come back when reverting
+                let previous = scene.wire_chunk_step(id).zip(scene.remove_wire(id));
+                scene.add_wire(chunk_step_idx, wire);
+
+                Ok(match previous {
+                    Some((chunk_step_idx, wire)) => Command::AddWire { chunk_step_idx, wire },
+                    None => Command::RemoveWire(id),
+                })
+            }
+            Command::RemoveWire(id) => {
+                let scene = scene_manager.scene_mut();
+                let chunk_step_idx = scene.wire_chunk_step(id).ok_or(SceneManagerError::WireNotFound(id))?;
+                let wire = scene.remove_wire(id).ok_or(SceneManagerError::WireNotFound(id))?;
+
+                Ok(Command::AddWire { chunk_step_idx, wire })
+            }
+            Command::TranslateComponents { ranges, offset } => {
+                let scene = scene_manager.scene_mut();
+                let mut moved = Vec::new();
+                for id in ranges.iter().flat_map(|(first_id, last_id)| *first_id..=*last_id) {
+                    let Some(component) = scene.get_component(id) else {
+                        continue;
+                    };
+                    let position = component.position() + offset;
+                    let rotation = component.rotation();
+                    if let Err(e) = scene.move_component(id, position, rotation) {
+                        // The components moved so far go back, so the scene is left as it was
+                        for id in moved {
+                            let component = scene.get_component(id).expect("the component was just moved");
+                            let (position, rotation) = (component.position() - offset, component.rotation());
+                            let _ = scene.move_component(id, position, rotation);
+                        }
+                        return Err(e.into());
+                    }
+                    moved.push(id);
+                }
+
+                Ok(Command::TranslateComponents {
+                    ranges,
+                    offset: -offset,
+                })
+            }
+            Command::AddConstruct { parent, construct } => {
+                let first_node = scene_manager.next_free_node_id();
+                let node_id = scene_manager.add_construct_to(parent, construct)?;
+                Ok(Command::RemoveConstructNode { node_id, first_node })
+            }
+            Command::AddConstructGroup {
+                parent,
+                name,
+                kind,
+                transform,
+            } => {
+                let first_node = scene_manager.next_free_node_id();
+                let node_id = scene_manager.add_construct_group(parent, name, kind, transform)?;
+                Ok(Command::RemoveConstructNode { node_id, first_node })
+            }
+            Command::RemoveConstructNode { node_id, first_node } => {
+                let (node, construct) = scene_manager.remove_last_construct_node(node_id, first_node)?;
+                let parent = node.parent().ok_or(ConstructTreeError::NotLastNode(node_id))?;
+
+                Ok(match construct {
+                    Some(construct) => Command::AddConstruct { parent, construct },
+                    None => Command::AddConstructGroup {
+                        parent,
+                        name: node.name().to_string(),
+                        kind: node.kind(),
+                        transform: *node.transform(),
+                    },
+                })
+            }
+            Command::SetConstructTransform { node_id, transform } => {
+                let previous = *scene_manager
+                    .hierarchy()
+                    .node(node_id)
+                    .ok_or(ConstructTreeError::NodeNotFound(node_id))?
+                    .transform();
+
+                scene_manager.set_construct_transform(node_id, transform)?;
+                Ok(Command::SetConstructTransform {
+                    node_id,
+                    transform: previous,
+                })
+            }
+            Command::Batch(commands) => {
+                let mut inverses = Vec::with_capacity(commands.len());
+                for command in commands {
+                    match command.apply(scene_manager) {
+                        Ok(inverse) => inverses.push(inverse),
+                        Err(e) => {
+                            inverses.reverse();
+                            // The commands applied so far were valid, so are their inverses
+                            let _ = Command::Batch(inverses).apply(scene_manager);
+                            return Err(e);
+                        }
+                    }
+                }
+
+                inverses.reverse();
+                Ok(Command::Batch(inverses))
+            }
+        }
+    }
+
+    /// Whether applying the command changes nothing
+    pub fn is_empty(&self) -> bool {
+        match self {
+            Command::Batch(commands) => commands.iter().all(|command| command.is_empty()),
+            Command::TranslateComponents { ranges, offset } => ranges.is_empty() || *offset == Vector2::zeros(),
+            _ => false,
+        }
+    }
+}
+
+/// Constructs are copied with the same components and nodes
+impl Clone for Command {
+    fn clone(&self) -> Command {
+        match self {
+            Command::AddComponent {
+                chunk_step_idx,
+                component,
+                bindings,
+            } => Command::AddComponent {
+                chunk_step_idx: *chunk_step_idx,
+                component: component.clone(),
+                bindings: bindings.clone(),
+            },
+            Command::RemoveComponent(id) => Command::RemoveComponent(*id),
+            Command::TransformComponent {
+                id,
+                position,
+                rotation,
+                scale,
+            } => Command::TransformComponent {
+                id: *id,
+                position: *position,
+                rotation: *rotation,
+                scale: *scale,
+            },
+            Command::SetComponentParams { id, params } => Command::SetComponentParams {
+                id: *id,
+                params: *params,
+            },
+            Command::AddWire { chunk_step_idx, wire } => Command::AddWire {
+                chunk_step_idx: *chunk_step_idx,
+                wire: wire.clone(),
+            },
+            Command::RemoveWire(id) => Command::RemoveWire(*id),
+            Command::TranslateComponents { ranges, offset } => Command::TranslateComponents {
+                ranges: ranges.clone(),
+                offset: *offset,
+            },
+            Command::AddConstruct { parent, construct } => Command::AddConstruct {
+                parent: *parent,
+                construct: construct.instantiate(construct.components_id_range().0, &|node| node.to_string()),
+            },
+            Command::AddConstructGroup {
+                parent,
+                name,
+                kind,
+                transform,
+            } => Command::AddConstructGroup {
+                parent: *parent,
+                name: name.clone(),
+                kind: *kind,
+                transform: *transform,
+            },
+            Command::RemoveConstructNode { node_id, first_node } => Command::RemoveConstructNode {
+                node_id: *node_id,
+                first_node: *first_node,
+            },
+            Command::SetConstructTransform { node_id, transform } => Command::SetConstructTransform {
+                node_id: *node_id,
+                transform: *transform,
+            },
+            Command::Batch(commands) => Command::Batch(commands.iter().map(Command::clone).collect()),
+        }
+    }
+}
+
+/// Sorted ```(first id, last id)``` ranges of consecutive ids that cover ```ids```, see
+/// ```Command::TranslateComponents```
+pub fn id_ranges(ids: impl IntoIterator<Item = Id>) -> Vec<(Id, Id)> {
+    let mut ids: Vec<Id> = ids.into_iter().collect();
+    ids.sort_unstable();
+    ids.dedup();
+
+    let mut ranges: Vec<(Id, Id)> = Vec::new();
+    for id in ids {
+        match ranges.last_mut() {
+            Some((_, last_id)) if *last_id + 1 == id => *last_id = id,
+            _ => ranges.push((id, id)),
+        }
+    }
+    ranges
+}
+
+/// Undo and redo stacks of the edits of a scene. The edits made between ```begin_group``` and ```end_group``` are
+/// undone and redone as a single one.
+#[derive(Debug, Default)]
+pub struct History {
+    /// Commands that revert the last edits, the last one first
+    undo: Vec<Command>,
+    /// Commands that apply again the last undone edits, the last one first
+    redo: Vec<Command>,
+    /// Commands that revert the edits of the open group, the first one first
+    group: Vec<Command>,
+    /// Number of ```begin_group``` not closed yet
+    group_depth: usize,
+}
+
+impl History {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Applies ```command``` and records it, the edits undone before cannot be redone anymore
+    pub fn execute(&mut self, scene_manager: &mut SceneManager, command: Command) -> Result<(), SceneManagerError> {
+        if command.is_empty() {
+            return Ok(());
+        }
+
+        let inverse = command.apply(scene_manager)?;
+        self.record(inverse);
+        Ok(())
+    }
+
+    /// Records an edit that was already applied to the scene, e.g. while it was previewed during a drag. ```inverse```
+    /// reverts it.
+    pub fn record(&mut self, inverse: Command) {
+        if inverse.is_empty() {
+            return;
+        }
+
+        self.redo.clear();
+        if self.group_depth > 0 {
+            self.group.push(inverse);
+        } else {
+            self.undo.push(inverse);
+        }
+    }
+
+    /// Starts grouping the next edits into a single one, until the matching ```end_group```. Groups can be nested,
+    /// only the outermost one is recorded.
+    pub fn begin_group(&mut self) {
+        self.group_depth += 1;
+    }
+
+    /// Closes the group opened by the last ```begin_group```
+    pub fn end_group(&mut self) {
+        if self.group_depth == 0 {
+            return;
+        }
+
+        self.group_depth -= 1;
+        if self.group_depth == 0 {
+            self.close_group();
+        }
+    }
+
+    /// Whether a group is open
+    pub fn is_grouping(&self) -> bool {
+        self.group_depth > 0
+    }
+
+    fn close_group(&mut self) {
+        let mut inverses = std::mem::take(&mut self.group);
+        if inverses.is_empty() {
+            return;
+        }
+
+        inverses.reverse();
+        self.undo.push(Command::Batch(inverses));
+    }
+
+    /// Reverts the last edit, returns whether there was one. An open group is closed first.
+    pub fn undo(&mut self, scene_manager: &mut SceneManager) -> Result<bool, SceneManagerError> {
+        self.group_depth = 0;
+        self.close_group();
+
+        // The edit is only taken from the history once it has been reverted, a failed one stays in it
+        let Some(command) = self.undo.last() else {
+            return Ok(false);
+        };
+
+        let inverse = command.clone().apply(scene_manager)?;
+        self.undo.pop();
+        self.redo.push(inverse);
+        Ok(true)
+    }
+
+    /// Applies again the last undone edit, returns whether there was one. An open group is closed first.
+    pub fn redo(&mut self, scene_manager: &mut SceneManager) -> Result<bool, SceneManagerError> {
+        self.group_depth = 0;
+        self.close_group();
+
+        let Some(command) = self.redo.last() else {
+            return Ok(false);
+        };
+
+        let inverse = command.clone().apply(scene_manager)?;
+        self.redo.pop();
+        self.undo.push(inverse);
+        Ok(true)
+    }
+
+    pub fn can_undo(&self) -> bool {
+        !self.undo.is_empty() || !self.group.is_empty()
+    }
+
+    pub fn can_redo(&self) -> bool {
+        !self.redo.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+        self.group.clear();
+        self.group_depth = 0;
+    }
+}
+
+#[cfg(test)]
+mod history_test {
+    use super::*;
+    use crate::app::utils::chunk_size_from_step_idx;
+    use crate::scene::component::DefaultComponentTypes;
+    use crate::scene::scene_manager::Crossbar;
+
+    fn resistor(id: Id, x: f32) -> Command {
+        let mut component = Component::new(id, 0, Vector2::new(x, 0.0), 0.0, DefaultComponentTypes::Resistor.into());
+        component.set_params(DefaultComponentTypes::Resistor.default_params());
+        Command::AddComponent {
+            chunk_step_idx: 0,
+            component,
+            bindings: vec![],
+        }
+    }
+
+    #[test]
+    fn test_undo_redo() {
+        let mut scene_manager = SceneManager::new();
+        let mut history = History::new();
+        let gnd = scene_manager.ground();
+
+        history.execute(&mut scene_manager, resistor(0, 0.0)).unwrap();
+        history.execute(&mut scene_manager, resistor(1, 1.0)).unwrap();
+        scene_manager.connect(1, gnd, gnd).unwrap();
+
+        let wire = Wire::new(
+            0,
+            Vector2::new(0.0, -1.0),
+            Vector2::new(1.0, -1.0),
+            Vector2::zeros(),
+            Vector2::zeros(),
+            chunk_size_from_step_idx(0),
+        );
+        let edit = Command::Batch(vec![
+            Command::TransformComponent {
+                id: 0,
+                position: Vector2::new(20.0, 0.0),
+                rotation: std::f32::consts::FRAC_PI_2,
+                scale: 1.0,
+            },
+            Command::RemoveComponent(1),
+            Command::AddWire {
+                chunk_step_idx: 0,
+                wire,
+            },
+        ]);
+        history.execute(&mut scene_manager, edit).unwrap();
+        assert_eq!(
+            scene_manager.scene().get_component(0).unwrap().position(),
+            &Vector2::new(20.0, 0.0)
+        );
+        assert!(scene_manager.scene().get_component(1).is_none());
+        assert!(!scene_manager.netlist().contains_component(1));
+        assert_eq!(scene_manager.scene().wires().len(), 1);
+
+        // The whole batch is reverted at once, the bindings of the removed component included
+        assert!(history.undo(&mut scene_manager).unwrap());
+        assert_eq!(
+            scene_manager.scene().get_component(0).unwrap().position(),
+            &Vector2::new(0.0, 0.0)
+        );
+        assert_eq!(scene_manager.scene().get_component(0).unwrap().rotation(), 0.0);
+        assert!(scene_manager.scene().get_component(1).is_some());
+        assert_eq!(scene_manager.netlist().ports_of(1), vec![(0, gnd), (1, gnd)]);
+        assert!(scene_manager.scene().wires().is_empty());
+
+        assert!(history.redo(&mut scene_manager).unwrap());
+        assert!(scene_manager.scene().get_component(1).is_none());
+        assert_eq!(scene_manager.scene().wires().len(), 1);
+        assert!(!history.can_redo());
+
+        // A new edit drops the undone ones
+        assert!(history.undo(&mut scene_manager).unwrap());
+        history
+            .execute(
+                &mut scene_manager,
+                Command::SetComponentParams {
+                    id: 0,
+                    params: ComponentParams::Resistance(5.0),
+                },
+            )
+            .unwrap();
+        assert!(!history.can_redo());
+        assert!(history.undo(&mut scene_manager).unwrap());
+        assert_eq!(
+            scene_manager.scene().get_component(0).unwrap().params(),
+            &ComponentParams::Resistance(1e3)
+        );
+
+        assert!(history.undo(&mut scene_manager).unwrap());
+        assert!(history.undo(&mut scene_manager).unwrap());
+        assert_eq!(scene_manager.scene().component_count(), 0);
+        assert!(!history.undo(&mut scene_manager).unwrap());
+    }
+
+    #[test]
+    fn test_constructs_and_groups() {
+        let mut scene_manager = SceneManager::new();
+        let mut history = History::new();
+        let root = scene_manager.hierarchy().root();
+
+        history
+            .execute(
+                &mut scene_manager,
+                Command::AddConstructGroup {
+                    parent: root,
+                    name: "layer".to_string(),
+                    kind: ConstructNodeKind::Layer(0),
+                    transform: Transform::from_translation(Vector2::new(10.0, 0.0)),
+                },
+            )
+            .unwrap();
+        let layer = scene_manager.hierarchy().len() - 1;
+        let crossbar = Crossbar::new(
+            0,
+            vec!["x0".to_string(), "x1".to_string()],
+            vec!["c0".to_string(), "c1".to_string(), "c2".to_string()],
+            2,
+            3,
+            0,
+            1.0,
+            Vector2::zeros(),
+        )
+        .unwrap();
+        history
+            .execute(
+                &mut scene_manager,
+                Command::AddConstruct {
+                    parent: layer,
+                    construct: Box::new(crossbar),
+                },
+            )
+            .unwrap();
+        assert_eq!(scene_manager.scene().component_count(), 6);
+        let x0 = scene_manager.node_id("x0").unwrap();
+        let position = *scene_manager.scene().get_component(0).unwrap().position();
+
+        // Moving the components and then their layer is undone at once
+        history.begin_group();
+        history
+            .execute(
+                &mut scene_manager,
+                Command::TranslateComponents {
+                    ranges: id_ranges(0..6),
+                    offset: Vector2::new(0.0, 3.0),
+                },
+            )
+            .unwrap();
+        history
+            .execute(
+                &mut scene_manager,
+                Command::SetConstructTransform {
+                    node_id: layer,
+                    transform: Transform::from_translation(Vector2::new(20.0, 0.0)),
+                },
+            )
+            .unwrap();
+        history.end_group();
+        let moved = *scene_manager.scene().get_component(0).unwrap().position();
+        assert!((moved - position - Vector2::new(10.0, 3.0)).norm() < 1e-4);
+
+        assert!(history.undo(&mut scene_manager).unwrap());
+        let restored = *scene_manager.scene().get_component(0).unwrap().position();
+        assert!((restored - position).norm() < 1e-4);
+
+        // Removing the crossbar removes its nets, adding it back gives them the same ids
+        assert!(history.undo(&mut scene_manager).unwrap());
+        assert_eq!(scene_manager.scene().component_count(), 0);
+        assert!(scene_manager.node_id("x0").is_none());
+        assert!(scene_manager.constructs().is_empty());
+        assert!(history.redo(&mut scene_manager).unwrap());
+        assert_eq!(scene_manager.node_id("x0"), Some(x0));
+        assert_eq!(scene_manager.hierarchy().construct_of(5), Some(layer + 1));
+
+        assert!(history.undo(&mut scene_manager).unwrap());
+        assert!(history.undo(&mut scene_manager).unwrap());
+        assert!(scene_manager.hierarchy().is_empty());
+    }
+
+    #[test]
+    fn test_id_ranges() {
+        assert_eq!(id_ranges([5, 1, 2, 3, 7, 6, 2]), vec![(1, 3), (5, 7)]);
+        assert!(id_ranges([]).is_empty());
+    }
+
+    #[test]
+    fn test_failed_batch_is_reverted() {
+        let mut scene_manager = SceneManager::new();
+        let mut history = History::new();
+        history.execute(&mut scene_manager, resistor(0, 0.0)).unwrap();
+
+        let edit = Command::Batch(vec![Command::RemoveComponent(0), Command::RemoveWire(3)]);
+        assert!(matches!(
+            history.execute(&mut scene_manager, edit),
+            Err(SceneManagerError::WireNotFound(3))
+        ));
+        assert!(scene_manager.scene().get_component(0).is_some());
+
+        // Only the first edit was recorded
+        assert!(history.undo(&mut scene_manager).unwrap());
+        assert!(!history.can_undo());
+    }
+
+    #[test]
+    fn test_failed_undo_and_redo_are_kept() {
+        let mut scene_manager = SceneManager::new();
+        let mut history = History::new();
+        history.execute(&mut scene_manager, resistor(0, 0.0)).unwrap();
+
+        // Removed behind the back of the history, undoing the addition fails until it is back
+        let add = Command::RemoveComponent(0).apply(&mut scene_manager).unwrap();
+        assert!(history.undo(&mut scene_manager).is_err());
+        assert!(history.can_undo());
+        add.apply(&mut scene_manager).unwrap();
+        assert!(history.undo(&mut scene_manager).unwrap());
+        assert!(scene_manager.scene().get_component(0).is_none());
+
+        // Same for redoing it while the id is taken
+        history.execute(&mut scene_manager, resistor(0, 5.0)).unwrap();
+        history.undo(&mut scene_manager).unwrap();
+        assert!(history.can_redo());
+        resistor(0, 1.0).apply(&mut scene_manager).unwrap();
+        assert!(history.redo(&mut scene_manager).is_err());
+        assert!(history.can_redo());
+        scene_manager.remove_component(0).unwrap();
+        assert!(history.redo(&mut scene_manager).unwrap());
+        assert_eq!(
+            scene_manager.scene().get_component(0).unwrap().position(),
+            &Vector2::new(5.0, 0.0)
+        );
+    }
+}
@@ -0,0 +1,298 @@
+//! Text shown in the scene: the designators of the components (```R1```, ```M23```), the names of the nets, the
+//! indices of the rows and columns of the crossbars and the titles of the layers.
+//!
+//! Labels are placed in scene coordinates and their size is the height of a line of text in scene units, so they
+//! scale with the zoom like the components. A label is hidden once it is smaller than ```MIN_LABEL_PIXELS``` on
+//! screen, so the designators disappear first when zooming out and the layer titles last.
+
+use super::component::DefaultComponentTypes;
+use super::hierarchy::ConstructNodeKind;
+use super::lod;
+use super::scene_manager::{Construct, SceneManager};
+
+use crate::{types::Id, utils::AaBb};
+
+use nalgebra::Vector2;
+
+/// Labels smaller than this on screen, in pixels, are not drawn
+pub const MIN_LABEL_PIXELS: f32 = 6.0;
+
+pub const DESIGNATOR_SIZE: f32 = 0.3;
+pub const NET_NAME_SIZE: f32 = 0.25;
+pub const CROSSBAR_INDEX_SIZE: f32 = 0.5;
+/// Layer titles are a fraction of the width of the layer, so the title of a large layer is readable from afar
+pub const LAYER_TITLE_SIZE_RATIO: f32 = 0.05;
+
+/// Point of the box of a label that is placed at its position
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Anchor {
+    BottomLeft,
+    Bottom,
+    BottomRight,
+    Left,
+    #[default]
+    Center,
+    Right,
+    TopLeft,
+    Top,
+    TopRight,
+}
+
+impl Anchor {
+    /// Offset from the anchor to the bottom left corner of a box of size ```size```
+    pub fn offset(&self, size: &Vector2<f32>) -> Vector2<f32> {
+        let x = match self {
+            Anchor::BottomLeft | Anchor::Left | Anchor::TopLeft => 0.0,
+            Anchor::Bottom | Anchor::Center | Anchor::Top => -size.x / 2.0,
+            Anchor::BottomRight | Anchor::Right | Anchor::TopRight => -size.x,
+        };
+        let y = match self {
+            Anchor::BottomLeft | Anchor::Bottom | Anchor::BottomRight => 0.0,
+            Anchor::Left | Anchor::Center | Anchor::Right => -size.y / 2.0,
+            Anchor::TopLeft | Anchor::Top | Anchor::TopRight => -size.y,
+        };
+
+        Vector2::new(x, y)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LabelKind {
+    Designator,
+    NetName,
+    CrossbarIndex,
+    LayerTitle,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Label {
+    text: String,
+    position: Vector2<f32>,
+    /// Height of a line of text in scene units
+    size: f32,
+    anchor: Anchor,
+    kind: LabelKind,
+}
+
+impl Label {
+    pub fn new(text: String, position: Vector2<f32>, size: f32, anchor: Anchor, kind: LabelKind) -> Self {
+        Self {
+            text,
+            position,
+            size,
+            anchor,
+            kind,
+        }
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+
+    pub fn position(&self) -> &Vector2<f32> {
+        &self.position
+    }
+
+    pub fn size(&self) -> f32 {
+        self.size
+    }
+
+    pub fn anchor(&self) -> Anchor {
+        self.anchor
+    }
+
+    pub fn kind(&self) -> LabelKind {
+        self.kind
+    }
+
+    /// Whether the label is large enough to be read with ```pixels_per_unit``` screen pixels per scene unit
+    pub fn is_readable(&self, pixels_per_unit: f32) -> bool {
+        self.size * pixels_per_unit >= MIN_LABEL_PIXELS
+    }
+}
+
+/// Labels of every component, net, crossbar and layer of the scene
+pub fn scene_labels(scene_manager: &SceneManager) -> Vec<Label> {
+    let mut labels = designators(scene_manager);
+    labels.extend(net_names(scene_manager));
+    labels.extend(crossbar_indices(scene_manager));
+    labels.extend(layer_titles(scene_manager));
+    labels
+}
+
+/// Designator of every component above its box, e.g. ```R12``` for the resistor with id 12
+fn designators(scene_manager: &SceneManager) -> Vec<Label> {
+    let scene = scene_manager.scene();
+    let Some(chunks) = scene.components().get(&0) else {
+        return Vec::new();
+    };
+
+    chunks
+        .values()
+        .flatten()
+        .filter(|component| !lod::is_aggregate_type(component.ty()))
+        .map(|component| {
+            let aabb = scene.component_aabb(component);
+            let position = Vector2::new((aabb.min.x + aabb.max.x) / 2.0, aabb.max.y + DESIGNATOR_SIZE * 0.2);
+            let text = format!(
+                "{}{}",
+                DefaultComponentTypes::designator_prefix(component.ty()),
+                component.id()
+            );
+            Label::new(text, position, DESIGNATOR_SIZE, Anchor::Bottom, LabelKind::Designator)
+        })
+        .collect()
+}
+
+/// Name of every net but ground, next to the first port bound to it
+fn net_names(scene_manager: &SceneManager) -> Vec<Label> {
+    let scene = scene_manager.scene();
+    let netlist = scene_manager.netlist();
+    let ground = scene_manager.ground();
+
+    netlist
+        .nets()
+        .filter(|net| *net != ground)
+        .filter_map(|net| {
+            let name = netlist.net_name(net).filter(|name| !name.is_empty())?;
+            let (component_id, port) = netlist.components_on(net).into_iter().min()?;
+            let component = scene.get_component(component_id)?;
+            let position = component
+                .ports(scene.primitives())
+                .get(port)
+                .map(|port| component.port_position(port))?;
+
+            Some(Label::new(
+                name.to_string(),
+                position,
+                NET_NAME_SIZE,
+                Anchor::BottomLeft,
+                LabelKind::NetName,
+            ))
+        })
+        .collect()
+}
+
+/// Index of every row at the left of the crossbars and of every column below them
+fn crossbar_indices(scene_manager: &SceneManager) -> Vec<Label> {
+    let scene = scene_manager.scene();
+    let aabb_of = |id: Id| scene.get_component(id).map(|component| scene.component_aabb(component));
+    let gap = CROSSBAR_INDEX_SIZE * 0.5;
+
+    let mut labels = Vec::new();
+    for crossbar in scene_manager.crossbars() {
+        let first_id = crossbar.components_id_range().0;
+        let (rows, cols) = (crossbar.rows(), crossbar.cols());
+        let id = |row: u32, col: u32| first_id + row + col * rows;
+
+        for row in 0..rows {
+            if let Some(aabb) = aabb_of(id(row, 0)) {
+                let position = Vector2::new(aabb.min.x - gap, (aabb.min.y + aabb.max.y) / 2.0);
+                labels.push(Label::new(
+                    format!("{row}"),
+                    position,
+                    CROSSBAR_INDEX_SIZE,
+                    Anchor::Right,
+                    LabelKind::CrossbarIndex,
+                ));
+            }
+        }
+        for col in 0..cols {
+            if let Some(aabb) = rows.checked_sub(1).and_then(|last_row| aabb_of(id(last_row, col))) {
+                let position = Vector2::new((aabb.min.x + aabb.max.x) / 2.0, aabb.min.y - gap);
+                labels.push(Label::new(
+                    format!("{col}"),
+                    position,
+                    CROSSBAR_INDEX_SIZE,
+                    Anchor::Top,
+                    LabelKind::CrossbarIndex,
+                ));
+            }
+        }
+    }
+
+    labels
+}
+
+/// Name of every layer above the box of its components
+fn layer_titles(scene_manager: &SceneManager) -> Vec<Label> {
+    let scene = scene_manager.scene();
+    let hierarchy = scene_manager.hierarchy();
+
+    hierarchy
+        .nodes()
+        .filter(|(_, node)| matches!(node.kind(), ConstructNodeKind::Layer(_)))
+        .filter_map(|(node_id, node)| {
+            let aabbs = hierarchy
+                .component_ranges(node_id)
+                .into_iter()
+                .flat_map(|(first_id, last_id)| first_id..=last_id)
+                .filter_map(|id| scene.get_component(id))
+                .map(|component| scene.component_aabb(component));
+            let aabb = AaBb::from_points(aabbs.flat_map(|aabb| [aabb.min, aabb.max]))?;
+
+            let size = ((aabb.max.x - aabb.min.x) * LAYER_TITLE_SIZE_RATIO).max(CROSSBAR_INDEX_SIZE * 2.0);
+            let position = Vector2::new((aabb.min.x + aabb.max.x) / 2.0, aabb.max.y + size * 0.5);
+            Some(Label::new(
+                node.name().to_string(),
+                position,
+                size,
+                Anchor::Bottom,
+                LabelKind::LayerTitle,
+            ))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod labels_test {
+    use super::*;
+    use crate::scene::{hierarchy::Transform, scene_manager::Crossbar};
+
+    #[test]
+    fn test_scene_labels() {
+        let mut scene_manager = SceneManager::new();
+        let layer = scene_manager
+            .add_construct_group(
+                scene_manager.hierarchy().root(),
+                "Layer 0".to_string(),
+                ConstructNodeKind::Layer(0),
+                Transform::IDENTITY,
+            )
+            .unwrap();
+        let crossbar = Crossbar::new(
+            0,
+            vec!["x0".to_string(), "x1".to_string()],
+            vec!["c0".to_string(), String::new(), "c2".to_string()],
+            2,
+            3,
+            0,
+            2.0,
+            Vector2::zeros(),
+        )
+        .unwrap();
+        scene_manager.add_construct_to(layer, Box::new(crossbar)).unwrap();
+
+        let labels = scene_labels(&scene_manager);
+        let count = |kind: LabelKind| labels.iter().filter(|label| label.kind() == kind).count();
+        assert_eq!(count(LabelKind::Designator), 6);
+        // The unnamed column is left unconnected
+        assert_eq!(count(LabelKind::NetName), 4);
+        assert_eq!(count(LabelKind::CrossbarIndex), 5);
+        assert_eq!(count(LabelKind::LayerTitle), 1);
+        assert!(labels.iter().any(|label| label.text() == "M5"));
+        assert!(labels.iter().any(|label| label.text() == "Layer 0"));
+
+        let designator = labels.iter().find(|label| label.text() == "M0").unwrap();
+        assert!(designator.is_readable(MIN_LABEL_PIXELS / DESIGNATOR_SIZE));
+        assert!(!designator.is_readable(MIN_LABEL_PIXELS / DESIGNATOR_SIZE * 0.9));
+    }
+
+    #[test]
+    fn test_anchor() {
+        let size = Vector2::new(4.0, 2.0);
+        assert_eq!(Anchor::BottomLeft.offset(&size), Vector2::new(0.0, 0.0));
+        assert_eq!(Anchor::Center.offset(&size), Vector2::new(-2.0, -1.0));
+        assert_eq!(Anchor::TopRight.offset(&size), Vector2::new(-4.0, -2.0));
+    }
+}
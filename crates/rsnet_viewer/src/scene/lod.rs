@@ -0,0 +1,379 @@
+//! Coarser levels of the scene, shown when zoomed out.
+//!
+//! The components added at step 0 are aggregated in square cells at every step up to ```MAX_LOD_STEP```. A cell
+//! whose components are mostly conductances (the memristors of a crossbar) becomes a heatmap cell colored by its mean
+//! conductance, so a crossbar collapses into one block showing its weights. Any other cell becomes a density tile
+//! colored by its number of components. The statistics of each cell are updated with every edit, so only the cells
+//! of the edited components are aggregated again.
+
+use super::component::{Component, ComponentParams, Range};
+use super::types::{ChunkId, ChunkStepIdx, ChunkedStorage, ComponentType, DirtyChunks};
+use super::utils::chunk_id_from_position;
+
+use crate::{
+    app::utils::chunk_size_from_step_idx,
+    renderer::primitives::{
+        common::{DENSITY_PRIMITIVES, HEATMAP_PRIMITIVES},
+        ComponentTyPrimitives,
+    },
+    types::Id,
+};
+
+use nalgebra::Vector2;
+use std::collections::HashMap;
+
+/// Coarsest step that is generated, the camera does not zoom out further
+pub const MAX_LOD_STEP: ChunkStepIdx = 3;
+
+/// Number of colors of the heatmap and of the density tiles
+pub const N_LOD_BUCKETS: u32 = 8;
+
+/// Type of the heatmap cell with the lowest conductance, the next ```N_LOD_BUCKETS``` types are the other colors
+pub const HEATMAP_TY_START: ComponentType = 1000;
+
+/// Type of the sparsest density tile, the next ```N_LOD_BUCKETS``` types are the denser ones
+pub const DENSITY_TY_START: ComponentType = 1100;
+
+/// Position of a cell in the grid of a step, the cell ```(i, j)``` spans ```[i, i + 1) x [j, j + 1)``` cell sizes
+pub type CellId = (i32, i32);
+
+/// Whether ```ty``` is the type of a generated heatmap cell or density tile
+pub fn is_aggregate_type(ty: ComponentType) -> bool {
+    (HEATMAP_TY_START..HEATMAP_TY_START + N_LOD_BUCKETS).contains(&ty)
+        || (DENSITY_TY_START..DENSITY_TY_START + N_LOD_BUCKETS).contains(&ty)
+}
+
+/// Primitives of the aggregate types, they are shown at any distance
+pub fn primitives() -> Vec<(ComponentType, Vec<(&'static ComponentTyPrimitives, f32)>)> {
+    let heatmap = HEATMAP_PRIMITIVES
+        .iter()
+        .enumerate()
+        .map(|(i, primitives)| (HEATMAP_TY_START + i as u32, vec![(primitives, f32::MAX)]));
+    let density = DENSITY_PRIMITIVES
+        .iter()
+        .enumerate()
+        .map(|(i, primitives)| (DENSITY_TY_START + i as u32, vec![(primitives, f32::MAX)]));
+
+    heatmap.chain(density).collect()
+}
+
+/// Side of the cells of a step, a tenth of its chunks so every chunk holds 10x10 cells
+pub fn cell_size(chunk_step_idx: ChunkStepIdx) -> f32 {
+    chunk_size_from_step_idx(chunk_step_idx + 1) / 10.0
+}
+
+pub fn cell_from_position(position: &Vector2<f32>, cell_size: f32) -> CellId {
+    (
+        (position.x / cell_size).floor() as i32,
+        (position.y / cell_size).floor() as i32,
+    )
+}
+
+fn cell_center(cell: CellId, cell_size: f32) -> Vector2<f32> {
+    Vector2::new(cell.0 as f32 + 0.5, cell.1 as f32 + 0.5) * cell_size
+}
+
+/// Statistics of the components inside a cell
+#[derive(Debug, Default, Clone, Copy)]
+pub struct LodCell {
+    /// Id of the aggregate of the cell, unique among the aggregates of a step
+    id: Id,
+    n_components: u32,
+    n_conductances: u32,
+    /// [S]
+    conductance_sum: f64,
+}
+
+impl LodCell {
+    fn new(id: Id) -> Self {
+        Self {
+            id,
+            ..Default::default()
+        }
+    }
+
+    pub fn n_components(&self) -> u32 {
+        self.n_components
+    }
+
+    /// Mean conductance of the components with one, ```None``` if there are none
+    pub fn mean_conductance(&self) -> Option<f64> {
+        (self.n_conductances > 0).then(|| self.conductance_sum / self.n_conductances as f64)
+    }
+
+    fn add(&mut self, component: &Component) {
+        self.n_components += 1;
+        if let ComponentParams::Conductance(g) = component.params() {
+            self.n_conductances += 1;
+            self.conductance_sum += g;
+        }
+    }
+
+    fn remove(&mut self, component: &Component) {
+        self.n_components -= 1;
+        if let ComponentParams::Conductance(g) = component.params() {
+            self.n_conductances -= 1;
+            self.conductance_sum -= g;
+        }
+    }
+
+    /// The component drawn in place of the cell
+    fn aggregate(&self, cell: CellId, cell_size: f32, heat_range: (f64, f64)) -> Option<Component> {
+        if self.n_components == 0 {
+            return None;
+        }
+
+        let (ty, params) = match self.mean_conductance() {
+            Some(g) if 2 * self.n_conductances >= self.n_components => {
+                let bucket = (heat(g, heat_range) * (N_LOD_BUCKETS - 1) as f64).round() as u32;
+
+                (HEATMAP_TY_START + bucket, ComponentParams::Conductance(g))
+            }
+            _ => {
+                // One more color every time the number of components doubles
+                let bucket = self.n_components.ilog2().min(N_LOD_BUCKETS - 1);
+
+                (DENSITY_TY_START + bucket, ComponentParams::None)
+            }
+        };
+
+        let mut component = Component::new(self.id, 0, cell_center(cell, cell_size), 0.0, ty);
+        component.set_scale(cell_size);
+        component.set_range(Range::Far);
+        component.set_params(params);
+
+        Some(component)
+    }
+}
+
+/// Position of ```g``` in the heatmap, from 0 (```g_min```) to 1 (```g_max```). The scale is logarithmic, or linear when
+/// the range starts at 0 (a logarithmic scale would put every conductance at the end).
+fn heat(g: f64, (g_min, g_max): (f64, f64)) -> f64 {
+    if g_max <= g_min {
+        return 0.0;
+    }
+
+    let t = if g_min > 0.0 {
+        (g.max(f64::MIN_POSITIVE) / g_min).ln() / (g_max / g_min).ln()
+    } else {
+        (g - g_min) / (g_max - g_min)
+    };
+    t.clamp(0.0, 1.0)
+}
+
+/// The generated levels of a scene, from step 1 to ```MAX_LOD_STEP```
+#[derive(Debug)]
+pub struct Lods {
+    cells: HashMap<ChunkStepIdx, HashMap<CellId, LodCell>>,
+    /// Id given to the next cell created at each step
+    next_ids: HashMap<ChunkStepIdx, Id>,
+    components: ChunkedStorage<Component>,
+    /// Conductances mapped to the first and last color of the heatmap [S]
+    heat_range: (f64, f64),
+}
+
+impl Default for Lods {
+    fn default() -> Self {
+        Self {
+            cells: HashMap::new(),
+            next_ids: HashMap::new(),
+            components: HashMap::new(),
+            heat_range: (1e-6, 1e-4),
+        }
+    }
+}
+
+impl Lods {
+    pub fn components(&self) -> &ChunkedStorage<Component> {
+        &self.components
+    }
+
+    pub fn cells(&self, chunk_step_idx: ChunkStepIdx) -> Option<&HashMap<CellId, LodCell>> {
+        self.cells.get(&chunk_step_idx)
+    }
+
+    pub fn heat_range(&self) -> (f64, f64) {
+        self.heat_range
+    }
+
+    /// Sets the conductances of the first and last heatmap colors, every cell is colored again
+    pub fn set_heat_range(&mut self, g_min: f64, g_max: f64, dirty_chunks: &mut DirtyChunks) {
+        self.heat_range = (g_min, g_max);
+
+        let cells: Vec<(ChunkStepIdx, CellId)> = self
+            .cells
+            .iter()
+            .flat_map(|(step, cells)| cells.keys().map(|cell| (*step, *cell)))
+            .collect();
+        for (chunk_step_idx, cell) in cells {
+            self.update_aggregate(chunk_step_idx, cell, dirty_chunks);
+        }
+    }
+
+    /// Accounts a component added at step 0
+    pub fn add(&mut self, component: &Component, dirty_chunks: &mut DirtyChunks) {
+        self.edit(component, dirty_chunks, LodCell::add);
+    }
+
+    /// Accounts a component removed from step 0
+    pub fn remove(&mut self, component: &Component, dirty_chunks: &mut DirtyChunks) {
+        self.edit(component, dirty_chunks, LodCell::remove);
+    }
+
+    fn edit(&mut self, component: &Component, dirty_chunks: &mut DirtyChunks, f: fn(&mut LodCell, &Component)) {
+        for chunk_step_idx in 1..=MAX_LOD_STEP {
+            let cell = cell_from_position(component.position(), cell_size(chunk_step_idx));
+            let next_id = self.next_ids.entry(chunk_step_idx).or_default();
+            let lod_cell = self
+                .cells
+                .entry(chunk_step_idx)
+                .or_default()
+                .entry(cell)
+                .or_insert_with(|| {
+                    *next_id += 1;
+                    LodCell::new(*next_id - 1)
+                });
+            f(lod_cell, component);
+            self.update_aggregate(chunk_step_idx, cell, dirty_chunks);
+        }
+    }
+
+    /// Replaces the aggregate of a cell with one built from its current statistics
+    fn update_aggregate(&mut self, chunk_step_idx: ChunkStepIdx, cell: CellId, dirty_chunks: &mut DirtyChunks) {
+        let cell_size = cell_size(chunk_step_idx);
+        let cells = self.cells.entry(chunk_step_idx).or_default();
+        let Some(lod_cell) = cells.get(&cell) else {
+            return;
+        };
+        let id = lod_cell.id;
+        let aggregate = lod_cell.aggregate(cell, cell_size, self.heat_range);
+        if aggregate.is_none() {
+            cells.remove(&cell);
+        }
+
+        let chunk_id: ChunkId = chunk_id_from_position(
+            &cell_center(cell, cell_size),
+            chunk_size_from_step_idx(chunk_step_idx + 1),
+        );
+        let chunks = self.components.entry(chunk_step_idx).or_default();
+        let components = chunks.entry(chunk_id).or_default();
+        match (components.binary_search_by_key(&id, |c| c.id()), aggregate) {
+            (Ok(pos), Some(aggregate)) => components[pos] = aggregate,
+            (Ok(pos), None) => {
+                components.remove(pos);
+            }
+            (Err(pos), Some(aggregate)) => components.insert(pos, aggregate),
+            (Err(_), None) => (),
+        }
+        if components.is_empty() {
+            chunks.remove(&chunk_id);
+        }
+
+        dirty_chunks.entry(chunk_step_idx).or_default().insert(chunk_id);
+    }
+}
+
+#[cfg(test)]
+mod lod_test {
+    use super::*;
+    use crate::scene::{component::DefaultComponentTypes, Scene};
+
+    #[test]
+    fn test_lods() {
+        let mut scene = Scene::new_empty();
+
+        // A 20x20 crossbar, with the conductance growing along the columns
+        for i in 0..20 {
+            for j in 0..20 {
+                let position = Vector2::new(j as f32, i as f32) + Vector2::new(0.5, 0.5);
+                let id = i * 20 + j;
+                let mut component = Component::new(id, 0, position, 0.0, DefaultComponentTypes::Memristor as u32);
+                component.set_params(ComponentParams::Conductance(if j < 10 { 1e-6 } else { 1e-4 }));
+                scene.add_component(0, component).unwrap();
+            }
+        }
+        // An op-amp far from it
+        scene
+            .add_component(
+                0,
+                Component::new(
+                    400,
+                    0,
+                    Vector2::new(155.0, 5.0),
+                    0.0,
+                    DefaultComponentTypes::OpAmp as u32,
+                ),
+            )
+            .unwrap();
+
+        let level: Vec<&Component> = scene.level(1).unwrap().values().flatten().collect();
+        assert_eq!(level.len(), 5);
+        assert_eq!(level.iter().filter(|c| c.ty() == HEATMAP_TY_START).count(), 2);
+        assert_eq!(
+            level
+                .iter()
+                .filter(|c| c.ty() == HEATMAP_TY_START + N_LOD_BUCKETS - 1)
+                .count(),
+            2
+        );
+        assert_eq!(level.iter().filter(|c| c.ty() == DENSITY_TY_START).count(), 1);
+        assert!(level.iter().all(|c| is_aggregate_type(c.ty()) && c.scale() == 10.0));
+
+        // The whole crossbar falls in one cell of step 2, with the mean conductance
+        let cells = scene.lods().cells(2).unwrap();
+        assert_eq!(cells[&(0, 0)].n_components(), 400);
+        assert!((cells[&(0, 0)].mean_conductance().unwrap() - 0.5 * (1e-6 + 1e-4)).abs() < 1e-12);
+
+        // Edits only update the cells they touch
+        scene.take_dirty_chunks();
+        scene.remove_component(400).unwrap();
+        assert!(scene
+            .level(1)
+            .unwrap()
+            .values()
+            .flatten()
+            .all(|c| c.ty() != DENSITY_TY_START));
+        assert_eq!(scene.take_dirty_chunks()[&1].len(), 1);
+
+        scene
+            .set_component_params(0, ComponentParams::Conductance(1e-4))
+            .unwrap();
+        let g = scene.lods().cells(1).unwrap()[&(0, 0)].mean_conductance().unwrap();
+        assert!((g - (99.0 * 1e-6 + 1e-4) / 100.0).abs() < 1e-12);
+
+        for id in 0..400 {
+            scene.remove_component(id).unwrap();
+        }
+        assert!((1..=MAX_LOD_STEP).all(|step| scene.level(step).unwrap().is_empty()));
+    }
+
+    #[test]
+    fn test_heat() {
+        let close = |a: f64, b: f64| (a - b).abs() < 1e-12;
+
+        assert!(close(heat(1e-5, (1e-6, 1e-4)), 0.5));
+        assert!(close(heat(1e-7, (1e-6, 1e-4)), 0.0));
+        // A range that starts at 0 is linear
+        assert!(close(heat(0.0, (0.0, 1e-4)), 0.0));
+        assert!(close(heat(2.5e-5, (0.0, 1e-4)), 0.25));
+        assert!(close(heat(1e-4, (0.0, 1e-4)), 1.0));
+        assert!(close(heat(1e-5, (1e-4, 1e-4)), 0.0));
+    }
+
+    #[test]
+    fn test_aggregate_ids() {
+        let mut scene = Scene::new_empty();
+
+        // Cells 65536 cells apart used to share the id of their aggregate
+        let far = cell_size(1) * 65536.0;
+        for (id, x) in [(0, 0.5), (1, far + 0.5)] {
+            let component = Component::new(id, 0, Vector2::new(x, 0.5), 0.0, DefaultComponentTypes::OpAmp as u32);
+            scene.add_component(0, component).unwrap();
+        }
+
+        let mut ids: Vec<Id> = scene.level(1).unwrap().values().flatten().map(|c| c.id()).collect();
+        assert_eq!(ids.len(), 2);
+        ids.dedup();
+        assert_eq!(ids.len(), 2);
+    }
+}
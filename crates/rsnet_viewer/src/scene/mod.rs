@@ -2,6 +2,14 @@ pub mod scene;
 pub use scene::Scene;
 
 pub mod component;
+pub mod format;
+pub mod hierarchy;
+pub mod history;
+pub mod labels;
+pub mod lod;
+pub mod netlist;
+pub mod peripherals;
+pub mod router;
 pub mod scene_manager;
 pub mod shared;
 pub mod types;
@@ -0,0 +1,332 @@
+use std::collections::HashMap;
+
+use crate::types::{Id, NodeId};
+
+use petgraph::stable_graph::{NodeIndex, StableUnGraph};
+use petgraph::visit::{EdgeRef, IntoEdgeReferences};
+use thiserror::Error;
+
+#[derive(Error, Debug)]
+pub enum NetlistError {
+    #[error("Node {0} does not exist")]
+    NodeNotFound(NodeId),
+    #[error("Component {0} is not part of the netlist")]
+    ComponentNotFound(Id),
+    #[error("Node {0} already exists")]
+    NodeAlreadyExists(NodeId),
+    #[error("The name {0} already refers to a node")]
+    NameAlreadyExists(String),
+}
+
+/// Vertex of the netlist graph. The graph is bipartite, every edge binds a port of a component to a net.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum NetlistVertex {
+    Net(NodeId),
+    Component(Id),
+}
+
+/// Connectivity of the circuit: nets (nodes) identified by a ```NodeId``` and a name, and the ports of the components
+/// bound to them. Ports are identified by their index in the ports of the component type.
+#[derive(Debug, Clone, Default)]
+pub struct Netlist {
+    /// Edges are weighted with the index of the port of the component
+    graph: StableUnGraph<NetlistVertex, usize>,
+
+    nets: HashMap<NodeId, NodeIndex>,
+    components: HashMap<Id, NodeIndex>,
+
+    /// Every name that refers to a net, merged nets keep the names of both
+    net_names: HashMap<String, NodeId>,
+    /// Name given to the net when it was created
+    names: HashMap<NodeId, String>,
+
+    last_net_id: Option<NodeId>,
+}
+
+impl Netlist {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn graph(&self) -> &StableUnGraph<NetlistVertex, usize> {
+        &self.graph
+    }
+
+    /// Returns the net called ```name```, creating it if it does not exist
+    pub fn add_net(&mut self, name: String) -> NodeId {
+        if let Some(node_id) = self.net_names.get(&name) {
+            return *node_id;
+        }
+
+        let node_id = self.last_net_id.map_or(0, |id| id + 1);
+        let index = self.graph.add_node(NetlistVertex::Net(node_id));
+
+        self.nets.insert(node_id, index);
+        self.net_names.insert(name.clone(), node_id);
+        self.names.insert(node_id, name);
+        self.last_net_id = Some(node_id);
+
+        node_id
+    }
+
+    /// Creates the net ```node_id``` called ```name```, used to restore a saved netlist. Nets created later get
+    /// higher ids.
+    pub fn insert_net(&mut self, node_id: NodeId, name: String) -> Result<(), NetlistError> {
+        if self.nets.contains_key(&node_id) {
+            return Err(NetlistError::NodeAlreadyExists(node_id));
+        }
+        if self.net_names.contains_key(&name) {
+            return Err(NetlistError::NameAlreadyExists(name));
+        }
+
+        let index = self.graph.add_node(NetlistVertex::Net(node_id));
+        self.nets.insert(node_id, index);
+        self.net_names.insert(name.clone(), node_id);
+        self.names.insert(node_id, name);
+        self.reserve_net_ids(node_id);
+
+        Ok(())
+    }
+
+    /// Makes ```name``` refer to ```node_id```, as it happens with the names of a merged net
+    pub fn add_alias(&mut self, name: String, node_id: NodeId) -> Result<(), NetlistError> {
+        if !self.nets.contains_key(&node_id) {
+            return Err(NetlistError::NodeNotFound(node_id));
+        }
+        if self.net_names.contains_key(&name) {
+            return Err(NetlistError::NameAlreadyExists(name));
+        }
+
+        self.net_names.insert(name, node_id);
+        Ok(())
+    }
+
+    /// Every name that refers to a net, including the names of merged nets
+    pub fn net_names(&self) -> impl Iterator<Item = (&str, NodeId)> + '_ {
+        self.net_names.iter().map(|(name, node_id)| (name.as_str(), *node_id))
+    }
+
+    /// Highest id given to a net, merged nets included
+    pub fn last_net_id(&self) -> Option<NodeId> {
+        self.last_net_id
+    }
+
+    /// Makes sure that new nets get ids higher than ```node_id```
+    pub fn reserve_net_ids(&mut self, node_id: NodeId) {
+        self.last_net_id = Some(self.last_net_id.map_or(node_id, |id| id.max(node_id)));
+    }
+
+    pub fn net_id(&self, name: &str) -> Option<NodeId> {
+        self.net_names.get(name).copied()
+    }
+
+    pub fn net_name(&self, node_id: NodeId) -> Option<&str> {
+        self.names.get(&node_id).map(|name| name.as_str())
+    }
+
+    pub fn contains_net(&self, node_id: NodeId) -> bool {
+        self.nets.contains_key(&node_id)
+    }
+
+    pub fn nets(&self) -> impl Iterator<Item = NodeId> + '_ {
+        self.nets.keys().copied()
+    }
+
+    pub fn net_count(&self) -> usize {
+        self.nets.len()
+    }
+
+    pub fn components(&self) -> impl Iterator<Item = Id> + '_ {
+        self.components.keys().copied()
+    }
+
+    pub fn contains_component(&self, id: Id) -> bool {
+        self.components.contains_key(&id)
+    }
+
+    /// Number of bound ports
+    pub fn binding_count(&self) -> usize {
+        self.graph.edge_count()
+    }
+
+    /// Every bound port as ```(component, port, net)```
+    pub fn bindings(&self) -> impl Iterator<Item = (Id, usize, NodeId)> + '_ {
+        self.graph
+            .edge_references()
+            .filter_map(|edge| match (self.graph[edge.source()], self.graph[edge.target()]) {
+                (NetlistVertex::Component(id), NetlistVertex::Net(net))
+                | (NetlistVertex::Net(net), NetlistVertex::Component(id)) => Some((id, *edge.weight(), net)),
+                _ => None,
+            })
+    }
+
+    /// Binds the port ```port``` of ```component``` to ```net```, replacing its previous binding
+    pub fn bind(&mut self, component: Id, port: usize, net: NodeId) -> Result<(), NetlistError> {
+        let net_index = *self.nets.get(&net).ok_or(NetlistError::NodeNotFound(net))?;
+        let component_index = *self
+            .components
+            .entry(component)
+            .or_insert_with(|| self.graph.add_node(NetlistVertex::Component(component)));
+
+        let previous = self
+            .graph
+            .edges(component_index)
+            .find(|edge| *edge.weight() == port)
+            .map(|edge| edge.id());
+        if let Some(edge) = previous {
+            self.graph.remove_edge(edge);
+        }
+
+        self.graph.add_edge(component_index, net_index, port);
+        Ok(())
+    }
+
+    /// Nets of the bound ports of ```component```, sorted by port
+    pub fn ports_of(&self, component: Id) -> Vec<(usize, NodeId)> {
+        let Some(index) = self.components.get(&component) else {
+            return Vec::new();
+        };
+
+        let mut ports: Vec<(usize, NodeId)> = self
+            .graph
+            .edges(*index)
+            .filter_map(|edge| match self.graph[edge.target()] {
+                NetlistVertex::Net(net) => Some((*edge.weight(), net)),
+                NetlistVertex::Component(_) => None,
+            })
+            .collect();
+        ports.sort();
+
+        ports
+    }
+
+    /// Net bound to the port ```port``` of ```component```
+    pub fn net_of(&self, component: Id, port: usize) -> Option<NodeId> {
+        self.ports_of(component)
+            .into_iter()
+            .find_map(|(p, net)| (p == port).then_some(net))
+    }
+
+    /// Components with a port bound to ```net```, with the index of the port, sorted by component
+    pub fn components_on(&self, net: NodeId) -> Vec<(Id, usize)> {
+        let Some(index) = self.nets.get(&net) else {
+            return Vec::new();
+        };
+
+        let mut components: Vec<(Id, usize)> = self
+            .graph
+            .edges(*index)
+            .filter_map(|edge| match self.graph[edge.target()] {
+                NetlistVertex::Component(id) => Some((id, *edge.weight())),
+                NetlistVertex::Net(_) => None,
+            })
+            .collect();
+        components.sort();
+
+        components
+    }
+
+    /// Moves every port bound to ```from``` to ```into``` and removes ```from```, its names now refer to ```into```
+    pub fn merge(&mut self, into: NodeId, from: NodeId) -> Result<(), NetlistError> {
+        let into_index = *self.nets.get(&into).ok_or(NetlistError::NodeNotFound(into))?;
+        if into == from {
+            return Ok(());
+        }
+        let from_index = self.nets.remove(&from).ok_or(NetlistError::NodeNotFound(from))?;
+
+        let bindings: Vec<(NodeIndex, usize)> = self
+            .graph
+            .edges(from_index)
+            .map(|edge| (edge.target(), *edge.weight()))
+            .collect();
+        for (component_index, port) in bindings {
+            self.graph.add_edge(component_index, into_index, port);
+        }
+        self.graph.remove_node(from_index);
+
+        for node_id in self.net_names.values_mut() {
+            if *node_id == from {
+                *node_id = into;
+            }
+        }
+        self.names.remove(&from);
+
+        Ok(())
+    }
+
+    /// Removes the nets with an id from ```first``` on and the bindings to them, the nets created next get those ids
+    /// again
+    pub fn remove_nets_from(&mut self, first: NodeId) {
+        let removed: Vec<NodeId> = self.nets.keys().copied().filter(|node_id| *node_id >= first).collect();
+        for node_id in removed {
+            if let Some(index) = self.nets.remove(&node_id) {
+                self.graph.remove_node(index);
+            }
+            self.names.remove(&node_id);
+        }
+        self.net_names.retain(|_, node_id| *node_id < first);
+
+        if self.last_net_id.is_some_and(|id| id >= first) {
+            self.last_net_id = first.checked_sub(1);
+        }
+    }
+
+    /// Removes ```component``` and the bindings of its ports
+    pub fn remove_component(&mut self, component: Id) -> Result<(), NetlistError> {
+        let index = self
+            .components
+            .remove(&component)
+            .ok_or(NetlistError::ComponentNotFound(component))?;
+        self.graph.remove_node(index);
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod netlist_test {
+    use super::*;
+
+    #[test]
+    fn test_bind_and_query() {
+        let mut netlist = Netlist::new();
+        let a = netlist.add_net("a".to_string());
+        let b = netlist.add_net("b".to_string());
+        assert_eq!(netlist.add_net("a".to_string()), a);
+
+        netlist.bind(0, 0, a).unwrap();
+        netlist.bind(0, 1, b).unwrap();
+        netlist.bind(1, 0, b).unwrap();
+
+        assert_eq!(netlist.ports_of(0), vec![(0, a), (1, b)]);
+        assert_eq!(netlist.components_on(b), vec![(0, 1), (1, 0)]);
+
+        // Binding a port again moves it
+        netlist.bind(0, 1, a).unwrap();
+        assert_eq!(netlist.components_on(b), vec![(1, 0)]);
+        assert_eq!(netlist.net_of(0, 1), Some(a));
+
+        assert!(matches!(netlist.bind(2, 0, 42), Err(NetlistError::NodeNotFound(42))));
+    }
+
+    #[test]
+    fn test_merge() {
+        let mut netlist = Netlist::new();
+        let a = netlist.add_net("a".to_string());
+        let b = netlist.add_net("b".to_string());
+        netlist.bind(0, 0, a).unwrap();
+        netlist.bind(1, 1, b).unwrap();
+
+        netlist.merge(a, b).unwrap();
+
+        assert!(!netlist.contains_net(b));
+        assert_eq!(netlist.net_id("b"), Some(a));
+        assert_eq!(netlist.components_on(a), vec![(0, 0), (1, 1)]);
+        assert_eq!(netlist.ports_of(1), vec![(1, a)]);
+        // New nets do not reuse the id of the merged one
+        assert_ne!(netlist.add_net("c".to_string()), b);
+
+        netlist.remove_component(0).unwrap();
+        assert_eq!(netlist.components_on(a), vec![(1, 1)]);
+    }
+}
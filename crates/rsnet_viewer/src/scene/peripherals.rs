@@ -0,0 +1,844 @@
+use std::any::Any;
+
+use super::component::{Component, ComponentParams, DefaultComponentTypes};
+use super::format::Value;
+use super::scene_manager::{rename_nodes, shift_id_range, Construct, SceneManager, SceneManagerError};
+
+use crate::types::Id;
+
+use rsnet_sim::mna::{Element, ElementKind};
+
+use nalgebra::Vector2;
+
+/// Saturation current of the diodes of the ReLU blocks. [A]
+const DIODE_SATURATION_CURRENT: f64 = 1e-14;
+
+/// Parameters of the circuitry around the crossbars, shared by all the layers of a network.
+///
+/// Signals between layers are voltages, a value `x` of the network is represented by `x * read_voltage`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PeripheralConfig {
+    /// Voltage that represents a unit input. [V]
+    pub read_voltage: f64,
+    /// Resolution of the input DACs, `None` for ideal converters.
+    pub dac_bits: Option<u32>,
+    /// Resolution of the output ADCs, `None` for ideal converters.
+    pub adc_bits: Option<u32>,
+    /// Largest magnitude of the values converted by the DACs and ADCs, only used when they have a resolution.
+    pub full_scale: f64,
+    /// Output resistance of the DACs. [Ohm]
+    pub driver_resistance: f64,
+    /// Input resistance of the ADCs. [Ohm]
+    pub adc_input_resistance: f64,
+    /// Resistance that loads the output of the ReLU blocks. [Ohm]
+    pub relu_load_resistance: f64,
+    /// Open loop gain of the op-amps
+    pub op_amp_gain: f64,
+}
+
+impl Default for PeripheralConfig {
+    fn default() -> Self {
+        Self {
+            read_voltage: 0.2,
+            dac_bits: None,
+            adc_bits: None,
+            full_scale: 1.0,
+            driver_resistance: 1.0,
+            adc_input_resistance: 1e9,
+            relu_load_resistance: 1e5,
+            op_amp_gain: 1e6,
+        }
+    }
+}
+
+/// Rounds `value` to the closest of the evenly spaced signed levels of a `bits` converter spanning
+/// `[-full_scale, full_scale]` (zero is a level), values outside of the range are clamped. At least 2 bits are used.
+pub fn quantize(value: f64, bits: Option<u32>, full_scale: f64) -> f64 {
+    match bits {
+        Some(bits) => {
+            let levels = ((1u64 << (bits.clamp(2, 32) - 1)) - 1) as f64;
+            let step = full_scale / levels;
+            (value.clamp(-full_scale, full_scale) / step).round() * step
+        }
+        None => value,
+    }
+}
+
+/// Places a component of type `ty` with `params` in the scene of `scene_manager`
+fn add_component(
+    scene_manager: &mut SceneManager,
+    id: Id,
+    position: Vector2<f32>,
+    ty: DefaultComponentTypes,
+    params: ComponentParams,
+) -> Result<(), SceneManagerError> {
+    let mut component = Component::new(id, 0u32, position, 0.0, ty.into());
+    component.set_params(params);
+    scene_manager.scene_mut().add_component(0, component)?;
+
+    Ok(())
+}
+
+/// DACs that drive the rows of a crossbar. Every row has an ideal source, quantized to the resolution of the DAC,
+/// behind the output resistance of the driver.
+///
+/// The first ```rows``` ids are the output resistors (in the scene), the next ```rows``` ids are the sources.
+#[derive(Clone)]
+pub struct InputDriver {
+    /// The index of the layer whose rows are driven.
+    layer_idx: usize,
+    /// Whether the driver feeds the bias row of the layer (a constant unit input) instead of the network inputs.
+    bias: bool,
+
+    /// The driven nodes, one per value.
+    output_nodes: Vec<String>,
+    /// Values of the network represented by the output voltages.
+    values: Vec<f64>,
+
+    config: PeripheralConfig,
+
+    spacing: f32,
+    position: Vector2<f32>,
+
+    components_id_range: (Id, Id),
+}
+
+impl InputDriver {
+    pub fn new(
+        layer_idx: usize,
+        bias: bool,
+        output_nodes: Vec<String>,
+        config: PeripheralConfig,
+        start_component_id: Id,
+        spacing: f32,
+        position: Vector2<f32>,
+    ) -> InputDriver {
+        let rows = output_nodes.len() as Id;
+        let values = vec![if bias { 1.0 } else { 0.0 }; output_nodes.len()];
+
+        InputDriver {
+            layer_idx,
+            bias,
+            output_nodes,
+            values,
+            config,
+            spacing,
+            position,
+            components_id_range: (start_component_id, start_component_id + 2 * rows - 1),
+        }
+    }
+
+    pub fn layer_idx(&self) -> usize {
+        self.layer_idx
+    }
+
+    pub fn is_bias(&self) -> bool {
+        self.bias
+    }
+
+    pub fn output_nodes(&self) -> &Vec<String> {
+        &self.output_nodes
+    }
+
+    pub fn values(&self) -> &Vec<f64> {
+        &self.values
+    }
+
+    /// Sets the values driven on the rows, the sources of the scene are updated by
+    /// ```SceneManager::run_inference```
+    pub fn set_values(&mut self, values: Vec<f64>) -> Result<(), SceneManagerError> {
+        if values.len() != self.output_nodes.len() {
+            return Err(SceneManagerError::DimensionMismatch {
+                what: "input values",
+                expected: self.output_nodes.len(),
+                got: values.len(),
+            });
+        }
+
+        self.values = values;
+        Ok(())
+    }
+
+    /// Id of the source that drives ```row```
+    pub fn source_id(&self, row: usize) -> Id {
+        self.components_id_range.0 + (self.output_nodes.len() + row) as Id
+    }
+
+    /// Output voltage of the DAC of ```row```. [V]
+    pub fn voltage(&self, row: usize) -> f64 {
+        quantize(self.values[row], self.config.dac_bits, self.config.full_scale) * self.config.read_voltage
+    }
+
+    /// Decodes a driver saved with ```to_value```
+    pub fn from_value(value: &Value) -> Result<InputDriver, SceneManagerError> {
+        let mut driver = InputDriver::new(
+            value.field("layer_idx")?.as_usize()?,
+            value.field("bias")?.as_bool()?,
+            value.field("output_nodes")?.as_strings()?,
+            value.field("config")?.try_into()?,
+            value.field("start_component_id")?.as_u32()?,
+            value.field("spacing")?.as_f32()?,
+            value.field("position")?.as_vector2()?,
+        );
+        driver.set_values(value.field("values")?.as_f64s()?)?;
+
+        Ok(driver)
+    }
+}
+
+impl Construct for InputDriver {
+    fn components_id_range(&self) -> (Id, Id) {
+        self.components_id_range
+    }
+
+    fn add_to_scene(&self, scene_manager: &mut SceneManager) -> Result<(), SceneManagerError> {
+        let ground = scene_manager.ground();
+
+        for (row, output) in self.output_nodes.iter().enumerate() {
+            let id = self.components_id_range.0 + row as Id;
+            let dac = scene_manager.add_node(format!("{}.dac", output));
+            let output = scene_manager.add_node(output.clone());
+
+            add_component(
+                scene_manager,
+                id,
+                self.position + Vector2::new(0.0, -(row as f32) * self.spacing),
+                DefaultComponentTypes::Resistor,
+                ComponentParams::Resistance(self.config.driver_resistance),
+            )?;
+            scene_manager.connect(id, dac, output)?;
+
+            scene_manager.add_source(Element {
+                id: self.source_id(row),
+                kind: ElementKind::VoltageSource(self.voltage(row)),
+                pos: dac,
+                neg: ground,
+            });
+        }
+
+        Ok(())
+    }
+
+    fn type_name(&self) -> &'static str {
+        "input_driver"
+    }
+
+    fn to_value(&self) -> Value {
+        Value::object([
+            ("type", self.type_name().into()),
+            ("layer_idx", self.layer_idx.into()),
+            ("bias", self.bias.into()),
+            ("output_nodes", self.output_nodes.clone().into()),
+            ("values", self.values.clone().into()),
+            ("config", (&self.config).into()),
+            ("start_component_id", self.components_id_range.0.into()),
+            ("spacing", self.spacing.into()),
+            ("position", (&self.position).into()),
+        ])
+    }
+
+    fn instantiate(&self, start_component_id: Id, rename: &dyn Fn(&str) -> String) -> Box<dyn Construct> {
+        let mut driver = self.clone();
+        driver.output_nodes = rename_nodes(&self.output_nodes, rename);
+        driver.components_id_range = shift_id_range(self.components_id_range, start_component_id);
+        Box::new(driver)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+/// Transimpedance amplifiers that turn the column currents of a crossbar into the output voltages of a layer.
+///
+/// Every output has two inverting stages. The first one holds its column at virtual ground and converts its current,
+/// the second one converts the current of the other column of a differential pair plus the output of the first stage
+/// through a coupling resistor, so that ```v = R * (I+ - I-)```, or ```-R * (I+ - I-)``` when ```inverted```. Single
+/// device columns are rejected: without a reference column the ```g_min``` and offset terms of their mapping, which
+/// depend on the inputs, would stay in the outputs.
+///
+/// Components are ordered by output: first stage op-amp and feedback resistor, second stage op-amp and feedback
+/// resistor, coupling resistor.
+#[derive(Clone)]
+pub struct Tia {
+    /// The index of the layer whose columns are converted.
+    layer_idx: usize,
+    /// Whether every output is a pair of columns (G+ then G-), always the case.
+    differential: bool,
+    inverted: bool,
+
+    /// The columns of the crossbar, two per output if ```differential```.
+    input_nodes: Vec<String>,
+    output_nodes: Vec<String>,
+
+    /// [Ohm]
+    feedback_resistance: f64,
+    gain: f64,
+
+    spacing: f32,
+    position: Vector2<f32>,
+
+    components_id_range: (Id, Id),
+}
+
+impl Tia {
+    const COMPONENTS_PER_OUTPUT: u32 = 5;
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        layer_idx: usize,
+        differential: bool,
+        inverted: bool,
+        input_nodes: Vec<String>,
+        output_nodes: Vec<String>,
+        feedback_resistance: f64,
+        gain: f64,
+        start_component_id: Id,
+        spacing: f32,
+        position: Vector2<f32>,
+    ) -> Result<Tia, SceneManagerError> {
+        if !differential {
+            return Err(SceneManagerError::SingleEndedMapping);
+        }
+        let expected = output_nodes.len() * 2;
+        if input_nodes.len() != expected {
+            return Err(SceneManagerError::DimensionMismatch {
+                what: "TIA inputs",
+                expected,
+                got: input_nodes.len(),
+            });
+        }
+
+        let width = output_nodes.len() as Id;
+        Ok(Tia {
+            layer_idx,
+            differential,
+            inverted,
+            input_nodes,
+            output_nodes,
+            feedback_resistance,
+            gain,
+            spacing,
+            position,
+            components_id_range: (
+                start_component_id,
+                start_component_id + width * Self::COMPONENTS_PER_OUTPUT - 1,
+            ),
+        })
+    }
+
+    pub fn layer_idx(&self) -> usize {
+        self.layer_idx
+    }
+
+    pub fn output_nodes(&self) -> &Vec<String> {
+        &self.output_nodes
+    }
+
+    pub fn feedback_resistance(&self) -> f64 {
+        self.feedback_resistance
+    }
+
+    /// Decodes TIAs saved with ```to_value```
+    pub fn from_value(value: &Value) -> Result<Tia, SceneManagerError> {
+        Tia::new(
+            value.field("layer_idx")?.as_usize()?,
+            value.field("differential")?.as_bool()?,
+            value.field("inverted")?.as_bool()?,
+            value.field("input_nodes")?.as_strings()?,
+            value.field("output_nodes")?.as_strings()?,
+            value.field("feedback_resistance")?.as_f64()?,
+            value.field("gain")?.as_f64()?,
+            value.field("start_component_id")?.as_u32()?,
+            value.field("spacing")?.as_f32()?,
+            value.field("position")?.as_vector2()?,
+        )
+    }
+}
+
+impl Construct for Tia {
+    fn components_id_range(&self) -> (Id, Id) {
+        self.components_id_range
+    }
+
+    fn add_to_scene(&self, scene_manager: &mut SceneManager) -> Result<(), SceneManagerError> {
+        let ground = scene_manager.ground();
+        for (idx, output) in self.output_nodes.iter().enumerate() {
+            let positive = self.input_nodes[2 * idx].clone();
+            let negative = self.input_nodes[2 * idx + 1].clone();
+            let (first_input, second_input) = match self.inverted {
+                false => (positive, negative),
+                true => (negative, positive),
+            };
+
+            let first_input = scene_manager.add_node(first_input);
+            let second_input = scene_manager.add_node(second_input);
+            let first_output = scene_manager.add_node(format!("{}.tia", output));
+            let output = scene_manager.add_node(output.clone());
+
+            let first_id = self.components_id_range.0 + idx as Id * Self::COMPONENTS_PER_OUTPUT;
+            let x = (2 * idx) as f32 * self.spacing;
+            let stages = [
+                (DefaultComponentTypes::OpAmp, ComponentParams::Gain(self.gain)),
+                (DefaultComponentTypes::Resistor, ComponentParams::Resistance(self.feedback_resistance)),
+                (DefaultComponentTypes::OpAmp, ComponentParams::Gain(self.gain)),
+                (DefaultComponentTypes::Resistor, ComponentParams::Resistance(self.feedback_resistance)),
+                (DefaultComponentTypes::Resistor, ComponentParams::Resistance(self.feedback_resistance)),
+            ];
+            for (offset, (ty, params)) in stages.into_iter().enumerate() {
+                let position = self.position + Vector2::new(x, -(offset as f32) * self.spacing);
+                add_component(scene_manager, first_id + offset as Id, position, ty, params)?;
+            }
+
+            scene_manager.connect_op_amp(first_id, ground, first_input, first_output)?;
+            scene_manager.connect(first_id + 1, first_input, first_output)?;
+            scene_manager.connect_op_amp(first_id + 2, ground, second_input, output)?;
+            scene_manager.connect(first_id + 3, second_input, output)?;
+            scene_manager.connect(first_id + 4, first_output, second_input)?;
+        }
+
+        Ok(())
+    }
+
+    fn type_name(&self) -> &'static str {
+        "tia"
+    }
+
+    fn to_value(&self) -> Value {
+        Value::object([
+            ("type", self.type_name().into()),
+            ("layer_idx", self.layer_idx.into()),
+            ("differential", self.differential.into()),
+            ("inverted", self.inverted.into()),
+            ("input_nodes", self.input_nodes.clone().into()),
+            ("output_nodes", self.output_nodes.clone().into()),
+            ("feedback_resistance", self.feedback_resistance.into()),
+            ("gain", self.gain.into()),
+            ("start_component_id", self.components_id_range.0.into()),
+            ("spacing", self.spacing.into()),
+            ("position", (&self.position).into()),
+        ])
+    }
+
+    fn instantiate(&self, start_component_id: Id, rename: &dyn Fn(&str) -> String) -> Box<dyn Construct> {
+        let mut tia = self.clone();
+        tia.input_nodes = rename_nodes(&self.input_nodes, rename);
+        tia.output_nodes = rename_nodes(&self.output_nodes, rename);
+        tia.components_id_range = shift_id_range(self.components_id_range, start_component_id);
+        Box::new(tia)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+/// ReLU built as a precision rectifier: the op-amp drives a diode and takes the feedback from the cathode, so the
+/// output follows positive inputs and the load resistor pulls it to ground otherwise.
+///
+/// Components are ordered by output: op-amp, diode, load resistor.
+#[derive(Clone)]
+pub struct ReluBlock {
+    /// The index of the activation layer.
+    layer_idx: usize,
+
+    input_nodes: Vec<String>,
+    output_nodes: Vec<String>,
+
+    /// [Ohm]
+    load_resistance: f64,
+    gain: f64,
+
+    spacing: f32,
+    position: Vector2<f32>,
+
+    components_id_range: (Id, Id),
+}
+
+impl ReluBlock {
+    const COMPONENTS_PER_OUTPUT: u32 = 3;
+
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        layer_idx: usize,
+        input_nodes: Vec<String>,
+        output_nodes: Vec<String>,
+        load_resistance: f64,
+        gain: f64,
+        start_component_id: Id,
+        spacing: f32,
+        position: Vector2<f32>,
+    ) -> Result<ReluBlock, SceneManagerError> {
+        if input_nodes.len() != output_nodes.len() {
+            return Err(SceneManagerError::DimensionMismatch {
+                what: "ReLU inputs",
+                expected: output_nodes.len(),
+                got: input_nodes.len(),
+            });
+        }
+
+        let width = output_nodes.len() as Id;
+        Ok(ReluBlock {
+            layer_idx,
+            input_nodes,
+            output_nodes,
+            load_resistance,
+            gain,
+            spacing,
+            position,
+            components_id_range: (
+                start_component_id,
+                start_component_id + width * Self::COMPONENTS_PER_OUTPUT - 1,
+            ),
+        })
+    }
+
+    pub fn layer_idx(&self) -> usize {
+        self.layer_idx
+    }
+
+    pub fn output_nodes(&self) -> &Vec<String> {
+        &self.output_nodes
+    }
+
+    /// Decodes a ReLU block saved with ```to_value```
+    pub fn from_value(value: &Value) -> Result<ReluBlock, SceneManagerError> {
+        ReluBlock::new(
+            value.field("layer_idx")?.as_usize()?,
+            value.field("input_nodes")?.as_strings()?,
+            value.field("output_nodes")?.as_strings()?,
+            value.field("load_resistance")?.as_f64()?,
+            value.field("gain")?.as_f64()?,
+            value.field("start_component_id")?.as_u32()?,
+            value.field("spacing")?.as_f32()?,
+            value.field("position")?.as_vector2()?,
+        )
+    }
+}
+
+impl Construct for ReluBlock {
+    fn components_id_range(&self) -> (Id, Id) {
+        self.components_id_range
+    }
+
+    fn add_to_scene(&self, scene_manager: &mut SceneManager) -> Result<(), SceneManagerError> {
+        let ground = scene_manager.ground();
+
+        for (idx, (input, output)) in self.input_nodes.iter().zip(self.output_nodes.iter()).enumerate() {
+            let driver = scene_manager.add_node(format!("{}.drv", output));
+            let input = scene_manager.add_node(input.clone());
+            let output = scene_manager.add_node(output.clone());
+
+            let first_id = self.components_id_range.0 + idx as Id * Self::COMPONENTS_PER_OUTPUT;
+            let parts = [
+                (DefaultComponentTypes::OpAmp, ComponentParams::Gain(self.gain)),
+                (
+                    DefaultComponentTypes::Diode,
+                    ComponentParams::Diode {
+                        saturation_current: DIODE_SATURATION_CURRENT,
+                        ideality: 1.0,
+                    },
+                ),
+                (DefaultComponentTypes::Resistor, ComponentParams::Resistance(self.load_resistance)),
+            ];
+            for (offset, (ty, params)) in parts.into_iter().enumerate() {
+                let position = self.position + Vector2::new(offset as f32, -(idx as f32)) * self.spacing;
+                add_component(scene_manager, first_id + offset as Id, position, ty, params)?;
+            }
+
+            scene_manager.connect_op_amp(first_id, input, output, driver)?;
+            scene_manager.connect(first_id + 1, driver, output)?;
+            scene_manager.connect(first_id + 2, output, ground)?;
+        }
+
+        Ok(())
+    }
+
+    fn type_name(&self) -> &'static str {
+        "relu"
+    }
+
+    fn to_value(&self) -> Value {
+        Value::object([
+            ("type", self.type_name().into()),
+            ("layer_idx", self.layer_idx.into()),
+            ("input_nodes", self.input_nodes.clone().into()),
+            ("output_nodes", self.output_nodes.clone().into()),
+            ("load_resistance", self.load_resistance.into()),
+            ("gain", self.gain.into()),
+            ("start_component_id", self.components_id_range.0.into()),
+            ("spacing", self.spacing.into()),
+            ("position", (&self.position).into()),
+        ])
+    }
+
+    fn instantiate(&self, start_component_id: Id, rename: &dyn Fn(&str) -> String) -> Box<dyn Construct> {
+        let mut relu = self.clone();
+        relu.input_nodes = rename_nodes(&self.input_nodes, rename);
+        relu.output_nodes = rename_nodes(&self.output_nodes, rename);
+        relu.components_id_range = shift_id_range(self.components_id_range, start_component_id);
+        Box::new(relu)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+/// ADCs that sample the outputs of the network, every input is loaded by the input resistance of its converter.
+#[derive(Clone)]
+pub struct Adc {
+    input_nodes: Vec<String>,
+
+    config: PeripheralConfig,
+
+    spacing: f32,
+    position: Vector2<f32>,
+
+    components_id_range: (Id, Id),
+}
+
+impl Adc {
+    pub fn new(
+        input_nodes: Vec<String>,
+        config: PeripheralConfig,
+        start_component_id: Id,
+        spacing: f32,
+        position: Vector2<f32>,
+    ) -> Adc {
+        let width = input_nodes.len() as Id;
+
+        Adc {
+            input_nodes,
+            config,
+            spacing,
+            position,
+            components_id_range: (start_component_id, start_component_id + width - 1),
+        }
+    }
+
+    pub fn input_nodes(&self) -> &Vec<String> {
+        &self.input_nodes
+    }
+
+    /// Value of the network represented by the sampled ```voltage```, quantized to the resolution of the ADC
+    pub fn convert(&self, voltage: f64) -> f64 {
+        quantize(voltage / self.config.read_voltage, self.config.adc_bits, self.config.full_scale)
+    }
+
+    /// Decodes ADCs saved with ```to_value```
+    pub fn from_value(value: &Value) -> Result<Adc, SceneManagerError> {
+        Ok(Adc::new(
+            value.field("input_nodes")?.as_strings()?,
+            value.field("config")?.try_into()?,
+            value.field("start_component_id")?.as_u32()?,
+            value.field("spacing")?.as_f32()?,
+            value.field("position")?.as_vector2()?,
+        ))
+    }
+}
+
+impl Construct for Adc {
+    fn components_id_range(&self) -> (Id, Id) {
+        self.components_id_range
+    }
+
+    fn add_to_scene(&self, scene_manager: &mut SceneManager) -> Result<(), SceneManagerError> {
+        let ground = scene_manager.ground();
+
+        for (idx, input) in self.input_nodes.iter().enumerate() {
+            let id = self.components_id_range.0 + idx as Id;
+            let input = scene_manager.add_node(input.clone());
+
+            add_component(
+                scene_manager,
+                id,
+                self.position + Vector2::new(0.0, -(idx as f32) * self.spacing),
+                DefaultComponentTypes::Resistor,
+                ComponentParams::Resistance(self.config.adc_input_resistance),
+            )?;
+            scene_manager.connect(id, input, ground)?;
+        }
+
+        Ok(())
+    }
+
+    fn type_name(&self) -> &'static str {
+        "adc"
+    }
+
+    fn to_value(&self) -> Value {
+        Value::object([
+            ("type", self.type_name().into()),
+            ("input_nodes", self.input_nodes.clone().into()),
+            ("config", (&self.config).into()),
+            ("start_component_id", self.components_id_range.0.into()),
+            ("spacing", self.spacing.into()),
+            ("position", (&self.position).into()),
+        ])
+    }
+
+    fn instantiate(&self, start_component_id: Id, rename: &dyn Fn(&str) -> String) -> Box<dyn Construct> {
+        let mut adc = self.clone();
+        adc.input_nodes = rename_nodes(&self.input_nodes, rename);
+        adc.components_id_range = shift_id_range(self.components_id_range, start_component_id);
+        Box::new(adc)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+#[cfg(test)]
+mod peripherals_test {
+    use super::*;
+    use crate::scene::scene_manager::Crossbar;
+    use rsnet_sim::mapping::ConductanceMapping;
+
+    #[test]
+    fn test_quantize() {
+        assert_eq!(quantize(0.37, None, 1.0), 0.37);
+        // 3 bits: levels every 1/3
+        assert!((quantize(0.4, Some(3), 1.0) - 1.0 / 3.0).abs() < 1e-12);
+        assert_eq!(quantize(-5.0, Some(3), 1.0), -1.0);
+        assert!((quantize(0.1, Some(8), 1.0) - 13.0 / 127.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_layer_matches_matmul() {
+        let config = PeripheralConfig {
+            driver_resistance: 1e-3,
+            ..PeripheralConfig::default()
+        };
+        // 3 inputs x 2 outputs, with a bias row
+        let weights = [0.5, -1.0, 0.25, -0.5, 0.75, 1.0];
+        let bias = [0.1, -0.3];
+        let mapped = ConductanceMapping::new(1e-6, 1e-4, true, None)
+            .map_linear(3, 2, &weights, Some(&bias))
+            .unwrap();
+
+        let forward = |inputs: &[f64]| -> Vec<f64> {
+            (0..2)
+                .map(|o| {
+                    let y: f64 = (0..3).map(|i| weights[o * 3 + i] * inputs[i]).sum();
+                    (y + bias[o]).max(0.0)
+                })
+                .collect()
+        };
+
+        let rows: Vec<String> = ["x0", "x1", "x2", "bias"].map(String::from).to_vec();
+        let columns: Vec<String> = (0..4).map(|col| format!("c{}", col)).collect();
+        let outputs: Vec<String> = ["y0", "y1"].map(String::from).to_vec();
+        let activations: Vec<String> = ["a0", "a1"].map(String::from).to_vec();
+
+        for inputs in [[0.5, -0.25, 1.0], [-1.0, 0.5, 0.0], [1.0, 1.0, -1.0]] {
+            let mut scene_manager = SceneManager::new();
+
+            let mut driver = InputDriver::new(
+                0,
+                false,
+                rows[..3].to_vec(),
+                config,
+                scene_manager.next_free_id(),
+                1.0,
+                Vector2::new(0.0, 0.0),
+            );
+            driver.set_values(inputs.to_vec()).unwrap();
+            scene_manager.add_construct(Box::new(driver)).unwrap();
+            let bias_driver = InputDriver::new(
+                0,
+                true,
+                rows[3..].to_vec(),
+                config,
+                scene_manager.next_free_id(),
+                1.0,
+                Vector2::new(0.0, -4.0),
+            );
+            scene_manager.add_construct(Box::new(bias_driver)).unwrap();
+
+            let mut crossbar = Crossbar::new(
+                0,
+                rows.clone(),
+                columns.clone(),
+                4,
+                4,
+                scene_manager.next_free_id(),
+                1.0,
+                Vector2::new(4.0, 0.0),
+            )
+            .unwrap();
+            crossbar.set_conductances(mapped.conductances.clone()).unwrap();
+            scene_manager.add_construct(Box::new(crossbar)).unwrap();
+
+            let tia = Tia::new(
+                0,
+                true,
+                false,
+                columns.clone(),
+                outputs.clone(),
+                1.0 / mapped.scale,
+                config.op_amp_gain,
+                scene_manager.next_free_id(),
+                1.0,
+                Vector2::new(4.0, -6.0),
+            )
+            .unwrap();
+            scene_manager.add_construct(Box::new(tia)).unwrap();
+
+            let relu = ReluBlock::new(
+                1,
+                outputs.clone(),
+                activations.clone(),
+                config.relu_load_resistance,
+                config.op_amp_gain,
+                scene_manager.next_free_id(),
+                1.0,
+                Vector2::new(10.0, -6.0),
+            )
+            .unwrap();
+            scene_manager.add_construct(Box::new(relu)).unwrap();
+
+            let adc = Adc::new(
+                activations.clone(),
+                config,
+                scene_manager.next_free_id(),
+                1.0,
+                Vector2::new(14.0, -6.0),
+            );
+            scene_manager.add_construct(Box::new(adc.clone())).unwrap();
+
+            let op = scene_manager.dc_operating_point().unwrap();
+            for (activation, expected) in activations.iter().zip(forward(&inputs)) {
+                let voltage = op.voltage(scene_manager.node_id(activation).unwrap()).unwrap();
+                let value = adc.convert(voltage);
+                assert!((value - expected).abs() < 1e-3, "{} != {}", value, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_single_ended_tia() {
+        let columns: Vec<String> = ["c0", "c1"].map(String::from).to_vec();
+        let outputs: Vec<String> = ["y0", "y1"].map(String::from).to_vec();
+
+        let tia = Tia::new(0, false, false, columns, outputs, 1e4, 1e6, 0, 1.0, Vector2::zeros());
+        assert!(matches!(tia, Err(SceneManagerError::SingleEndedMapping)));
+    }
+}
@@ -0,0 +1,318 @@
+use std::collections::{BTreeMap, HashMap};
+
+use super::wire::{Wire, CIRCLE_AT_END, CIRCLE_AT_START};
+
+use crate::types::Id;
+
+use nalgebra::Vector2;
+
+/// Straight piece of a route, always horizontal or vertical
+pub type Segment = (Vector2<f32>, Vector2<f32>);
+
+/// Positions closer than this are the same point of a route
+const POSITION_TOLERANCE: f32 = 1e-3;
+
+/// Straight line shared by the same port of many components, e.g. the word and bit lines of a crossbar. The ports
+/// are laid out on a line, so the router draws a single wire through them instead of connecting them one by one.
+#[derive(Debug, Clone)]
+pub struct Rail {
+    /// Name of the net of the rail
+    net: String,
+    /// Component and port index of every port on the rail
+    ports: Vec<(Id, usize)>,
+}
+
+impl Rail {
+    pub fn new(net: String, ports: Vec<(Id, usize)>) -> Self {
+        Self { net, ports }
+    }
+
+    pub fn net(&self) -> &str {
+        &self.net
+    }
+
+    pub fn ports(&self) -> &Vec<(Id, usize)> {
+        &self.ports
+    }
+}
+
+/// Orthogonal route of a net
+#[derive(Debug, Clone, Default)]
+pub struct NetRoute {
+    segments: Vec<Segment>,
+    /// Positions of the ports of the net, a port on a wire counts as a branch of the junction
+    terminals: Vec<Vector2<f32>>,
+}
+
+impl NetRoute {
+    pub fn segments(&self) -> &Vec<Segment> {
+        &self.segments
+    }
+
+    pub fn terminals(&self) -> &Vec<Vector2<f32>> {
+        &self.terminals
+    }
+
+    /// Converts the route into wires with ids starting at ```first_id```. The ends where two segments meet are mitered
+    /// and junctions of three or more branches get a dot.
+    pub fn to_wires(&self, first_id: Id, chunk_size: f32) -> Vec<Wire> {
+        let mut ends: HashMap<(i64, i64), Vec<usize>> = HashMap::new();
+        for (idx, (start, end)) in self.segments.iter().enumerate() {
+            ends.entry(point_key(start)).or_default().push(idx);
+            ends.entry(point_key(end)).or_default().push(idx);
+        }
+
+        let branches = |point: &Vector2<f32>| -> usize {
+            let terminal = self
+                .terminals
+                .iter()
+                .any(|terminal| point_key(terminal) == point_key(point));
+            ends.get(&point_key(point)).map_or(0, |segments| segments.len()) + usize::from(terminal)
+        };
+
+        // Direction of the other segment that ends at ```point```, pointing away from it
+        let other_direction = |idx: usize, point: &Vector2<f32>| -> Option<Vector2<f32>> {
+            let segments = ends.get(&point_key(point))?;
+            if segments.len() != 2 {
+                return None;
+            }
+            let other = segments.iter().find(|other| **other != idx)?;
+            let (start, end) = self.segments[*other];
+            Some(if point_key(&start) == point_key(point) {
+                end - start
+            } else {
+                start - end
+            })
+        };
+
+        self.segments
+            .iter()
+            .enumerate()
+            .map(|(idx, (start, end))| {
+                let direction = end - start;
+                // The start cap follows the direction of the previous wire (pointing into the start) and the end cap the
+                // direction of the next one
+                let prev_direction = other_direction(idx, start).map_or(direction, |other| -other);
+                let next_direction = other_direction(idx, end).unwrap_or(direction);
+
+                let mut wire = Wire::new(
+                    first_id + idx as Id,
+                    *start,
+                    *end,
+                    next_direction,
+                    prev_direction,
+                    chunk_size,
+                );
+
+                let mut circle_overlay = 0;
+                if branches(start) >= 3 {
+                    circle_overlay |= CIRCLE_AT_START;
+                }
+                if branches(end) >= 3 {
+                    circle_overlay |= CIRCLE_AT_END;
+                }
+                wire.set_circle_overlay(circle_overlay);
+
+                wire
+            })
+            .collect()
+    }
+}
+
+/// Routes a net connecting ```terminals``` and ```rails```.
+///
+/// Every rail is drawn as a single straight wire between its outermost ports. The terminals, and the end of the rail
+/// closest to them, are connected with a vertical trunk at their median x and horizontal branches at the height of
+/// each terminal. The other rails are joined to the closest point already routed with an L shaped wire.
+pub fn route_net(terminals: &[Vector2<f32>], rails: &[Vec<Vector2<f32>>]) -> NetRoute {
+    let centroid = (!terminals.is_empty()).then(|| terminals.iter().sum::<Vector2<f32>>() / terminals.len() as f32);
+    let distance = |segment: &Segment| match centroid {
+        Some(centroid) => manhattan(&segment.0, &centroid).min(manhattan(&segment.1, &centroid)),
+        None => 0.0,
+    };
+
+    let mut rail_segments: Vec<Segment> = rails.iter().filter_map(|rail| rail_segment(rail)).collect();
+    rail_segments.sort_by(|a, b| distance(a).total_cmp(&distance(b)));
+
+    let mut segments = Vec::new();
+    let mut anchors: Vec<Vector2<f32>> = terminals.to_vec();
+    // Points the next rail can be joined to
+    let mut routed: Vec<Vector2<f32>> = terminals.to_vec();
+
+    for (idx, (start, end)) in rail_segments.iter().enumerate() {
+        if point_key(start) != point_key(end) {
+            segments.push((*start, *end));
+        }
+
+        if idx == 0 {
+            let anchor = match centroid {
+                Some(centroid) if manhattan(end, &centroid) < manhattan(start, &centroid) => *end,
+                _ => *start,
+            };
+            anchors.push(anchor);
+        } else {
+            let closest = [*start, *end]
+                .into_iter()
+                .flat_map(|from| routed.iter().map(move |to| (from, *to)))
+                .min_by(|a, b| manhattan(&a.0, &a.1).total_cmp(&manhattan(&b.0, &b.1)));
+            if let Some((rail_end, to)) = closest {
+                segments.extend(l_route(&to, &rail_end));
+            }
+        }
+
+        routed.extend([*start, *end]);
+    }
+
+    segments.extend(trunk(&anchors));
+
+    NetRoute {
+        segments,
+        terminals: terminals.iter().chain(rails.iter().flatten()).copied().collect(),
+    }
+}
+
+/// Horizontal then vertical segments from ```from``` to ```to```
+fn l_route(from: &Vector2<f32>, to: &Vector2<f32>) -> Vec<Segment> {
+    let corner = Vector2::new(to.x, from.y);
+
+    [(*from, corner), (corner, *to)]
+        .into_iter()
+        .filter(|(start, end)| point_key(start) != point_key(end))
+        .collect()
+}
+
+fn manhattan(a: &Vector2<f32>, b: &Vector2<f32>) -> f32 {
+    (a.x - b.x).abs() + (a.y - b.y).abs()
+}
+
+/// Segment between the outermost of the collinear ```points```
+fn rail_segment(points: &[Vector2<f32>]) -> Option<Segment> {
+    let order = |a: &&Vector2<f32>, b: &&Vector2<f32>| a.x.total_cmp(&b.x).then(a.y.total_cmp(&b.y));
+    let first = points.iter().min_by(order)?;
+    let last = points.iter().max_by(order)?;
+
+    Some((*first, *last))
+}
+
+fn trunk(points: &[Vector2<f32>]) -> Vec<Segment> {
+    // Points are grouped by height, every group is a horizontal line through the trunk
+    let mut levels: BTreeMap<i64, Vec<Vector2<f32>>> = BTreeMap::new();
+    for point in points {
+        let level = levels.entry(point_key(point).1).or_default();
+        if !level.iter().any(|other| point_key(other) == point_key(point)) {
+            level.push(*point);
+        }
+    }
+    if levels.values().map(|level| level.len()).sum::<usize>() < 2 {
+        return Vec::new();
+    }
+
+    let mut xs: Vec<f32> = levels.values().flatten().map(|point| point.x).collect();
+    xs.sort_by(f32::total_cmp);
+    let trunk_x = xs[(xs.len() - 1) / 2];
+
+    let mut segments = Vec::new();
+
+    let heights: Vec<f32> = levels.values().map(|level| level[0].y).collect();
+    for pair in heights.windows(2) {
+        segments.push((Vector2::new(trunk_x, pair[0]), Vector2::new(trunk_x, pair[1])));
+    }
+
+    for level in levels.values() {
+        let y = level[0].y;
+        let mut xs: Vec<f32> = level.iter().map(|point| point.x).chain([trunk_x]).collect();
+        xs.sort_by(f32::total_cmp);
+        xs.dedup_by(|a, b| (*a - *b).abs() < POSITION_TOLERANCE);
+
+        for pair in xs.windows(2) {
+            segments.push((Vector2::new(pair[0], y), Vector2::new(pair[1], y)));
+        }
+    }
+
+    segments
+}
+
+fn point_key(point: &Vector2<f32>) -> (i64, i64) {
+    (
+        (point.x / POSITION_TOLERANCE).round() as i64,
+        (point.y / POSITION_TOLERANCE).round() as i64,
+    )
+}
+
+#[cfg(test)]
+mod router_test {
+    use super::*;
+
+    fn is_orthogonal((start, end): &Segment) -> bool {
+        (start.x - end.x).abs() < POSITION_TOLERANCE || (start.y - end.y).abs() < POSITION_TOLERANCE
+    }
+
+    #[test]
+    fn test_two_terminals() {
+        let route = route_net(&[Vector2::new(0.0, 0.0), Vector2::new(4.0, 3.0)], &[]);
+
+        assert_eq!(route.segments().len(), 2);
+        assert!(route.segments().iter().all(is_orthogonal));
+
+        let wires = route.to_wires(10, 10.0);
+        assert_eq!(wires.iter().map(|wire| wire.id()).collect::<Vec<_>>(), vec![10, 11]);
+        // A corner is mitered with the other wire and has no junction
+        assert!(wires.iter().all(|wire| wire.circle_overlay() == 0));
+        let corner = Vector2::new(0.0, 3.0);
+        for wire in &wires {
+            if *wire.end() == corner {
+                assert_eq!(*wire.next_direction(), Vector2::new(4.0, 0.0));
+            } else {
+                assert_eq!(*wire.start(), corner);
+                assert_eq!(*wire.prev_direction(), Vector2::new(0.0, 3.0));
+            }
+        }
+    }
+
+    #[test]
+    fn test_junction() {
+        let terminals = [Vector2::new(0.0, 0.0), Vector2::new(2.0, 1.0), Vector2::new(4.0, 2.0)];
+        let route = route_net(&terminals, &[]);
+        assert!(route.segments().iter().all(is_orthogonal));
+
+        // The middle terminal sits on the trunk, it joins the two halves of the trunk
+        let wires = route.to_wires(0, 10.0);
+        let junction = Vector2::new(2.0, 1.0);
+        let dots: Vec<&Wire> = wires
+            .iter()
+            .filter(|wire| {
+                (*wire.start() == junction && wire.circle_overlay() & CIRCLE_AT_START != 0)
+                    || (*wire.end() == junction && wire.circle_overlay() & CIRCLE_AT_END != 0)
+            })
+            .collect();
+        assert_eq!(dots.len(), 2);
+        assert_eq!(wires.iter().filter(|wire| wire.circle_overlay() != 0).count(), 2);
+    }
+
+    #[test]
+    fn test_rails() {
+        // Two word lines of the same net and the driver on their left
+        let rails = vec![
+            (0..4)
+                .map(|col| Vector2::new(col as f32 + 2.0, 0.0))
+                .collect::<Vec<_>>(),
+            (0..4)
+                .map(|col| Vector2::new(col as f32 + 8.0, 0.0))
+                .collect::<Vec<_>>(),
+        ];
+        let route = route_net(&[Vector2::new(0.0, 0.0)], &rails);
+
+        assert!(route
+            .segments()
+            .contains(&(Vector2::new(2.0, 0.0), Vector2::new(5.0, 0.0))));
+        assert!(route
+            .segments()
+            .contains(&(Vector2::new(8.0, 0.0), Vector2::new(11.0, 0.0))));
+        assert!(route
+            .segments()
+            .contains(&(Vector2::new(5.0, 0.0), Vector2::new(8.0, 0.0))));
+        // No segment overlaps another
+        let length: f32 = route.segments().iter().map(|(start, end)| (end - start).norm()).sum();
+        assert!((length - 11.0).abs() < 1e-4);
+    }
+}
@@ -1,13 +1,15 @@
 use super::component;
 use super::component::DefaultComponentTypes;
+use super::lod;
 use super::scene_manager;
 use super::types;
 use super::utils;
 use super::wire;
 
-use component::Component;
+use component::{Component, ComponentParams};
+use lod::Lods;
 use nalgebra::Vector2;
-use tracing::info;
+use tracing::{info, warn};
 use types::*;
 use utils::*;
 use wire::{Wire, WireSegment};
@@ -22,6 +24,7 @@ use crate::{
         ComponentTyPrimitives,
     },
     types::Id,
+    utils::AaBb,
 };
 use rsnet_derive::unwrap_option_or_return_none;
 
@@ -32,6 +35,8 @@ use thiserror::Error;
 pub enum SceneError {
     #[error("Cannot add component with id {0}, it already exists, try calling the update_component method instead")]
     ComponentAlreadyExists(Id),
+    #[error("Component with id {0} does not exist")]
+    ComponentNotFound(Id),
 }
 
 #[derive(Debug)]
@@ -43,6 +48,10 @@ pub struct Scene {
     /// Stores the IDs of the wires contained in each chunk
     wires_chunk_cache: ChunkedStorage<Id>,
     wires: HashMap<Id, Wire>,
+    /// Chunks whose components or wires changed since the last call to ```take_dirty_chunks```
+    dirty_chunks: DirtyChunks,
+    /// Levels generated from the components of step 0, shown when zoomed out
+    lods: Lods,
 
     // primitives: HashMap<ComponentType, Vec<(&'static ComponentTyPrimitives, f32)>>,
     primitives: Primitives,
@@ -57,9 +66,21 @@ impl Default for Scene {
 impl Scene {
     pub fn new() -> Self {
         // let mut scene = Scene::new_empty();
-        let nn =
-            rsnet_net_parser::extract_nn(include_str!("../../../rsnet_net_parser/src/test.py"));
-        let mut scene = scene_manager::gen_from_nn(nn.unwrap());
+        #[cfg(feature = "torch")]
+        let mut scene =
+            match rsnet_net_parser::extract_nn(include_str!("../../../rsnet_net_parser/src/test.py")) {
+                Ok(nn) => scene_manager::gen_from_nn(nn).unwrap_or_else(|error| {
+                    warn!("The default network could not be built, starting empty: {}", error);
+                    Scene::new_empty()
+                }),
+                Err(error) => {
+                    warn!("The default network could not be read, starting empty: {}", error);
+                    Scene::new_empty()
+                }
+            };
+        // The default network is described in python, it needs torch
+        #[cfg(not(feature = "torch"))]
+        let mut scene = Scene::new_empty();
 
         let chunk_size = 10;
         let chunk_step_idx = 0;
@@ -146,6 +167,8 @@ impl Scene {
             id_to_chunksize_chunk: HashMap::new(),
             wires_chunk_cache: HashMap::new(),
             wires: HashMap::new(),
+            dirty_chunks: HashMap::new(),
+            lods: Lods::default(),
             primitives: DefaultComponentTypes::primitives(),
         }
     }
@@ -165,8 +188,15 @@ impl Scene {
         );
         // println!("Adding component to chunk (size: {:?}): {:?}", chunk_size_from_step_idx(chunk_step_idx+1), chunk_id);
 
+        if self.id_to_chunksize_chunk.contains_key(&component.id()) {
+            return Err(SceneError::ComponentAlreadyExists(component.id()));
+        }
         self.id_to_chunksize_chunk
             .insert(component.id(), (chunk_step_idx, chunk_id));
+        self.mark_dirty(chunk_step_idx, chunk_id);
+        if chunk_step_idx == 0 {
+            self.lods.add(&component, &mut self.dirty_chunks);
+        }
 
         let chunked_comps = self
             .components
@@ -187,10 +217,77 @@ impl Scene {
         }
     }
 
+    /// Adds every component of a chunk at once, faster than calling ```add_component``` for each of them. The
+    /// components must be placed inside ```chunk_id```, it is not checked.
+    pub fn insert_chunk(
+        &mut self,
+        chunk_step_idx: ChunkStepIdx,
+        chunk_id: ChunkId,
+        mut components: Vec<Component>,
+    ) -> Result<(), SceneError> {
+        components.sort_unstable_by_key(|c| c.id());
+        if let Some(pair) = components.windows(2).find(|pair| pair[0].id() == pair[1].id()) {
+            return Err(SceneError::ComponentAlreadyExists(pair[0].id()));
+        }
+
+        self.id_to_chunksize_chunk.reserve(components.len());
+        for component in &components {
+            if self.id_to_chunksize_chunk.contains_key(&component.id()) {
+                return Err(SceneError::ComponentAlreadyExists(component.id()));
+            }
+        }
+        for component in &components {
+            self.id_to_chunksize_chunk
+                .insert(component.id(), (chunk_step_idx, chunk_id));
+            if chunk_step_idx == 0 {
+                self.lods.add(component, &mut self.dirty_chunks);
+            }
+        }
+        self.mark_dirty(chunk_step_idx, chunk_id);
+
+        let chunked_comps = self
+            .components
+            .entry(chunk_step_idx)
+            .or_default();
+
+        match chunked_comps.get_mut(&chunk_id) {
+            Some(existing) => {
+                existing.append(&mut components);
+                existing.sort_unstable_by_key(|c| c.id());
+            }
+            None => {
+                chunked_comps.insert(chunk_id, components);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Number of components of the scene
+    pub fn component_count(&self) -> usize {
+        self.id_to_chunksize_chunk.len()
+    }
+
     pub fn components(&self) -> &ChunkedStorage<Component> {
         &self.components
     }
 
+    /// Components shown at a step, the generated level when no component was added at that step
+    pub fn level(&self, chunk_step_idx: ChunkStepIdx) -> Option<&HashMap<ChunkId, Vec<Component>>> {
+        self.components
+            .get(&chunk_step_idx)
+            .or_else(|| self.lods.components().get(&chunk_step_idx))
+    }
+
+    pub fn lods(&self) -> &Lods {
+        &self.lods
+    }
+
+    /// Sets the conductances shown with the first and last colors of the heatmap of the zoomed out levels
+    pub fn set_heatmap_range(&mut self, g_min: f64, g_max: f64) {
+        self.lods.set_heat_range(g_min, g_max, &mut self.dirty_chunks);
+    }
+
     pub fn get_component(&self, id: Id) -> Option<&Component> {
         let chunk_size_chunk_id =
             unwrap_option_or_return_none!(self.id_to_chunksize_chunk.get(&id));
@@ -206,9 +303,220 @@ impl Scene {
         }
     }
 
-    pub fn get_components_in_chunk(&self, chunk_id: &ChunkId) -> Option<&Vec<Component>> {
-        // self.components.get(chunk_id)
-        None
+    /// The step and chunk a component is stored in
+    pub fn component_chunk(&self, id: Id) -> Option<(ChunkStepIdx, ChunkId)> {
+        self.id_to_chunksize_chunk.get(&id).copied()
+    }
+
+    /// Sets the electrical parameters of a component, they do not affect its placement so it stays in its chunk
+    pub fn set_component_params(&mut self, id: Id, params: ComponentParams) -> Result<(), SceneError> {
+        let (chunk_step_idx, chunk_id) = self
+            .id_to_chunksize_chunk
+            .get(&id)
+            .ok_or(SceneError::ComponentNotFound(id))?;
+
+        let components = self
+            .components
+            .get_mut(chunk_step_idx)
+            .and_then(|chunked_components| chunked_components.get_mut(chunk_id))
+            .ok_or(SceneError::ComponentNotFound(id))?;
+
+        let pos = components
+            .binary_search_by_key(&id, |c| c.id())
+            .map_err(|_| SceneError::ComponentNotFound(id))?;
+
+        if *chunk_step_idx == 0 {
+            self.lods.remove(&components[pos], &mut self.dirty_chunks);
+            components[pos].set_params(params);
+            self.lods.add(&components[pos], &mut self.dirty_chunks);
+        } else {
+            components[pos].set_params(params);
+        }
+
+        Ok(())
+    }
+
+    /// Moves and rotates a component, it is moved to the chunk of its new position
+    pub fn move_component(
+        &mut self,
+        id: Id,
+        position: Vector2<f32>,
+        rotation: f32,
+    ) -> Result<(), SceneError> {
+        let (chunk_step_idx, mut component) = self.take_component(id)?;
+        component.set_position(position);
+        component.set_rotation(rotation);
+
+        self.add_component(chunk_step_idx, component)
+    }
+
+    /// Moves, rotates and scales a component, it is moved to the chunk of its new position
+    pub fn transform_component(
+        &mut self,
+        id: Id,
+        position: Vector2<f32>,
+        rotation: f32,
+        scale: f32,
+    ) -> Result<(), SceneError> {
+        let (chunk_step_idx, mut component) = self.take_component(id)?;
+        component.set_position(position);
+        component.set_rotation(rotation);
+        component.set_scale(scale);
+
+        self.add_component(chunk_step_idx, component)
+    }
+
+    /// Replaces the component with the id of ```component```, in the chunk of its (possibly new) position
+    pub fn update_component(&mut self, component: Component) -> Result<(), SceneError> {
+        let (chunk_step_idx, _) = self.take_component(component.id())?;
+
+        self.add_component(chunk_step_idx, component)
+    }
+
+    /// Removes a component from the scene and returns it
+    pub fn remove_component(&mut self, id: Id) -> Result<Component, SceneError> {
+        self.take_component(id).map(|(_, component)| component)
+    }
+
+    /// Removes a component, returns it together with the step it was stored at
+    fn take_component(&mut self, id: Id) -> Result<(ChunkStepIdx, Component), SceneError> {
+        let (chunk_step_idx, chunk_id) = *self
+            .id_to_chunksize_chunk
+            .get(&id)
+            .ok_or(SceneError::ComponentNotFound(id))?;
+
+        let chunked_components = self
+            .components
+            .get_mut(&chunk_step_idx)
+            .ok_or(SceneError::ComponentNotFound(id))?;
+        let components = chunked_components
+            .get_mut(&chunk_id)
+            .ok_or(SceneError::ComponentNotFound(id))?;
+        let pos = components
+            .binary_search_by_key(&id, |c| c.id())
+            .map_err(|_| SceneError::ComponentNotFound(id))?;
+
+        let component = components.remove(pos);
+        if components.is_empty() {
+            chunked_components.remove(&chunk_id);
+        }
+        self.id_to_chunksize_chunk.remove(&id);
+        self.mark_dirty(chunk_step_idx, chunk_id);
+        if chunk_step_idx == 0 {
+            self.lods.remove(&component, &mut self.dirty_chunks);
+        }
+
+        Ok((chunk_step_idx, component))
+    }
+
+    /// Chunks changed since the last call, so only those are uploaded to the GPU again. They are cleared.
+    pub fn take_dirty_chunks(&mut self) -> DirtyChunks {
+        std::mem::take(&mut self.dirty_chunks)
+    }
+
+    fn mark_dirty(&mut self, chunk_step_idx: ChunkStepIdx, chunk_id: ChunkId) {
+        self.dirty_chunks
+            .entry(chunk_step_idx)
+            .or_default()
+            .insert(chunk_id);
+    }
+
+    /// Components of a chunk of a step
+    pub fn get_components_in_chunk(
+        &self,
+        chunk_step_idx: ChunkStepIdx,
+        chunk_id: &ChunkId,
+    ) -> Option<&Vec<Component>> {
+        self.level(chunk_step_idx)?.get(chunk_id)
+    }
+
+    /// Box of a component in scene coordinates, from the most detailed primitives of its type. A component of a type
+    /// without primitives is a point.
+    pub fn component_aabb(&self, component: &Component) -> AaBb {
+        self.primitives
+            .0
+            .get(&component.ty())
+            .and_then(|levels| levels.first())
+            .and_then(|(primitives, _)| primitives.aabb())
+            .map(|aabb| aabb.transformed(component.transform()))
+            .unwrap_or_else(|| AaBb::new(*component.position(), *component.position()))
+    }
+
+    /// Components of a step whose box overlaps ```rect```. They are chunked by their position, so the chunks around
+    /// ```rect``` are searched too, assuming no component is larger than a chunk.
+    pub fn components_in_rect(&self, chunk_step_idx: ChunkStepIdx, rect: &AaBb) -> Vec<&Component> {
+        let Some(chunks) = self.level(chunk_step_idx) else {
+            return Vec::new();
+        };
+        let chunk_size = chunk_size_from_step_idx(chunk_step_idx + 1);
+
+        ChunkRange::from_aabb(&rect.expanded(chunk_size), chunk_size)
+            .into_iter()
+            .filter_map(|chunk_id| chunks.get(&chunk_id))
+            .flatten()
+            .filter(|component| self.component_aabb(component).overlaps(rect))
+            .collect()
+    }
+
+    /// Components of a step whose box contains ```point```, the smallest first as it is the most specific one
+    pub fn components_at(
+        &self,
+        chunk_step_idx: ChunkStepIdx,
+        point: &Vector2<f32>,
+    ) -> Vec<&Component> {
+        let mut components: Vec<(f32, &Component)> = self
+            .components_in_rect(chunk_step_idx, &AaBb::new(*point, *point))
+            .into_iter()
+            .map(|component| (self.component_aabb(component).area(), component))
+            .collect();
+        components.sort_by(|(a, _), (b, _)| a.total_cmp(b));
+
+        components
+            .into_iter()
+            .map(|(_, component)| component)
+            .collect()
+    }
+
+    /// Wires of a step with some part inside ```rect```, sorted by id
+    pub fn wires_in_rect(&self, chunk_step_idx: ChunkStepIdx, rect: &AaBb) -> Vec<&Wire> {
+        let Some(chunks) = self.wires_chunk_cache.get(&chunk_step_idx) else {
+            return Vec::new();
+        };
+
+        let mut wire_ids: Vec<Id> =
+            ChunkRange::from_aabb(rect, chunk_size_from_step_idx(chunk_step_idx))
+                .into_iter()
+                .filter_map(|chunk_id| chunks.get(&chunk_id))
+                .flatten()
+                .copied()
+                .collect();
+        wire_ids.sort_unstable();
+        wire_ids.dedup();
+
+        wire_ids
+            .iter()
+            .filter_map(|id| self.wires.get(id))
+            .filter(|wire| wire.intersects(rect))
+            .collect()
+    }
+
+    /// Wires of a step closer than ```tolerance``` to ```point```, the closest first
+    pub fn wires_at(
+        &self,
+        chunk_step_idx: ChunkStepIdx,
+        point: &Vector2<f32>,
+        tolerance: f32,
+    ) -> Vec<&Wire> {
+        let rect = AaBb::new(*point, *point).expanded(tolerance);
+        let mut wires: Vec<(f32, &Wire)> = self
+            .wires_in_rect(chunk_step_idx, &rect)
+            .into_iter()
+            .map(|wire| (wire.distance_to(point), wire))
+            .filter(|(distance, _)| *distance <= tolerance)
+            .collect();
+        wires.sort_by(|(a, _), (b, _)| a.total_cmp(b));
+
+        wires.into_iter().map(|(_, wire)| wire).collect()
     }
 
     pub fn wire_segments(&self) -> &ChunkedStorage<Id> {
@@ -219,39 +527,86 @@ impl Scene {
         &self.wires
     }
 
+    /// Removes every wire of the scene
+    pub fn clear_wires(&mut self) {
+        for (chunk_step_idx, chunks) in self.wires_chunk_cache.drain() {
+            self.dirty_chunks
+                .entry(chunk_step_idx)
+                .or_default()
+                .extend(chunks.into_keys());
+        }
+        self.wires.clear();
+    }
+
+    /// Adds a wire, replacing the wire with the same id if there is one
     pub fn add_wire(&mut self, chunk_step_idx: u32, wire: Wire) {
-        let wire_chunk_cache = &mut self.wires_chunk_cache;
+        self.remove_wire(wire.id());
 
-        if self.wires.contains_key(&wire.id()) {
-            // Remove the wire from the cache
-            remove_wire_from_chunks_cache(wire_chunk_cache, chunk_step_idx, wire.id());
-        }
-        add_wire_to_chunk_cache(wire_chunk_cache, chunk_step_idx, &wire);
+        let occupied_chunks =
+            add_wire_to_chunk_cache(&mut self.wires_chunk_cache, chunk_step_idx, &wire);
+        self.dirty_chunks
+            .entry(chunk_step_idx)
+            .or_default()
+            .extend(occupied_chunks);
         self.wires.insert(wire.id(), wire);
     }
-}
 
-fn remove_wire_from_chunks_cache(
-    wire_chunk_cache: &mut ChunkedStorage<Id>,
-    chunk_step_idx: ChunkStepIdx,
-    wire_id: Id,
-) {
-    if let Some(chunkid_wireids_map) = wire_chunk_cache.get_mut(&chunk_step_idx) {
-        chunkid_wireids_map
-            .iter_mut()
-            .for_each(|(_chunk_id, wire_ids)| {
-                wire_ids
-                    .binary_search(&wire_id)
-                    .map(|pos| wire_ids.remove(pos));
-            });
+    /// Step a wire was added at, found in the cache of the chunks it occupies
+    pub fn wire_chunk_step(&self, id: Id) -> Option<ChunkStepIdx> {
+        let wire = self.wires.get(&id)?;
+
+        self.wires_chunk_cache
+            .iter()
+            .find(|(chunk_step_idx, chunks)| {
+                wire.occupied_chunks(chunk_size_from_step_idx(**chunk_step_idx))
+                    .iter()
+                    .any(|chunk_id| {
+                        chunks
+                            .get(chunk_id)
+                            .is_some_and(|ids| ids.binary_search(&id).is_ok())
+                    })
+            })
+            .map(|(chunk_step_idx, _)| *chunk_step_idx)
+    }
+
+    /// Id of the first wire added after the current ones
+    pub fn next_wire_id(&self) -> Id {
+        self.wires.keys().max().map_or(0, |id| id + 1)
+    }
+
+    /// Removes a wire from the scene and returns it, ```None``` if there is no wire with that id
+    pub fn remove_wire(&mut self, id: Id) -> Option<Wire> {
+        let wire = self.wires.remove(&id)?;
+
+        // The step of a wire is not stored, it is only found in the cache of the step it was added to
+        for (chunk_step_idx, chunks) in self.wires_chunk_cache.iter_mut() {
+            for chunk_id in wire.occupied_chunks(chunk_size_from_step_idx(*chunk_step_idx)) {
+                let Some(ids) = chunks.get_mut(&chunk_id) else {
+                    continue;
+                };
+                if let Ok(pos) = ids.binary_search(&id) {
+                    ids.remove(pos);
+                    if ids.is_empty() {
+                        chunks.remove(&chunk_id);
+                    }
+                    self.dirty_chunks
+                        .entry(*chunk_step_idx)
+                        .or_default()
+                        .insert(chunk_id);
+                }
+            }
+        }
+
+        Some(wire)
     }
 }
 
+/// Adds the id of ```wire``` to the chunks it occupies and returns them
 fn add_wire_to_chunk_cache(
     wire_chunk_cache: &mut ChunkedStorage<Id>,
     chunk_step_idx: ChunkStepIdx,
     wire: &Wire,
-) {
+) -> Vec<ChunkId> {
     let occupied_chunkids: Vec<ChunkId> =
         wire.occupied_chunks(chunk_size_from_step_idx(chunk_step_idx));
 
@@ -259,8 +614,8 @@ fn add_wire_to_chunk_cache(
         .entry(chunk_step_idx)
         .or_insert(HashMap::new());
 
-    occupied_chunkids.into_iter().for_each(|chunk_id| {
-        let ids = chunked_wire_ids.entry(chunk_id).or_insert(Vec::new());
+    occupied_chunkids.iter().for_each(|chunk_id| {
+        let ids = chunked_wire_ids.entry(*chunk_id).or_default();
 
         // info!("Adding wire segment to chunk: {:?}", chunk_id);
 
@@ -273,4 +628,141 @@ fn add_wire_to_chunk_cache(
             }
         }
     });
+
+    occupied_chunkids
+}
+
+#[cfg(test)]
+mod scene_test {
+    use super::*;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_edit_keeps_chunks_consistent() {
+        let mut scene = Scene::new_empty();
+        let at = |id: Id, x: f32, y: f32| Component::new(id, 0, Vector2::new(x, y), 0.0, 0);
+        scene.add_component(0, at(1, 1.0, 1.0)).unwrap();
+        scene.add_component(0, at(2, 2.0, 2.0)).unwrap();
+
+        let dirty = scene.take_dirty_chunks();
+        assert_eq!(dirty[&0], HashSet::from([(0, 0)]));
+        assert!(scene.take_dirty_chunks().is_empty());
+
+        // Moving to another chunk drops it from the old one
+        scene
+            .move_component(1, Vector2::new(22.0, 1.0), 0.0)
+            .unwrap();
+        assert_eq!(scene.component_chunk(1), Some((0, (2, 0))));
+        assert_eq!(scene.components()[&0][&(0, 0)].len(), 1);
+        assert_eq!(
+            scene.take_dirty_chunks()[&0],
+            HashSet::from([(0, 0), (2, 0)])
+        );
+
+        scene.update_component(at(1, 3.0, 3.0)).unwrap();
+        assert!(!scene.components()[&0].contains_key(&(2, 0)));
+        assert_eq!(
+            scene.get_component(1).unwrap().position(),
+            &Vector2::new(3.0, 3.0)
+        );
+
+        scene.remove_component(2).unwrap();
+        assert!(scene.get_component(2).is_none());
+        assert_eq!(scene.component_count(), 1);
+        assert!(matches!(
+            scene.remove_component(2),
+            Err(SceneError::ComponentNotFound(2))
+        ));
+        // An id already in another chunk is rejected too
+        assert!(matches!(
+            scene.add_component(0, at(1, 50.0, 50.0)),
+            Err(SceneError::ComponentAlreadyExists(1))
+        ));
+
+        scene.take_dirty_chunks();
+        let wire = Wire::new(
+            7,
+            Vector2::new(1.0, 1.0),
+            Vector2::new(32.0, 1.0),
+            Vector2::zeros(),
+            Vector2::zeros(),
+            10.0,
+        );
+        let occupied_chunks: HashSet<ChunkId> = wire.occupied_chunks(10.0).into_iter().collect();
+        assert!(occupied_chunks.len() > 1);
+        scene.add_wire(0, wire);
+        for chunk_id in &occupied_chunks {
+            assert_eq!(scene.wire_segments()[&0][chunk_id], vec![7]);
+        }
+
+        assert!(scene.remove_wire(7).is_some());
+        assert!(scene.remove_wire(7).is_none());
+        assert!(scene.wire_segments()[&0].is_empty());
+        assert_eq!(scene.take_dirty_chunks()[&0], occupied_chunks);
+    }
+
+    #[test]
+    fn test_spatial_queries() {
+        let mut scene = Scene::new_empty();
+        let memristor = DefaultComponentTypes::Memristor as u32;
+        for (id, x) in [(0, 0.0), (1, 1.0), (2, 4.95), (3, 12.0)] {
+            scene
+                .add_component(
+                    0,
+                    Component::new(id, 0, Vector2::new(x, 0.0), 0.0, memristor),
+                )
+                .unwrap();
+        }
+        scene.add_wire(
+            0,
+            Wire::new(
+                7,
+                Vector2::new(0.0, -2.0),
+                Vector2::new(20.0, -2.0),
+                Vector2::zeros(),
+                Vector2::zeros(),
+                10.0,
+            ),
+        );
+
+        let ids =
+            |components: Vec<&Component>| components.iter().map(|c| c.id()).collect::<Vec<_>>();
+        let sorted_ids = |components: Vec<&Component>| {
+            let mut ids = ids(components);
+            ids.sort_unstable();
+            ids
+        };
+
+        assert_eq!(scene.get_components_in_chunk(0, &(0, 0)).unwrap().len(), 3);
+        assert_eq!(
+            ids(scene.components_at(0, &Vector2::new(0.05, 0.3))),
+            vec![0]
+        );
+        assert!(scene.components_at(0, &Vector2::new(0.5, 0.0)).is_empty());
+        // The component is in chunk (0, 0), but its box reaches chunk (1, 0)
+        assert_eq!(
+            ids(scene.components_at(0, &Vector2::new(5.05, 0.0))),
+            vec![2]
+        );
+
+        let rect = AaBb::new(Vector2::new(0.5, -1.0), Vector2::new(13.0, 1.0));
+        assert_eq!(
+            sorted_ids(scene.components_in_rect(0, &rect)),
+            vec![1, 2, 3]
+        );
+        assert!(scene.components_in_rect(1, &rect).is_empty());
+
+        let wire_ids = |wires: Vec<&Wire>| wires.iter().map(|w| w.id()).collect::<Vec<_>>();
+        assert_eq!(
+            wire_ids(scene.wires_at(0, &Vector2::new(15.0, -2.05), 0.1)),
+            vec![7]
+        );
+        assert!(scene.wires_at(0, &Vector2::new(15.0, -2.5), 0.1).is_empty());
+        assert!(scene.wires_at(0, &Vector2::new(20.5, -2.0), 0.1).is_empty());
+        assert_eq!(
+            wire_ids(scene.wires_in_rect(0, &rect.expanded(1.5))),
+            vec![7]
+        );
+        assert!(scene.wires_in_rect(0, &rect).is_empty());
+    }
 }
@@ -1,104 +1,72 @@
-use std::collections::HashMap;
+use std::any::Any;
+use std::collections::{BTreeMap, HashMap, HashSet, VecDeque};
+use std::fmt;
+use std::fs::File;
+use std::io::{BufWriter, Read, Write};
 use std::num::TryFromIntError;
+use std::path::Path;
 
 use super::component;
+use super::format::{self, FormatError, SceneFormat, Value};
+use super::hierarchy::{
+    ConstructNode, ConstructNodeId, ConstructNodeKind, ConstructTree, ConstructTreeError, Transform,
+};
+use super::netlist::{Netlist, NetlistError};
+use super::peripherals::{Adc, InputDriver, PeripheralConfig, ReluBlock, Tia};
+use super::router::{route_net, Rail};
 use super::scene;
+use super::types::ChunkStepIdx;
 
-use component::{Component, DefaultComponentTypes};
+use component::{Component, ComponentParams, DefaultComponentTypes};
 use egui::emath::Numeric;
 use egui::output;
 use nalgebra::Vector;
 use scene::{Scene, SceneError};
 use tracing_subscriber::layer;
 
+use crate::app::utils::chunk_size_from_step_idx;
+use crate::renderer::primitives::Port;
 use crate::types::Id;
 use crate::types::NodeId;
 
-use rsnet_net_parser::types::{Layer, LinearLayer, Nn};
+use rsnet_net_parser::types::{Activation, Layer, LinearLayer, Nn};
+use rsnet_sim::mna::{
+    solve_dc, Circuit, DcOptions, Element, ElementKind, MnaError, OperatingPoint, TransientAnalysis,
+    TransientOptions, TransientResult, Waveform,
+};
+use rsnet_sim::crossbar::{CrossbarCircuit, CrossbarError, CrossbarParasitics, IrDropReport};
+use rsnet_sim::mapping::{ConductanceMapping, MappedLayer, MappingError};
+use rsnet_sim::spice::{
+    is_ground, parse_spice, SpiceElement, SpiceElementKind, SpiceError, SpiceNetlist, SpiceOptions, SpiceWriter,
+};
+use rsnet_sim::stanford::{gap_for_conductance, StanfordModel, StanfordModelError, StanfordModelParams};
+use rsnet_sim::tiling::{Tile, TilingConfig, TilingError, TilingPlan};
 
+use memmap2::Mmap;
 use nalgebra::Vector2;
-use petgraph::graph::UnGraph;
 use thiserror::Error;
 
 // Generate a new scene from a neural network
-pub fn gen_from_nn(nn: Nn) -> Scene {
-    let mut scene = Scene::new_empty();
-
-    let mut offset = Vector2::new(0.0, 0.0);
-    let spacing = 2.0;
-    let mut last_id = 0u32;
-    // Add crossbar
-    for layer in nn.layers {
-        match layer {
-            Layer::Activation(activation_ty) => {
-                // Add activation
-            }
-            Layer::Linear(linear_layer) => {
-                last_id =
-                    add_linear(&mut scene, &linear_layer, offset, spacing, last_id).unwrap() + 1;
-                offset += Vector2::new(
-                    linear_layer.output_size as f32 * spacing + spacing * 10.0,
-                    0.0,
-                );
-            }
-        }
-    }
+pub fn gen_from_nn(nn: Nn) -> Result<Scene, SceneManagerError> {
+    let mut scene_manager = SceneManager::new();
+    scene_manager.load_nn(nn)?;
 
-    scene
+    Ok(scene_manager.into_scene())
 }
 
-fn add_linear(
-    scene: &mut Scene,
-    layer: &LinearLayer,
-    offset: Vector2<f32>,
-    spacing: f32,
-    start_id: u32,
-) -> Result<Id, SceneError> {
-    let n_rows = layer.input_size;
-    let n_cols = layer.output_size;
-
-    let total_spacing_y = n_rows as f32 * spacing;
-
-    for row in 0..n_rows {
-        for col in 0..n_cols {
-            let id: u32 = ((row + col * n_rows) as u32) + start_id;
-
-            // if col == 0 {
-            //     scene.add_wire(
-            //         0,
-            //         Wire::new(
-            //             id as u32,
-            //             Vector2::new(
-            //                 col as f32 * spacing,
-            //                 total_spacing_y - row as f32 * spacing) + offset,
-            //             Vector2::new(
-            //                 col as f32 * spacing,
-            //                 total_spacing_y - row as f32 * spacing) + offset,
-            //             Vector2::new(1.0, 0.0),
-            //             Vector2::new(1.0, 1.0),
-            //             0u32,
-            //         ),
-            //     );
-            // }
-
-            scene.add_component(
-                0,
-                Component::new(
-                    id as u32,
-                    0u32,
-                    Vector2::new(col as f32 * spacing, total_spacing_y - row as f32 * spacing)
-                        + offset,
-                    // 45.0f32.to_radians(),
-                    45.0f32.to_radians(),
-                    0u32, // id as u32 % 5u32,
-                ),
-            );
-        }
-    }
+// Generate a new scene from a SPICE netlist
+pub fn gen_from_spice(source: &str) -> Result<Scene, SceneManagerError> {
+    let mut scene_manager = SceneManager::new();
+    scene_manager.load_spice(&parse_spice(source).map_err(SceneManagerError::SpiceImportError)?)?;
+    Ok(scene_manager.into_scene())
+}
 
-    Ok(((n_rows - 1) + (n_cols - 1) * n_rows) as u32 + start_id + 100)
+// Open a scene saved with SceneManager::save
+pub fn gen_from_file(path: &Path) -> Result<Scene, SceneManagerError> {
+    Ok(SceneManager::open(path)?.into_scene())
 }
 
+#[derive(Clone)]
 pub struct Crossbar {
     /// The index of the layer that the crossbar belongs to (a layer in a MLP consists only of one crossbar).
     layer_idx: usize,
@@ -123,6 +91,16 @@ pub struct Crossbar {
 
     /// The range of components Ids (inside the) that belong to the crossbar.
     components_id_range: (Id, Id),
+
+    /// Resistances of the word/bit lines, drivers and sense amplifiers, used to estimate the IR drop.
+    parasitics: CrossbarParasitics,
+
+    /// Target conductance of every memristor, in the same (column major) order as the component ids. When set, the
+    /// components added to the scene carry it as their parameters.
+    conductances: Option<Vec<f64>>,
+
+    /// Part of the layer matrix implemented by the crossbar when the layer is split across several arrays.
+    tile: Option<Tile>,
 }
 
 impl Crossbar {
@@ -135,8 +113,24 @@ impl Crossbar {
         start_component_id: Id,
         spacing: f32,
         center: Vector2<f32>,
-    ) -> Crossbar {
-        Crossbar {
+    ) -> Result<Crossbar, SceneManagerError> {
+        if rows == 0 || cols == 0 {
+            return Err(CrossbarError::EmptyCrossbar {
+                rows: rows as usize,
+                cols: cols as usize,
+            }
+            .into());
+        }
+        let last_component_id = rows
+            .checked_mul(cols)
+            .and_then(|count| start_component_id.checked_add(count - 1))
+            .ok_or(SceneManagerError::CrossbarTooLarge {
+                rows,
+                cols,
+                start: start_component_id,
+            })?;
+
+        Ok(Crossbar {
             layer_idx,
             input_nodes,
             output_nodes,
@@ -144,11 +138,168 @@ impl Crossbar {
             cols: cols,
             spacing,
             center,
-            components_id_range: (
-                start_component_id,
-                (rows - 1 + (cols - 1) * rows) + start_component_id as Id,
-            ),
+            components_id_range: (start_component_id, last_component_id),
+            parasitics: CrossbarParasitics::default(),
+            conductances: None,
+            tile: None,
+        })
+    }
+
+    /// Creates a crossbar whose memristors are programmed to the conductances of ```mapped```, its rows and columns
+    /// are connected to the nodes ```l<layer_idx>.x<row>``` and ```l<layer_idx>.c<col>```
+    pub fn from_mapped_layer(
+        layer_idx: usize,
+        mapped: &MappedLayer,
+        start_component_id: Id,
+        spacing: f32,
+        center: Vector2<f32>,
+    ) -> Result<Crossbar, SceneManagerError> {
+        let mut crossbar = Crossbar::new(
+            layer_idx,
+            (0..mapped.rows).map(|row| format!("l{}.x{}", layer_idx, row)).collect(),
+            (0..mapped.cols).map(|col| format!("l{}.c{}", layer_idx, col)).collect(),
+            mapped.rows.try_into()?,
+            mapped.cols.try_into()?,
+            start_component_id,
+            spacing,
+            center,
+        )?;
+        crossbar.set_conductances(mapped.conductances.clone())?;
+
+        Ok(crossbar)
+    }
+
+    pub fn layer_idx(&self) -> usize {
+        self.layer_idx
+    }
+
+    pub fn input_nodes(&self) -> &Vec<String> {
+        &self.input_nodes
+    }
+
+    pub fn output_nodes(&self) -> &Vec<String> {
+        &self.output_nodes
+    }
+
+    pub fn rows(&self) -> u32 {
+        self.rows
+    }
+
+    pub fn cols(&self) -> u32 {
+        self.cols
+    }
+
+    pub fn parasitics(&self) -> &CrossbarParasitics {
+        &self.parasitics
+    }
+
+    pub fn set_parasitics(&mut self, parasitics: CrossbarParasitics) {
+        self.parasitics = parasitics;
+    }
+
+    pub fn tile(&self) -> Option<&Tile> {
+        self.tile.as_ref()
+    }
+
+    pub fn set_tile(&mut self, tile: Option<Tile>) {
+        self.tile = tile;
+    }
+
+    pub fn conductances(&self) -> Option<&Vec<f64>> {
+        self.conductances.as_ref()
+    }
+
+    /// Sets the target conductance of every memristor, ```conductances[row + col * rows]```
+    pub fn set_conductances(&mut self, conductances: Vec<f64>) -> Result<(), SceneManagerError> {
+        let expected = self.rows as usize * self.cols as usize;
+        if conductances.len() != expected {
+            return Err(CrossbarError::DimensionMismatch {
+                what: "conductances",
+                expected,
+                got: conductances.len(),
+            }
+            .into());
+        }
+
+        self.conductances = Some(conductances);
+        Ok(())
+    }
+
+    /// Builds the resistive network of the crossbar from the conductances of its memristors in ```scene```
+    pub fn to_circuit(&self, scene: &Scene) -> Result<CrossbarCircuit, SceneManagerError> {
+        let (first, last) = self.components_id_range;
+
+        let conductances = (first..=last)
+            .map(|id| {
+                let component = scene
+                    .get_component(id)
+                    .ok_or(SceneManagerError::ComponentNotFound(id))?;
+
+                match *component.params() {
+                    ComponentParams::Conductance(g) => Ok(g),
+                    ComponentParams::Resistance(r) => Ok(1.0 / r),
+                    _ => Err(SceneManagerError::MissingComponentParams(id)),
+                }
+            })
+            .collect::<Result<Vec<f64>, SceneManagerError>>()?;
+
+        Ok(CrossbarCircuit::new(
+            self.rows as usize,
+            self.cols as usize,
+            conductances,
+            self.parasitics,
+        )?)
+    }
+
+    /// Error of the output currents caused by the parasitics when ```inputs``` (one voltage per row) are applied
+    pub fn ir_drop(&self, scene: &Scene, inputs: &[f64]) -> Result<IrDropReport, SceneManagerError> {
+        Ok(self.to_circuit(scene)?.ir_drop(inputs)?)
+    }
+
+    /// Word lines (first port of the devices of a row) and bit lines (second port of the devices of a column) of the
+    /// named rows and columns
+    pub fn rails(&self) -> Vec<Rail> {
+        let id = |row: u32, col: u32| row + col * self.rows + self.components_id_range.0;
+
+        let word_lines = self.input_nodes.iter().zip(0..self.rows).map(|(name, row)| {
+            Rail::new(name.clone(), (0..self.cols).map(|col| (id(row, col), 0)).collect())
+        });
+        let bit_lines = self.output_nodes.iter().zip(0..self.cols).map(|(name, col)| {
+            Rail::new(name.clone(), (0..self.rows).map(|row| (id(row, col), 1)).collect())
+        });
+
+        word_lines.chain(bit_lines).filter(|rail| !rail.net().is_empty()).collect()
+    }
+
+    /// Decodes a crossbar saved with ```to_value```, its conductances are taken from the memristors in ```scene```
+    pub fn from_value(value: &Value, scene: &Scene) -> Result<Crossbar, SceneManagerError> {
+        let mut crossbar = Crossbar::new(
+            value.field("layer_idx")?.as_usize()?,
+            value.field("input_nodes")?.as_strings()?,
+            value.field("output_nodes")?.as_strings()?,
+            value.field("rows")?.as_u32()?,
+            value.field("cols")?.as_u32()?,
+            value.field("start_component_id")?.as_u32()?,
+            value.field("spacing")?.as_f32()?,
+            value.field("center")?.as_vector2()?,
+        )?;
+        crossbar.set_parasitics(value.field("parasitics")?.try_into()?);
+        crossbar.set_tile(value.field("tile")?.as_option(|tile| tile.try_into())?);
+
+        if value.field("conductances")?.as_bool()? {
+            let (first, last) = crossbar.components_id_range;
+            let conductances = (first..=last)
+                .map(|id| match scene.get_component(id).map(|component| *component.params()) {
+                    Some(ComponentParams::Conductance(g)) => Ok(g),
+                    Some(ComponentParams::Resistance(r)) => Ok(1.0 / r),
+                    Some(_) => Err(SceneManagerError::MissingComponentParams(id)),
+                    None => Err(SceneManagerError::ComponentNotFound(id)),
+                })
+                .collect::<Result<Vec<f64>, SceneManagerError>>()?;
+            crossbar.set_conductances(conductances)?;
         }
+
+        Ok(crossbar)
     }
 }
 
@@ -159,7 +310,15 @@ impl Construct for Crossbar {
 
     fn add_to_scene(&self, scene_manager: &mut SceneManager) -> Result<(), SceneManagerError> {
 
-        let scene = scene_manager.scene_mut();
+        // Unnamed rows and columns are left unconnected
+        let mut node_ids = |names: &Vec<String>| -> Vec<Option<NodeId>> {
+            names
+                .iter()
+                .map(|name| (!name.is_empty()).then(|| scene_manager.add_node(name.clone())))
+                .collect()
+        };
+        let input_node_ids = node_ids(&self.input_nodes);
+        let output_node_ids = node_ids(&self.output_nodes);
 
         let n_rows = self.rows;
         let n_cols = self.cols;
@@ -208,24 +367,258 @@ impl Construct for Crossbar {
 
 
 
-                scene.add_component(
-                    0,
-                    Component::new(
-                        id,
-                        0u32,
-                        Vector2::new(
-                            col_f32 * self.spacing,
-                            total_spacing_y - row_f32 * self.spacing,
-                        ) + self.center,
-                        45.0f32.to_radians(),
-                        DefaultComponentTypes::Memristor.into(),
-                    ),
+                let mut component = Component::new(
+                    id,
+                    0u32,
+                    Vector2::new(
+                        col_f32 * self.spacing,
+                        total_spacing_y - row_f32 * self.spacing,
+                    ) + self.center,
+                    45.0f32.to_radians(),
+                    DefaultComponentTypes::Memristor.into(),
                 );
+
+                if let Some(conductances) = &self.conductances {
+                    component.set_params(ComponentParams::Conductance(
+                        conductances[(row + col * n_rows) as usize],
+                    ));
+                }
+
+                scene_manager.scene_mut().add_component(0, component)?;
+
+                let nodes = (input_node_ids.get(row as usize), output_node_ids.get(col as usize));
+                if let (Some(Some(input)), Some(Some(output))) = nodes {
+                    scene_manager.connect(id, *input, *output)?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    fn type_name(&self) -> &'static str {
+        "crossbar"
+    }
+
+    /// The conductances are not saved, they are the parameters of the memristors of the scene
+    fn to_value(&self) -> Value {
+        Value::object([
+            ("type", self.type_name().into()),
+            ("layer_idx", self.layer_idx.into()),
+            ("input_nodes", self.input_nodes.clone().into()),
+            ("output_nodes", self.output_nodes.clone().into()),
+            ("rows", self.rows.into()),
+            ("cols", self.cols.into()),
+            ("start_component_id", self.components_id_range.0.into()),
+            ("spacing", self.spacing.into()),
+            ("center", (&self.center).into()),
+            ("parasitics", (&self.parasitics).into()),
+            ("tile", self.tile.as_ref().map(Value::from).into()),
+            ("conductances", self.conductances.is_some().into()),
+        ])
+    }
+
+    fn instantiate(&self, start_component_id: Id, rename: &dyn Fn(&str) -> String) -> Box<dyn Construct> {
+        let mut crossbar = self.clone();
+        crossbar.input_nodes = rename_nodes(&self.input_nodes, rename);
+        crossbar.output_nodes = rename_nodes(&self.output_nodes, rename);
+        crossbar.components_id_range = shift_id_range(self.components_id_range, start_component_id);
+        Box::new(crossbar)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
+}
+
+/// Inverting summing amplifiers that add the partial sums of the tiles that share the same outputs.
+///
+/// Every output has an op-amp with ```inputs``` input resistors (one per tile) and a feedback resistor, all of
+/// ```resistance```, so ```v = -sum(v_tile)```. Components are ordered by output, op-amp first, then the feedback and
+/// the input resistors.
+#[derive(Clone)]
+pub struct PartialSumAdder {
+    /// The index of the layer whose partial sums are accumulated.
+    layer_idx: usize,
+    /// The number of partial sums added for every output.
+    inputs: u32,
+    /// The number of outputs.
+    width: u32,
+
+    /// The partial sums, output major: input ```k``` of output ```o``` is ```input_nodes[o * inputs + k]```.
+    input_nodes: Vec<String>,
+    output_nodes: Vec<String>,
+
+    resistance: f64,
+    gain: f64,
+
+    spacing: f32,
+    position: Vector2<f32>,
+
+    /// The range of components Ids that belong to the adder.
+    components_id_range: (Id, Id),
+}
+
+impl PartialSumAdder {
+    #[allow(clippy::too_many_arguments)]
+    pub fn new(
+        layer_idx: usize,
+        input_nodes: Vec<String>,
+        output_nodes: Vec<String>,
+        resistance: f64,
+        gain: f64,
+        start_component_id: Id,
+        spacing: f32,
+        position: Vector2<f32>,
+    ) -> Result<PartialSumAdder, SceneManagerError> {
+        let width = output_nodes.len();
+        if width == 0 || !input_nodes.len().is_multiple_of(width) {
+            return Err(SceneManagerError::DimensionMismatch {
+                what: "partial sums",
+                expected: width,
+                got: input_nodes.len(),
+            });
+        }
+        let inputs: u32 = (input_nodes.len() / width).try_into()?;
+        let width: u32 = width.try_into()?;
+
+        Ok(PartialSumAdder {
+            layer_idx,
+            inputs,
+            width,
+            input_nodes,
+            output_nodes,
+            resistance,
+            gain,
+            spacing,
+            position,
+            components_id_range: (
+                start_component_id,
+                start_component_id + width * Self::components_per_output(inputs) - 1,
+            ),
+        })
+    }
+
+    fn components_per_output(inputs: u32) -> u32 {
+        inputs + 2
+    }
+
+    pub fn layer_idx(&self) -> usize {
+        self.layer_idx
+    }
+
+    pub fn inputs(&self) -> u32 {
+        self.inputs
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn resistance(&self) -> f64 {
+        self.resistance
+    }
+
+    pub fn output_nodes(&self) -> &Vec<String> {
+        &self.output_nodes
+    }
+
+    /// Decodes an adder saved with ```to_value```
+    pub fn from_value(value: &Value) -> Result<PartialSumAdder, SceneManagerError> {
+        PartialSumAdder::new(
+            value.field("layer_idx")?.as_usize()?,
+            value.field("input_nodes")?.as_strings()?,
+            value.field("output_nodes")?.as_strings()?,
+            value.field("resistance")?.as_f64()?,
+            value.field("gain")?.as_f64()?,
+            value.field("start_component_id")?.as_u32()?,
+            value.field("spacing")?.as_f32()?,
+            value.field("position")?.as_vector2()?,
+        )
+    }
+}
+
+impl Construct for PartialSumAdder {
+    fn components_id_range(&self) -> (Id, Id) {
+        self.components_id_range
+    }
+
+    fn add_to_scene(&self, scene_manager: &mut SceneManager) -> Result<(), SceneManagerError> {
+        let ground = scene_manager.ground();
+        let per_output = Self::components_per_output(self.inputs);
+
+        for (output_idx, output) in self.output_nodes.iter().enumerate() {
+            let x = output_idx as f32 * self.spacing;
+            let first_id = self.components_id_range.0 + output_idx as Id * per_output;
+
+            for idx in 0..per_output {
+                let position = self.position + Vector2::new(x, -(idx as f32) * self.spacing);
+                let (ty, params) = if idx == 0 {
+                    (DefaultComponentTypes::OpAmp, ComponentParams::Gain(self.gain))
+                } else {
+                    (DefaultComponentTypes::Resistor, ComponentParams::Resistance(self.resistance))
+                };
+
+                let mut component = Component::new(first_id + idx, 0u32, position, 0.0, ty.into());
+                component.set_params(params);
+                scene_manager.scene_mut().add_component(0, component)?;
+            }
+
+            let summing = scene_manager.add_node(format!("{}.sum", output));
+            let output = scene_manager.add_node(output.clone());
+            scene_manager.connect_op_amp(first_id, ground, summing, output)?;
+            scene_manager.connect(first_id + 1, summing, output)?;
+
+            let inputs = self.inputs as usize;
+            for (k, input) in self.input_nodes[output_idx * inputs..(output_idx + 1) * inputs]
+                .iter()
+                .enumerate()
+            {
+                let input = scene_manager.add_node(input.clone());
+                scene_manager.connect(first_id + 2 + k as Id, input, summing)?;
             }
         }
 
         Ok(())
     }
+
+    fn type_name(&self) -> &'static str {
+        "partial_sum_adder"
+    }
+
+    fn to_value(&self) -> Value {
+        Value::object([
+            ("type", self.type_name().into()),
+            ("layer_idx", self.layer_idx.into()),
+            ("input_nodes", self.input_nodes.clone().into()),
+            ("output_nodes", self.output_nodes.clone().into()),
+            ("resistance", self.resistance.into()),
+            ("gain", self.gain.into()),
+            ("start_component_id", self.components_id_range.0.into()),
+            ("spacing", self.spacing.into()),
+            ("position", (&self.position).into()),
+        ])
+    }
+
+    fn instantiate(&self, start_component_id: Id, rename: &dyn Fn(&str) -> String) -> Box<dyn Construct> {
+        let mut adder = self.clone();
+        adder.input_nodes = rename_nodes(&self.input_nodes, rename);
+        adder.output_nodes = rename_nodes(&self.output_nodes, rename);
+        adder.components_id_range = shift_id_range(self.components_id_range, start_component_id);
+        Box::new(adder)
+    }
+
+    fn as_any(&self) -> &dyn Any {
+        self
+    }
+
+    fn as_any_mut(&mut self) -> &mut dyn Any {
+        self
+    }
 }
 
 pub trait Construct {
@@ -233,6 +626,37 @@ pub trait Construct {
     fn components_id_range(&self) -> (Id, Id);
     /// Adds the construct to the scene using components
     fn add_to_scene(&self, scene_manager: &mut SceneManager) -> Result<(), SceneManagerError>;
+    /// Name of the kind of construct, the ```type``` field of ```to_value```
+    fn type_name(&self) -> &'static str;
+    /// Saves the construct, the value has a ```type``` field used to decode it, see ```format```
+    fn to_value(&self) -> Value;
+    /// Copy of the construct made of the components starting at ```start_component_id```, connected to the nodes that
+    /// ```rename``` returns for the nodes of the original
+    fn instantiate(&self, start_component_id: Id, rename: &dyn Fn(&str) -> String) -> Box<dyn Construct>;
+    /// Used to recover the concrete construct, e.g. to query the crossbars of the scene
+    fn as_any(&self) -> &dyn Any;
+    fn as_any_mut(&mut self) -> &mut dyn Any;
+}
+
+impl fmt::Debug for dyn Construct {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct(self.type_name())
+            .field("components_id_range", &self.components_id_range())
+            .finish()
+    }
+}
+
+/// ```range``` moved so that it starts at ```start```
+pub(crate) fn shift_id_range(range: (Id, Id), start: Id) -> (Id, Id) {
+    (start, start + (range.1 - range.0))
+}
+
+/// Renames the nodes of a construct, unnamed (unconnected) nodes stay unnamed
+pub(crate) fn rename_nodes(nodes: &[String], rename: &dyn Fn(&str) -> String) -> Vec<String> {
+    nodes
+        .iter()
+        .map(|node| if node.is_empty() { String::new() } else { rename(node) })
+        .collect()
 }
 
 #[derive(Error, Debug)]
@@ -243,6 +667,78 @@ pub enum SceneManagerError {
     IntConversionError(TryFromIntError),
     #[error("Error while converting between float and int types: {0}")]
     FloatIntConversionError(String),
+    #[error("Component {0} is part of the netlist but not of the scene")]
+    ComponentNotFound(Id),
+    #[error("Component {0} has no electrical parameters, it cannot be simulated")]
+    MissingComponentParams(Id),
+    #[error("There was an error while simulating the circuit: {0}")]
+    SimulationError(MnaError),
+    #[error("Invalid memristor model: {0}")]
+    DeviceModelError(StanfordModelError),
+    #[error("There was an error while analysing a crossbar: {0}")]
+    CrossbarError(CrossbarError),
+    #[error("The weights could not be mapped to conductances: {0}")]
+    MappingError(MappingError),
+    #[error("The layer could not be split into crossbars: {0}")]
+    TilingError(TilingError),
+    #[error("Layer {0} ({1}) has no circuit yet")]
+    UnsupportedLayer(usize, String),
+    #[error("A {rows}x{cols} crossbar starting at component {start} does not fit in the range of component ids")]
+    CrossbarTooLarge { rows: u32, cols: u32, start: Id },
+    #[error("Layer {0} ({1}) has no inputs, it needs to follow a Linear or Conv2d layer")]
+    MissingLayerInputs(usize, String),
+    #[error("Single device columns are not supported, the weights need differential pairs")]
+    SingleEndedMapping,
+    #[error("Expected {expected} {what}, got {got}")]
+    DimensionMismatch {
+        what: &'static str,
+        expected: usize,
+        got: usize,
+    },
+    #[error("Port {1} of component {0} is not connected to any node")]
+    UnconnectedPort(Id, String),
+    #[error("Component {0} has no port called {1}")]
+    UnknownPort(Id, String),
+    #[error("Component {0} (type {1}) cannot be simulated with its parameters")]
+    UnsupportedComponent(Id, u32),
+    #[error("There was an error while editing the netlist: {0}")]
+    NetlistError(NetlistError),
+    #[error("The scene has no {0}, load a network first")]
+    MissingConstruct(&'static str),
+    #[error("There was an error while exporting the netlist: {0}")]
+    SpiceError(SpiceError),
+    #[error("There was an error while importing the netlist: {0}")]
+    SpiceImportError(SpiceError),
+    #[error("There was an error while saving or opening the scene: {0}")]
+    FormatError(FormatError),
+    #[error("Wire {0} is not part of the scene")]
+    WireNotFound(Id),
+    #[error("There was an error while editing the constructs: {0}")]
+    ConstructTreeError(ConstructTreeError),
+}
+
+impl From<ConstructTreeError> for SceneManagerError {
+    fn from(value: ConstructTreeError) -> Self {
+        Self::ConstructTreeError(value)
+    }
+}
+
+impl From<FormatError> for SceneManagerError {
+    fn from(value: FormatError) -> Self {
+        Self::FormatError(value)
+    }
+}
+
+impl From<SpiceError> for SceneManagerError {
+    fn from(value: SpiceError) -> Self {
+        Self::SpiceError(value)
+    }
+}
+
+impl From<NetlistError> for SceneManagerError {
+    fn from(value: NetlistError) -> Self {
+        Self::NetlistError(value)
+    }
 }
 
 impl From<SceneError> for SceneManagerError {
@@ -251,40 +747,179 @@ impl From<SceneError> for SceneManagerError {
     }
 }
 
+impl From<MnaError> for SceneManagerError {
+    fn from(value: MnaError) -> Self {
+        Self::SimulationError(value)
+    }
+}
+
+impl From<StanfordModelError> for SceneManagerError {
+    fn from(value: StanfordModelError) -> Self {
+        Self::DeviceModelError(value)
+    }
+}
+
+impl From<CrossbarError> for SceneManagerError {
+    fn from(value: CrossbarError) -> Self {
+        Self::CrossbarError(value)
+    }
+}
+
+impl From<MappingError> for SceneManagerError {
+    fn from(value: MappingError) -> Self {
+        Self::MappingError(value)
+    }
+}
+
+impl From<TilingError> for SceneManagerError {
+    fn from(value: TilingError) -> Self {
+        Self::TilingError(value)
+    }
+}
+
 impl From<TryFromIntError> for SceneManagerError {
     fn from(value: TryFromIntError) -> Self {
         Self::IntConversionError(value)
     }
 }
 
+/// IR drop of a layer, the partial sums of its tiles are added before comparing the outputs
+#[derive(Debug, Clone)]
+pub struct LayerIrDrop {
+    pub layer_idx: usize,
+    pub report: IrDropReport,
+}
+
+/// How a layer was split into crossbar arrays
+#[derive(Debug, Clone)]
+pub struct LayerTiling {
+    pub layer_idx: usize,
+    pub plan: TilingPlan,
+}
+
+/// Name of the node created by every ```SceneManager``` as the reference for voltages
+pub const GROUND_NODE_NAME: &str = "gnd";
+
+/// A component taken out of the scene, with the step it was stored at and the bindings of its ports
+pub type RemovedComponent = (ChunkStepIdx, Component, Vec<(usize, NodeId)>);
+
 pub struct SceneManager {
     scene: Scene,
 
-    /// Just store the connections, the components are stored in the scene. Nodes are ```usize```, edges do not have any data.
-    netlist: UnGraph<usize, ()>,
+    /// Just store the connections, the components are stored in the scene. Every port of a component is bound to a
+    /// named node (net).
+    netlist: Netlist,
 
-    /// Map from node name to id
-    node_name_map: HashMap<String, usize>,
+    /// Ideal sources used to drive the circuit when simulating it, they are not part of the scene.
+    sources: Vec<Element>,
 
     last_component_id: Option<Id>,
-    last_node_id: Option<NodeId>,
+
+    /// How the weights of the loaded networks are programmed into the crossbars
+    conductance_mapping: ConductanceMapping,
+
+    /// Maximum size of the physical crossbar arrays, larger layers are split into tiles
+    tiling: TilingConfig,
+    /// Tiling of every layer loaded with ```load_nn```
+    layer_tilings: Vec<LayerTiling>,
+    /// DACs, TIAs, ADCs and activations added around the crossbars by ```load_nn```
+    peripherals: PeripheralConfig,
 
     /// A construct is a group of components such that they define a higher level circuital entity.
     /// For example, a crossbar is a construct, an activation function is a construct, etc.
     /// This is useful for grouping components. For example, if we want to move a crossbar, we can
     /// move all the components that are part of the crossbar.
     constructs: Vec<Box<dyn Construct>>,
+    /// Groups the constructs into networks, layers and tiles, its leaves index ```constructs```
+    hierarchy: ConstructTree,
 }
 
 impl SceneManager {
     pub fn new() -> SceneManager {
-        SceneManager {
+        let mut scene_manager = SceneManager {
             scene: Scene::new_empty(),
-            netlist: UnGraph::new_undirected(),
-            node_name_map: HashMap::new(),
+            netlist: Netlist::new(),
+            sources: Vec::new(),
             last_component_id: None,
-            last_node_id: None,
+            conductance_mapping: ConductanceMapping::default(),
+            tiling: TilingConfig::default(),
+            layer_tilings: Vec::new(),
+            peripherals: PeripheralConfig::default(),
             constructs: Vec::new(),
+            hierarchy: ConstructTree::new(),
+        };
+
+        scene_manager.add_node(GROUND_NODE_NAME.to_string());
+
+        scene_manager
+    }
+
+    /// Wraps a scene built without a scene manager, like the default one of the viewer. Its components are not bound
+    /// to any net.
+    pub fn from_scene(scene: Scene) -> SceneManager {
+        let last_component_id = scene
+            .components()
+            .values()
+            .flat_map(|chunks| chunks.values())
+            .flat_map(|components| components.iter().map(|component| component.id()))
+            .max();
+
+        let mut scene_manager = SceneManager::new();
+        scene_manager.scene = scene;
+        scene_manager.last_component_id = last_component_id;
+
+        scene_manager
+    }
+
+    /// Restores a scene manager from the parts of a scene file, the configuration is left at its default
+    pub(super) fn from_parts(
+        scene: Scene,
+        netlist: Netlist,
+        sources: Vec<Element>,
+        last_component_id: Option<Id>,
+        layer_tilings: Vec<LayerTiling>,
+        constructs: Vec<Box<dyn Construct>>,
+        hierarchy: ConstructTree,
+    ) -> SceneManager {
+        SceneManager {
+            scene,
+            netlist,
+            sources,
+            last_component_id,
+            conductance_mapping: ConductanceMapping::default(),
+            tiling: TilingConfig::default(),
+            layer_tilings,
+            peripherals: PeripheralConfig::default(),
+            constructs,
+            hierarchy,
+        }
+    }
+
+    /// Saves the scene, the netlist, the sources and the constructs to ```path```
+    pub fn save(&self, path: &Path, format: SceneFormat) -> Result<(), SceneManagerError> {
+        let writer = BufWriter::new(File::create(path).map_err(FormatError::from)?);
+        match format {
+            SceneFormat::Binary => format::write_binary(self, writer)?,
+            SceneFormat::Json => format::write_json(self, writer)?,
+        };
+
+        Ok(())
+    }
+
+    /// Opens a file written by ```save```, the format is detected from its contents. Binary files are memory mapped.
+    pub fn open(path: &Path) -> Result<SceneManager, SceneManagerError> {
+        let mut file = File::open(path).map_err(FormatError::from)?;
+
+        let mut magic = [0u8; format::MAGIC.len()];
+        let is_binary = file.read_exact(&mut magic).is_ok() && magic == format::MAGIC;
+        if is_binary {
+            // SAFETY: the file is only read while it is mapped. Changing it from another process meanwhile is
+            // undefined behaviour, as it is for any memory mapped file.
+            let bytes = unsafe { Mmap::map(&file) }.map_err(FormatError::from)?;
+            format::read_binary(&bytes)
+        } else {
+            let source = std::fs::read_to_string(path).map_err(FormatError::from)?;
+            format::read_json(&source)
         }
     }
 
@@ -296,51 +931,1818 @@ impl SceneManager {
         &mut self.scene
     }
 
-    pub fn add_node(&mut self, name: String) {
-        
+    pub fn into_scene(self) -> Scene {
+        self.scene
     }
 
-    pub fn load_nn(&mut self, nn: Nn) -> Result<(), SceneManagerError> {
-        let mut scene = Scene::new_empty();
+    pub fn netlist(&self) -> &Netlist {
+        &self.netlist
+    }
 
-        let mut offset = Vector2::new(0.0, 0.0);
-        let crossbar_spacing = 2.0;
+    pub fn ground(&self) -> NodeId {
+        self.netlist
+            .net_id(GROUND_NODE_NAME)
+            .expect("the ground node is created with the scene manager")
+    }
 
-        // let mut last_nodes = None;
+    pub fn node_id(&self, name: &str) -> Option<NodeId> {
+        self.netlist.net_id(name)
+    }
 
-        // Add crossbar
-        for (layer_idx, layer) in nn.layers.iter().enumerate() {
-            match layer {
-                Layer::Activation(activation_ty) => {
-                    // Add activation
-                }
-                Layer::Linear(linear_layer) => {
+    pub fn node_name(&self, node_id: NodeId) -> Option<&str> {
+        self.netlist.net_name(node_id)
+    }
 
-                    // layer_idx: usize,
-                    // input_nodes: Vec<String>,
-                    // output_nodes: Vec<String>,
-                    // rows: u32,
-                    // cols: u32,
-                    // start_component_id: Id,
-                    // spacing: f32,
-                    // center: Vector2<f32>,
+    /// Returns the id of the node called ```name```, creating it if it does not exist
+    pub fn add_node(&mut self, name: String) -> NodeId {
+        self.netlist.add_net(name)
+    }
 
-                    let crossbar = Crossbar::new(
-                        layer_idx,
-                        vec!["".to_string(); linear_layer.input_size as usize],
-                        vec!["".to_string(); linear_layer.output_size as usize],
-                        linear_layer.input_size.try_into()?,
-                        linear_layer.output_size as u32,
-                        self.last_component_id.map_or(0, |id| id + 1),
-                        crossbar_spacing,
-                        offset,
-                    );
-                    crossbar.add_to_scene(self)?;
-                    self.last_component_id = Some(crossbar.components_id_range().1);
+    /// Merges node ```from``` into ```into```, every port and source connected to ```from``` is moved and its names
+    /// refer to ```into``` afterwards. The ground node is always kept. Returns the id of the merged node.
+    pub fn merge_nodes(&mut self, into: NodeId, from: NodeId) -> Result<NodeId, SceneManagerError> {
+        let (into, from) = if from == self.ground() { (from, into) } else { (into, from) };
+
+        self.netlist.merge(into, from)?;
+        for source in self.sources.iter_mut() {
+            for node in [&mut source.pos, &mut source.neg] {
+                if *node == from {
+                    *node = into;
                 }
             }
         }
 
+        Ok(into)
+    }
+
+    /// Binds the port called ```port``` of a component of the scene to ```node```
+    pub fn bind_port(&mut self, component_id: Id, port: &str, node: NodeId) -> Result<(), SceneManagerError> {
+        let component = self
+            .scene
+            .get_component(component_id)
+            .ok_or(SceneManagerError::ComponentNotFound(component_id))?;
+        let port_idx = component
+            .port_index(self.scene.primitives(), port)
+            .ok_or_else(|| SceneManagerError::UnknownPort(component_id, port.to_string()))?;
+
+        Ok(self.netlist.bind(component_id, port_idx, node)?)
+    }
+
+    /// Connects a two terminal component between nodes ```pos``` and ```neg```, binding its first and second ports
+    pub fn connect(&mut self, component_id: Id, pos: NodeId, neg: NodeId) -> Result<(), SceneManagerError> {
+        self.netlist.bind(component_id, 0, pos)?;
+        self.netlist.bind(component_id, 1, neg)?;
         Ok(())
     }
+
+    /// Connects an op-amp of the scene, its output is referred to ground
+    pub fn connect_op_amp(
+        &mut self,
+        component_id: Id,
+        non_inverting: NodeId,
+        inverting: NodeId,
+        output: NodeId,
+    ) -> Result<(), SceneManagerError> {
+        self.bind_port(component_id, "plus", non_inverting)?;
+        self.bind_port(component_id, "minus", inverting)?;
+        self.bind_port(component_id, "out", output)
+    }
+
+    /// Node bound to the port called ```port``` of a component of the scene
+    pub fn port_node(&self, component_id: Id, port: &str) -> Result<NodeId, SceneManagerError> {
+        let component = self
+            .scene
+            .get_component(component_id)
+            .ok_or(SceneManagerError::ComponentNotFound(component_id))?;
+
+        component
+            .port_index(self.scene.primitives(), port)
+            .and_then(|port_idx| self.netlist.net_of(component_id, port_idx))
+            .ok_or_else(|| SceneManagerError::UnconnectedPort(component_id, port.to_string()))
+    }
+
+    /// Ports of a component with the node each one is bound to, ```None``` for the unconnected ones
+    pub fn component_ports(&self, component_id: Id) -> Result<Vec<(Port, Option<NodeId>)>, SceneManagerError> {
+        let component = self
+            .scene
+            .get_component(component_id)
+            .ok_or(SceneManagerError::ComponentNotFound(component_id))?;
+
+        Ok(component
+            .ports(self.scene.primitives())
+            .into_iter()
+            .enumerate()
+            .map(|(port_idx, port)| (port, self.netlist.net_of(component_id, port_idx)))
+            .collect())
+    }
+
+    /// Position of the port with index ```port``` of a component of the scene
+    fn port_position(&self, component_id: Id, port: usize) -> Result<Vector2<f32>, SceneManagerError> {
+        let component = self
+            .scene
+            .get_component(component_id)
+            .ok_or(SceneManagerError::ComponentNotFound(component_id))?;
+
+        component
+            .ports(self.scene.primitives())
+            .get(port)
+            .map(|port| component.port_position(port))
+            .ok_or_else(|| SceneManagerError::UnknownPort(component_id, port.to_string()))
+    }
+
+    /// Replaces the wires of the scene with an orthogonal route of every net. The word and bit lines of the crossbars
+    /// are drawn as single straight wires. Ground is not routed, it would cross the whole scene. Returns the number of
+    /// wires.
+    pub fn route_wires(&mut self) -> Result<usize, SceneManagerError> {
+        let chunk_step_idx = 0;
+        let chunk_size = chunk_size_from_step_idx(chunk_step_idx);
+
+        let mut rails: HashMap<NodeId, Vec<Rail>> = HashMap::new();
+        for rail in self.crossbars().flat_map(|crossbar| crossbar.rails()) {
+            if let Some(net) = self.node_id(rail.net()) {
+                rails.entry(net).or_default().push(rail);
+            }
+        }
+
+        let ground = self.ground();
+        let mut nets: Vec<NodeId> = self.netlist.nets().filter(|net| *net != ground).collect();
+        nets.sort();
+
+        let mut wires = Vec::new();
+        for net in nets {
+            let mut on_rail = HashSet::new();
+            let mut rail_positions = Vec::new();
+            for rail in rails.remove(&net).unwrap_or_default() {
+                let mut positions = Vec::new();
+                for (component_id, port) in rail.ports() {
+                    // Devices of rows or columns that are not connected are skipped
+                    if self.netlist.net_of(*component_id, *port) == Some(net) {
+                        positions.push(self.port_position(*component_id, *port)?);
+                        on_rail.insert((*component_id, *port));
+                    }
+                }
+                rail_positions.push(positions);
+            }
+
+            let terminals = self
+                .netlist
+                .components_on(net)
+                .into_iter()
+                .filter(|binding| !on_rail.contains(binding))
+                .map(|(component_id, port)| self.port_position(component_id, port))
+                .collect::<Result<Vec<Vector2<f32>>, SceneManagerError>>()?;
+
+            let route = route_net(&terminals, &rail_positions);
+            let first_id = Id::try_from(wires.len())?;
+            wires.extend(route.to_wires(first_id, chunk_size));
+        }
+
+        let count = wires.len();
+        self.scene.clear_wires();
+        for wire in wires {
+            self.scene.add_wire(chunk_step_idx, wire);
+        }
+
+        Ok(count)
+    }
+
+    /// Highest id given to a component
+    pub fn last_component_id(&self) -> Option<Id> {
+        self.last_component_id
+    }
+
+    /// Id of the first component added after the current ones
+    pub fn next_free_id(&self) -> Id {
+        self.last_component_id.map_or(0, |id| id + 1)
+    }
+
+    fn next_component_id(&mut self) -> Id {
+        let id = self.last_component_id.map_or(0, |id| id + 1);
+        self.last_component_id = Some(id);
+        id
+    }
+
+    /// Adds a component to the scene at ```chunk_step_idx``` and binds its ports, ```bindings``` pairs the index of a
+    /// port with its node
+    pub fn insert_component(
+        &mut self,
+        chunk_step_idx: ChunkStepIdx,
+        component: Component,
+        bindings: &[(usize, NodeId)],
+    ) -> Result<(), SceneManagerError> {
+        let id = component.id();
+        self.scene.add_component(chunk_step_idx, component)?;
+        for (port, node) in bindings {
+            self.netlist.bind(id, *port, *node)?;
+        }
+        self.last_component_id = Some(self.last_component_id.map_or(id, |last_id| last_id.max(id)));
+
+        Ok(())
+    }
+
+    /// Removes a component from the scene and the netlist. Returns it with the step it was stored at and the bindings
+    /// of its ports, so it can be inserted back.
+    pub fn remove_component(&mut self, id: Id) -> Result<RemovedComponent, SceneManagerError> {
+        let (chunk_step_idx, _) = self.scene.component_chunk(id).ok_or(SceneManagerError::ComponentNotFound(id))?;
+        let component = self.scene.remove_component(id)?;
+
+        let bindings = self.netlist.ports_of(id);
+        if self.netlist.contains_component(id) {
+            self.netlist.remove_component(id)?;
+        }
+
+        Ok((chunk_step_idx, component, bindings))
+    }
+
+    pub fn sources(&self) -> &Vec<Element> {
+        &self.sources
+    }
+
+    /// Adds an ideal voltage source (```v(pos) - v(neg) = voltage```), returns the id of its branch
+    pub fn add_voltage_source(&mut self, pos: NodeId, neg: NodeId, voltage: f64) -> Id {
+        let id = self.next_component_id();
+        self.sources.push(Element {
+            id,
+            kind: ElementKind::VoltageSource(voltage),
+            pos,
+            neg,
+        });
+        id
+    }
+
+    /// Adds a source whose id was reserved by the caller (e.g. inside the id range of a construct)
+    pub fn add_source(&mut self, source: Element) {
+        self.sources.push(source);
+    }
+
+    /// Changes the voltage (or current) of the source ```id```
+    pub fn set_source_value(&mut self, id: Id, value: f64) -> Result<(), SceneManagerError> {
+        let source = self
+            .sources
+            .iter_mut()
+            .find(|source| source.id == id)
+            .ok_or(MnaError::UnknownElement(id))?;
+
+        match &mut source.kind {
+            ElementKind::VoltageSource(v) | ElementKind::CurrentSource(v) => *v = value,
+            _ => return Err(MnaError::UnknownElement(id).into()),
+        }
+
+        Ok(())
+    }
+
+    /// Adds an ideal current source driving ```current``` from ```pos``` to ```neg``` through it
+    pub fn add_current_source(&mut self, pos: NodeId, neg: NodeId, current: f64) -> Id {
+        let id = self.next_component_id();
+        self.sources.push(Element {
+            id,
+            kind: ElementKind::CurrentSource(current),
+            pos,
+            neg,
+        });
+        id
+    }
+
+    /// Builds the circuit described by the netlist and the sources, using the components parameters
+    pub fn to_circuit(&self) -> Result<Circuit, SceneManagerError> {
+        let mut circuit = Circuit::new(self.ground());
+
+        for id in self.netlist_component_ids() {
+            let element = self.element(id)?;
+            circuit.add_element(element.id, element.kind, element.pos, element.neg);
+        }
+
+        for source in self.sources.iter() {
+            circuit.add_element(source.id, source.kind, source.pos, source.neg);
+        }
+
+        Ok(circuit)
+    }
+
+    /// Ids of the components of the netlist, sorted
+    fn netlist_component_ids(&self) -> Vec<Id> {
+        let mut ids: Vec<Id> = self.netlist.components().collect();
+        ids.sort();
+        ids
+    }
+
+    /// Circuit element of a component of the netlist, built from its type, parameters and connections
+    fn element(&self, id: Id) -> Result<Element, SceneManagerError> {
+        let ground = self.ground();
+        let component = self
+            .scene
+            .get_component(id)
+            .ok_or(SceneManagerError::ComponentNotFound(id))?;
+
+        let ty = component.ty();
+        let two_terminal = || -> Result<(NodeId, NodeId), SceneManagerError> {
+            let node = |port: usize| {
+                self.netlist
+                    .net_of(id, port)
+                    .ok_or_else(|| SceneManagerError::UnconnectedPort(id, port.to_string()))
+            };
+            Ok((node(0)?, node(1)?))
+        };
+
+        let (kind, pos, neg) = match (*component.params(), ty) {
+            (ComponentParams::Gain(gain), ty) if ty == DefaultComponentTypes::OpAmp as u32 => {
+                let kind = ElementKind::OpAmp {
+                    gain,
+                    non_inverting: self.port_node(id, "plus")?,
+                    inverting: self.port_node(id, "minus")?,
+                };
+                // The output is referred to ground
+                (kind, self.port_node(id, "out")?, ground)
+            }
+            (ComponentParams::Diode { saturation_current, ideality }, ty)
+                if ty == DefaultComponentTypes::Diode as u32 =>
+            {
+                let kind = ElementKind::Diode {
+                    saturation_current,
+                    ideality,
+                };
+                (kind, self.port_node(id, "anode")?, self.port_node(id, "cathode")?)
+            }
+            (ComponentParams::Conductance(g), ty)
+                if ty == DefaultComponentTypes::Memristor as u32 =>
+            {
+                let (pos, neg) = two_terminal()?;
+                (ElementKind::Memristor(g), pos, neg)
+            }
+            (ComponentParams::Conductance(g), ty) if ty == DefaultComponentTypes::Resistor as u32 => {
+                let (pos, neg) = two_terminal()?;
+                (ElementKind::Resistor(1.0 / g), pos, neg)
+            }
+            (ComponentParams::Resistance(r), ty)
+                if ty == DefaultComponentTypes::Resistor as u32
+                    || ty == DefaultComponentTypes::Memristor as u32 =>
+            {
+                let (pos, neg) = two_terminal()?;
+                (ElementKind::Resistor(r), pos, neg)
+            }
+            (ComponentParams::None, _) => return Err(SceneManagerError::MissingComponentParams(id)),
+            _ => return Err(SceneManagerError::UnsupportedComponent(id, ty)),
+        };
+
+        Ok(Element { id, kind, pos, neg })
+    }
+
+    /// Writes the circuit as a SPICE netlist, one card per component, so that it can be streamed to a file.
+    ///
+    /// Crossbar inputs that are not driven by any source get a voltage source of ```input_voltage``` to ground. Sources
+    /// present in `waveforms` follow the waveform instead of their DC value, see ```transient```.
+    pub fn write_spice<W: Write>(
+        &self,
+        writer: W,
+        options: SpiceOptions,
+        input_voltage: f64,
+        waveforms: &HashMap<Id, Waveform>,
+    ) -> Result<W, SceneManagerError> {
+        let mut spice = SpiceWriter::new(writer, self.ground(), options);
+        for net in self.netlist.nets() {
+            if let Some(name) = self.node_name(net) {
+                spice.set_node_name(net, name);
+            }
+        }
+
+        spice.begin()?;
+
+        for id in self.netlist_component_ids() {
+            spice.element(&self.element(id)?, None)?;
+        }
+
+        spice.comment("Sources")?;
+        for source in self.sources.iter() {
+            spice.element(source, waveforms.get(&source.id))?;
+        }
+
+        let driven: HashSet<NodeId> = self.sources.iter().flat_map(|source| [source.pos, source.neg]).collect();
+        let mut inputs: Vec<NodeId> = self
+            .crossbars()
+            .flat_map(|crossbar| crossbar.input_nodes().iter())
+            .filter_map(|name| self.node_id(name))
+            .filter(|node| !driven.contains(node))
+            .collect();
+        inputs.sort();
+        inputs.dedup();
+
+        // Ids of the extra sources continue after the ones of the scene
+        for (input, id) in inputs.into_iter().zip(self.next_free_id()..) {
+            let kind = ElementKind::VoltageSource(input_voltage);
+            spice.element(&Element { id, kind, pos: input, neg: self.ground() }, None)?;
+        }
+
+        Ok(spice.end()?)
+    }
+
+    /// Computes the DC operating point of the circuit, voltages are keyed by ```NodeId``` and branch currents by the
+    /// component (or source) ```Id```
+    pub fn dc_operating_point(&self) -> Result<OperatingPoint, SceneManagerError> {
+        Ok(solve_dc(&self.to_circuit()?, &DcOptions::default())?)
+    }
+
+    /// Runs a transient analysis where every memristor evolves according to the Stanford model with `params`,
+    /// starting from the gap of its programmed conductance. The variability of every device is seeded from ```seed```
+    /// and its ```Id```, so runs with the same seed give the same results. Sources present in `waveforms` (keyed by
+    /// the ```Id``` returned when adding them) follow the waveform instead of their DC value.
+    pub fn transient(
+        &self,
+        options: TransientOptions,
+        params: &StanfordModelParams,
+        seed: u64,
+        waveforms: HashMap<Id, Waveform>,
+    ) -> Result<TransientResult, SceneManagerError> {
+        let circuit = self.to_circuit()?;
+        let mut analysis = TransientAnalysis::new(&circuit, options)?;
+
+        for element in circuit.elements() {
+            if let ElementKind::Memristor(g) = element.kind {
+                let params = StanfordModelParams {
+                    gap_ini: gap_for_conductance(params, g),
+                    ..params.clone()
+                };
+                let model = StanfordModel::with_seed(params, seed.wrapping_add(element.id as u64))?;
+                analysis.set_device(element.id, model)?;
+            }
+        }
+
+        for (id, waveform) in waveforms {
+            analysis.set_waveform(id, waveform)?;
+        }
+
+        Ok(analysis.run()?)
+    }
+
+    pub fn conductance_mapping(&self) -> &ConductanceMapping {
+        &self.conductance_mapping
+    }
+
+    /// Sets the mapping used by the next calls to ```load_nn```, its range is also the one of the heatmap shown when
+    /// zoomed out
+    pub fn set_conductance_mapping(&mut self, conductance_mapping: ConductanceMapping) {
+        self.scene
+            .set_heatmap_range(conductance_mapping.g_min, conductance_mapping.g_max);
+        self.conductance_mapping = conductance_mapping;
+    }
+
+    pub fn tiling(&self) -> &TilingConfig {
+        &self.tiling
+    }
+
+    /// Sets the array size used by the next calls to ```load_nn```
+    pub fn set_tiling(&mut self, tiling: TilingConfig) {
+        self.tiling = tiling;
+    }
+
+    pub fn peripherals(&self) -> &PeripheralConfig {
+        &self.peripherals
+    }
+
+    /// Sets the peripheral circuitry used by the next calls to ```load_nn```
+    pub fn set_peripherals(&mut self, peripherals: PeripheralConfig) {
+        self.peripherals = peripherals;
+    }
+
+    /// Tiling of every loaded layer, with its array count and utilization
+    pub fn layer_tilings(&self) -> &Vec<LayerTiling> {
+        &self.layer_tilings
+    }
+
+    pub fn constructs(&self) -> &Vec<Box<dyn Construct>> {
+        &self.constructs
+    }
+
+    /// Tree of the constructs, its leaves index ```constructs```
+    pub fn hierarchy(&self) -> &ConstructTree {
+        &self.hierarchy
+    }
+
+    /// Construct of a leaf of ```hierarchy```, ```None``` for groups
+    pub fn construct(&self, node_id: ConstructNodeId) -> Option<&dyn Construct> {
+        let construct_idx = self.hierarchy.node(node_id)?.construct()?;
+        self.constructs.get(construct_idx).map(|construct| construct.as_ref())
+    }
+
+    /// Adds the construct at the root of ```hierarchy```
+    pub fn add_construct(&mut self, construct: Box<dyn Construct>) -> Result<ConstructNodeId, SceneManagerError> {
+        self.add_construct_to(self.hierarchy.root(), construct)
+    }
+
+    /// Adds the construct below the group ```parent```, its components are moved to the frame of ```parent```
+    pub fn add_construct_to(
+        &mut self,
+        parent: ConstructNodeId,
+        construct: Box<dyn Construct>,
+    ) -> Result<ConstructNodeId, SceneManagerError> {
+        // Checked before the components are added, so a failure leaves the scene untouched
+        let world_transform = self.hierarchy.world_transform(parent)?;
+        if self.construct(parent).is_some() {
+            return Err(ConstructTreeError::NotAGroup(parent).into());
+        }
+
+        construct.add_to_scene(self)?;
+
+        let (first_id, last_id) = construct.components_id_range();
+        self.last_component_id = Some(self.last_component_id.map_or(last_id, |id| id.max(last_id)));
+
+        let node_id = self.hierarchy.add_construct(
+            parent,
+            construct.type_name().to_string(),
+            self.constructs.len(),
+            (first_id, last_id),
+        )?;
+        self.constructs.push(construct);
+
+        if !world_transform.is_identity() {
+            self.transform_components(node_id, &world_transform)?;
+        }
+
+        Ok(node_id)
+    }
+
+    /// Adds an empty group of constructs below ```parent```
+    pub fn add_construct_group(
+        &mut self,
+        parent: ConstructNodeId,
+        name: String,
+        kind: ConstructNodeKind,
+        transform: Transform,
+    ) -> Result<ConstructNodeId, SceneManagerError> {
+        Ok(self.hierarchy.add_group(parent, name, kind, transform)?)
+    }
+
+    /// Id of the first node (net) added after the current ones
+    pub fn next_free_node_id(&self) -> NodeId {
+        self.netlist.last_net_id().map_or(0, |id| id + 1)
+    }
+
+    /// Removes ```node_id```, which must be the node of the construct tree added last, so that the ids of the others
+    /// do not change. If it is a construct, its components and sources are removed too, as the nodes (nets) from
+    /// ```first_node``` on that it created. Returns the removed node and construct, so they can be added back.
+    pub fn remove_last_construct_node(
+        &mut self,
+        node_id: ConstructNodeId,
+        first_node: NodeId,
+    ) -> Result<(ConstructNode, Option<Box<dyn Construct>>), SceneManagerError> {
+        // Constructs and their leaves are added together, the last leaf always refers to the last construct
+        let is_last_construct = |idx: usize| idx + 1 == self.constructs.len();
+        if let Some(node) = self.hierarchy.node(node_id) {
+            if node.construct().is_some_and(|idx| !is_last_construct(idx)) {
+                return Err(ConstructTreeError::NotLastNode(node_id).into());
+            }
+        }
+
+        let node = self.hierarchy.remove_last(node_id)?;
+        if node.construct().is_none() {
+            return Ok((node, None));
+        }
+        let construct = self.constructs.pop().ok_or(ConstructTreeError::NotLastNode(node_id))?;
+
+        let (first_id, last_id) = construct.components_id_range();
+        for id in first_id..=last_id {
+            if self.scene.get_component(id).is_some() {
+                self.scene.remove_component(id)?;
+            }
+            if self.netlist.contains_component(id) {
+                self.netlist.remove_component(id)?;
+            }
+        }
+        self.sources.retain(|source| !(first_id..=last_id).contains(&source.id));
+        self.netlist.remove_nets_from(first_node);
+
+        Ok((node, Some(construct)))
+    }
+
+    /// Sets the transform of a node relative to its parent, moving every component below it. The wires are routed
+    /// again if the scene has any.
+    pub fn set_construct_transform(
+        &mut self,
+        node_id: ConstructNodeId,
+        transform: Transform,
+    ) -> Result<(), SceneManagerError> {
+        self.move_construct_node(node_id, transform)?;
+        self.reroute_wires()
+    }
+
+    /// Adds a copy of ```node_id```, and of everything below it, to the group ```parent``` with the transform
+    /// ```transform``` relative to it. The copied constructs get new components, connected to the nodes that
+    /// ```rename``` returns for the nodes of the originals. Returns the node of the copy.
+    pub fn instantiate(
+        &mut self,
+        node_id: ConstructNodeId,
+        parent: ConstructNodeId,
+        name: String,
+        transform: Transform,
+        rename: &dyn Fn(&str) -> String,
+    ) -> Result<ConstructNodeId, SceneManagerError> {
+        if self.hierarchy.ancestors(parent).any(|ancestor| ancestor == node_id) {
+            return Err(ConstructTreeError::InstanceInsideItself(node_id).into());
+        }
+
+        let instance = self.instantiate_node(node_id, parent, transform, rename)?;
+        self.hierarchy.set_name(instance, name)?;
+        self.reroute_wires()?;
+
+        Ok(instance)
+    }
+
+    fn instantiate_node(
+        &mut self,
+        node_id: ConstructNodeId,
+        parent: ConstructNodeId,
+        transform: Transform,
+        rename: &dyn Fn(&str) -> String,
+    ) -> Result<ConstructNodeId, SceneManagerError> {
+        let node = self.hierarchy.node(node_id).ok_or(ConstructTreeError::NodeNotFound(node_id))?.clone();
+
+        match node.construct() {
+            Some(construct_idx) => {
+                let copy = self.constructs[construct_idx].instantiate(self.next_free_id(), rename);
+                let instance = self.add_construct_to(parent, copy)?;
+                self.move_construct_node(instance, transform)?;
+                Ok(instance)
+            }
+            None => {
+                let instance = self.hierarchy.add_group(parent, node.name().to_string(), node.kind(), transform)?;
+                for child in node.children() {
+                    let child_transform = *self.hierarchy.node(*child).expect("children exist").transform();
+                    self.instantiate_node(*child, instance, child_transform, rename)?;
+                }
+                Ok(instance)
+            }
+        }
+    }
+
+    /// Sets the transform of a node and moves its components, without routing the wires
+    fn move_construct_node(&mut self, node_id: ConstructNodeId, transform: Transform) -> Result<(), SceneManagerError> {
+        let old_transform = self.hierarchy.world_transform(node_id)?;
+        self.hierarchy.set_transform(node_id, transform)?;
+        let new_transform = self.hierarchy.world_transform(node_id)?;
+
+        let delta = new_transform.compose(&old_transform.inverse());
+        if delta.is_identity() {
+            return Ok(());
+        }
+        self.transform_components(node_id, &delta)
+    }
+
+    /// Applies ```transform``` to the components below a node
+    fn transform_components(
+        &mut self,
+        node_id: ConstructNodeId,
+        transform: &Transform,
+    ) -> Result<(), SceneManagerError> {
+        for (first_id, last_id) in self.hierarchy.component_ranges(node_id) {
+            for id in first_id..=last_id {
+                // Some ids of a construct are not components, e.g. the sources of the input drivers
+                let Some(component) = self.scene.get_component(id) else { continue };
+                let position = transform.apply(component.position());
+                let rotation = component.rotation() + transform.rotation;
+                self.scene.move_component(id, position, rotation)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn reroute_wires(&mut self) -> Result<(), SceneManagerError> {
+        if !self.scene.wires().is_empty() {
+            self.route_wires()?;
+        }
+        Ok(())
+    }
+
+    pub fn crossbars(&self) -> impl Iterator<Item = &Crossbar> {
+        self.constructs
+            .iter()
+            .filter_map(|construct| construct.as_any().downcast_ref::<Crossbar>())
+    }
+
+    /// Sets the same parasitics on every crossbar of the scene
+    pub fn set_crossbar_parasitics(&mut self, parasitics: CrossbarParasitics) {
+        for construct in self.constructs.iter_mut() {
+            if let Some(crossbar) = construct.as_any_mut().downcast_mut::<Crossbar>() {
+                crossbar.set_parasitics(parasitics);
+            }
+        }
+    }
+
+    /// IR drop of every layer with ```read_voltage``` applied to all the rows of its crossbars, the worst case for
+    /// the line resistances. The currents of tiles that share outputs are added. Sorted by layer.
+    pub fn ir_drop_summary(&self, read_voltage: f64) -> Result<Vec<LayerIrDrop>, SceneManagerError> {
+        // (ideal currents, currents) of every layer, indexed by the column of the layer matrix
+        let mut layers: BTreeMap<usize, (Vec<f64>, Vec<f64>)> = BTreeMap::new();
+
+        for crossbar in self.crossbars() {
+            let inputs = vec![read_voltage; crossbar.rows() as usize];
+            let report = crossbar.ir_drop(&self.scene, &inputs)?;
+            let col_start = crossbar.tile().map_or(0, |tile| tile.col_start);
+
+            let (ideal_currents, currents) = layers.entry(crossbar.layer_idx()).or_default();
+            let len = col_start + report.currents.len();
+            if ideal_currents.len() < len {
+                ideal_currents.resize(len, 0.0);
+                currents.resize(len, 0.0);
+            }
+
+            for (col, (ideal, actual)) in report.ideal_currents.iter().zip(report.currents.iter()).enumerate() {
+                ideal_currents[col_start + col] += ideal;
+                currents[col_start + col] += actual;
+            }
+        }
+
+        Ok(layers
+            .into_iter()
+            .map(|(layer_idx, (ideal_currents, currents))| LayerIrDrop {
+                layer_idx,
+                report: IrDropReport::new(ideal_currents, currents),
+            })
+            .collect())
+    }
+
+    /// Maps ```linear_layer``` to crossbars placed at ```offset``` and adds the circuitry that drives and senses them,
+    /// grouped by layer and tile below ```network```.
+    /// The rows are driven by ```inputs``` (the outputs of the previous layer) when their number matches, by DACs
+    /// otherwise. Returns the output nodes of the layer and the offset for the next layer.
+    ///
+    /// Layers larger than the tiling config are split into a grid of crossbars, tiles that share inputs are placed
+    /// side by side and tiles that share outputs one below the other. Every tile has its own TIAs, and an adder below
+    /// every column of tiles accumulates their partial sums.
+    #[allow(clippy::too_many_arguments)]
+    fn add_linear_crossbar(
+        &mut self,
+        network: ConstructNodeId,
+        layer_idx: usize,
+        linear_layer: &LinearLayer,
+        inputs: Option<Vec<String>>,
+        spacing: f32,
+        offset: Vector2<f32>,
+    ) -> Result<(Vec<String>, Vector2<f32>), SceneManagerError> {
+        let mapped = self.conductance_mapping.map_linear(
+            linear_layer.input_size,
+            linear_layer.output_size,
+            &linear_layer.weights,
+            linear_layer.bias.as_deref(),
+        )?;
+
+        let devices = self.conductance_mapping.devices_per_weight();
+        let plan = TilingPlan::new(mapped.rows, mapped.cols, self.tiling, devices)?;
+        let peripherals = self.peripherals;
+        let layer = self.hierarchy.add_group(
+            network,
+            format!("l{}", layer_idx),
+            ConstructNodeKind::Layer(layer_idx),
+            Transform::IDENTITY,
+        )?;
+
+        // Nodes of the rows: the inputs followed by the bias row
+        let mut rows = match inputs {
+            Some(inputs) if inputs.len() == linear_layer.input_size => inputs,
+            _ => {
+                let rows: Vec<String> = (0..linear_layer.input_size)
+                    .map(|row| format!("l{}.x{}", layer_idx, row))
+                    .collect();
+                let driver = InputDriver::new(
+                    layer_idx,
+                    false,
+                    rows.clone(),
+                    peripherals,
+                    self.next_free_id(),
+                    spacing,
+                    offset + Vector2::new(-3.0 * spacing, mapped.rows as f32 * spacing),
+                );
+                self.add_construct_to(layer, Box::new(driver))?;
+                rows
+            }
+        };
+        if linear_layer.has_bias() {
+            let bias = format!("l{}.bias", layer_idx);
+            let driver = InputDriver::new(
+                layer_idx,
+                true,
+                vec![bias.clone()],
+                peripherals,
+                self.next_free_id(),
+                spacing,
+                offset + Vector2::new(-3.0 * spacing, spacing),
+            );
+            self.add_construct_to(layer, Box::new(driver))?;
+            rows.push(bias);
+        }
+
+        let outputs: Vec<String> = (0..linear_layer.output_size)
+            .map(|output| format!("l{}.y{}", layer_idx, output))
+            .collect();
+        // Node of the output (or partial sum) of a tile
+        let tile_output = |tile: &Tile, output: usize| match plan.needs_accumulation() {
+            true => format!("l{}.t{}_{}.y{}", layer_idx, tile.grid_pos.0, tile.grid_pos.1, output),
+            false => outputs[output].clone(),
+        };
+
+        // The weights are the conductances divided by ```scale```, so a feedback of 1 / scale keeps the voltage scale
+        // of the inputs on the outputs
+        let feedback_resistance = 1.0 / mapped.scale;
+
+        let gap = spacing * 8.0;
+        let tile_offset = |row_start: usize, col_start: usize, grid_pos: (usize, usize)| {
+            Vector2::new(
+                col_start as f32 * spacing + grid_pos.1 as f32 * gap,
+                -(row_start as f32 * spacing + grid_pos.0 as f32 * gap),
+            )
+        };
+
+        for tile in plan.tiles.iter() {
+            let columns: Vec<String> = (tile.col_start..tile.col_start + tile.cols)
+                .map(|col| format!("l{}.t{}_{}.c{}", layer_idx, tile.grid_pos.0, tile.grid_pos.1, col))
+                .collect();
+            let center = offset + tile_offset(tile.row_start, tile.col_start, tile.grid_pos);
+            let tile_node = self.hierarchy.add_group(
+                layer,
+                format!("l{}.t{}_{}", layer_idx, tile.grid_pos.0, tile.grid_pos.1),
+                ConstructNodeKind::Tile(tile.grid_pos.0, tile.grid_pos.1),
+                Transform::IDENTITY,
+            )?;
+
+            let mut crossbar = Crossbar::new(
+                layer_idx,
+                rows[tile.row_start..tile.row_start + tile.rows].to_vec(),
+                columns.clone(),
+                tile.rows.try_into()?,
+                tile.cols.try_into()?,
+                self.next_free_id(),
+                spacing,
+                center,
+            )?;
+            crossbar.set_conductances(plan.tile_conductances(tile, &mapped.conductances)?)?;
+            crossbar.set_tile(Some(*tile));
+            self.add_construct_to(tile_node, Box::new(crossbar))?;
+
+            // Partial sums are inverted so that the (inverting) adders restore the sign
+            let tia = Tia::new(
+                layer_idx,
+                self.conductance_mapping.differential,
+                plan.needs_accumulation(),
+                columns,
+                (tile.col_start / devices..(tile.col_start + tile.cols) / devices)
+                    .map(|output| tile_output(tile, output))
+                    .collect(),
+                feedback_resistance,
+                peripherals.op_amp_gain,
+                self.next_free_id(),
+                spacing,
+                center - Vector2::new(0.0, 2.0 * spacing),
+            )?;
+            self.add_construct_to(tile_node, Box::new(tia))?;
+        }
+
+        if plan.needs_accumulation() {
+            for grid_col in 0..plan.grid.1 {
+                let tiles: Vec<&Tile> = plan.column_tiles(grid_col).collect();
+                let Some(tile) = tiles.first().copied() else { continue };
+
+                let tile_outputs = tile.col_start / devices..(tile.col_start + tile.cols) / devices;
+                let adder = PartialSumAdder::new(
+                    layer_idx,
+                    tile_outputs
+                        .clone()
+                        .flat_map(|output| tiles.iter().map(move |tile| tile_output(tile, output)))
+                        .collect(),
+                    tile_outputs.map(|output| outputs[output].clone()).collect(),
+                    1.0 / self.conductance_mapping.g_max,
+                    peripherals.op_amp_gain,
+                    self.next_free_id(),
+                    spacing,
+                    offset + tile_offset(plan.rows, tile.col_start, (plan.grid.0, grid_col)),
+                )?;
+                self.add_construct_to(layer, Box::new(adder))?;
+            }
+        }
+
+        let width = plan.cols as f32 * spacing + plan.grid.1.saturating_sub(1) as f32 * gap;
+        self.layer_tilings.push(LayerTiling { layer_idx, plan });
+
+        Ok((outputs, offset + Vector2::new(width + spacing * 10.0, 0.0)))
+    }
+
+    /// Builds the inference circuit of ```nn```: crossbars driven by DACs, TIAs, activations and the ADCs that read
+    /// the outputs of the last layer. Only linear layers and ReLU activations have a circuit, any other layer is an
+    /// error, and the conductance mapping must use differential pairs.
+    pub fn load_nn(&mut self, nn: Nn) -> Result<(), SceneManagerError> {
+        if !self.conductance_mapping.differential {
+            return Err(SceneManagerError::SingleEndedMapping);
+        }
+
+        let mut offset = Vector2::new(0.0, 0.0);
+        let crossbar_spacing = 2.0;
+        let network = self.hierarchy.add_group(
+            self.hierarchy.root(),
+            String::from("network"),
+            ConstructNodeKind::Network,
+            Transform::IDENTITY,
+        )?;
+
+        // Nodes with the outputs of the last layer
+        let mut outputs: Option<Vec<String>> = None;
+
+        for (layer_idx, layer) in nn.layers.iter().enumerate() {
+            match layer {
+                Layer::Activation(Activation::ReLU) => {
+                    if let Some(inputs) = outputs.take() {
+                        let relu_outputs: Vec<String> = (0..inputs.len())
+                            .map(|output| format!("l{}.a{}", layer_idx, output))
+                            .collect();
+
+                        let relu = ReluBlock::new(
+                            layer_idx,
+                            inputs,
+                            relu_outputs.clone(),
+                            self.peripherals.relu_load_resistance,
+                            self.peripherals.op_amp_gain,
+                            self.next_free_id(),
+                            crossbar_spacing,
+                            offset - Vector2::new(7.0 * crossbar_spacing, 0.0),
+                        )?;
+                        let layer = self.hierarchy.add_group(
+                            network,
+                            format!("l{}", layer_idx),
+                            ConstructNodeKind::Layer(layer_idx),
+                            Transform::IDENTITY,
+                        )?;
+                        self.add_construct_to(layer, Box::new(relu))?;
+                        outputs = Some(relu_outputs);
+                    } else {
+                        return Err(SceneManagerError::MissingLayerInputs(layer_idx, "ReLU".to_string()));
+                    }
+                }
+                Layer::Activation(activation) => {
+                    return Err(SceneManagerError::UnsupportedLayer(layer_idx, format!("{:?}", activation)));
+                }
+                Layer::Linear(linear_layer) => {
+                    let (layer_outputs, next_offset) = self.add_linear_crossbar(
+                        network,
+                        layer_idx,
+                        linear_layer,
+                        outputs.take(),
+                        crossbar_spacing,
+                        offset,
+                    )?;
+                    outputs = Some(layer_outputs);
+                    offset = next_offset;
+                }
+                // The unrolled kernels (im2col) are a single crossbar, reused for every patch of the input. Its rows are
+                // driven with one patch at a time and its columns give every output channel at that position.
+                Layer::Conv2d(conv_layer) => {
+                    let (layer_outputs, next_offset) = self.add_linear_crossbar(
+                        network,
+                        layer_idx,
+                        &conv_layer.to_linear(),
+                        outputs.take(),
+                        crossbar_spacing,
+                        offset,
+                    )?;
+                    outputs = Some(layer_outputs);
+                    offset = next_offset;
+                }
+                // The outputs of a crossbar are a single position, there is no spatial map to pool
+                Layer::Pool2d(_) => {
+                    return Err(SceneManagerError::UnsupportedLayer(layer_idx, "Pool2d".to_string()));
+                }
+                Layer::Flatten => {}
+            }
+        }
+
+        if let Some(outputs) = outputs {
+            let adc = Adc::new(
+                outputs,
+                self.peripherals,
+                self.next_free_id(),
+                crossbar_spacing,
+                offset - Vector2::new(3.0 * crossbar_spacing, 0.0),
+            );
+            self.add_construct_to(network, Box::new(adc))?;
+        }
+
+        self.route_wires()?;
+
+        Ok(())
+    }
+
+    /// Adds the elements of a SPICE netlist, with its subcircuits flattened, and routes the wires.
+    ///
+    /// Components are placed on a grid in the order of a breadth first walk of the netlist, so connected components
+    /// end up close to each other. Sources become sources of the scene. The reference of voltage controlled voltage
+    /// sources (op-amps) must be ground.
+    pub fn load_spice(&mut self, netlist: &SpiceNetlist) -> Result<(), SceneManagerError> {
+        let spacing = 3.0;
+        let elements = netlist.flatten().map_err(SceneManagerError::SpiceImportError)?;
+
+        let is_source = |element: &SpiceElement| {
+            matches!(
+                element.kind,
+                SpiceElementKind::VoltageSource(_) | SpiceElementKind::CurrentSource(_)
+            )
+        };
+        let order = placement_order(&elements, &is_source);
+        let columns = (order.len() as f64).sqrt().ceil().max(1.0) as usize;
+
+        for element in elements.iter().filter(|element| is_source(element)) {
+            let (pos, neg) = (self.spice_node(&element.nodes[0]), self.spice_node(&element.nodes[1]));
+            match element.kind {
+                SpiceElementKind::VoltageSource(v) => self.add_voltage_source(pos, neg, v),
+                SpiceElementKind::CurrentSource(i) => self.add_current_source(pos, neg, i),
+                _ => unreachable!("only sources are added here"),
+            };
+        }
+
+        for (slot, idx) in order.into_iter().enumerate() {
+            let element = &elements[idx];
+            let unsupported = |message: &str| {
+                SceneManagerError::SpiceImportError(SpiceError::Parse {
+                    line: element.line,
+                    message: format!("{} {}", element.name, message),
+                })
+            };
+
+            let (ty, params) = match &element.kind {
+                SpiceElementKind::Resistor(r) => (DefaultComponentTypes::Resistor, ComponentParams::Resistance(*r)),
+                SpiceElementKind::Memristor { model, params } => {
+                    if element.nodes.len() != 2 {
+                        return Err(unsupported("is not a two terminal memristor"));
+                    }
+                    let g = netlist.memristor_conductance(model, params);
+                    (DefaultComponentTypes::Memristor, ComponentParams::Conductance(g))
+                }
+                SpiceElementKind::Diode { model } => {
+                    let model_params = netlist.models.get(model).map(|model| &model.params);
+                    let param = |name: &str, default: f64| {
+                        model_params.and_then(|params| params.get(name)).copied().unwrap_or(default)
+                    };
+                    let params = ComponentParams::Diode {
+                        saturation_current: param("is", 1e-14),
+                        ideality: param("n", 1.0),
+                    };
+                    (DefaultComponentTypes::Diode, params)
+                }
+                SpiceElementKind::Mosfet { .. } => (DefaultComponentTypes::Nmos, ComponentParams::None),
+                SpiceElementKind::Vcvs(gain) => {
+                    if !is_ground(&element.nodes[1]) {
+                        return Err(unsupported("is an op-amp whose output is not referred to ground"));
+                    }
+                    (DefaultComponentTypes::OpAmp, ComponentParams::Gain(*gain))
+                }
+                SpiceElementKind::VoltageSource(_)
+                | SpiceElementKind::CurrentSource(_)
+                | SpiceElementKind::Instance { .. } => unreachable!("sources and instances are not placed"),
+            };
+
+            let position = Vector2::new((slot % columns) as f32, -((slot / columns) as f32)) * spacing;
+            let id = self.next_component_id();
+            let mut component = Component::new(id, 0, position, 0.0, ty.into());
+            component.set_params(params);
+            self.scene.add_component(0, component)?;
+
+            let nodes: Vec<NodeId> = element.nodes.iter().map(|node| self.spice_node(node)).collect();
+            match element.kind {
+                SpiceElementKind::Diode { .. } => {
+                    self.bind_port(id, "anode", nodes[0])?;
+                    self.bind_port(id, "cathode", nodes[1])?;
+                }
+                SpiceElementKind::Mosfet { .. } => {
+                    // The bulk has no port
+                    self.bind_port(id, "drain", nodes[0])?;
+                    self.bind_port(id, "gate", nodes[1])?;
+                    self.bind_port(id, "source", nodes[2])?;
+                }
+                SpiceElementKind::Vcvs(_) => self.connect_op_amp(id, nodes[2], nodes[3], nodes[0])?,
+                _ => self.connect(id, nodes[0], nodes[1])?,
+            }
+        }
+
+        self.route_wires()?;
+
+        Ok(())
+    }
+
+    /// Node of the scene for a node of a SPICE netlist, the global ground of SPICE is the ground of the scene
+    fn spice_node(&mut self, name: &str) -> NodeId {
+        if is_ground(name) {
+            self.ground()
+        } else {
+            self.add_node(name.to_string())
+        }
+    }
+
+    /// Drives the network loaded with ```load_nn``` with ```inputs```, solves the circuit and returns the values read
+    /// by the ADCs
+    pub fn run_inference(&mut self, inputs: &[f64]) -> Result<Vec<f64>, SceneManagerError> {
+        let driver = self
+            .constructs
+            .iter_mut()
+            .filter_map(|construct| construct.as_any_mut().downcast_mut::<InputDriver>())
+            .filter(|driver| !driver.is_bias())
+            .min_by_key(|driver| driver.layer_idx())
+            .ok_or(SceneManagerError::MissingConstruct("input driver"))?;
+
+        driver.set_values(inputs.to_vec())?;
+        let voltages: Vec<(Id, f64)> = (0..inputs.len())
+            .map(|row| (driver.source_id(row), driver.voltage(row)))
+            .collect();
+        for (id, voltage) in voltages {
+            self.set_source_value(id, voltage)?;
+        }
+
+        let op = self.dc_operating_point()?;
+
+        let adc = self
+            .constructs
+            .iter()
+            .find_map(|construct| construct.as_any().downcast_ref::<Adc>())
+            .ok_or(SceneManagerError::MissingConstruct("ADC"))?;
+
+        Ok(adc
+            .input_nodes()
+            .iter()
+            .map(|name| {
+                let voltage = self.node_id(name).and_then(|node| op.voltage(node));
+                adc.convert(voltage.unwrap_or(0.0))
+            })
+            .collect())
+    }
+}
+
+/// Indices of the elements that are placed, in breadth first order through their shared nodes. Ground is not followed,
+/// it would connect everything.
+fn placement_order(elements: &[SpiceElement], skip: &dyn Fn(&SpiceElement) -> bool) -> Vec<usize> {
+    let mut on_node: HashMap<&str, Vec<usize>> = HashMap::new();
+    for (idx, element) in elements.iter().enumerate().filter(|(_, element)| !skip(element)) {
+        for node in element.nodes.iter().filter(|node| !is_ground(node)) {
+            on_node.entry(node.as_str()).or_default().push(idx);
+        }
+    }
+
+    let mut visited = vec![false; elements.len()];
+    let mut order = Vec::new();
+    for start in 0..elements.len() {
+        if visited[start] || skip(&elements[start]) {
+            continue;
+        }
+
+        visited[start] = true;
+        let mut queue = VecDeque::from([start]);
+        while let Some(idx) = queue.pop_front() {
+            order.push(idx);
+            for node in elements[idx].nodes.iter() {
+                for next in on_node.get(node.as_str()).into_iter().flatten() {
+                    if !visited[*next] {
+                        visited[*next] = true;
+                        queue.push_back(*next);
+                    }
+                }
+            }
+        }
+    }
+
+    order
+}
+
+#[cfg(test)]
+mod scene_manager_test {
+    use super::*;
+    use crate::scene::peripherals::{PeripheralConfig, ReluBlock};
+    use rsnet_sim::spice::{MemristorModel, SpiceAnalysis};
+
+    #[test]
+    fn test_dc_operating_point() {
+        let mut scene_manager = SceneManager::new();
+        let gnd = scene_manager.ground();
+        let input = scene_manager.add_node("in".to_string());
+        let output = scene_manager.add_node("out".to_string());
+
+        for (id, ty, params) in [
+            (0, DefaultComponentTypes::Resistor, ComponentParams::Resistance(1e3)),
+            (1, DefaultComponentTypes::Memristor, ComponentParams::Conductance(1e-3)),
+        ] {
+            let mut component = Component::new(id, 0, Vector2::new(id as f32, 0.0), 0.0, ty.into());
+            component.set_params(params);
+            scene_manager.scene_mut().add_component(0, component).unwrap();
+        }
+        scene_manager.last_component_id = Some(1);
+
+        scene_manager.connect(0, input, output).unwrap();
+        scene_manager.connect(1, output, gnd).unwrap();
+        let source = scene_manager.add_voltage_source(input, gnd, 2.0);
+
+        let op = scene_manager.dc_operating_point().unwrap();
+
+        assert!((op.voltage(output).unwrap() - 1.0).abs() < 1e-6);
+        assert!((op.current(1).unwrap() - 1e-3).abs() < 1e-9);
+        assert!((op.current(source).unwrap() + 1e-3).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_transient_starts_from_programmed_conductance() {
+        let params = StanfordModelParams::default();
+        let gap = 1e-9;
+        let g = rsnet_sim::stanford::device_conductance(&params, gap, 0.0);
+
+        let mut scene_manager = SceneManager::new();
+        let gnd = scene_manager.ground();
+        let input = scene_manager.add_node("in".to_string());
+        let mut component = Component::new(0, 0, Vector2::zeros(), 0.0, DefaultComponentTypes::Memristor.into());
+        component.set_params(ComponentParams::Conductance(g));
+        scene_manager.scene_mut().add_component(0, component).unwrap();
+        scene_manager.last_component_id = Some(0);
+        scene_manager.connect(0, input, gnd).unwrap();
+        scene_manager.add_voltage_source(input, gnd, 0.1);
+
+        let options = TransientOptions {
+            t_stop: 1e-9,
+            ..TransientOptions::default()
+        };
+        let result = scene_manager.transient(options, &params, 7, HashMap::new()).unwrap();
+        let history = &result.devices[&0];
+        assert!((history.gap[0] - gap).abs() < 1e-15);
+        assert!((history.current[0] - rsnet_sim::stanford::device_current(&params, gap, 0.1)).abs() < 1e-12);
+
+        // The same seed gives the same run
+        let again = scene_manager.transient(options, &params, 7, HashMap::new()).unwrap();
+        assert_eq!(again.devices[&0].gap, history.gap);
+    }
+
+    #[test]
+    fn test_netlist_connectivity() {
+        let mut scene_manager = SceneManager::new();
+        let nn = Nn {
+            layers: vec![
+                Layer::Linear(LinearLayer {
+                    input_size: 2,
+                    output_size: 1,
+                    weights: vec![1.0, -1.0],
+                    bias: None,
+                }),
+                Layer::Activation(Activation::ReLU),
+            ],
+        };
+        scene_manager.load_nn(nn).unwrap();
+        let netlist = scene_manager.netlist();
+
+        // A row is driven by its DAC resistor and feeds a memristor per column
+        let row = scene_manager.node_id("l0.x0").unwrap();
+        let on_row = netlist.components_on(row);
+        let types: Vec<u32> = on_row
+            .iter()
+            .map(|(id, _)| scene_manager.scene().get_component(*id).unwrap().ty())
+            .collect();
+        assert_eq!(on_row.len(), 3);
+        assert_eq!(types.iter().filter(|ty| **ty == DefaultComponentTypes::Memristor as u32).count(), 2);
+        assert_eq!(types.iter().filter(|ty| **ty == DefaultComponentTypes::Resistor as u32).count(), 1);
+
+        // Every port of every component is bound, op-amps included
+        let primitives = scene_manager.scene().primitives();
+        for id in netlist.components() {
+            let component = scene_manager.scene().get_component(id).unwrap();
+            assert_eq!(netlist.ports_of(id).len(), component.ports(primitives).len());
+        }
+
+        let relu = scene_manager
+            .constructs()
+            .iter()
+            .find_map(|construct| construct.as_any().downcast_ref::<ReluBlock>())
+            .unwrap();
+        let op_amp = relu.components_id_range().0;
+        assert_eq!(scene_manager.port_node(op_amp, "plus").unwrap(), scene_manager.node_id("l0.y0").unwrap());
+        assert_eq!(scene_manager.port_node(op_amp, "minus").unwrap(), scene_manager.node_id("l1.a0").unwrap());
+        assert!(matches!(
+            scene_manager.port_node(op_amp, "gate"),
+            Err(SceneManagerError::UnconnectedPort(..))
+        ));
+    }
+
+    #[test]
+    fn test_from_scene() {
+        let mut scene = Scene::new_empty();
+        for id in [3, 7] {
+            let ty = DefaultComponentTypes::Resistor.into();
+            scene.add_component(0, Component::new(id, 0, Vector2::new(id as f32, 0.0), 0.0, ty)).unwrap();
+        }
+
+        let mut scene_manager = SceneManager::from_scene(scene);
+        assert_eq!(scene_manager.last_component_id(), Some(7));
+
+        let ports = scene_manager.component_ports(3).unwrap();
+        assert_eq!(ports.len(), 2);
+        assert!(ports.iter().all(|(_, node)| node.is_none()));
+
+        let gnd = scene_manager.ground();
+        let node = scene_manager.add_node("n".to_string());
+        scene_manager.connect(3, node, gnd).unwrap();
+        let ports = scene_manager.component_ports(3).unwrap();
+        assert_eq!(ports.iter().map(|(_, node)| *node).collect::<Vec<_>>(), vec![Some(node), Some(gnd)]);
+        assert!(matches!(scene_manager.component_ports(4), Err(SceneManagerError::ComponentNotFound(4))));
+    }
+
+    #[test]
+    fn test_merge_nodes() {
+        let mut scene_manager = SceneManager::new();
+        let gnd = scene_manager.ground();
+        let a = scene_manager.add_node("a".to_string());
+        let b = scene_manager.add_node("b".to_string());
+        let c = scene_manager.add_node("c".to_string());
+
+        for id in 0..2 {
+            let mut component = Component::new(id, 0, Vector2::new(id as f32, 0.0), 0.0, DefaultComponentTypes::Resistor.into());
+            component.set_params(ComponentParams::Resistance(1e3));
+            scene_manager.insert_component(0, component, &[]).unwrap();
+        }
+        scene_manager.connect(0, a, b).unwrap();
+        scene_manager.connect(1, c, gnd).unwrap();
+        scene_manager.add_voltage_source(a, gnd, 1.0);
+
+        // Joining b and c puts both resistors in series
+        let merged = scene_manager.merge_nodes(b, c).unwrap();
+        assert_eq!(merged, b);
+        assert_eq!(scene_manager.node_id("c"), Some(b));
+        assert_eq!(scene_manager.netlist().components_on(b), vec![(0, 1), (1, 0)]);
+
+        let op = scene_manager.dc_operating_point().unwrap();
+        assert!((op.voltage(b).unwrap() - 0.5).abs() < 1e-6);
+
+        // Merging into ground keeps the ground node
+        assert_eq!(scene_manager.merge_nodes(b, gnd).unwrap(), gnd);
+        assert_eq!(scene_manager.node_id("b"), Some(gnd));
+    }
+
+    #[test]
+    fn test_ir_drop_summary() {
+        let mut scene_manager = SceneManager::new();
+
+        for (layer_idx, start_id) in [(0, 0), (1, 100)] {
+            let crossbar = Crossbar::new(
+                layer_idx,
+                vec!["".to_string(); 4],
+                vec!["".to_string(); 3],
+                4,
+                3,
+                start_id,
+                2.0,
+                Vector2::new(start_id as f32, 0.0),
+            )
+            .unwrap();
+            scene_manager.add_construct(Box::new(crossbar)).unwrap();
+        }
+
+        let ids: Vec<Id> = (0..12).chain(100..112).collect();
+        for id in ids {
+            scene_manager
+                .scene_mut()
+                .set_component_params(id, ComponentParams::Conductance(1e-4))
+                .unwrap();
+        }
+
+        // Without parasitics the crossbars compute the ideal product
+        let summary = scene_manager.ir_drop_summary(0.2).unwrap();
+        assert_eq!(summary.len(), 2);
+        assert!(summary.iter().all(|layer| layer.report.max_relative_error < 1e-6));
+
+        scene_manager.set_crossbar_parasitics(CrossbarParasitics::new(2.0, 10.0, 10.0));
+        let summary = scene_manager.ir_drop_summary(0.2).unwrap();
+        assert_eq!(summary[1].layer_idx, 1);
+        for layer in summary.iter() {
+            assert!(layer.report.max_relative_error >= layer.report.mean_relative_error);
+            assert!(layer.report.mean_relative_error > 0.0);
+        }
+    }
+
+    #[test]
+    fn test_empty_crossbar() {
+        for (rows, cols) in [(0, 3), (4, 0), (0, 0)] {
+            let crossbar = Crossbar::new(0, Vec::new(), Vec::new(), rows, cols, 0, 2.0, Vector2::zeros());
+            assert!(matches!(
+                crossbar,
+                Err(SceneManagerError::CrossbarError(CrossbarError::EmptyCrossbar { .. }))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_write_spice() {
+        let mut scene_manager = SceneManager::new();
+        let mapped = MappedLayer {
+            rows: 2,
+            cols: 3,
+            conductances: vec![1e-4, 5e-5, 1e-5, 2e-5, 1e-6, 8e-5],
+            scale: 1e-4,
+            offset: 0.0,
+        };
+        let crossbar = Crossbar::from_mapped_layer(0, &mapped, 0, 2.0, Vector2::new(0.0, 0.0)).unwrap();
+        scene_manager.add_construct(Box::new(crossbar)).unwrap();
+        // The columns are read at virtual ground
+        for col in 0..3 {
+            let node = scene_manager.node_id(&format!("l0.c{}", col)).unwrap();
+            scene_manager.add_voltage_source(node, scene_manager.ground(), 0.0);
+        }
+
+        let options = SpiceOptions {
+            memristor_model: MemristorModel::Stanford {
+                module: "RRAM_v0".to_string(),
+                params: StanfordModelParams::default(),
+            },
+            analyses: vec![SpiceAnalysis::Op, SpiceAnalysis::Tran { step: 1e-9, stop: 1e-6 }],
+            ..SpiceOptions::default()
+        };
+        let netlist = scene_manager.write_spice(Vec::new(), options, 0.2, &HashMap::new()).unwrap();
+        let netlist = String::from_utf8(netlist).unwrap();
+        let lines: Vec<&str> = netlist.lines().collect();
+
+        assert_eq!(lines.iter().filter(|line| line.starts_with('N')).count(), 6);
+        assert!(lines.iter().any(|line| line.starts_with("N1 l0.x1 l0.c0 rram gap_ini=")));
+        // A source per undriven input, the outputs already have theirs
+        let sources: Vec<&&str> = lines.iter().filter(|line| line.starts_with('V')).collect();
+        assert_eq!(sources.len(), 5);
+        assert!(lines.contains(&"V9 l0.x0 0 DC 2e-1"));
+        assert!(lines.contains(&"V10 l0.x1 0 DC 2e-1"));
+        assert_eq!(&lines[lines.len() - 3..], &[".op", ".tran 1e-9 1e-6", ".end"]);
+    }
+
+    #[test]
+    fn test_load_spice() {
+        let source = "\
+inverting amplifier
+.subckt amp in out
+R1 in minus 1k
+R2 minus out 2k
+E1 out 0 0 minus 1e6
+.ends
+.model rram RRAM_v0 g0=2.5e-10 V0=0.25 I0=1e-3
+V1 a 0 DC 0.5
+X1 a y amp
+N1 y gnd rram gap_ini=1e-9
+.op
+.end
+";
+        let mut scene_manager = SceneManager::new();
+        scene_manager.load_spice(&parse_spice(source).unwrap()).unwrap();
+
+        assert_eq!(scene_manager.netlist().components().count(), 4);
+        assert_eq!(scene_manager.sources().len(), 1);
+        assert!(scene_manager.node_id("X1.minus").is_some());
+        assert!(!scene_manager.scene().wires().is_empty());
+
+        let op = scene_manager.dc_operating_point().unwrap();
+        let y = scene_manager.node_id("y").unwrap();
+        assert!((op.voltage(y).unwrap() + 1.0).abs() < 1e-4);
+
+        // Errors point to the line of the element
+        let mut scene_manager = SceneManager::new();
+        let error = scene_manager
+            .load_spice(&parse_spice("title\nR1 a 0 1k\nE1 a b c 0 10\n").unwrap())
+            .unwrap_err();
+        assert!(matches!(
+            error,
+            SceneManagerError::SpiceImportError(SpiceError::Parse { line: 3, .. })
+        ));
+    }
+
+    #[test]
+    fn test_load_nn_conductances() {
+        let mut scene_manager = SceneManager::new();
+        scene_manager.set_conductance_mapping(ConductanceMapping::new(1e-6, 1e-4, true, None));
+
+        let nn = Nn {
+            layers: vec![Layer::Linear(LinearLayer {
+                input_size: 2,
+                output_size: 2,
+                weights: vec![1.0, -0.5, 0.0, 0.25],
+                bias: Some(vec![0.5, -1.0]),
+            })],
+        };
+        scene_manager.load_nn(nn).unwrap();
+
+        let crossbar = scene_manager.crossbars().next().unwrap();
+        // Inputs plus the bias row, and a differential pair per output
+        assert_eq!((crossbar.rows(), crossbar.cols()), (3, 4));
+        let first_id = crossbar.components_id_range().0;
+
+        let conductance = |row: u32, col: u32| match scene_manager
+            .scene()
+            .get_component(first_id + row + col * 3)
+            .unwrap()
+            .params()
+        {
+            ComponentParams::Conductance(g) => *g,
+            params => panic!("unexpected params {:?}", params),
+        };
+
+        // Weight 1.0 (the largest) is the whole range on G+, -1.0 of the bias is the whole range on G-
+        assert_eq!(conductance(0, 0), 1e-4);
+        assert_eq!(conductance(0, 1), 1e-6);
+        assert_eq!(conductance(2, 3), 1e-4);
+        assert!((conductance(1, 1) - conductance(1, 0) - 0.5 * 99e-6).abs() < 1e-15);
+    }
+
+    #[test]
+    fn test_load_nn_unsupported_layers() {
+        let linear = Layer::Linear(LinearLayer {
+            input_size: 4,
+            output_size: 2,
+            weights: vec![0.5; 8],
+            bias: None,
+        });
+        let pool = Layer::Pool2d(rsnet_net_parser::types::Pool2dLayer {
+            kind: rsnet_net_parser::types::PoolingKind::Max,
+            kernel_size: (2, 2),
+            stride: (2, 2),
+            padding: (0, 0),
+        });
+
+        for (layers, unsupported) in [
+            (vec![pool, linear.clone()], (0, "Pool2d")),
+            (vec![linear.clone(), Layer::Activation(Activation::Sigmoid)], (1, "Sigmoid")),
+        ] {
+            match SceneManager::new().load_nn(Nn { layers }) {
+                Err(SceneManagerError::UnsupportedLayer(layer_idx, layer)) => {
+                    assert_eq!((layer_idx, layer.as_str()), unsupported)
+                }
+                result => panic!("unexpected result {:?}", result),
+            }
+        }
+
+        // A ReLU needs the outputs of a previous layer
+        let layers = vec![Layer::Flatten, Layer::Activation(Activation::ReLU), linear];
+        assert!(matches!(
+            SceneManager::new().load_nn(Nn { layers }),
+            Err(SceneManagerError::MissingLayerInputs(1, _))
+        ));
+    }
+
+    #[test]
+    fn test_load_nn_tiling() {
+        let mut scene_manager = SceneManager::new();
+        scene_manager.set_conductance_mapping(ConductanceMapping::new(1e-6, 1e-4, true, None));
+        scene_manager.set_tiling(TilingConfig::new(4, 4));
+
+        // 6 inputs x 3 outputs: 6 rows and 6 differential columns, 2x2 arrays of 4x4
+        let linear_layer = LinearLayer {
+            input_size: 6,
+            output_size: 3,
+            weights: (0..18).map(|w| w as f64 / 17.0 - 0.5).collect(),
+            bias: None,
+        };
+        scene_manager
+            .load_nn(Nn { layers: vec![Layer::Linear(linear_layer)] })
+            .unwrap();
+
+        let tiling = &scene_manager.layer_tilings()[0];
+        assert_eq!(tiling.plan.array_count(), 4);
+        assert!((tiling.plan.utilization() - 36.0 / 64.0).abs() < 1e-12);
+        assert_eq!(scene_manager.crossbars().count(), 4);
+
+        // One adder per column of tiles, summing the partial sums of the 2 tiles
+        let adders: Vec<&PartialSumAdder> = scene_manager
+            .constructs()
+            .iter()
+            .filter_map(|construct| construct.as_any().downcast_ref::<PartialSumAdder>())
+            .collect();
+        assert_eq!(adders.len(), 2);
+        assert!(adders.iter().all(|adder| adder.inputs() == 2));
+        assert_eq!(adders.iter().map(|adder| adder.width()).sum::<u32>(), 3);
+
+        // Ids are not reused between constructs
+        let mut ranges: Vec<(Id, Id)> = scene_manager
+            .constructs()
+            .iter()
+            .map(|construct| construct.components_id_range())
+            .collect();
+        ranges.sort();
+        assert!(ranges.windows(2).all(|pair| pair[1].0 == pair[0].1 + 1));
+
+        // The partial sums of the tiles add up to the ideal product of the whole layer
+        let summary = scene_manager.ir_drop_summary(0.2).unwrap();
+        assert_eq!(summary.len(), 1);
+        assert_eq!(summary[0].report.currents.len(), 6);
+        assert!(summary[0].report.max_relative_error < 1e-6);
+
+        // TIAs and adders rebuild the outputs of the whole layer
+        let inputs = [0.5, -0.25, 1.0, 0.0, 0.75, -1.0];
+        let outputs = scene_manager.run_inference(&inputs).unwrap();
+        for (o, output) in outputs.iter().enumerate() {
+            let expected: f64 = (0..6).map(|i| (o * 6 + i) as f64 / 17.0 - 0.5).zip(inputs).map(|(w, x)| w * x).sum();
+            assert!((output - expected).abs() < 1e-3, "{} != {}", output, expected);
+        }
+    }
+
+    #[test]
+    fn test_load_nn_conv() {
+        let mut scene_manager = SceneManager::new();
+        scene_manager.set_tiling(TilingConfig::new(8, 8));
+
+        // 2 channels of 2x2 kernels: patches of 8 inputs, plus the bias row, split over 2 arrays of rows
+        let conv_layer = rsnet_net_parser::types::Conv2dLayer {
+            in_channels: 2,
+            out_channels: 3,
+            kernel_size: (2, 2),
+            stride: (1, 1),
+            padding: (0, 0),
+            dilation: (1, 1),
+            weights: (0..24).map(|w| w as f64 / 23.0 - 0.5).collect(),
+            bias: Some(vec![0.1, -0.2, 0.0]),
+        };
+        scene_manager
+            .load_nn(Nn {
+                layers: vec![Layer::Conv2d(conv_layer.clone())],
+            })
+            .unwrap();
+        assert_eq!(scene_manager.layer_tilings()[0].plan.array_count(), 2);
+
+        // The crossbar computes every output channel of one patch
+        let input: Vec<f64> = (0..18).map(|x| (x % 5) as f64 / 4.0 - 0.5).collect();
+        let patches = conv_layer.im2col(&input, 3, 3).unwrap();
+        let linear_layer = conv_layer.to_linear();
+        for patch in [&patches[0], &patches[3]] {
+            let outputs = scene_manager.run_inference(patch).unwrap();
+            for (o, output) in outputs.iter().enumerate() {
+                let expected: f64 = linear_layer.weights[o * 8..(o + 1) * 8]
+                    .iter()
+                    .zip(patch.iter())
+                    .map(|(w, x)| w * x)
+                    .sum::<f64>()
+                    + linear_layer.bias.as_ref().unwrap()[o];
+                assert!((output - expected).abs() < 1e-3, "{} != {}", output, expected);
+            }
+        }
+    }
+
+    #[test]
+    fn test_construct_hierarchy() {
+        let mut scene_manager = SceneManager::new();
+        scene_manager.set_tiling(TilingConfig::new(4, 4));
+        let nn = Nn {
+            layers: vec![Layer::Linear(LinearLayer {
+                input_size: 6,
+                output_size: 3,
+                weights: (0..18).map(|w| w as f64 / 17.0 - 0.5).collect(),
+                bias: None,
+            })],
+        };
+        scene_manager.load_nn(nn).unwrap();
+        let inputs = [0.5, -0.25, 1.0, 0.0, 0.75, -1.0];
+        let expected = scene_manager.run_inference(&inputs).unwrap();
+
+        // network > layer > 4 tiles of a crossbar and its TIAs, the DACs and the adders are shared by the layer
+        let hierarchy = scene_manager.hierarchy();
+        let network = hierarchy.node(hierarchy.root()).unwrap().children()[0];
+        let layer = hierarchy.node(network).unwrap().children()[0];
+        assert_eq!(hierarchy.node(layer).unwrap().kind(), ConstructNodeKind::Layer(0));
+        let tiles: Vec<ConstructNodeId> = hierarchy
+            .descendants(layer)
+            .into_iter()
+            .filter(|node| matches!(hierarchy.node(*node).unwrap().kind(), ConstructNodeKind::Tile(..)))
+            .collect();
+        assert_eq!(tiles.len(), 4);
+        let crossbar_id = scene_manager.crossbars().next().unwrap().components_id_range().0;
+        let leaf = hierarchy.construct_of(crossbar_id).unwrap();
+        assert_eq!(hierarchy.ancestors(leaf).skip(1).take(3).collect::<Vec<_>>(), vec![tiles[0], layer, network]);
+        assert!(scene_manager.construct(leaf).unwrap().as_any().is::<Crossbar>());
+
+        // Moving the layer moves every component below it
+        let before = *scene_manager.scene().get_component(crossbar_id).unwrap().position();
+        let offset = Vector2::new(100.0, -20.0);
+        scene_manager.set_construct_transform(layer, Transform::from_translation(offset)).unwrap();
+        let after = *scene_manager.scene().get_component(crossbar_id).unwrap().position();
+        assert!((after - before - offset).norm() < 1e-4);
+        let outputs = scene_manager.run_inference(&inputs).unwrap();
+        assert!(outputs.iter().zip(&expected).all(|(output, expected)| (output - expected).abs() < 1e-12));
+
+        // A copy of a tile has its own components and nets, placed relative to the layer
+        let components = scene_manager.scene().component_count();
+        let copy = scene_manager
+            .instantiate(
+                tiles[0],
+                layer,
+                "copy".to_string(),
+                Transform::from_translation(Vector2::new(0.0, 50.0)),
+                &|node| format!("copy.{}", node),
+            )
+            .unwrap();
+        let copied: usize = scene_manager
+            .hierarchy()
+            .component_ranges(tiles[0])
+            .into_iter()
+            .map(|(first, last)| (first..=last).filter(|id| scene_manager.scene().get_component(*id).is_some()).count())
+            .sum();
+        assert_eq!(scene_manager.scene().component_count(), components + copied);
+        assert_eq!(scene_manager.crossbars().count(), 5);
+        assert!(scene_manager.node_id("copy.l0.x0").is_some());
+
+        let copy_leaf = scene_manager.hierarchy().node(copy).unwrap().children()[0];
+        let copy_id = scene_manager.construct(copy_leaf).unwrap().components_id_range().0;
+        let original = scene_manager.scene().get_component(crossbar_id).unwrap();
+        let instance = scene_manager.scene().get_component(copy_id).unwrap();
+        assert!((instance.position() - original.position() - Vector2::new(0.0, 50.0)).norm() < 1e-4);
+        assert_eq!(instance.params(), original.params());
+
+        assert!(matches!(
+            scene_manager.instantiate(layer, copy, "loop".to_string(), Transform::IDENTITY, &|node| node.to_string()),
+            Err(SceneManagerError::ConstructTreeError(ConstructTreeError::InstanceInsideItself(_)))
+        ));
+    }
+
+    #[test]
+    fn test_route_wires() {
+        let mut scene_manager = SceneManager::new();
+        let nn = Nn {
+            layers: vec![Layer::Linear(LinearLayer {
+                input_size: 3,
+                output_size: 2,
+                weights: vec![1.0, -0.5, 0.0, 0.25, 0.75, -1.0],
+                bias: None,
+            })],
+        };
+        scene_manager.load_nn(nn).unwrap();
+
+        let wires = scene_manager.scene().wires();
+        assert!(!wires.is_empty());
+        assert!(wires.values().all(|wire| {
+            (wire.start().x - wire.end().x).abs() < 1e-3 || (wire.start().y - wire.end().y).abs() < 1e-3
+        }));
+
+        // Every word and bit line is a single wire between the ports of its outermost devices
+        let crossbar = scene_manager.crossbars().next().unwrap();
+        for rail in crossbar.rails() {
+            let positions: Vec<Vector2<f32>> = rail
+                .ports()
+                .iter()
+                .map(|(id, port)| scene_manager.port_position(*id, *port).unwrap())
+                .collect();
+            let (first, last) = (positions[0], positions[positions.len() - 1]);
+            assert!(
+                wires.values().any(|wire| {
+                    let ends = [*wire.start(), *wire.end()];
+                    ends.iter().any(|end| (end - first).norm() < 1e-3) && ends.iter().any(|end| (end - last).norm() < 1e-3)
+                }),
+                "no wire for {}",
+                rail.net()
+            );
+        }
+
+        // Routing again replaces the wires
+        let count = wires.len();
+        assert_eq!(scene_manager.route_wires().unwrap(), count);
+        assert_eq!(scene_manager.scene().wires().len(), count);
+    }
+
+    #[test]
+    fn test_run_inference() {
+        let mut scene_manager = SceneManager::new();
+        scene_manager.set_peripherals(PeripheralConfig {
+            driver_resistance: 1e-3,
+            ..PeripheralConfig::default()
+        });
+
+        let layers = [
+            LinearLayer {
+                input_size: 2,
+                output_size: 3,
+                weights: vec![1.0, -0.5, -1.0, 0.25, 0.5, 0.5],
+                bias: Some(vec![0.1, 0.0, -0.2]),
+            },
+            LinearLayer {
+                input_size: 3,
+                output_size: 1,
+                weights: vec![0.5, -1.0, 1.0],
+                bias: None,
+            },
+        ];
+        let nn = Nn {
+            layers: vec![
+                Layer::Linear(layers[0].clone()),
+                Layer::Activation(Activation::ReLU),
+                Layer::Linear(layers[1].clone()),
+            ],
+        };
+        scene_manager.load_nn(nn).unwrap();
+
+        // One DAC for the inputs and one for the bias row, the second layer is driven by the ReLU blocks
+        let drivers = scene_manager
+            .constructs()
+            .iter()
+            .filter(|construct| construct.as_any().is::<InputDriver>())
+            .count();
+        assert_eq!(drivers, 2);
+
+        let forward = |inputs: &[f64]| -> f64 {
+            let hidden: Vec<f64> = (0..3)
+                .map(|o| {
+                    let y = layers[0].weight(o, 0) * inputs[0] + layers[0].weight(o, 1) * inputs[1];
+                    (y + layers[0].bias.as_ref().unwrap()[o]).max(0.0)
+                })
+                .collect();
+            (0..3).map(|i| layers[1].weight(0, i) * hidden[i]).sum()
+        };
+
+        for inputs in [[0.5, 0.5], [-0.5, 1.0], [1.0, -1.0]] {
+            let outputs = scene_manager.run_inference(&inputs).unwrap();
+            assert_eq!(outputs.len(), 1);
+            assert!((outputs[0] - forward(&inputs)).abs() < 1e-3, "{} != {}", outputs[0], forward(&inputs));
+        }
+    }
 }
@@ -19,6 +19,8 @@ pub struct ComponentBufferEntry {
     pub model: Matrix3<f32>,
     pub id: u32,
     pub ty: u32,
+    /// 1 if the component is selected, so it is highlighted
+    pub selected: u32,
 }
 
 impl ComponentBufferEntry {
@@ -32,6 +34,7 @@ impl ComponentBufferEntry {
             id: component.id(),
             model,
             ty: component.ty().into(),
+            selected: 0,
         }
     }
 
@@ -55,7 +58,9 @@ pub struct WireBufferEntry {
     pub end: Vector2<f32>,
     pub prev_direction: Vector2<f32>,
     pub next_direction: Vector2<f32>,
-    // pub circle_overlay: u32
+    pub circle_overlay: u32,
+    /// 1 if the wire is selected, so it is highlighted
+    pub selected: u32,
 }
 
 impl WireBufferEntry {
@@ -66,7 +71,8 @@ impl WireBufferEntry {
             end: wire.end().clone(),
             prev_direction: wire.prev_direction().clone(),
             next_direction: wire.next_direction().clone(),
-            // circle_overlay: wire.circle_overlay(),
+            circle_overlay: wire.circle_overlay(),
+            selected: 0,
         }
     }
 
@@ -181,7 +187,8 @@ impl SceneStorage {
                         has_dynamic_offset: false,
                         min_binding_size: None,
                     },
-                    visibility: wgpu::ShaderStages::VERTEX,
+                    // The fragment shader of the wires draws the junction dots
+                    visibility: wgpu::ShaderStages::VERTEX | wgpu::ShaderStages::FRAGMENT,
                 },
             ],
         });
@@ -1,4 +1,4 @@
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
 use crate::renderer::primitives::ComponentTyPrimitives;
 
@@ -14,6 +14,9 @@ pub type ChunkStepIdx = u32;
 /// Maps a Chunk step index to a ChunkSize
 pub type ChunkedStorage<T> = HashMap<ChunkStepIdx, HashMap<ChunkId, Vec<T>>>;
 
+/// The chunks of each step whose content changed, so they need to be uploaded again
+pub type DirtyChunks = HashMap<ChunkStepIdx, HashSet<ChunkId>>;
+
 // Primitives
 #[derive(Debug)]
 pub struct Primitives(pub HashMap<ComponentType, Vec<(&'static ComponentTyPrimitives, f32)>>);
@@ -1,5 +1,7 @@
 use super::types::*;
 
+use crate::utils::AaBb;
+
 use nalgebra::Vector2;
 
 pub trait FromPosition {
@@ -29,6 +31,14 @@ impl PartialEq for ChunkRange {
 }
 
 impl ChunkRange {
+    /// Chunks overlapped by ```aabb```
+    pub fn from_aabb(aabb: &AaBb, chunk_size: f32) -> Self {
+        Self {
+            min_chunk: chunk_id_from_position(&aabb.min, chunk_size),
+            max_chunk: chunk_id_from_position(&aabb.max, chunk_size),
+        }
+    }
+
     pub fn contains(&self, chunk: &ChunkId) -> bool {
         let min_chunk = &self.min_chunk;
         let max_chunk = &self.max_chunk;
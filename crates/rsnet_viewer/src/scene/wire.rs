@@ -4,7 +4,10 @@ use super::utils;
 use types::*;
 use utils::{chunk_id_from_position, ChunkRange, FromPosition};
 
-use crate::{renderer::effects::grid, utils::merge_sorted_vecs};
+use crate::{
+    renderer::effects::grid,
+    utils::{merge_sorted_vecs, AaBb},
+};
 
 use nalgebra::{ComplexField, Vector2};
 
@@ -35,7 +38,12 @@ impl WireSegment {
     }
 }
 
-#[derive(Debug)]
+/// Bit of ```Wire::circle_overlay``` that draws a junction dot at the start of the wire
+pub const CIRCLE_AT_START: u32 = 0b10;
+/// Bit of ```Wire::circle_overlay``` that draws a junction dot at the end of the wire
+pub const CIRCLE_AT_END: u32 = 0b01;
+
+#[derive(Debug, Clone)]
 pub struct Wire {
     id: u32,
 
@@ -166,6 +174,45 @@ impl Wire {
         &self.end
     }
 
+    /// Distance from ```point``` to the closest point of the wire
+    pub fn distance_to(&self, point: &Vector2<f32>) -> f32 {
+        let dir = self.end - self.start;
+        let len_sq = dir.norm_squared();
+        let t = if len_sq > 0.0 {
+            ((point - self.start).dot(&dir) / len_sq).clamp(0.0, 1.0)
+        } else {
+            0.0
+        };
+
+        (self.start + dir * t - point).norm()
+    }
+
+    /// Whether some part of the wire lies inside ```aabb```, by clipping it against each side
+    pub fn intersects(&self, aabb: &AaBb) -> bool {
+        let dir = self.end - self.start;
+        let (mut t0, mut t1) = (0.0f32, 1.0f32);
+
+        for (p, q) in [
+            (-dir.x, self.start.x - aabb.min.x),
+            (dir.x, aabb.max.x - self.start.x),
+            (-dir.y, self.start.y - aabb.min.y),
+            (dir.y, aabb.max.y - self.start.y),
+        ] {
+            if p == 0.0 {
+                // Parallel to the side, and outside of it
+                if q < 0.0 {
+                    return false;
+                }
+            } else if p < 0.0 {
+                t0 = t0.max(q / p);
+            } else {
+                t1 = t1.min(q / p);
+            }
+        }
+
+        t0 <= t1
+    }
+
     pub fn aabb(&self) -> &ChunkRange {
         &self.aabb
     }
@@ -181,6 +228,10 @@ impl Wire {
     pub fn circle_overlay(&self) -> u32 {
         self.circle_overlay
     }
+
+    pub fn set_circle_overlay(&mut self, circle_overlay: u32) {
+        self.circle_overlay = circle_overlay;
+    }
 }
 
 fn eval_line_eq(t: f32, x0: f32, x1: f32, y0: f32, y1: f32) -> (f32, f32) {
@@ -3,17 +3,63 @@ pub mod wgpu;
 
 pub use frame_counter::FrameCounter;
 
-use nalgebra::Vector2;
+use nalgebra::{Matrix3, Vector2};
 use rayon::prelude::*;
 use std::{collections::HashMap, fmt::Debug};
 
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, PartialEq)]
 pub struct AaBb {
     pub min: Vector2<f32>,
     pub max: Vector2<f32>,
 }
 
 impl AaBb {
+    pub fn new(min: Vector2<f32>, max: Vector2<f32>) -> Self {
+        Self { min, max }
+    }
+
+    /// The smallest box holding every point, ```None``` if there are none
+    pub fn from_points(points: impl IntoIterator<Item = Vector2<f32>>) -> Option<Self> {
+        points.into_iter().fold(None, |aabb, point| match aabb {
+            Some(AaBb { min, max }) => Some(AaBb::new(min.inf(&point), max.sup(&point))),
+            None => Some(AaBb::new(point, point)),
+        })
+    }
+
+    pub fn overlaps(&self, other: &AaBb) -> bool {
+        self.min.x <= other.max.x
+            && self.min.y <= other.max.y
+            && self.max.x >= other.min.x
+            && self.max.y >= other.min.y
+    }
+
+    /// The box grown by ```margin``` on every side
+    pub fn expanded(&self, margin: f32) -> AaBb {
+        let margin = Vector2::new(margin, margin);
+        AaBb::new(self.min - margin, self.max + margin)
+    }
+
+    /// The box holding this one once transformed by ```transform```
+    pub fn transformed(&self, transform: &Matrix3<f32>) -> AaBb {
+        let corners = [
+            self.min,
+            Vector2::new(self.min.x, self.max.y),
+            Vector2::new(self.max.x, self.min.y),
+            self.max,
+        ];
+
+        AaBb::from_points(
+            corners
+                .iter()
+                .map(|corner| transform.transform_point(&(*corner).into()).coords),
+        )
+        .unwrap()
+    }
+
+    pub fn area(&self) -> f32 {
+        (self.max.x - self.min.x) * (self.max.y - self.min.y)
+    }
+
     pub fn inside(&self, container: &AaBb) -> bool {
         container.contains(&self.min) && container.contains(&self.max)
     }